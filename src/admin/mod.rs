@@ -0,0 +1,375 @@
+//! Admin HTTP server for operational introspection
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post, put},
+    Json, Router,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+use tokio::net::TcpListener;
+
+use crate::{
+    device_stats::get_device_stats,
+    metrics::{self, HIGH_WATER_MARK_SUFFIX},
+    processing::{self, get_processing_control, ProcessingMode},
+    telematics_cache::{
+        dead_letter_event::DeadLetterEvent, failed_api_request::FailedApiRequest,
+        failed_event::FailedEvent, get_device_cache_path, Cacheable,
+    },
+    teltonika::{
+        actions::{get_device_actions, DeviceAction},
+        commands::{get_command_channel, CommandError, CommandResponse},
+        connection::registry::{get_connection_registry, ConnectionSummary},
+        device_family::DeviceFamily,
+        io_elements::{IoElement, IO_ELEMENTS},
+        records::TeltonikaRecordsHandler,
+    },
+    utils::outbound_capture::{self, CapturedRequest},
+};
+
+/// OpenAPI document describing the admin API
+pub const OPENAPI_DOCUMENT: &str = include_str!("openapi.yaml");
+/// Name of the gauge describing the number of items waiting in a per-device queue or cache
+pub const QUEUE_DEPTH_METRIC: &str = "receiver_queue_depth";
+
+/// Depth of a single per-device queue
+#[derive(Serialize)]
+struct QueueDepth {
+    imei: String,
+    queue: String,
+    depth: f64,
+    high_water_mark: f64,
+}
+
+/// Request to set the processing mode of a device
+#[derive(Deserialize)]
+struct ProcessingModeRequest {
+    mode: ProcessingMode,
+}
+
+/// Request to send a command to a device
+#[derive(Deserialize)]
+struct CommandRequest {
+    command: String,
+}
+
+/// Events of a device waiting in its caches or failed
+#[derive(Serialize)]
+struct DeviceBacklog {
+    imei: String,
+    connected: bool,
+    caches: BTreeMap<String, usize>,
+    failed_events: usize,
+    failed_api_requests: usize,
+    dead_letter_events: usize,
+}
+
+/// Maintenance mode state
+#[derive(Serialize, Deserialize)]
+struct MaintenanceMode {
+    enabled: bool,
+}
+
+/// Starts the admin HTTP server
+///
+/// Errors are logged and the server is not restarted, as it is not critical for receiving data.
+///
+/// # Arguments
+/// * `address` - Address to bind the server to
+/// * `cache_base_path` - Base path of the device caches
+pub async fn start_admin_server(address: String, cache_base_path: PathBuf) {
+    let router = Router::new()
+        .route("/metrics", get(get_metrics))
+        .route("/queues", get(list_queue_depths))
+        .route("/requests", get(list_captured_requests))
+        .route("/io-elements", get(list_io_elements))
+        .route(
+            "/maintenance",
+            get(get_maintenance_mode).put(set_maintenance_mode),
+        )
+        .route("/connections", get(list_connections))
+        .route("/devices/processing", get(list_processing_modes))
+        .route("/devices/statistics.csv", get(export_device_statistics))
+        .route("/devices/:imei/processing", put(set_processing_mode))
+        .route(
+            "/devices/:imei/commands",
+            get(list_command_responses).post(enqueue_command),
+        )
+        .route("/devices/:imei/backlog", get(get_device_backlog))
+        .route("/devices/:imei/cache/purge", post(purge_device_cache))
+        .route(
+            "/devices/:imei/dead-letter-events",
+            get(list_dead_letter_events),
+        )
+        .route("/actions", get(list_device_actions))
+        .route(
+            "/devices/:imei/actions/:action",
+            post(trigger_device_action),
+        )
+        .route("/openapi.yaml", get(get_openapi_document))
+        .with_state(cache_base_path);
+
+    let listener = match TcpListener::bind(&address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Failed to bind admin server to [{}]: {}", address, err);
+            return;
+        }
+    };
+    info!("Admin server listening on: {}", address);
+
+    if let Err(err) = axum::serve(listener, router).await {
+        error!("Admin server stopped: {}", err);
+    }
+}
+
+/// Returns all metrics in Prometheus text exposition format
+async fn get_metrics() -> String {
+    metrics::render()
+}
+
+/// Lists the depths and high-water marks of the per-device queues
+async fn list_queue_depths() -> Json<Vec<QueueDepth>> {
+    let high_water_marks =
+        metrics::get_gauges(&format!("{QUEUE_DEPTH_METRIC}{HIGH_WATER_MARK_SUFFIX}"));
+    let queue_depths = metrics::get_gauges(QUEUE_DEPTH_METRIC)
+        .into_iter()
+        .map(|(labels, depth)| {
+            let high_water_mark = high_water_marks
+                .iter()
+                .find(|(high_water_mark_labels, _)| *high_water_mark_labels == labels)
+                .map(|(_, value)| *value)
+                .unwrap_or(depth);
+            let get_label = |key: &str| {
+                labels
+                    .iter()
+                    .find(|(label_key, _)| label_key == key)
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or_default()
+            };
+            QueueDepth {
+                imei: get_label("imei"),
+                queue: get_label("queue"),
+                depth,
+                high_water_mark,
+            }
+        })
+        .collect();
+
+    Json(queue_depths)
+}
+
+/// Lists the latest outgoing API requests by operation
+async fn list_captured_requests() -> Json<BTreeMap<String, Vec<CapturedRequest>>> {
+    Json(outbound_capture::get_captured_requests())
+}
+
+/// Lists the known Teltonika IO elements with their names and units
+async fn list_io_elements() -> Json<&'static [IoElement]> {
+    Json(IO_ELEMENTS)
+}
+
+/// Returns whether maintenance mode is enabled
+async fn get_maintenance_mode() -> Json<MaintenanceMode> {
+    Json(MaintenanceMode {
+        enabled: processing::is_maintenance_mode(),
+    })
+}
+
+/// Enables or disables maintenance mode
+async fn set_maintenance_mode(Json(request): Json<MaintenanceMode>) -> Json<MaintenanceMode> {
+    processing::set_maintenance_mode(request.enabled);
+
+    Json(request)
+}
+
+/// Lists the connected devices
+async fn list_connections() -> Json<Vec<ConnectionSummary>> {
+    Json(get_connection_registry().get_summaries())
+}
+
+/// Lists the processing modes of the devices not processed normally
+async fn list_processing_modes() -> Json<BTreeMap<String, ProcessingMode>> {
+    Json(get_processing_control().get_modes())
+}
+
+/// Exports the daily statistics of the devices as CSV
+async fn export_device_statistics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/csv")],
+        get_device_stats().to_csv(),
+    )
+}
+
+/// Sets the processing mode of a device
+async fn set_processing_mode(
+    Path(imei): Path<String>,
+    Json(request): Json<ProcessingModeRequest>,
+) -> StatusCode {
+    match get_processing_control().set_mode(&imei, request.mode) {
+        Ok(()) => {
+            info!(target: &imei, "Processing mode set to {:?}", request.mode);
+            StatusCode::NO_CONTENT
+        }
+        Err(err) => {
+            error!(target: &imei, "Failed to persist processing mode: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Lists the latest command responses received from a device
+async fn list_command_responses(Path(imei): Path<String>) -> Json<Vec<CommandResponse>> {
+    Json(get_command_channel().get_responses(&imei))
+}
+
+/// Enqueues a Codec 12 command to be sent to a connected device
+async fn enqueue_command(
+    Path(imei): Path<String>,
+    Json(request): Json<CommandRequest>,
+) -> (StatusCode, String) {
+    return enqueue_device_command(&imei, &request.command);
+}
+
+/// Returns the number of events of a device waiting in its caches or failed
+async fn get_device_backlog(
+    State(cache_base_path): State<PathBuf>,
+    Path(imei): Path<String>,
+) -> Result<Json<DeviceBacklog>, StatusCode> {
+    return read_device_backlog(&cache_base_path, &imei)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND);
+}
+
+/// Retries sending the cached events of a connected device regardless of the backoff of its retries
+async fn purge_device_cache(
+    State(cache_base_path): State<PathBuf>,
+    Path(imei): Path<String>,
+) -> Result<Json<DeviceBacklog>, (StatusCode, String)> {
+    let Some(connection) = get_connection_registry().get_connection(&imei) else {
+        return Err((StatusCode::NOT_FOUND, "Device is not connected".to_string()));
+    };
+    info!(target: &imei, "Purging cache through the admin API");
+    if !connection
+        .get_records_handler()
+        .retry_cached_events_now()
+        .await
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            "Truck of the device is not known yet or a purge is already in progress".to_string(),
+        ));
+    }
+
+    return read_device_backlog(&cache_base_path, &imei)
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, String::new()));
+}
+
+/// Reads the backlog of a device
+///
+/// # Arguments
+/// * `cache_base_path` - Base path of the device caches
+/// * `imei` - IMEI of the device
+///
+/// # Returns
+/// * The backlog, or `None` if the device is not connected and has no cache directory
+fn read_device_backlog(cache_base_path: &std::path::Path, imei: &str) -> Option<DeviceBacklog> {
+    let connection = get_connection_registry().get_connection(imei);
+    let device_cache_path = get_device_cache_path(cache_base_path, imei);
+    // Reading the caches creates the cache directory, which is not wanted for unknown devices
+    if connection.is_none() && !device_cache_path.is_dir() {
+        return None;
+    }
+    let records_handler = match &connection {
+        Some(connection) => connection.get_records_handler().clone(),
+        None => Arc::new(TeltonikaRecordsHandler::new(
+            &device_cache_path,
+            None,
+            imei.to_string(),
+            DeviceFamily::default(),
+        )),
+    };
+    let device_cache_path = device_cache_path.to_str().unwrap();
+
+    return Some(DeviceBacklog {
+        imei: imei.to_string(),
+        connected: connection.is_some(),
+        caches: records_handler
+            .get_cache_depths()
+            .into_iter()
+            .map(|(cache_file_path, depth)| {
+                (cache_file_path.trim_end_matches(".json").to_string(), depth)
+            })
+            .collect(),
+        failed_events: FailedEvent::read_from_file(device_cache_path).len(),
+        failed_api_requests: FailedApiRequest::read_from_file(device_cache_path).len(),
+        dead_letter_events: DeadLetterEvent::read_from_file(device_cache_path).len(),
+    });
+}
+
+/// Lists the events of a device that failed to be sent the maximum number of attempts
+async fn list_dead_letter_events(
+    State(cache_base_path): State<PathBuf>,
+    Path(imei): Path<String>,
+) -> Json<Vec<DeadLetterEvent>> {
+    let device_cache_path = get_device_cache_path(&cache_base_path, &imei);
+    // Reading the cache creates the cache directory, which is not wanted for unknown devices
+    if !device_cache_path.is_dir() {
+        return Json(Vec::new());
+    }
+
+    Json(DeadLetterEvent::read_from_file(
+        device_cache_path.to_str().unwrap(),
+    ))
+}
+
+/// Lists the device actions configured in `DEVICE_ACTIONS`
+async fn list_device_actions() -> Json<Vec<&'static DeviceAction>> {
+    Json(get_device_actions().values().collect())
+}
+
+/// Enqueues the command of a named device action to be sent to a connected device
+async fn trigger_device_action(
+    Path((imei, action)): Path<(String, String)>,
+) -> (StatusCode, String) {
+    let Some(device_action) = get_device_actions().get(&action) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Unknown device action [{}]", action),
+        );
+    };
+    info!(target: &imei, "Triggering device action [{}]", action);
+
+    return enqueue_device_command(&imei, &device_action.to_command());
+}
+
+/// Enqueues a command to be sent to a connected device
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `command` - Command to send
+fn enqueue_device_command(imei: &str, command: &str) -> (StatusCode, String) {
+    match get_command_channel().enqueue(imei, command) {
+        Ok(()) => (StatusCode::ACCEPTED, String::new()),
+        Err(err) => {
+            let status = match err {
+                CommandError::InvalidCommand => StatusCode::BAD_REQUEST,
+                CommandError::NotConnected => StatusCode::NOT_FOUND,
+                CommandError::QueueFull => StatusCode::TOO_MANY_REQUESTS,
+            };
+            (status, err.to_string())
+        }
+    }
+}
+
+/// Returns the OpenAPI document describing the admin API
+async fn get_openapi_document() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/yaml")],
+        OPENAPI_DOCUMENT,
+    )
+}
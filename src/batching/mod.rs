@@ -0,0 +1,262 @@
+//! Windowed batches of high-frequency events
+//!
+//! Locations and speeds are sent for nearly every record, so that a driving truck causes a request per event type
+//! every few seconds. When `EVENT_BATCH_WINDOW_SECONDS` is set, the locations and speeds of a device with a known truck
+//! are collected into batches instead, which are submitted when the window has elapsed since the first event of the
+//! batch, when the batch reaches `EVENT_BATCH_SIZE` events or when the connection closes.
+//!
+//! Batches are submitted through [crate::utils::api::VehicleApi::create_truck_locations] and
+//! [crate::utils::api::TruckEventApi::create_truck_speeds]. The Vehicle Management Service has no bulk endpoints yet, so
+//! these still send the events of a batch one by one, but switching them to bulk endpoints is all that's needed for a
+//! single request per batch. Events of a batch failing to be sent are cached like any other events.
+//!
+//! When `EVENT_BATCH_DEGRADED_WINDOW_SECONDS` or `EVENT_BATCH_DEGRADED_SIZE` is set, batches adapt to the health of the
+//! API: the window and size grow from their configured values towards the degraded bounds as the observed latency and
+//! error rate of the API requests grow, and shrink back once the API recovers.
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::{config::get_config, metrics};
+
+/// Error rate of the API requests at which the API is considered fully degraded
+const DEGRADED_ERROR_RATE: f64 = 0.5;
+/// Weight of the latest request in the moving averages of the API health
+const API_HEALTH_SMOOTHING: f64 = 0.2;
+/// Name of the counter describing the number of submitted batches by event type
+pub const EVENT_BATCHES_METRIC: &str = "receiver_event_batches_total";
+/// Name of the counter describing the number of events submitted in batches by event type
+pub const BATCHED_EVENTS_METRIC: &str = "receiver_batched_events_total";
+/// Name of the gauge describing the degradation of the API between 0 (healthy) and 1 (fully degraded)
+pub const API_DEGRADATION_METRIC: &str = "receiver_api_degradation";
+
+static API_HEALTH: OnceLock<ApiHealth> = OnceLock::new();
+
+/// Health of the API observed from the latency and results of the API requests
+pub struct ApiHealth {
+    degraded_latency: Duration,
+    /// Moving averages of the latency in milliseconds and of the error rate
+    averages: Mutex<(f64, f64)>,
+}
+
+impl ApiHealth {
+    /// Creates a new [ApiHealth] starting from a healthy API
+    ///
+    /// # Arguments
+    /// * `degraded_latency` - Latency at which the API is considered fully degraded
+    pub fn new(degraded_latency: Duration) -> Self {
+        ApiHealth {
+            degraded_latency,
+            averages: Mutex::new((0.0, 0.0)),
+        }
+    }
+
+    /// Records the result of an API request
+    ///
+    /// # Arguments
+    /// * `latency` - Time from sending the request to its result, including retries
+    /// * `succeeded` - Whether the API handled the request, i.e. it didn't fail due to a transport or server error
+    pub fn record_request(&self, latency: Duration, succeeded: bool) {
+        let mut averages = self.averages.lock().unwrap();
+        let (latency_millis, error_rate) = *averages;
+        let error = if succeeded { 0.0 } else { 1.0 };
+        *averages = (
+            latency_millis
+                + API_HEALTH_SMOOTHING * (latency.as_secs_f64() * 1000.0 - latency_millis),
+            error_rate + API_HEALTH_SMOOTHING * (error - error_rate),
+        );
+    }
+
+    /// Gets the degradation of the API
+    ///
+    /// # Returns
+    /// * Degradation between 0 (healthy) and 1 (fully degraded) by the latency or error rate, whichever is worse
+    pub fn get_degradation(&self) -> f64 {
+        let (latency_millis, error_rate) = *self.averages.lock().unwrap();
+        let degraded_latency_millis = (self.degraded_latency.as_secs_f64() * 1000.0).max(1.0);
+        let degradation =
+            (latency_millis / degraded_latency_millis).max(error_rate / DEGRADED_ERROR_RATE);
+
+        return degradation.clamp(0.0, 1.0);
+    }
+}
+
+/// Gets the health of the API with the degraded latency of the configuration
+pub fn get_api_health() -> &'static ApiHealth {
+    API_HEALTH.get_or_init(|| ApiHealth::new(get_config().api.degraded_latency))
+}
+
+/// Records the result of an API request in the health of the API
+///
+/// # Arguments
+/// * `latency` - Time from sending the request to its result, including retries
+/// * `succeeded` - Whether the API handled the request, i.e. it didn't fail due to a transport or server error
+pub fn record_api_request(latency: Duration, succeeded: bool) {
+    let api_health = get_api_health();
+    api_health.record_request(latency, succeeded);
+    metrics::set_gauge(API_DEGRADATION_METRIC, &[], api_health.get_degradation());
+}
+
+/// Bounds of the window and size of batches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchBounds {
+    /// Window while the API is healthy
+    pub window: Duration,
+    /// Maximum number of events while the API is healthy
+    pub max_size: usize,
+    /// Window while the API is fully degraded
+    pub degraded_window: Duration,
+    /// Maximum number of events while the API is fully degraded
+    pub degraded_max_size: usize,
+}
+
+impl BatchBounds {
+    /// Scales the window and size of batches by the degradation of the API
+    ///
+    /// # Arguments
+    /// * `degradation` - Degradation of the API between 0 (healthy) and 1 (fully degraded)
+    ///
+    /// # Returns
+    /// * The window and the maximum number of events of batches
+    pub fn scale(&self, degradation: f64) -> (Duration, usize) {
+        let degradation = degradation.clamp(0.0, 1.0);
+        let window = self.window.as_secs_f64()
+            + (self.degraded_window.as_secs_f64() - self.window.as_secs_f64()) * degradation;
+        let max_size = self.max_size as f64
+            + (self.degraded_max_size as f64 - self.max_size as f64) * degradation;
+
+        return (
+            Duration::from_secs_f64(window.max(0.0)),
+            (max_size.round() as usize).max(1),
+        );
+    }
+
+    /// Checks whether the bounds adapt to the health of the API
+    fn is_adaptive(&self) -> bool {
+        self.window != self.degraded_window || self.max_size != self.degraded_max_size
+    }
+}
+
+/// Events collected in a batch
+struct EventBatchState<T> {
+    events: Vec<T>,
+    /// Time the first event of the batch was collected
+    opened_at: Option<Instant>,
+}
+
+/// Batch of events waiting to be submitted together
+pub struct EventBatch<T> {
+    bounds: BatchBounds,
+    state: Mutex<EventBatchState<T>>,
+}
+
+impl<T> EventBatch<T> {
+    /// Creates a new [EventBatch]
+    ///
+    /// # Arguments
+    /// * `window` - Maximum time to collect events after the first event of the batch
+    /// * `max_size` - Maximum number of events in the batch
+    #[cfg(test)]
+    pub fn new(window: Duration, max_size: usize) -> Self {
+        return EventBatch::with_bounds(BatchBounds {
+            window,
+            max_size,
+            degraded_window: window,
+            degraded_max_size: max_size,
+        });
+    }
+
+    /// Creates a new [EventBatch] adapting its window and size to the health of the API
+    ///
+    /// # Arguments
+    /// * `bounds` - Bounds of the window and size of the batch
+    pub fn with_bounds(bounds: BatchBounds) -> Self {
+        EventBatch {
+            bounds,
+            state: Mutex::new(EventBatchState {
+                events: Vec::new(),
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Creates a new [EventBatch] from the configuration
+    ///
+    /// # Returns
+    /// * The batch, or `None` if batching is disabled by leaving `EVENT_BATCH_WINDOW_SECONDS` unset or zero
+    pub fn from_config() -> Option<Self> {
+        return get_config()
+            .tunables
+            .event_batch_bounds
+            .map(EventBatch::with_bounds);
+    }
+
+    /// Gets the current window and maximum number of events of the batch
+    fn get_limits(&self) -> (Duration, usize) {
+        if !self.bounds.is_adaptive() {
+            return self.bounds.scale(0.0);
+        }
+
+        return self.bounds.scale(get_api_health().get_degradation());
+    }
+
+    /// Adds an event to the batch
+    ///
+    /// # Arguments
+    /// * `event` - Event to add
+    /// * `now` - Current time
+    ///
+    /// # Returns
+    /// * The events of the batch to submit if the batch is full or its window has elapsed, otherwise `None`
+    pub fn push(&self, event: T, now: Instant) -> Option<Vec<T>> {
+        let (window, max_size) = self.get_limits();
+        let mut state = self.state.lock().unwrap();
+        let opened_at = *state.opened_at.get_or_insert(now);
+        state.events.push(event);
+        if state.events.len() < max_size && now.duration_since(opened_at) < window {
+            return None;
+        }
+        state.opened_at = None;
+
+        return Some(std::mem::take(&mut state.events));
+    }
+
+    /// Takes the events of the batch if its window has elapsed
+    ///
+    /// # Arguments
+    /// * `now` - Current time
+    pub fn take_due(&self, now: Instant) -> Vec<T> {
+        let (window, _) = self.get_limits();
+        let mut state = self.state.lock().unwrap();
+        match state.opened_at {
+            Some(opened_at) if now.duration_since(opened_at) >= window => {
+                state.opened_at = None;
+                std::mem::take(&mut state.events)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Takes all events of the batch regardless of its window
+    pub fn take_all(&self) -> Vec<T> {
+        let mut state = self.state.lock().unwrap();
+        state.opened_at = None;
+
+        return std::mem::take(&mut state.events);
+    }
+}
+
+/// Records a submitted batch
+///
+/// # Arguments
+/// * `event_type` - Type of the events in the batch
+/// * `size` - Number of events in the batch
+pub fn record_batch(event_type: &str, size: usize) {
+    metrics::increment_counter(EVENT_BATCHES_METRIC, &[("event_type", event_type)]);
+    metrics::add_to_counter(
+        BATCHED_EVENTS_METRIC,
+        &[("event_type", event_type)],
+        size as u64,
+    );
+}
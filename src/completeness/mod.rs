@@ -0,0 +1,161 @@
+//! Daily data completeness report
+//!
+//! Compares the expected reporting cadence of each device to the records actually received during the last 24 hours.
+//! Vehicle Management Service doesn't provide an endpoint for the reports, so they are emitted as log events.
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::info;
+use nom_teltonika::AVLRecord;
+
+/// Length of the reporting window in seconds
+const REPORT_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+/// Shortest gap included in the report in seconds
+const MIN_REPORTED_GAP_SECONDS: i64 = 15 * 60;
+
+/// Received reporting intervals by device
+struct CompletenessTracker {
+    expected_interval_seconds: i64,
+    received_intervals: Mutex<HashMap<String, BTreeSet<i64>>>,
+}
+
+static TRACKER: OnceLock<CompletenessTracker> = OnceLock::new();
+
+/// Completeness report of a single device
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletenessReport {
+    pub imei: String,
+    pub expected_intervals: u64,
+    pub received_intervals: u64,
+    pub gaps: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+impl CompletenessReport {
+    /// Gets the percentage of expected reporting intervals with at least one record
+    pub fn get_percentage(&self) -> f64 {
+        if self.expected_intervals == 0 {
+            return 100.0;
+        }
+
+        return self.received_intervals as f64 / self.expected_intervals as f64 * 100.0;
+    }
+}
+
+/// Records the timestamps of received records for the completeness report
+///
+/// Does nothing unless the completeness reporter has been started.
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `records` - Received records
+pub fn record_received(imei: &str, records: &[AVLRecord]) {
+    let Some(tracker) = TRACKER.get() else {
+        return;
+    };
+    let window_start = Utc::now().timestamp() - REPORT_WINDOW_SECONDS;
+    let mut received_intervals = tracker.received_intervals.lock().unwrap();
+    let device_intervals = received_intervals.entry(imei.to_string()).or_default();
+    for record in records
+        .iter()
+        .filter(|record| record.timestamp.timestamp() >= window_start)
+    {
+        device_intervals.insert(record.timestamp.timestamp() / tracker.expected_interval_seconds);
+    }
+}
+
+/// Periodically reports the data completeness of each device for the last 24 hours
+///
+/// # Arguments
+/// * `expected_interval_seconds` - Expected interval between records of a device
+pub async fn start_completeness_reporter(expected_interval_seconds: i64) {
+    let tracker = TRACKER.get_or_init(|| CompletenessTracker {
+        expected_interval_seconds,
+        received_intervals: Mutex::new(HashMap::new()),
+    });
+    let mut ticker = tokio::time::interval(Duration::from_secs(REPORT_WINDOW_SECONDS as u64));
+    // First tick completes immediately
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let window_end = Utc::now();
+        let mut received_intervals = tracker.received_intervals.lock().unwrap();
+        for (imei, device_intervals) in received_intervals.iter_mut() {
+            let report = build_completeness_report(
+                imei,
+                device_intervals,
+                window_end,
+                tracker.expected_interval_seconds,
+            );
+            info!(target: imei,
+                "Data completeness of the last 24 h: {:.1} % ({}/{} intervals), {} gaps: {}",
+                report.get_percentage(),
+                report.received_intervals,
+                report.expected_intervals,
+                report.gaps.len(),
+                report
+                    .gaps
+                    .iter()
+                    .map(|(start, end)| format!("{} - {}", start, end))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+            device_intervals.clear();
+        }
+        received_intervals.clear();
+    }
+}
+
+/// Builds a completeness report from the received reporting intervals of a device
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `received_intervals` - Indices of the intervals with at least one record
+/// * `window_end` - End of the reporting window
+/// * `expected_interval_seconds` - Expected interval between records
+///
+/// # Returns
+/// * `CompletenessReport` - Report of the 24 hours before `window_end`
+pub fn build_completeness_report(
+    imei: &str,
+    received_intervals: &BTreeSet<i64>,
+    window_end: DateTime<Utc>,
+    expected_interval_seconds: i64,
+) -> CompletenessReport {
+    let last_interval = window_end.timestamp() / expected_interval_seconds;
+    let first_interval =
+        (window_end.timestamp() - REPORT_WINDOW_SECONDS) / expected_interval_seconds + 1;
+    let min_gap_intervals = (MIN_REPORTED_GAP_SECONDS / expected_interval_seconds).max(1);
+    let interval_start = |interval: i64| {
+        Utc.timestamp_opt(interval * expected_interval_seconds, 0)
+            .unwrap()
+    };
+
+    let mut gaps = Vec::new();
+    let mut gap_start: Option<i64> = None;
+    for interval in first_interval..=last_interval + 1 {
+        let received = interval > last_interval || received_intervals.contains(&interval);
+        match (received, gap_start) {
+            (false, None) => gap_start = Some(interval),
+            (true, Some(start)) => {
+                if interval - start >= min_gap_intervals {
+                    gaps.push((interval_start(start), interval_start(interval)));
+                }
+                gap_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    return CompletenessReport {
+        imei: imei.to_string(),
+        expected_intervals: (last_interval - first_interval + 1) as u64,
+        received_intervals: received_intervals
+            .range(first_interval..=last_interval)
+            .count() as u64,
+        gaps,
+    };
+}
@@ -0,0 +1,109 @@
+//! Logger whose filters can be reloaded at runtime
+//!
+//! Logs are written with `tracing`, and records of the `log` macros are forwarded to it, so that every log line
+//! carries the spans it was written in, e.g. the IMEI, connection and truck of a device session and the frame being
+//! handled. With `LOG_FORMAT=json` each line is a JSON object with the fields of the spans for log aggregators such as
+//! Loki. The filters of `RUST_LOG` are kept in a reloadable layer and replaced whenever the configuration is reloaded.
+//! The filtered spans are also exported as OpenTelemetry traces, see [super::telemetry].
+use std::{str::FromStr, sync::OnceLock};
+
+use tracing_subscriber::{
+    filter::LevelFilter, fmt as tracing_fmt, layer::SubscriberExt, reload, util::SubscriberInitExt,
+    EnvFilter, Registry,
+};
+
+use super::{get_config, telemetry::build_telemetry_layer};
+
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Format of the log lines
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LogFormat {
+    /// Human-readable lines prefixed with the spans they were written in
+    #[default]
+    Text,
+    /// JSON objects with the message, target, current span and all entered spans
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "Unknown log format [{}], expected text or json",
+                value
+            )),
+        }
+    }
+}
+
+/// Builds a filter from `RUST_LOG` of the configuration
+///
+/// Invalid directives are ignored, and only errors are logged if no filters are configured.
+fn build_filter() -> EnvFilter {
+    EnvFilter::new(&get_config().tunables.log_filter)
+}
+
+/// Lets the records of the `log` macros enabled by the filter through to `tracing`
+///
+/// # Arguments
+/// * `max_level` - Most verbose level enabled by the filter, if known
+fn set_log_max_level(max_level: Option<LevelFilter>) {
+    log::set_max_level(
+        max_level
+            .unwrap_or(LevelFilter::TRACE)
+            .to_string()
+            .parse()
+            .unwrap_or(log::LevelFilter::Trace),
+    );
+}
+
+/// Initializes the global logger
+///
+/// Spans are also exported as OpenTelemetry traces when an OTLP endpoint is configured.
+pub fn init_logger() {
+    let filter = build_filter();
+    let max_level = filter.max_level_hint();
+    let (filter, filter_handle) = reload::Layer::new(filter);
+    let (telemetry_layer, telemetry_error) = match build_telemetry_layer() {
+        Ok(telemetry_layer) => (telemetry_layer, None),
+        Err(err) => (None, Some(err)),
+    };
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(telemetry_layer);
+    match get_config().monitoring.log_format {
+        LogFormat::Text => subscriber.with(tracing_fmt::layer()).init(),
+        LogFormat::Json => subscriber
+            .with(
+                tracing_fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true),
+            )
+            .init(),
+    }
+    set_log_max_level(max_level);
+    FILTER_HANDLE
+        .set(filter_handle)
+        .expect("Logger is already initialized");
+    if let Some(err) = telemetry_error {
+        log::error!("Failed to initialize exporting traces: {}", err);
+    }
+}
+
+/// Rebuilds the filters of the global logger
+pub fn reload_logger() {
+    let Some(filter_handle) = FILTER_HANDLE.get() else {
+        return;
+    };
+    let filter = build_filter();
+    set_log_max_level(filter.max_level_hint());
+    if let Err(err) = filter_handle.reload(filter) {
+        log::error!("Failed to reload log filters: {}", err);
+    }
+}
@@ -0,0 +1,1155 @@
+//! Configuration of the receiver
+//!
+//! The receiver is configured with environment variables, optionally complemented by a TOML configuration file
+//! given in `CONFIG_FILE`. The file uses the environment variable names as keys, and environment variables override
+//! the values of the file. Empty values are treated as unset.
+//!
+//! The values are parsed into a typed [Config] and validated once on startup, so that an invalid value stops the
+//! receiver right away instead of failing on first use. Sending `SIGHUP` to the receiver reloads the file and applies
+//! the [Tunables], i.e. the log level and the settings of new device connections. The other settings set up the
+//! listeners, background tasks and shared clients on startup, so changing them requires a restart.
+//!
+//! A commented default configuration can be printed with `--print-default-config`, so that new deployments can be
+//! bootstrapped by editing a single file.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+use log::{error, info, warn};
+
+use crate::{
+    batching::BatchBounds,
+    connection_limits::ConnectionLimits,
+    device_auth::parse_device_auth_tokens,
+    load_shedding::LoadSheddingThresholds,
+    retention::DEFAULT_RAW_CAPTURES_RETENTION_DAYS,
+    teltonika::{
+        actions::{parse_device_actions, DeviceAction},
+        device_family::DeviceFamily,
+        events::ble_sensor_event_handler::{
+            parse_ble_sensor_compartments, parse_ble_sensor_macs, SlotSettings,
+        },
+        records::{
+            teltonika_location_validator::LocationValidationMode,
+            teltonika_record_ordering::RecordOrdering,
+            teltonika_timestamp_normalizer::parse_timestamp_offsets,
+            teltonika_timestamp_validator::TimestampPolicy,
+        },
+    },
+    utils::{
+        api::ApiClientSettings,
+        api_routing::{parse_routing_overrides, RoutingOverride},
+        socket_options::SocketOptions,
+    },
+};
+
+use self::logger::LogFormat;
+
+pub mod logger;
+pub mod telemetry;
+
+/// Command line flag for printing the default configuration
+pub const PRINT_DEFAULT_CONFIG_FLAG: &str = "--print-default-config";
+/// Default configuration in environment file format
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.env");
+const CONFIG_FILE_ENV_KEY: &str = "CONFIG_FILE";
+
+const BASE_FILE_PATH_ENV_KEY: &str = "BASE_FILE_PATH";
+const WRITE_TO_FILE_ENV_KEY: &str = "WRITE_TO_FILE";
+const MIN_CACHE_FREE_SPACE_MB_ENV_KEY: &str = "MIN_CACHE_FREE_SPACE_MB";
+const RAW_CAPTURES_RETENTION_DAYS_ENV_KEY: &str = "RAW_CAPTURES_RETENTION_DAYS";
+const PROCESSING_STATE_FILE_ENV_KEY: &str = "PROCESSING_STATE_FILE";
+const IO_MAPPINGS_FILE_ENV_KEY: &str = "IO_MAPPINGS_FILE";
+const CACHE_RETRY_INTERVAL_SECONDS_ENV_KEY: &str = "CACHE_RETRY_INTERVAL_SECONDS";
+const DEVICE_ACTIONS_ENV_KEY: &str = "DEVICE_ACTIONS";
+const DEVICE_AUTH_TOKENS_ENV_KEY: &str = "DEVICE_AUTH_TOKENS";
+const SPOOFING_DETECTION_WINDOW_SECONDS_ENV_KEY: &str = "SPOOFING_DETECTION_WINDOW_SECONDS";
+const QUARANTINE_SUSPECTED_SPOOFING_ENV_KEY: &str = "QUARANTINE_SUSPECTED_SPOOFING";
+const SYNTHETIC_IMEIS_ENV_KEY: &str = "SYNTHETIC_IMEIS";
+const SYNTHETIC_API_BASE_URL_ENV_KEY: &str = "SYNTHETIC_API_BASE_URL";
+const LOAD_SHEDDING_QUEUE_THRESHOLD_ENV_KEY: &str = "LOAD_SHEDDING_QUEUE_THRESHOLD";
+const LOAD_SHEDDING_CPU_THRESHOLD_ENV_KEY: &str = "LOAD_SHEDDING_CPU_THRESHOLD";
+
+const API_BASE_URL_ENV_KEY: &str = "API_BASE_URL";
+const VEHICLE_MANAGEMENT_SERVICE_API_KEY_ENV_KEY: &str = "VEHICLE_MANAGEMENT_SERVICE_API_KEY";
+const API_RUNTIME_WORKER_THREADS_ENV_KEY: &str = "API_RUNTIME_WORKER_THREADS";
+const API_CONNECT_TIMEOUT_SECONDS_ENV_KEY: &str = "API_CONNECT_TIMEOUT_SECONDS";
+const API_READ_TIMEOUT_SECONDS_ENV_KEY: &str = "API_READ_TIMEOUT_SECONDS";
+const API_POOL_IDLE_TIMEOUT_SECONDS_ENV_KEY: &str = "API_POOL_IDLE_TIMEOUT_SECONDS";
+const API_POOL_MAX_IDLE_PER_HOST_ENV_KEY: &str = "API_POOL_MAX_IDLE_PER_HOST";
+const API_CIRCUIT_BREAKER_FAILURE_THRESHOLD_ENV_KEY: &str = "API_CIRCUIT_BREAKER_FAILURE_THRESHOLD";
+const API_CIRCUIT_BREAKER_OPEN_SECONDS_ENV_KEY: &str = "API_CIRCUIT_BREAKER_OPEN_SECONDS";
+const API_ROUTING_OVERRIDES_ENV_KEY: &str = "API_ROUTING_OVERRIDES";
+const EVENT_BATCH_DEGRADED_LATENCY_MILLIS_ENV_KEY: &str = "EVENT_BATCH_DEGRADED_LATENCY_MILLIS";
+const TRUCK_CACHE_TTL_SECONDS_ENV_KEY: &str = "TRUCK_CACHE_TTL_SECONDS";
+const TRUCK_CACHE_NEGATIVE_TTL_SECONDS_ENV_KEY: &str = "TRUCK_CACHE_NEGATIVE_TTL_SECONDS";
+const TRUCK_CACHE_MAX_ENTRIES_ENV_KEY: &str = "TRUCK_CACHE_MAX_ENTRIES";
+const TRUCK_CACHE_WARMUP_ENV_KEY: &str = "TRUCK_CACHE_WARMUP";
+const TRUCK_ID_REFRESH_INTERVAL_SECONDS_ENV_KEY: &str = "TRUCK_ID_REFRESH_INTERVAL_SECONDS";
+
+const UDP_LISTENER_ADDRESS_ENV_KEY: &str = "UDP_LISTENER_ADDRESS";
+const FMB1XX_LISTENER_ADDRESS_ENV_KEY: &str = "FMB1XX_LISTENER_ADDRESS";
+const FMB6XX_LISTENER_ADDRESS_ENV_KEY: &str = "FMB6XX_LISTENER_ADDRESS";
+const ADMIN_SERVER_ADDRESS_ENV_KEY: &str = "ADMIN_SERVER_ADDRESS";
+const TCP_NODELAY_ENV_KEY: &str = "TCP_NODELAY";
+const TCP_KEEPALIVE_SECONDS_ENV_KEY: &str = "TCP_KEEPALIVE_SECONDS";
+const TCP_KEEPALIVE_INTERVAL_SECONDS_ENV_KEY: &str = "TCP_KEEPALIVE_INTERVAL_SECONDS";
+const TCP_RECV_BUFFER_SIZE_ENV_KEY: &str = "TCP_RECV_BUFFER_SIZE";
+const TCP_SEND_BUFFER_SIZE_ENV_KEY: &str = "TCP_SEND_BUFFER_SIZE";
+const UDP_RECV_BUFFER_SIZE_ENV_KEY: &str = "UDP_RECV_BUFFER_SIZE";
+const UDP_SEND_BUFFER_SIZE_ENV_KEY: &str = "UDP_SEND_BUFFER_SIZE";
+/// Prefix of the socket option keys overriding the global `TCP_*` keys for the FMB1xx listener
+const FMB1XX_SOCKET_OPTIONS_PREFIX: &str = "FMB1XX";
+/// Prefix of the socket option keys overriding the global `TCP_*` keys for the FMB6xx listener
+const FMB6XX_SOCKET_OPTIONS_PREFIX: &str = "FMB6XX";
+const MAX_CONNECTIONS_ENV_KEY: &str = "MAX_CONNECTIONS";
+const MAX_CONNECTIONS_PER_IP_ENV_KEY: &str = "MAX_CONNECTIONS_PER_IP";
+const MAX_ACCEPTS_PER_SECOND_ENV_KEY: &str = "MAX_ACCEPTS_PER_SECOND";
+const SHUTDOWN_TIMEOUT_SECONDS_ENV_KEY: &str = "SHUTDOWN_TIMEOUT_SECONDS";
+
+const LOG_FORMAT_ENV_KEY: &str = "LOG_FORMAT";
+const OTEL_EXPORTER_OTLP_ENDPOINT_ENV_KEY: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const OTEL_SERVICE_NAME_ENV_KEY: &str = "OTEL_SERVICE_NAME";
+const LOG_THROTTLE_INTERVAL_SECONDS_ENV_KEY: &str = "LOG_THROTTLE_INTERVAL_SECONDS";
+const STATISTICS_SUMMARY_INTERVAL_SECONDS_ENV_KEY: &str = "STATISTICS_SUMMARY_INTERVAL_SECONDS";
+const COMPLETENESS_EXPECTED_INTERVAL_SECONDS_ENV_KEY: &str =
+    "COMPLETENESS_EXPECTED_INTERVAL_SECONDS";
+const LATENCY_PROBE_INTERVAL_SECONDS_ENV_KEY: &str = "LATENCY_PROBE_INTERVAL_SECONDS";
+
+const RUST_LOG_ENV_KEY: &str = "RUST_LOG";
+const VALIDATE_IMEI_CHECKSUMS_ENV_KEY: &str = "VALIDATE_IMEI_CHECKSUMS";
+const IMEI_HANDSHAKE_TIMEOUT_SECONDS_ENV_KEY: &str = "IMEI_HANDSHAKE_TIMEOUT_SECONDS";
+const CONNECTION_IDLE_TIMEOUT_SECONDS_ENV_KEY: &str = "CONNECTION_IDLE_TIMEOUT_SECONDS";
+const ACK_WRITE_RETRIES_ENV_KEY: &str = "ACK_WRITE_RETRIES";
+const ACK_PIPELINE_DEPTH_ENV_KEY: &str = "ACK_PIPELINE_DEPTH";
+const MAX_CONNECTION_MEMORY_BYTES_ENV_KEY: &str = "MAX_CONNECTION_MEMORY_BYTES";
+const FRAME_QUEUE_REJECT_THRESHOLD_ENV_KEY: &str = "FRAME_QUEUE_REJECT_THRESHOLD";
+const CARD_REMOVE_THRESHOLD_ENV_KEY: &str = "CARD_REMOVE_THRESHOLD";
+const RECORD_ORDERING_ENV_KEY: &str = "RECORD_ORDERING";
+const DEVICE_TIMESTAMP_OFFSETS_ENV_KEY: &str = "DEVICE_TIMESTAMP_OFFSETS";
+const DETECT_TIMESTAMP_OFFSETS_ENV_KEY: &str = "DETECT_TIMESTAMP_OFFSETS";
+const RECORD_TIMESTAMP_POLICY_ENV_KEY: &str = "RECORD_TIMESTAMP_POLICY";
+const RECORD_MAX_AGE_SECONDS_ENV_KEY: &str = "RECORD_MAX_AGE_SECONDS";
+const RECORD_MAX_FUTURE_SECONDS_ENV_KEY: &str = "RECORD_MAX_FUTURE_SECONDS";
+const GAP_RECOVERY_COMMAND_ENV_KEY: &str = "GAP_RECOVERY_COMMAND";
+const GAP_RECOVERY_THRESHOLD_SECONDS_ENV_KEY: &str = "GAP_RECOVERY_THRESHOLD_SECONDS";
+const ODOMETER_DISCREPANCY_THRESHOLD_PERCENT_ENV_KEY: &str =
+    "ODOMETER_DISCREPANCY_THRESHOLD_PERCENT";
+const ODOMETER_RECONCILIATION_DISTANCE_METERS_ENV_KEY: &str =
+    "ODOMETER_RECONCILIATION_DISTANCE_METERS";
+const ODOMETER_MAX_DELTA_METERS_ENV_KEY: &str = "ODOMETER_MAX_DELTA_METERS";
+const ODOMETER_DELTA_WINDOW_SECONDS_ENV_KEY: &str = "ODOMETER_DELTA_WINDOW_SECONDS";
+const LOCATION_VALIDATION_ENV_KEY: &str = "LOCATION_VALIDATION";
+const LOCATION_MIN_SATELLITES_ENV_KEY: &str = "LOCATION_MIN_SATELLITES";
+const LOCATION_MAX_HDOP_ENV_KEY: &str = "LOCATION_MAX_HDOP";
+const LOCATION_MAX_SPEED_KMH_ENV_KEY: &str = "LOCATION_MAX_SPEED_KMH";
+const GNSS_SPEED_FALLBACK_ENV_KEY: &str = "GNSS_SPEED_FALLBACK";
+const BLE_SENSOR_MACS_ENV_KEY: &str = "BLE_SENSOR_MACS";
+const BLE_SENSOR_COMPARTMENTS_ENV_KEY: &str = "BLE_SENSOR_COMPARTMENTS";
+const EVENT_BATCH_WINDOW_SECONDS_ENV_KEY: &str = "EVENT_BATCH_WINDOW_SECONDS";
+const EVENT_BATCH_SIZE_ENV_KEY: &str = "EVENT_BATCH_SIZE";
+const EVENT_BATCH_DEGRADED_WINDOW_SECONDS_ENV_KEY: &str = "EVENT_BATCH_DEGRADED_WINDOW_SECONDS";
+const EVENT_BATCH_DEGRADED_SIZE_ENV_KEY: &str = "EVENT_BATCH_DEGRADED_SIZE";
+const CACHE_RETRY_MAX_BACKOFF_SECONDS_ENV_KEY: &str = "CACHE_RETRY_MAX_BACKOFF_SECONDS";
+const CACHE_MAX_RETRY_ATTEMPTS_ENV_KEY: &str = "CACHE_MAX_RETRY_ATTEMPTS";
+
+/// Keys of the [Tunables], which are applied when the configuration is reloaded
+const TUNABLE_KEYS: [&str; 34] = [
+    RUST_LOG_ENV_KEY,
+    VALIDATE_IMEI_CHECKSUMS_ENV_KEY,
+    IMEI_HANDSHAKE_TIMEOUT_SECONDS_ENV_KEY,
+    CONNECTION_IDLE_TIMEOUT_SECONDS_ENV_KEY,
+    ACK_WRITE_RETRIES_ENV_KEY,
+    ACK_PIPELINE_DEPTH_ENV_KEY,
+    MAX_CONNECTION_MEMORY_BYTES_ENV_KEY,
+    FRAME_QUEUE_REJECT_THRESHOLD_ENV_KEY,
+    CARD_REMOVE_THRESHOLD_ENV_KEY,
+    RECORD_ORDERING_ENV_KEY,
+    DEVICE_TIMESTAMP_OFFSETS_ENV_KEY,
+    DETECT_TIMESTAMP_OFFSETS_ENV_KEY,
+    RECORD_TIMESTAMP_POLICY_ENV_KEY,
+    RECORD_MAX_AGE_SECONDS_ENV_KEY,
+    RECORD_MAX_FUTURE_SECONDS_ENV_KEY,
+    GAP_RECOVERY_COMMAND_ENV_KEY,
+    GAP_RECOVERY_THRESHOLD_SECONDS_ENV_KEY,
+    ODOMETER_DISCREPANCY_THRESHOLD_PERCENT_ENV_KEY,
+    ODOMETER_RECONCILIATION_DISTANCE_METERS_ENV_KEY,
+    ODOMETER_MAX_DELTA_METERS_ENV_KEY,
+    ODOMETER_DELTA_WINDOW_SECONDS_ENV_KEY,
+    LOCATION_VALIDATION_ENV_KEY,
+    LOCATION_MIN_SATELLITES_ENV_KEY,
+    LOCATION_MAX_HDOP_ENV_KEY,
+    LOCATION_MAX_SPEED_KMH_ENV_KEY,
+    GNSS_SPEED_FALLBACK_ENV_KEY,
+    BLE_SENSOR_MACS_ENV_KEY,
+    BLE_SENSOR_COMPARTMENTS_ENV_KEY,
+    EVENT_BATCH_WINDOW_SECONDS_ENV_KEY,
+    EVENT_BATCH_SIZE_ENV_KEY,
+    EVENT_BATCH_DEGRADED_WINDOW_SECONDS_ENV_KEY,
+    EVENT_BATCH_DEGRADED_SIZE_ENV_KEY,
+    CACHE_RETRY_MAX_BACKOFF_SECONDS_ENV_KEY,
+    CACHE_MAX_RETRY_ATTEMPTS_ENV_KEY,
+];
+
+/// Default minimum free space in megabytes required in the cache directory
+const DEFAULT_MIN_CACHE_FREE_SPACE_MB: u64 = 100;
+/// Default interval of retrying the caches of devices in seconds
+const DEFAULT_CACHE_RETRY_INTERVAL_SECONDS: u64 = 60;
+/// Default timeout of establishing a connection to the API in seconds
+const DEFAULT_API_CONNECT_TIMEOUT_SECONDS: u64 = 5;
+/// Default timeout of each read of an API response in seconds
+const DEFAULT_API_READ_TIMEOUT_SECONDS: u64 = 30;
+/// Default time in seconds idle connections to the API are kept in the pool
+const DEFAULT_API_POOL_IDLE_TIMEOUT_SECONDS: u64 = 90;
+/// Default maximum number of idle connections to the API kept in the pool per host
+const DEFAULT_API_POOL_MAX_IDLE_PER_HOST: usize = 32;
+/// Default number of consecutive failed requests opening a circuit breaker
+const DEFAULT_API_CIRCUIT_BREAKER_FAILURE_THRESHOLD: u32 = 5;
+/// Default time in seconds a circuit breaker stays open
+const DEFAULT_API_CIRCUIT_BREAKER_OPEN_SECONDS: u64 = 30;
+/// Default average latency in milliseconds at which the API is considered degraded
+const DEFAULT_EVENT_BATCH_DEGRADED_LATENCY_MILLIS: u64 = 2000;
+/// Default time in seconds truck IDs are cached
+const DEFAULT_TRUCK_CACHE_TTL_SECONDS: i64 = 60 * 60;
+/// Default time in seconds VINs without a truck are cached
+const DEFAULT_TRUCK_CACHE_NEGATIVE_TTL_SECONDS: i64 = 5 * 60;
+/// Default maximum number of cached VINs
+const DEFAULT_TRUCK_CACHE_MAX_ENTRIES: usize = 10_000;
+/// Default interval of refreshing the truck IDs of connected devices in seconds
+const DEFAULT_TRUCK_ID_REFRESH_INTERVAL_SECONDS: u64 = 5 * 60;
+/// Default maximum time to wait for the connections to close on shutdown in seconds
+const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 30;
+/// Default interval repetitive log messages are throttled to in seconds
+const DEFAULT_LOG_THROTTLE_INTERVAL_SECONDS: u64 = 5 * 60;
+/// Default interval of the statistics summary log in seconds
+const DEFAULT_STATISTICS_SUMMARY_INTERVAL_SECONDS: u64 = 300;
+/// Default time in seconds a device may take to send its IMEI
+const DEFAULT_IMEI_HANDSHAKE_TIMEOUT_SECONDS: u64 = 5;
+/// Default number of retries of writing a frame ACK
+const DEFAULT_ACK_WRITE_RETRIES: u32 = 2;
+/// Default card remove threshold in milliseconds
+const DEFAULT_CARD_REMOVE_THRESHOLD: u16 = 15_000;
+/// Default maximum age of a record in seconds
+const DEFAULT_RECORD_MAX_AGE_SECONDS: i64 = 90 * 24 * 60 * 60;
+/// Default maximum time a record may be ahead of server time in seconds
+const DEFAULT_RECORD_MAX_FUTURE_SECONDS: i64 = 5 * 60;
+/// Default time between consecutive records considered a gap in seconds
+const DEFAULT_GAP_RECOVERY_THRESHOLD_SECONDS: i64 = 10 * 60;
+/// Default GPS distance driven between odometer reconciliations in meters
+const DEFAULT_ODOMETER_RECONCILIATION_DISTANCE_METERS: f64 = 50_000.0;
+/// Default maximum distance the odometer may advance per window in meters
+const DEFAULT_ODOMETER_MAX_DELTA_METERS: u64 = 5_000;
+/// Default length of the odometer delta window in seconds
+const DEFAULT_ODOMETER_DELTA_WINDOW_SECONDS: i64 = 60;
+/// Default minimum number of satellites of a valid location
+const DEFAULT_LOCATION_MIN_SATELLITES: u8 = 3;
+/// Default maximum plausible speed in km/h
+const DEFAULT_LOCATION_MAX_SPEED_KMH: f64 = 200.0;
+/// Default maximum number of events in a batch
+const DEFAULT_EVENT_BATCH_SIZE: usize = 100;
+/// Default maximum time between retries of the caches of a device in seconds
+const DEFAULT_CACHE_RETRY_MAX_BACKOFF_SECONDS: u64 = 60 * 60;
+/// Default number of failed attempts to send a cached event before it's dead-lettered
+const DEFAULT_CACHE_MAX_RETRY_ATTEMPTS: u32 = 100;
+
+static CONFIG_FILE: OnceLock<RwLock<ConfigFile>> = OnceLock::new();
+static CONFIG: OnceLock<RwLock<Arc<Config>>> = OnceLock::new();
+
+/// Error loading the configuration
+#[derive(Debug)]
+pub enum ConfigError {
+    /// File couldn't be read
+    Io(std::io::Error),
+    /// File isn't valid TOML
+    Parse(toml::de::Error),
+    /// Value of a key isn't a string, number or boolean
+    UnsupportedValue(String),
+    /// Required value isn't set
+    Missing(String),
+    /// Value can't be parsed to the type of the setting
+    InvalidValue { key: String, value: String },
+    /// Value is parsed but describes an invalid setting
+    InvalidSetting { key: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "Failed to read configuration file: {}", error),
+            ConfigError::Parse(error) => {
+                write!(f, "Failed to parse configuration file: {}", error)
+            }
+            ConfigError::UnsupportedValue(key) => {
+                write!(f, "Value of [{}] must be a string, number or boolean", key)
+            }
+            ConfigError::Missing(key) => write!(f, "{} must be set", key),
+            ConfigError::InvalidValue { key, value } => {
+                write!(f, "Invalid value [{}] of {}", value, key)
+            }
+            ConfigError::InvalidSetting { key, reason } => write!(f, "Invalid {}: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Values loaded from a configuration file
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigFile {
+    /// Path of the configuration file, if one is used
+    path: Option<PathBuf>,
+    /// Values of the configuration file by environment variable name
+    values: HashMap<String, String>,
+}
+
+impl ConfigFile {
+    /// Loads the values of a TOML file
+    ///
+    /// # Arguments
+    /// * `path` - Path of the configuration file
+    pub fn load(path: &Path) -> Result<ConfigFile, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let mut config_file = ConfigFile::parse(&contents)?;
+        config_file.path = Some(path.to_path_buf());
+
+        return Ok(config_file);
+    }
+
+    /// Parses the values of the contents of a TOML file
+    ///
+    /// # Arguments
+    /// * `contents` - Contents of the configuration file
+    pub fn parse(contents: &str) -> Result<ConfigFile, ConfigError> {
+        let table = contents
+            .parse::<toml::Table>()
+            .map_err(ConfigError::Parse)?;
+        let mut values = HashMap::new();
+        for (key, value) in table {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                _ => return Err(ConfigError::UnsupportedValue(key)),
+            };
+            values.insert(key, value);
+        }
+
+        return Ok(ConfigFile { path: None, values });
+    }
+
+    /// Gets the value of a key from the configuration file
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name of the value
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Gets the keys whose values differ from another configuration file
+    ///
+    /// # Arguments
+    /// * `other` - Configuration file to compare to
+    pub fn get_changed_keys(&self, other: &ConfigFile) -> Vec<String> {
+        let keys = self
+            .values
+            .keys()
+            .chain(other.values.keys())
+            .map(String::as_str)
+            .collect::<BTreeSet<&str>>();
+
+        return keys
+            .into_iter()
+            .filter(|key| self.get(key) != other.get(key))
+            .map(str::to_string)
+            .collect();
+    }
+
+    /// Reads a value from the environment, falling back to the configuration file
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name of the value
+    fn read(&self, key: &str) -> Option<String> {
+        let value = std::env::var(key)
+            .ok()
+            .or_else(|| self.get(key).map(str::to_string))?;
+
+        return (!value.trim().is_empty()).then_some(value);
+    }
+
+    /// Reads and parses an optional value
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name of the value
+    fn optional<T: FromStr>(&self, key: &str) -> Result<Option<T>, ConfigError> {
+        let Some(value) = self.read(key) else {
+            return Ok(None);
+        };
+
+        return match value.trim().parse() {
+            Ok(parsed) => Ok(Some(parsed)),
+            Err(_) => Err(ConfigError::InvalidValue {
+                key: key.to_string(),
+                value,
+            }),
+        };
+    }
+
+    /// Reads and parses a value, falling back to a default
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name of the value
+    /// * `default` - Value used if the key is not set
+    fn or<T: FromStr>(&self, key: &str, default: T) -> Result<T, ConfigError> {
+        return Ok(self.optional(key)?.unwrap_or(default));
+    }
+
+    /// Reads and parses a required value
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name of the value
+    fn required<T: FromStr>(&self, key: &str) -> Result<T, ConfigError> {
+        return self
+            .optional(key)?
+            .ok_or(ConfigError::Missing(key.to_string()));
+    }
+
+    /// Reads a duration in seconds, falling back to a default
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name of the value
+    /// * `default_seconds` - Seconds used if the key is not set
+    fn seconds(&self, key: &str, default_seconds: u64) -> Result<Duration, ConfigError> {
+        return Ok(Duration::from_secs(self.or(key, default_seconds)?));
+    }
+
+    /// Reads a value with a parser of the setting
+    ///
+    /// # Arguments
+    /// * `key` - Environment variable name of the value
+    /// * `parse` - Function parsing the value of the setting
+    fn parsed<T>(
+        &self,
+        key: &str,
+        parse: fn(&str) -> Result<T, String>,
+    ) -> Result<Option<T>, ConfigError> {
+        let Some(value) = self.read(key) else {
+            return Ok(None);
+        };
+
+        return parse(&value)
+            .map(Some)
+            .map_err(|reason| ConfigError::InvalidSetting {
+                key: key.to_string(),
+                reason,
+            });
+    }
+}
+
+/// Typed configuration of the receiver
+///
+/// Only the [Tunables] are replaced when the configuration is reloaded. The other settings are read on startup, so
+/// changing them requires a restart.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Directory of the caches and raw frame captures
+    pub base_file_path: String,
+    /// Whether raw frame captures are written to [Config::base_file_path]
+    pub write_to_file: bool,
+    /// Minimum free space in megabytes required in the cache directory on startup
+    pub min_cache_free_space_mb: u64,
+    /// Days raw frame captures are kept, 0 keeps them indefinitely
+    pub raw_captures_retention_days: u32,
+    /// File the processing modes of the devices are persisted to
+    pub processing_state_file: Option<PathBuf>,
+    /// File replacing or adding to the built-in IO mappings
+    pub io_mappings_file: Option<String>,
+    /// Interval of retrying the caches of devices in the background, zero disables it
+    pub cache_retry_interval: Duration,
+    /// Named actions controlling the digital outputs of devices
+    pub device_actions: BTreeMap<String, DeviceAction>,
+    /// Pre-shared tokens of devices by IMEI, if device authentication is enabled
+    pub device_auth_tokens: Option<HashMap<String, String>>,
+    /// Window in seconds for detecting spoofed IMEIs, if the detection is enabled
+    pub spoofing_detection_window_seconds: Option<u32>,
+    /// Whether the connection of the newer source of a suspected spoofed IMEI is closed
+    pub quarantine_suspected_spoofing: bool,
+    /// IMEIs of synthetic devices
+    pub synthetic_imeis: Vec<String>,
+    /// Base URL of the sandbox API of synthetic devices, set if there are synthetic devices
+    pub synthetic_api_base_url: Option<String>,
+    /// Thresholds of load shedding
+    pub load_shedding: LoadSheddingThresholds,
+    /// Settings of the Vehicle Management Service API
+    pub api: ApiConfig,
+    /// Settings of the listeners
+    pub listeners: ListenerConfig,
+    /// Settings of logging, traces and reports
+    pub monitoring: MonitoringConfig,
+    /// Settings applied when the configuration is reloaded
+    pub tunables: Tunables,
+}
+
+/// Settings of the Vehicle Management Service API
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    /// Base URL of the API
+    pub base_url: String,
+    /// API key of the API
+    pub api_key: String,
+    /// Number of worker threads of a dedicated runtime for API requests
+    pub runtime_worker_threads: Option<usize>,
+    /// Timeouts of the HTTP client
+    pub client: ApiClientSettings,
+    /// Number of consecutive failed requests opening a circuit breaker, `None` disables the circuit breakers
+    pub circuit_breaker_failure_threshold: Option<u32>,
+    /// Time an open circuit breaker fails requests right away
+    pub circuit_breaker_open: Duration,
+    /// Routes of specific trucks and devices to alternate APIs
+    pub routing_overrides: Vec<RoutingOverride>,
+    /// Average latency at which the API is considered degraded
+    pub degraded_latency: Duration,
+    /// Time in seconds truck IDs are cached
+    pub truck_cache_ttl_seconds: i64,
+    /// Time in seconds VINs without a truck are cached
+    pub truck_cache_negative_ttl_seconds: i64,
+    /// Maximum number of cached VINs
+    pub truck_cache_max_entries: usize,
+    /// Whether all public trucks are cached before accepting connections
+    pub truck_cache_warmup: bool,
+    /// Interval of refreshing the truck IDs of connected devices, zero disables it
+    pub truck_id_refresh_interval: Duration,
+}
+
+/// Settings of the listeners
+#[derive(Debug, Clone)]
+pub struct ListenerConfig {
+    /// Address of the UDP listener
+    pub udp_address: Option<String>,
+    /// Address of the TCP listener of FMB1xx devices
+    pub fmb1xx_address: Option<String>,
+    /// Address of the TCP listener of FMB6xx devices
+    pub fmb6xx_address: Option<String>,
+    /// Address of the admin HTTP server
+    pub admin_server_address: Option<String>,
+    /// Options of device sockets accepted by the default listener, also the fallback of the other listeners
+    pub socket_options: SocketOptions,
+    /// Options of device sockets accepted by the FMB1xx listener
+    pub fmb1xx_socket_options: SocketOptions,
+    /// Options of device sockets accepted by the FMB6xx listener
+    pub fmb6xx_socket_options: SocketOptions,
+    /// Options of the UDP listener socket, of which only the buffer sizes apply
+    pub udp_socket_options: SocketOptions,
+    /// Limits of accepted device connections
+    pub connection_limits: ConnectionLimits,
+    /// Maximum time to wait for the connections to close on shutdown
+    pub shutdown_timeout: Duration,
+}
+
+/// Settings of logging, traces and reports
+#[derive(Debug, Clone)]
+pub struct MonitoringConfig {
+    /// Format of the log lines
+    pub log_format: LogFormat,
+    /// Base URL of the OTLP/HTTP endpoint traces are exported to
+    pub otel_endpoint: Option<String>,
+    /// Name of the service in the exported traces
+    pub otel_service_name: String,
+    /// Interval repetitive log messages are throttled to per key
+    pub log_throttle_interval: Duration,
+    /// Interval of the statistics summary log, zero disables it
+    pub statistics_summary_interval: Duration,
+    /// Expected interval in seconds between records for the daily completeness report
+    pub completeness_expected_interval_seconds: Option<u32>,
+    /// Interval of the latency probe
+    pub latency_probe_interval: Option<Duration>,
+}
+
+/// Settings applied when the configuration is reloaded
+///
+/// Apart from the log level, the tunables are read when a device connects, so reloading them affects new connections
+/// while open connections keep their settings.
+#[derive(Debug, Clone)]
+pub struct Tunables {
+    /// Filters of the logger in `RUST_LOG` syntax
+    pub log_filter: String,
+    /// Whether devices whose IMEI fails checksum validation are denied
+    pub validate_imei_checksums: bool,
+    /// Time a device may take to send its IMEI
+    pub imei_handshake_timeout: Duration,
+    /// Time after which connections not sending anything are closed
+    pub connection_idle_timeout: Option<Duration>,
+    /// Number of retries of writing a frame ACK
+    pub ack_write_retries: u32,
+    /// Maximum number of acknowledged frames dispatched in the background per connection
+    pub ack_pipeline_depth: usize,
+    /// Approximate memory in bytes held per connection by records waiting to be sent
+    pub max_connection_memory_bytes: Option<usize>,
+    /// Number of frames queued across all connections at which frames are rejected
+    pub frame_queue_reject_threshold: Option<usize>,
+    /// Time in milliseconds the driver card must be removed before the removal is reported
+    pub card_remove_threshold: u16,
+    /// Order the records of a frame are handled in
+    pub record_ordering: RecordOrdering,
+    /// Timestamp offsets in seconds of devices sending local time by IMEI
+    pub device_timestamp_offsets: HashMap<String, i64>,
+    /// Whether the timestamp offsets of devices are detected from their first frame
+    pub detect_timestamp_offsets: bool,
+    /// Handling of records with timestamps outside the window relative to server time
+    pub record_timestamp_policy: TimestampPolicy,
+    /// Maximum age of a record in seconds
+    pub record_max_age_seconds: i64,
+    /// Maximum time in seconds a record may be ahead of server time
+    pub record_max_future_seconds: i64,
+    /// Codec 12 command requesting stored records after a gap
+    pub gap_recovery_command: Option<String>,
+    /// Time in seconds between consecutive records considered a gap
+    pub gap_recovery_threshold_seconds: i64,
+    /// Deviation in percent of the odometer from the GPS distance reported as a discrepancy
+    pub odometer_discrepancy_threshold_percent: Option<f64>,
+    /// GPS distance in meters driven between the odometer reconciliations
+    pub odometer_reconciliation_distance_meters: f64,
+    /// Maximum distance in meters the odometer may advance per window
+    pub odometer_max_delta_meters: Option<u64>,
+    /// Length in seconds of the window of [Tunables::odometer_max_delta_meters]
+    pub odometer_delta_window_seconds: i64,
+    /// Handling of locations failing validation
+    pub location_validation: LocationValidationMode,
+    /// Minimum number of satellites of a valid location
+    pub location_min_satellites: u8,
+    /// Maximum HDOP of a valid location
+    pub location_max_hdop: Option<f64>,
+    /// Maximum plausible speed in km/h
+    pub location_max_speed_kmh: f64,
+    /// Whether the GNSS speed is sent for records without a tachograph or CAN bus speed
+    pub gnss_speed_fallback: bool,
+    /// MAC addresses of the BLE sensors by IMEI and slot
+    pub ble_sensor_macs: SlotSettings,
+    /// Trailer compartments measured by the BLE sensors by IMEI and slot
+    pub ble_sensor_compartments: SlotSettings,
+    /// Bounds of event batches, `None` sends events right away
+    pub event_batch_bounds: Option<BatchBounds>,
+    /// Maximum time between retries of the caches of a device
+    pub cache_retry_max_backoff: Duration,
+    /// Number of failed attempts to send a cached event before it's dead-lettered, `None` retries it forever
+    pub cache_max_retry_attempts: Option<u32>,
+}
+
+impl Config {
+    /// Reads and validates the configuration from the environment and a configuration file
+    ///
+    /// # Arguments
+    /// * `file` - Values of the configuration file
+    pub fn read(file: &ConfigFile) -> Result<Config, ConfigError> {
+        let synthetic_imeis = file
+            .read(SYNTHETIC_IMEIS_ENV_KEY)
+            .map(|imeis| {
+                imeis
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|imei| !imei.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+        // Data of synthetic devices must never be sent to production due to a missing sandbox API
+        let synthetic_api_base_url = match synthetic_imeis.is_empty() {
+            true => None,
+            false => Some(file.required(SYNTHETIC_API_BASE_URL_ENV_KEY)?),
+        };
+
+        return Ok(Config {
+            base_file_path: file.required(BASE_FILE_PATH_ENV_KEY)?,
+            write_to_file: file.required(WRITE_TO_FILE_ENV_KEY)?,
+            min_cache_free_space_mb: file.or(
+                MIN_CACHE_FREE_SPACE_MB_ENV_KEY,
+                DEFAULT_MIN_CACHE_FREE_SPACE_MB,
+            )?,
+            raw_captures_retention_days: file.or(
+                RAW_CAPTURES_RETENTION_DAYS_ENV_KEY,
+                DEFAULT_RAW_CAPTURES_RETENTION_DAYS,
+            )?,
+            processing_state_file: file.optional(PROCESSING_STATE_FILE_ENV_KEY)?,
+            io_mappings_file: file.optional(IO_MAPPINGS_FILE_ENV_KEY)?,
+            cache_retry_interval: file.seconds(
+                CACHE_RETRY_INTERVAL_SECONDS_ENV_KEY,
+                DEFAULT_CACHE_RETRY_INTERVAL_SECONDS,
+            )?,
+            device_actions: file
+                .parsed(DEVICE_ACTIONS_ENV_KEY, parse_device_actions)?
+                .unwrap_or_default(),
+            device_auth_tokens: file
+                .parsed(DEVICE_AUTH_TOKENS_ENV_KEY, parse_device_auth_tokens)?,
+            spoofing_detection_window_seconds: file
+                .optional::<u32>(SPOOFING_DETECTION_WINDOW_SECONDS_ENV_KEY)?
+                .filter(|window| *window > 0),
+            quarantine_suspected_spoofing: file.or(QUARANTINE_SUSPECTED_SPOOFING_ENV_KEY, false)?,
+            synthetic_imeis,
+            synthetic_api_base_url,
+            load_shedding: LoadSheddingThresholds {
+                queue_depth: file.optional(LOAD_SHEDDING_QUEUE_THRESHOLD_ENV_KEY)?,
+                cpu_load: file.optional(LOAD_SHEDDING_CPU_THRESHOLD_ENV_KEY)?,
+            },
+            api: ApiConfig::read(file)?,
+            listeners: ListenerConfig::read(file)?,
+            monitoring: MonitoringConfig::read(file)?,
+            tunables: Tunables::read(file)?,
+        });
+    }
+}
+
+impl ApiConfig {
+    /// Reads the settings of the API
+    ///
+    /// # Arguments
+    /// * `file` - Values of the configuration file
+    fn read(file: &ConfigFile) -> Result<ApiConfig, ConfigError> {
+        return Ok(ApiConfig {
+            base_url: file.required(API_BASE_URL_ENV_KEY)?,
+            api_key: file.required(VEHICLE_MANAGEMENT_SERVICE_API_KEY_ENV_KEY)?,
+            runtime_worker_threads: file.optional(API_RUNTIME_WORKER_THREADS_ENV_KEY)?,
+            client: ApiClientSettings {
+                connect_timeout: file.seconds(
+                    API_CONNECT_TIMEOUT_SECONDS_ENV_KEY,
+                    DEFAULT_API_CONNECT_TIMEOUT_SECONDS,
+                )?,
+                read_timeout: file.seconds(
+                    API_READ_TIMEOUT_SECONDS_ENV_KEY,
+                    DEFAULT_API_READ_TIMEOUT_SECONDS,
+                )?,
+                pool_idle_timeout: file.seconds(
+                    API_POOL_IDLE_TIMEOUT_SECONDS_ENV_KEY,
+                    DEFAULT_API_POOL_IDLE_TIMEOUT_SECONDS,
+                )?,
+                pool_max_idle_per_host: file.or(
+                    API_POOL_MAX_IDLE_PER_HOST_ENV_KEY,
+                    DEFAULT_API_POOL_MAX_IDLE_PER_HOST,
+                )?,
+            },
+            circuit_breaker_failure_threshold: Some(file.or(
+                API_CIRCUIT_BREAKER_FAILURE_THRESHOLD_ENV_KEY,
+                DEFAULT_API_CIRCUIT_BREAKER_FAILURE_THRESHOLD,
+            )?)
+            .filter(|failure_threshold| *failure_threshold > 0),
+            circuit_breaker_open: file.seconds(
+                API_CIRCUIT_BREAKER_OPEN_SECONDS_ENV_KEY,
+                DEFAULT_API_CIRCUIT_BREAKER_OPEN_SECONDS,
+            )?,
+            routing_overrides: file
+                .parsed(API_ROUTING_OVERRIDES_ENV_KEY, parse_routing_overrides)?
+                .unwrap_or_default(),
+            degraded_latency: Duration::from_millis(file.or(
+                EVENT_BATCH_DEGRADED_LATENCY_MILLIS_ENV_KEY,
+                DEFAULT_EVENT_BATCH_DEGRADED_LATENCY_MILLIS,
+            )?),
+            truck_cache_ttl_seconds: file.or(
+                TRUCK_CACHE_TTL_SECONDS_ENV_KEY,
+                DEFAULT_TRUCK_CACHE_TTL_SECONDS,
+            )?,
+            truck_cache_negative_ttl_seconds: file.or(
+                TRUCK_CACHE_NEGATIVE_TTL_SECONDS_ENV_KEY,
+                DEFAULT_TRUCK_CACHE_NEGATIVE_TTL_SECONDS,
+            )?,
+            truck_cache_max_entries: file.or(
+                TRUCK_CACHE_MAX_ENTRIES_ENV_KEY,
+                DEFAULT_TRUCK_CACHE_MAX_ENTRIES,
+            )?,
+            truck_cache_warmup: file.or(TRUCK_CACHE_WARMUP_ENV_KEY, false)?,
+            truck_id_refresh_interval: file.seconds(
+                TRUCK_ID_REFRESH_INTERVAL_SECONDS_ENV_KEY,
+                DEFAULT_TRUCK_ID_REFRESH_INTERVAL_SECONDS,
+            )?,
+        });
+    }
+}
+
+impl ListenerConfig {
+    /// Reads the settings of the listeners
+    ///
+    /// # Arguments
+    /// * `file` - Values of the configuration file
+    fn read(file: &ConfigFile) -> Result<ListenerConfig, ConfigError> {
+        let socket_options =
+            ListenerConfig::read_socket_options(file, None, SocketOptions::default())?;
+
+        return Ok(ListenerConfig {
+            udp_address: file.optional(UDP_LISTENER_ADDRESS_ENV_KEY)?,
+            fmb1xx_address: file.optional(FMB1XX_LISTENER_ADDRESS_ENV_KEY)?,
+            fmb6xx_address: file.optional(FMB6XX_LISTENER_ADDRESS_ENV_KEY)?,
+            admin_server_address: file.optional(ADMIN_SERVER_ADDRESS_ENV_KEY)?,
+            socket_options,
+            fmb1xx_socket_options: ListenerConfig::read_socket_options(
+                file,
+                Some(FMB1XX_SOCKET_OPTIONS_PREFIX),
+                socket_options,
+            )?,
+            fmb6xx_socket_options: ListenerConfig::read_socket_options(
+                file,
+                Some(FMB6XX_SOCKET_OPTIONS_PREFIX),
+                socket_options,
+            )?,
+            udp_socket_options: SocketOptions {
+                recv_buffer_size: file
+                    .optional(UDP_RECV_BUFFER_SIZE_ENV_KEY)?
+                    .or(socket_options.recv_buffer_size),
+                send_buffer_size: file
+                    .optional(UDP_SEND_BUFFER_SIZE_ENV_KEY)?
+                    .or(socket_options.send_buffer_size),
+                ..SocketOptions::default()
+            },
+            connection_limits: ConnectionLimits {
+                max_connections: file.optional(MAX_CONNECTIONS_ENV_KEY)?,
+                max_connections_per_ip: file.optional(MAX_CONNECTIONS_PER_IP_ENV_KEY)?,
+                max_accepts_per_second: file
+                    .optional::<u32>(MAX_ACCEPTS_PER_SECOND_ENV_KEY)?
+                    .filter(|max_accepts_per_second| *max_accepts_per_second > 0),
+            },
+            shutdown_timeout: file.seconds(
+                SHUTDOWN_TIMEOUT_SECONDS_ENV_KEY,
+                DEFAULT_SHUTDOWN_TIMEOUT_SECONDS,
+            )?,
+        });
+    }
+
+    /// Reads the socket options of a TCP listener
+    ///
+    /// # Arguments
+    /// * `file` - Values of the configuration file
+    /// * `prefix` - Prefix of the keys of the listener, e.g. `FMB1XX` for `FMB1XX_TCP_NODELAY`, or `None` for the global keys
+    /// * `fallback` - Options used for the keys the listener doesn't set
+    fn read_socket_options(
+        file: &ConfigFile,
+        prefix: Option<&str>,
+        fallback: SocketOptions,
+    ) -> Result<SocketOptions, ConfigError> {
+        let key = |key: &str| match prefix {
+            Some(prefix) => format!("{}_{}", prefix, key),
+            None => key.to_string(),
+        };
+
+        return Ok(SocketOptions {
+            nodelay: file
+                .optional(&key(TCP_NODELAY_ENV_KEY))?
+                .or(fallback.nodelay),
+            keepalive_time: file
+                .optional(&key(TCP_KEEPALIVE_SECONDS_ENV_KEY))?
+                .map(Duration::from_secs)
+                .or(fallback.keepalive_time),
+            keepalive_interval: file
+                .optional(&key(TCP_KEEPALIVE_INTERVAL_SECONDS_ENV_KEY))?
+                .map(Duration::from_secs)
+                .or(fallback.keepalive_interval),
+            recv_buffer_size: file
+                .optional(&key(TCP_RECV_BUFFER_SIZE_ENV_KEY))?
+                .or(fallback.recv_buffer_size),
+            send_buffer_size: file
+                .optional(&key(TCP_SEND_BUFFER_SIZE_ENV_KEY))?
+                .or(fallback.send_buffer_size),
+        });
+    }
+
+    /// Gets the options of device sockets accepted by the listener of a device family
+    ///
+    /// # Arguments
+    /// * `device_family` - Family of the devices connecting to the listener
+    pub fn get_socket_options(&self, device_family: DeviceFamily) -> SocketOptions {
+        return match device_family {
+            DeviceFamily::Fmc => self.socket_options,
+            DeviceFamily::Fmb1xx => self.fmb1xx_socket_options,
+            DeviceFamily::Fmb6xx => self.fmb6xx_socket_options,
+        };
+    }
+}
+
+impl MonitoringConfig {
+    /// Reads the settings of logging, traces and reports
+    ///
+    /// # Arguments
+    /// * `file` - Values of the configuration file
+    fn read(file: &ConfigFile) -> Result<MonitoringConfig, ConfigError> {
+        return Ok(MonitoringConfig {
+            log_format: file.or(LOG_FORMAT_ENV_KEY, LogFormat::default())?,
+            otel_endpoint: file.optional(OTEL_EXPORTER_OTLP_ENDPOINT_ENV_KEY)?,
+            otel_service_name: file.or(
+                OTEL_SERVICE_NAME_ENV_KEY,
+                telemetry::DEFAULT_SERVICE_NAME.to_string(),
+            )?,
+            log_throttle_interval: file.seconds(
+                LOG_THROTTLE_INTERVAL_SECONDS_ENV_KEY,
+                DEFAULT_LOG_THROTTLE_INTERVAL_SECONDS,
+            )?,
+            statistics_summary_interval: file.seconds(
+                STATISTICS_SUMMARY_INTERVAL_SECONDS_ENV_KEY,
+                DEFAULT_STATISTICS_SUMMARY_INTERVAL_SECONDS,
+            )?,
+            completeness_expected_interval_seconds: file
+                .optional::<u32>(COMPLETENESS_EXPECTED_INTERVAL_SECONDS_ENV_KEY)?
+                .filter(|interval| *interval > 0),
+            latency_probe_interval: file
+                .optional::<u64>(LATENCY_PROBE_INTERVAL_SECONDS_ENV_KEY)?
+                .filter(|interval| *interval > 0)
+                .map(Duration::from_secs),
+        });
+    }
+}
+
+impl Tunables {
+    /// Reads the settings applied when the configuration is reloaded
+    ///
+    /// # Arguments
+    /// * `file` - Values of the configuration file
+    fn read(file: &ConfigFile) -> Result<Tunables, ConfigError> {
+        let event_batch_bounds = match file
+            .optional::<u64>(EVENT_BATCH_WINDOW_SECONDS_ENV_KEY)?
+            .filter(|window| *window > 0)
+        {
+            Some(window) => {
+                let max_size = file.or(EVENT_BATCH_SIZE_ENV_KEY, DEFAULT_EVENT_BATCH_SIZE)?;
+                let degraded_window = file
+                    .or(EVENT_BATCH_DEGRADED_WINDOW_SECONDS_ENV_KEY, window)?
+                    .max(window);
+                let degraded_max_size = file
+                    .or(EVENT_BATCH_DEGRADED_SIZE_ENV_KEY, max_size)?
+                    .max(max_size);
+                Some(BatchBounds {
+                    window: Duration::from_secs(window),
+                    max_size,
+                    degraded_window: Duration::from_secs(degraded_window),
+                    degraded_max_size,
+                })
+            }
+            None => None,
+        };
+
+        return Ok(Tunables {
+            log_filter: file.or(RUST_LOG_ENV_KEY, String::new())?,
+            validate_imei_checksums: file.or(VALIDATE_IMEI_CHECKSUMS_ENV_KEY, false)?,
+            imei_handshake_timeout: file.seconds(
+                IMEI_HANDSHAKE_TIMEOUT_SECONDS_ENV_KEY,
+                DEFAULT_IMEI_HANDSHAKE_TIMEOUT_SECONDS,
+            )?,
+            connection_idle_timeout: file
+                .optional::<u64>(CONNECTION_IDLE_TIMEOUT_SECONDS_ENV_KEY)?
+                .filter(|seconds| *seconds > 0)
+                .map(Duration::from_secs),
+            ack_write_retries: file.or(ACK_WRITE_RETRIES_ENV_KEY, DEFAULT_ACK_WRITE_RETRIES)?,
+            ack_pipeline_depth: file.or(ACK_PIPELINE_DEPTH_ENV_KEY, 0)?,
+            max_connection_memory_bytes: file.optional(MAX_CONNECTION_MEMORY_BYTES_ENV_KEY)?,
+            frame_queue_reject_threshold: file
+                .optional::<usize>(FRAME_QUEUE_REJECT_THRESHOLD_ENV_KEY)?
+                .filter(|threshold| *threshold > 0),
+            card_remove_threshold: file
+                .or(CARD_REMOVE_THRESHOLD_ENV_KEY, DEFAULT_CARD_REMOVE_THRESHOLD)?,
+            record_ordering: file.or(RECORD_ORDERING_ENV_KEY, RecordOrdering::default())?,
+            device_timestamp_offsets: file
+                .parsed(DEVICE_TIMESTAMP_OFFSETS_ENV_KEY, parse_timestamp_offsets)?
+                .unwrap_or_default(),
+            detect_timestamp_offsets: file.or(DETECT_TIMESTAMP_OFFSETS_ENV_KEY, false)?,
+            record_timestamp_policy: file
+                .or(RECORD_TIMESTAMP_POLICY_ENV_KEY, TimestampPolicy::default())?,
+            record_max_age_seconds: file.or(
+                RECORD_MAX_AGE_SECONDS_ENV_KEY,
+                DEFAULT_RECORD_MAX_AGE_SECONDS,
+            )?,
+            record_max_future_seconds: file.or(
+                RECORD_MAX_FUTURE_SECONDS_ENV_KEY,
+                DEFAULT_RECORD_MAX_FUTURE_SECONDS,
+            )?,
+            gap_recovery_command: file.optional(GAP_RECOVERY_COMMAND_ENV_KEY)?,
+            gap_recovery_threshold_seconds: file.or(
+                GAP_RECOVERY_THRESHOLD_SECONDS_ENV_KEY,
+                DEFAULT_GAP_RECOVERY_THRESHOLD_SECONDS,
+            )?,
+            odometer_discrepancy_threshold_percent: file
+                .optional(ODOMETER_DISCREPANCY_THRESHOLD_PERCENT_ENV_KEY)?,
+            odometer_reconciliation_distance_meters: file.or(
+                ODOMETER_RECONCILIATION_DISTANCE_METERS_ENV_KEY,
+                DEFAULT_ODOMETER_RECONCILIATION_DISTANCE_METERS,
+            )?,
+            odometer_max_delta_meters: Some(file.or(
+                ODOMETER_MAX_DELTA_METERS_ENV_KEY,
+                DEFAULT_ODOMETER_MAX_DELTA_METERS,
+            )?)
+            .filter(|max_delta_meters| *max_delta_meters > 0),
+            odometer_delta_window_seconds: file.or(
+                ODOMETER_DELTA_WINDOW_SECONDS_ENV_KEY,
+                DEFAULT_ODOMETER_DELTA_WINDOW_SECONDS,
+            )?,
+            location_validation: file.or(
+                LOCATION_VALIDATION_ENV_KEY,
+                LocationValidationMode::default(),
+            )?,
+            location_min_satellites: file.or(
+                LOCATION_MIN_SATELLITES_ENV_KEY,
+                DEFAULT_LOCATION_MIN_SATELLITES,
+            )?,
+            location_max_hdop: file.optional(LOCATION_MAX_HDOP_ENV_KEY)?,
+            location_max_speed_kmh: file.or(
+                LOCATION_MAX_SPEED_KMH_ENV_KEY,
+                DEFAULT_LOCATION_MAX_SPEED_KMH,
+            )?,
+            gnss_speed_fallback: file.or(GNSS_SPEED_FALLBACK_ENV_KEY, false)?,
+            ble_sensor_macs: file
+                .parsed(BLE_SENSOR_MACS_ENV_KEY, parse_ble_sensor_macs)?
+                .unwrap_or_default(),
+            ble_sensor_compartments: file
+                .parsed(
+                    BLE_SENSOR_COMPARTMENTS_ENV_KEY,
+                    parse_ble_sensor_compartments,
+                )?
+                .unwrap_or_default(),
+            event_batch_bounds,
+            cache_retry_max_backoff: file.seconds(
+                CACHE_RETRY_MAX_BACKOFF_SECONDS_ENV_KEY,
+                DEFAULT_CACHE_RETRY_MAX_BACKOFF_SECONDS,
+            )?,
+            cache_max_retry_attempts: Some(file.or(
+                CACHE_MAX_RETRY_ATTEMPTS_ENV_KEY,
+                DEFAULT_CACHE_MAX_RETRY_ATTEMPTS,
+            )?)
+            .filter(|max_attempts| *max_attempts > 0),
+        });
+    }
+}
+
+/// Loads the file given in `CONFIG_FILE` and initializes the global configuration
+///
+/// Without `CONFIG_FILE`, only environment variables are used.
+pub fn init_config() -> Result<(), ConfigError> {
+    let config_file = match std::env::var(CONFIG_FILE_ENV_KEY) {
+        Ok(path) => ConfigFile::load(Path::new(&path))?,
+        Err(_) => ConfigFile::default(),
+    };
+    let config = Config::read(&config_file)?;
+    let _ = CONFIG_FILE.set(RwLock::new(config_file));
+    let _ = CONFIG.set(RwLock::new(Arc::new(config)));
+
+    return Ok(());
+}
+
+/// Gets the global configuration
+///
+/// Panics if the configuration is not initialized.
+#[cfg(not(test))]
+pub fn get_config() -> Arc<Config> {
+    return CONFIG
+        .get()
+        .expect("Configuration is not initialized")
+        .read()
+        .unwrap()
+        .clone();
+}
+
+/// Gets the global configuration, initialized with the required values for tests
+#[cfg(test)]
+pub fn get_config() -> Arc<Config> {
+    return CONFIG
+        .get_or_init(|| {
+            let config_file = ConfigFile::parse(
+                r#"
+                    API_BASE_URL = "http://localhost"
+                    VEHICLE_MANAGEMENT_SERVICE_API_KEY = "API_KEY"
+                    BASE_FILE_PATH = "."
+                    WRITE_TO_FILE = false
+                "#,
+            )
+            .unwrap();
+            RwLock::new(Arc::new(Config::read(&config_file).unwrap()))
+        })
+        .read()
+        .unwrap()
+        .clone();
+}
+
+/// Updates the global configuration
+///
+/// # Arguments
+/// * `update` - Function updating the configuration
+#[cfg(test)]
+pub fn update_config(update: impl FnOnce(&mut Config)) {
+    let mut config = (*get_config()).clone();
+    update(&mut config);
+    *CONFIG.get().unwrap().write().unwrap() = Arc::new(config);
+}
+
+/// Reloads the configuration file and applies the [Tunables]
+///
+/// An invalid configuration file is logged and the previous configuration is kept. Changes to settings other than the
+/// tunables are logged as requiring a restart.
+pub fn reload_config() {
+    let (Some(config_file), Some(config)) = (CONFIG_FILE.get(), CONFIG.get()) else {
+        return;
+    };
+    let Some(path) = config_file.read().unwrap().path.clone() else {
+        info!(
+            "No configuration file to reload, {} is not set",
+            CONFIG_FILE_ENV_KEY
+        );
+        return;
+    };
+    let reloaded = ConfigFile::load(&path).and_then(|reloaded_config_file| {
+        let reloaded_config = Config::read(&reloaded_config_file)?;
+        return Ok((reloaded_config_file, reloaded_config));
+    });
+    match reloaded {
+        Ok((reloaded_config_file, reloaded_config)) => {
+            let changed_keys = config_file
+                .read()
+                .unwrap()
+                .get_changed_keys(&reloaded_config_file);
+            let restart_keys = changed_keys
+                .iter()
+                .filter(|key| !TUNABLE_KEYS.contains(&key.as_str()))
+                .cloned()
+                .collect::<Vec<String>>();
+            *config_file.write().unwrap() = reloaded_config_file;
+            let mut applied_config = (**config.read().unwrap()).clone();
+            applied_config.tunables = reloaded_config.tunables;
+            *config.write().unwrap() = Arc::new(applied_config);
+            logger::reload_logger();
+            info!(
+                "Reloaded configuration file [{}], changed keys: [{}]",
+                path.display(),
+                changed_keys.join(", ")
+            );
+            if !restart_keys.is_empty() {
+                warn!(
+                    "Changes to [{}] take effect after restarting the receiver",
+                    restart_keys.join(", ")
+                );
+            }
+        }
+        Err(error) => error!(
+            "Failed to reload configuration file [{}], keeping the previous configuration: {}",
+            path.display(),
+            error
+        ),
+    }
+}
+
+/// Starts reloading the configuration on `SIGHUP`
+pub async fn start_reload_on_sighup() {
+    let mut hangups = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(error) => {
+            error!(
+                "Failed to listen for SIGHUP, configuration reload is disabled: {}",
+                error
+            );
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        reload_config();
+    }
+}
+
+/// Prints the default configuration to stdout
+pub fn print_default_config() {
+    print!("{}", DEFAULT_CONFIG);
+}
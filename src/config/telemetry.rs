@@ -0,0 +1,117 @@
+//! OpenTelemetry traces of frames and API requests
+//!
+//! When an OTLP endpoint is configured, the `frame` spans of the handled frames and the `api_request` spans of the
+//! Vehicle Management Service API requests sent while handling them are exported as OTLP traces over HTTP. Each frame
+//! starts a new trace, and the trace context is passed to the API in the `traceparent` header, so that the latency
+//! from receiving a frame to e.g. creating a truck location can be followed end-to-end on the backend side.
+use opentelemetry::{
+    global,
+    propagation::Injector,
+    trace::{TraceError, TracerProvider as _},
+    KeyValue,
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Tracer, TracerProvider},
+    Resource,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{filter::filter_fn, registry::LookupSpan, Layer};
+
+use super::get_config;
+
+/// Default name of the service in the exported traces
+pub const DEFAULT_SERVICE_NAME: &str = "vp-kuljetus-vehicle-data-receiver";
+/// Path of the traces appended to the OTLP endpoint
+const OTLP_TRACES_PATH: &str = "/v1/traces";
+/// Name of the spans of the device connections
+const CONNECTION_SPAN_NAME: &str = "connection";
+
+/// Builds the layer exporting spans as OTLP traces
+///
+/// Must be called within the Tokio runtime, which the spans are exported on in batches.
+///
+/// # Returns
+/// * The layer, or `None` if no OTLP endpoint is configured in `OTEL_EXPORTER_OTLP_ENDPOINT`
+pub fn build_telemetry_layer<S>() -> Result<Option<impl Layer<S>>, TraceError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let config = get_config();
+    let Some(endpoint) = config.monitoring.otel_endpoint.as_deref() else {
+        return Ok(None);
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(get_traces_endpoint(endpoint))
+        .build()?;
+    let service_name = config.monitoring.otel_service_name.clone();
+    let tracer_provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            service_name,
+        )]))
+        .build();
+    let tracer = tracer_provider.tracer(DEFAULT_SERVICE_NAME);
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    return Ok(Some(get_telemetry_layer(tracer)));
+}
+
+/// Gets the layer exporting spans with a tracer
+///
+/// Spans of the connections are not exported. Frames are nested in their connections for the logs, but each frame is
+/// traced separately, as a connection may stay open for days.
+///
+/// # Arguments
+/// * `tracer` - Tracer to export the spans with
+pub fn get_telemetry_layer<S>(tracer: Tracer) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    return tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(filter_fn(|metadata| {
+            metadata.name() != CONNECTION_SPAN_NAME
+        }));
+}
+
+/// Gets the URL the traces are exported to
+///
+/// # Arguments
+/// * `endpoint` - Base URL of the OTLP endpoint, e.g. `http://otel-collector:4318`
+fn get_traces_endpoint(endpoint: &str) -> String {
+    return format!("{}{}", endpoint.trim_end_matches('/'), OTLP_TRACES_PATH);
+}
+
+/// Adds the trace context of the current span to the headers of an API request
+///
+/// Nothing is added if traces are not exported.
+///
+/// # Arguments
+/// * `headers` - Headers of the request
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+/// Injects the trace context to the headers of a request
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Limits of accepted device connections
+//!
+//! A misbehaving device reconnecting in a loop could otherwise exhaust the file descriptors of the receiver, so the
+//! total number of open connections, the number of open connections per IP address and the rate of accepted
+//! connections can be limited. Connections exceeding the limits are closed right after they are accepted.
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use log::warn;
+
+use crate::{metrics, utils::log_throttle};
+
+/// Name of the counter describing the number of rejected connections by reason
+pub const REJECTED_CONNECTIONS_METRIC: &str = "receiver_rejected_connections_total";
+
+/// Limits of accepted connections
+///
+/// Limits that are not set are not enforced.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectionLimits {
+    /// Maximum number of open connections
+    pub max_connections: Option<usize>,
+    /// Maximum number of open connections from a single IP address
+    pub max_connections_per_ip: Option<usize>,
+    /// Maximum number of connections accepted per second, also the number of connections accepted in a burst
+    pub max_accepts_per_second: Option<u32>,
+}
+
+/// Reason for rejecting a connection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RejectReason {
+    /// Connections are accepted faster than allowed
+    AcceptRate,
+    /// The maximum number of open connections is reached
+    MaxConnections,
+    /// The maximum number of open connections from the IP address is reached
+    MaxConnectionsPerIp,
+}
+
+impl RejectReason {
+    /// Gets the label of the reason in metrics
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            RejectReason::AcceptRate => "accept_rate",
+            RejectReason::MaxConnections => "max_connections",
+            RejectReason::MaxConnectionsPerIp => "max_connections_per_ip",
+        }
+    }
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::AcceptRate => write!(f, "connections are accepted too fast"),
+            RejectReason::MaxConnections => write!(f, "too many open connections"),
+            RejectReason::MaxConnectionsPerIp => {
+                write!(f, "too many open connections from the IP address")
+            }
+        }
+    }
+}
+
+/// Open connections and accept rate tracked by the [ConnectionLimiter]
+struct ConnectionLimiterState {
+    connections: usize,
+    connections_by_ip: HashMap<IpAddr, usize>,
+    /// Connections that can currently be accepted without exceeding the accept rate
+    accept_tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Enforces [ConnectionLimits] on accepted connections
+pub struct ConnectionLimiter {
+    limits: ConnectionLimits,
+    state: Mutex<ConnectionLimiterState>,
+}
+
+impl ConnectionLimiter {
+    /// Creates a new [ConnectionLimiter]
+    ///
+    /// # Arguments
+    /// * `limits` - Limits to enforce
+    pub fn new(limits: ConnectionLimits) -> Arc<Self> {
+        Arc::new(ConnectionLimiter {
+            limits,
+            state: Mutex::new(ConnectionLimiterState {
+                connections: 0,
+                connections_by_ip: HashMap::new(),
+                accept_tokens: limits.max_accepts_per_second.unwrap_or(0) as f64,
+                refilled_at: Instant::now(),
+            }),
+        })
+    }
+
+    /// Admits an accepted connection if it doesn't exceed the limits
+    ///
+    /// # Arguments
+    /// * `peer_ip` - IP address of the connection
+    /// * `now` - Time the connection was accepted
+    ///
+    /// # Returns
+    /// * Permit holding the connection open until dropped, or the reason for rejecting the connection
+    pub fn try_admit(
+        self: &Arc<Self>,
+        peer_ip: IpAddr,
+        now: Instant,
+    ) -> Result<ConnectionPermit, RejectReason> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(max_accepts_per_second) = self.limits.max_accepts_per_second {
+            let max_accepts_per_second = max_accepts_per_second as f64;
+            let elapsed = now.saturating_duration_since(state.refilled_at);
+            state.accept_tokens = (state.accept_tokens
+                + elapsed.as_secs_f64() * max_accepts_per_second)
+                .min(max_accepts_per_second);
+            state.refilled_at = now;
+            if state.accept_tokens < 1.0 {
+                return Err(RejectReason::AcceptRate);
+            }
+        }
+        if self
+            .limits
+            .max_connections
+            .is_some_and(|max_connections| state.connections >= max_connections)
+        {
+            return Err(RejectReason::MaxConnections);
+        }
+        let ip_connections = state.connections_by_ip.get(&peer_ip).copied().unwrap_or(0);
+        if self
+            .limits
+            .max_connections_per_ip
+            .is_some_and(|max_connections_per_ip| ip_connections >= max_connections_per_ip)
+        {
+            return Err(RejectReason::MaxConnectionsPerIp);
+        }
+        if self.limits.max_accepts_per_second.is_some() {
+            state.accept_tokens -= 1.0;
+        }
+        state.connections += 1;
+        state.connections_by_ip.insert(peer_ip, ip_connections + 1);
+
+        return Ok(ConnectionPermit {
+            limiter: self.clone(),
+            peer_ip,
+        });
+    }
+
+    /// Gets the number of open connections
+    pub fn get_connections_count(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    /// Releases the connection of a dropped permit
+    ///
+    /// # Arguments
+    /// * `peer_ip` - IP address of the connection
+    fn release(&self, peer_ip: IpAddr) {
+        let mut state = self.state.lock().unwrap();
+        state.connections = state.connections.saturating_sub(1);
+        if let Some(ip_connections) = state.connections_by_ip.get_mut(&peer_ip) {
+            *ip_connections -= 1;
+            if *ip_connections == 0 {
+                state.connections_by_ip.remove(&peer_ip);
+            }
+        }
+    }
+}
+
+/// Permit of an admitted connection, counted as open until dropped
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    peer_ip: IpAddr,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.release(self.peer_ip);
+    }
+}
+
+/// Records a rejected connection
+///
+/// Rejections are logged at most once per IP address and reason per throttling interval.
+///
+/// # Arguments
+/// * `peer_ip` - IP address of the connection
+/// * `reason` - Reason for rejecting the connection
+pub fn record_rejected_connection(peer_ip: IpAddr, reason: RejectReason) {
+    metrics::increment_counter(
+        REJECTED_CONNECTIONS_METRIC,
+        &[("reason", reason.as_label())],
+    );
+    if let Some(suppressed) =
+        log_throttle::throttle(&format!("{}:rejected:{}", peer_ip, reason.as_label()))
+    {
+        warn!(
+            "Rejected connection from [{}]: {}{}",
+            peer_ip,
+            reason,
+            log_throttle::describe_suppressed(suppressed)
+        );
+    }
+}
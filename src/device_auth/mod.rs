@@ -0,0 +1,145 @@
+//! Authentication of devices with pre-shared tokens
+//!
+//! Devices are trusted by their IMEI alone, so devices of high-value assets can be configured with a pre-shared token
+//! they must present after the IMEI handshake. The device sends the token as the text of its first Codec 12 message,
+//! e.g. from a scenario configured on the device, and the connection is closed unless it matches the token of the IMEI.
+use std::{collections::HashMap, sync::OnceLock};
+
+use log::warn;
+
+use crate::metrics;
+
+/// Name of the counter describing the number of failed device authentications by IMEI and reason
+pub const DEVICE_AUTH_FAILURES_METRIC: &str = "receiver_device_auth_failures_total";
+
+static AUTHENTICATOR: OnceLock<DeviceAuthenticator> = OnceLock::new();
+
+/// Result of authenticating a device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeviceAuthResult {
+    /// No token is configured for the IMEI, so it's trusted by the IMEI alone
+    NotRequired,
+    /// The presented token matches the token of the IMEI
+    Authenticated,
+    /// The presented token doesn't match the token of the IMEI or no token was presented
+    Rejected,
+}
+
+/// Holds the pre-shared tokens of the devices
+pub struct DeviceAuthenticator {
+    tokens: HashMap<String, String>,
+}
+
+impl DeviceAuthenticator {
+    /// Creates a new [DeviceAuthenticator]
+    ///
+    /// # Arguments
+    /// * `tokens` - Pre-shared tokens keyed by IMEI
+    pub fn new(tokens: HashMap<String, String>) -> Self {
+        DeviceAuthenticator { tokens }
+    }
+
+    /// Checks whether the device must present a token
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    pub fn is_required(&self, imei: &str) -> bool {
+        return self.tokens.contains_key(imei);
+    }
+
+    /// Authenticates a device with the token it presented
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `presented_token` - Token presented by the device, if any
+    pub fn authenticate(&self, imei: &str, presented_token: Option<&str>) -> DeviceAuthResult {
+        let Some(token) = self.tokens.get(imei) else {
+            return DeviceAuthResult::NotRequired;
+        };
+        let Some(presented_token) = presented_token else {
+            warn!(target: imei, "Device didn't present an authentication token");
+            metrics::increment_counter(
+                DEVICE_AUTH_FAILURES_METRIC,
+                &[("imei", imei), ("reason", "missing")],
+            );
+            return DeviceAuthResult::Rejected;
+        };
+        if !constant_time_eq(token.as_bytes(), presented_token.trim().as_bytes()) {
+            warn!(target: imei, "Device presented an invalid authentication token");
+            metrics::increment_counter(
+                DEVICE_AUTH_FAILURES_METRIC,
+                &[("imei", imei), ("reason", "invalid")],
+            );
+            return DeviceAuthResult::Rejected;
+        }
+
+        return DeviceAuthResult::Authenticated;
+    }
+}
+
+/// Compares two byte slices in time depending only on their lengths, so that the token can't be guessed by timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    return a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0;
+}
+
+/// Parses the pre-shared tokens of devices
+///
+/// Devices are separated by commas and consist of the IMEI and the token separated by `=`, e.g. `352093081452251=secret`.
+///
+/// # Arguments
+/// * `tokens` - Tokens to parse
+pub fn parse_device_auth_tokens(tokens: &str) -> Result<HashMap<String, String>, String> {
+    return tokens
+        .split(',')
+        .map(str::trim)
+        .filter(|device_token| !device_token.is_empty())
+        .map(|device_token| {
+            let (imei, token) = device_token
+                .split_once('=')
+                .ok_or(format!("Missing token for [{}]", device_token))?;
+            if imei.is_empty() || token.is_empty() {
+                return Err(format!("Invalid device token [{}]", device_token));
+            }
+            Ok((imei.to_string(), token.to_string()))
+        })
+        .collect();
+}
+
+/// Initializes the device authentication
+///
+/// # Arguments
+/// * `tokens` - Pre-shared tokens keyed by IMEI
+pub fn init_device_auth(tokens: HashMap<String, String>) {
+    if AUTHENTICATOR.set(DeviceAuthenticator::new(tokens)).is_err() {
+        warn!("Device authentication is already initialized");
+    }
+}
+
+/// Checks whether the device must present a token
+///
+/// Tokens are never required unless the device authentication has been initialized.
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+pub fn is_auth_required(imei: &str) -> bool {
+    return AUTHENTICATOR
+        .get()
+        .is_some_and(|authenticator| authenticator.is_required(imei));
+}
+
+/// Authenticates a device with the token it presented
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `presented_token` - Token presented by the device, if any
+pub fn authenticate(imei: &str, presented_token: Option<&str>) -> DeviceAuthResult {
+    let Some(authenticator) = AUTHENTICATOR.get() else {
+        return DeviceAuthResult::NotRequired;
+    };
+
+    return authenticator.authenticate(imei, presented_token);
+}
@@ -0,0 +1,119 @@
+//! Daily statistics of the devices
+//!
+//! Counts the frames, records, parse errors and failed frame ACKs of each device per UTC day, so that the
+//! installations team can audit the health of the trackers. The statistics are kept in memory for the last
+//! [RETENTION_DAYS] days and exported as CSV from the admin server.
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Mutex, OnceLock},
+};
+
+use chrono::{Duration, NaiveDate, Utc};
+
+/// Number of days the statistics are kept, including the current day
+const RETENTION_DAYS: i64 = 31;
+/// Header row of the CSV export
+const CSV_HEADER: &str = "date,imei,frames,records,parse_errors,ack_failures";
+
+static STORE: OnceLock<DeviceStatsStore> = OnceLock::new();
+
+/// Statistics of a device for a single day
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DailyDeviceStats {
+    pub frames: u64,
+    pub records: u64,
+    pub parse_errors: u64,
+    pub ack_failures: u64,
+}
+
+/// Store of the daily statistics by date and IMEI
+#[derive(Default)]
+pub struct DeviceStatsStore {
+    stats: Mutex<BTreeMap<(NaiveDate, String), DailyDeviceStats>>,
+}
+
+impl DeviceStatsStore {
+    /// Updates the statistics of a device for a day
+    ///
+    /// Statistics older than the retention are dropped.
+    ///
+    /// # Arguments
+    /// * `date` - Date to update the statistics of
+    /// * `imei` - IMEI of the device
+    /// * `update` - Function updating the statistics
+    pub fn update(&self, date: NaiveDate, imei: &str, update: impl FnOnce(&mut DailyDeviceStats)) {
+        let mut stats = self.stats.lock().unwrap();
+        let oldest_date = date - Duration::days(RETENTION_DAYS - 1);
+        stats.retain(|(stats_date, _), _| *stats_date >= oldest_date);
+        update(stats.entry((date, imei.to_string())).or_default());
+    }
+
+    /// Exports the statistics as CSV, ordered by date and IMEI
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for ((date, imei), stats) in self.stats.lock().unwrap().iter() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                date,
+                escape_csv_field(imei),
+                stats.frames,
+                stats.records,
+                stats.parse_errors,
+                stats.ack_failures
+            );
+        }
+
+        return csv;
+    }
+}
+
+/// Escapes a CSV field containing separators or quotes
+///
+/// # Arguments
+/// * `field` - Field to escape
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+
+    return field.to_string();
+}
+
+/// Gets the global statistics store
+pub fn get_device_stats() -> &'static DeviceStatsStore {
+    STORE.get_or_init(DeviceStatsStore::default)
+}
+
+/// Records a frame received from a device
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `records_count` - Number of records in the frame
+pub fn record_frame(imei: &str, records_count: usize) {
+    get_device_stats().update(Utc::now().date_naive(), imei, |stats| {
+        stats.frames += 1;
+        stats.records += records_count as u64;
+    });
+}
+
+/// Records a frame of a device that failed to parse
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+pub fn record_parse_error(imei: &str) {
+    get_device_stats().update(Utc::now().date_naive(), imei, |stats| {
+        stats.parse_errors += 1
+    });
+}
+
+/// Records a frame ACK that failed to be written to a device
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+pub fn record_ack_failure(imei: &str) {
+    get_device_stats().update(Utc::now().date_naive(), imei, |stats| {
+        stats.ack_failures += 1
+    });
+}
@@ -0,0 +1,180 @@
+//! Pipeline invariants
+//!
+//! With the `pipeline-invariants` feature enabled, invariants of the processing pipeline are evaluated per frame
+//! and violations are logged and counted, so that bugs in the pipeline surface instead of silently losing data:
+//! * Each record of a frame is either handled (sent or cached) or dropped
+//! * The depth of each cache equals its initial depth plus the items written to it or the items it was last replaced with
+//!
+//! Without the feature, the checks are no-ops.
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use log::error;
+
+use crate::metrics;
+
+/// Name of the counter describing the number of invariant violations by invariant
+pub const INVARIANT_VIOLATIONS_METRIC: &str = "receiver_invariant_violations_total";
+/// Name of the invariant of the records of a frame
+pub const FRAME_RECORDS_INVARIANT: &str = "frame_records";
+/// Name of the invariant of the depth of a cache
+pub const CACHE_DEPTH_INVARIANT: &str = "cache_depth";
+
+static INVARIANTS: OnceLock<PipelineInvariants> = OnceLock::new();
+
+/// Tracks the state needed for evaluating the pipeline invariants
+#[derive(Default)]
+pub struct PipelineInvariants {
+    expected_cache_depths: Mutex<HashMap<String, usize>>,
+}
+
+impl PipelineInvariants {
+    /// Checks that each record of a frame has been either handled or dropped
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `received` - Number of records received in the frame
+    /// * `handled` - Number of records sent or cached
+    /// * `dropped` - Number of records dropped
+    ///
+    /// # Returns
+    /// * Whether the invariant holds
+    pub fn check_frame_records(
+        &self,
+        imei: &str,
+        received: usize,
+        handled: usize,
+        dropped: usize,
+    ) -> bool {
+        if received == handled + dropped {
+            return true;
+        }
+        error!(target: imei,
+            "Invariant violation: received {} records, but handled {} and dropped {}",
+            received,
+            handled,
+            dropped
+        );
+        metrics::increment_counter(
+            INVARIANT_VIOLATIONS_METRIC,
+            &[("invariant", FRAME_RECORDS_INVARIANT)],
+        );
+
+        return false;
+    }
+
+    /// Records an item written to a cache
+    ///
+    /// # Arguments
+    /// * `cache_file_path` - Path of the cache file
+    /// * `existing_depth` - Number of items in the cache before writing
+    pub fn record_cache_write(&self, cache_file_path: &str, existing_depth: usize) {
+        *self
+            .expected_cache_depths
+            .lock()
+            .unwrap()
+            .entry(cache_file_path.to_string())
+            .or_insert(existing_depth) += 1;
+    }
+
+    /// Records the contents of a cache being replaced
+    ///
+    /// # Arguments
+    /// * `cache_file_path` - Path of the cache file
+    /// * `depth` - Number of items in the cache after replacing
+    pub fn record_cache_replace(&self, cache_file_path: &str, depth: usize) {
+        self.expected_cache_depths
+            .lock()
+            .unwrap()
+            .insert(cache_file_path.to_string(), depth);
+    }
+
+    /// Checks that the depth of a cache matches the items written to and replaced in it
+    ///
+    /// Caches not written to or replaced yet are not checked.
+    ///
+    /// # Arguments
+    /// * `cache_file_path` - Path of the cache file
+    /// * `depth` - Number of items in the cache
+    ///
+    /// # Returns
+    /// * Whether the invariant holds
+    pub fn check_cache_depth(&self, cache_file_path: &str, depth: usize) -> bool {
+        let expected_depth = match self
+            .expected_cache_depths
+            .lock()
+            .unwrap()
+            .get(cache_file_path)
+        {
+            Some(expected_depth) if *expected_depth != depth => *expected_depth,
+            _ => return true,
+        };
+        error!(
+            "Invariant violation: cache [{}] has {} items, but {} were expected",
+            cache_file_path, depth, expected_depth
+        );
+        metrics::increment_counter(
+            INVARIANT_VIOLATIONS_METRIC,
+            &[("invariant", CACHE_DEPTH_INVARIANT)],
+        );
+
+        return false;
+    }
+}
+
+/// Gets the global pipeline invariants if the `pipeline-invariants` feature is enabled
+fn get_invariants() -> Option<&'static PipelineInvariants> {
+    if !cfg!(feature = "pipeline-invariants") {
+        return None;
+    }
+
+    return Some(INVARIANTS.get_or_init(PipelineInvariants::default));
+}
+
+/// Checks that each record of a frame has been either handled or dropped
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `received` - Number of records received in the frame
+/// * `handled` - Number of records sent or cached
+/// * `dropped` - Number of records dropped
+pub fn check_frame_records(imei: &str, received: usize, handled: usize, dropped: usize) {
+    if let Some(invariants) = get_invariants() {
+        invariants.check_frame_records(imei, received, handled, dropped);
+    }
+}
+
+/// Records an item written to a cache
+///
+/// # Arguments
+/// * `cache_file_path` - Path of the cache file
+/// * `existing_depth` - Number of items in the cache before writing
+pub fn record_cache_write(cache_file_path: &str, existing_depth: usize) {
+    if let Some(invariants) = get_invariants() {
+        invariants.record_cache_write(cache_file_path, existing_depth);
+    }
+}
+
+/// Records the contents of a cache being replaced
+///
+/// # Arguments
+/// * `cache_file_path` - Path of the cache file
+/// * `depth` - Number of items in the cache after replacing
+pub fn record_cache_replace(cache_file_path: &str, depth: usize) {
+    if let Some(invariants) = get_invariants() {
+        invariants.record_cache_replace(cache_file_path, depth);
+    }
+}
+
+/// Checks that the depth of a cache matches the items written to and replaced in it
+///
+/// # Arguments
+/// * `cache_file_path` - Path of the cache file
+/// * `depth` - Number of items in the cache
+pub fn check_cache_depth(cache_file_path: &str, depth: usize) {
+    if let Some(invariants) = get_invariants() {
+        invariants.check_cache_depth(cache_file_path, depth);
+    }
+}
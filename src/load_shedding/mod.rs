@@ -0,0 +1,139 @@
+//! Load shedding under overload
+//!
+//! When the receiver is overloaded, the lowest-value events (speeds and frequent locations) are dropped
+//! while driver cards and drive states are always preserved. When too many acknowledged frames wait in memory for
+//! dispatching, further frames are rejected with a 0-record ACK, so that the devices keep them and send them again.
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use log::{info, warn};
+
+use crate::{admin::QUEUE_DEPTH_METRIC, metrics};
+
+/// Name of the counter describing the number of events dropped due to overload
+pub const SHED_EVENTS_METRIC: &str = "receiver_shed_events_total";
+/// Name of the gauge describing whether the receiver is overloaded
+const OVERLOADED_METRIC: &str = "receiver_overloaded";
+/// Name of the counter describing the number of frames rejected with a 0-record ACK due to too many queued frames
+pub const REJECTED_FRAMES_METRIC: &str = "receiver_rejected_frames_total";
+/// Name of the gauge describing the number of frames waiting in memory for dispatching across all connections
+const QUEUED_FRAMES_METRIC: &str = "receiver_queued_frames";
+/// Interval for evaluating the load
+const LOAD_EVALUATION_INTERVAL: Duration = Duration::from_secs(5);
+/// Minimum interval between locations forwarded while overloaded, in seconds
+pub const OVERLOAD_LOCATION_INTERVAL_SECONDS: i64 = 60;
+
+static OVERLOADED: AtomicBool = AtomicBool::new(false);
+static QUEUED_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+/// Thresholds after which the receiver is considered overloaded
+#[derive(Debug, Clone, Copy)]
+pub struct LoadSheddingThresholds {
+    /// Total number of items waiting in per-device queues
+    pub queue_depth: Option<usize>,
+    /// One minute load average per CPU core
+    pub cpu_load: Option<f64>,
+}
+
+/// Checks whether the receiver is currently overloaded
+pub fn is_overloaded() -> bool {
+    OVERLOADED.load(Ordering::Relaxed)
+}
+
+/// Records an event dropped due to overload
+///
+/// # Arguments
+/// * `event_type` - Type of the dropped event
+pub fn record_shed_event(event_type: &str) {
+    metrics::increment_counter(SHED_EVENTS_METRIC, &[("event_type", event_type)]);
+}
+
+/// Records a frame starting to wait for dispatching
+pub fn record_frame_queued() {
+    let queued_frames = QUEUED_FRAMES.fetch_add(1, Ordering::Relaxed) + 1;
+    metrics::set_gauge(QUEUED_FRAMES_METRIC, &[], queued_frames as f64);
+}
+
+/// Records a queued frame dispatched
+pub fn record_frame_dispatched() {
+    let queued_frames = QUEUED_FRAMES.fetch_sub(1, Ordering::Relaxed) - 1;
+    metrics::set_gauge(QUEUED_FRAMES_METRIC, &[], queued_frames as f64);
+}
+
+/// Checks whether a received frame should be rejected because too many frames are queued
+///
+/// # Arguments
+/// * `threshold` - Number of queued frames at which frames are rejected, `None` to never reject frames
+pub fn should_reject_frame(threshold: Option<usize>) -> bool {
+    threshold.is_some_and(|threshold| QUEUED_FRAMES.load(Ordering::Relaxed) >= threshold)
+}
+
+/// Records a frame rejected with a 0-record ACK
+pub fn record_rejected_frame() {
+    metrics::increment_counter(REJECTED_FRAMES_METRIC, &[]);
+}
+
+/// Periodically evaluates the load of the receiver and updates the overload state
+///
+/// # Arguments
+/// * `thresholds` - Thresholds after which the receiver is considered overloaded
+pub async fn start_load_monitor(thresholds: LoadSheddingThresholds) {
+    let mut interval = tokio::time::interval(LOAD_EVALUATION_INTERVAL);
+    loop {
+        interval.tick().await;
+        let total_queue_depth = metrics::get_gauges(QUEUE_DEPTH_METRIC)
+            .iter()
+            .map(|(_, depth)| *depth as usize)
+            .sum();
+        let overloaded = evaluate_overload(thresholds, total_queue_depth, read_cpu_load());
+        let was_overloaded = OVERLOADED.swap(overloaded, Ordering::Relaxed);
+        if overloaded && !was_overloaded {
+            warn!(
+                "Receiver is overloaded with {} queued items, shedding low priority events",
+                total_queue_depth
+            );
+        } else if !overloaded && was_overloaded {
+            info!("Receiver is no longer overloaded, stopped shedding events");
+        }
+        metrics::set_gauge(OVERLOADED_METRIC, &[], if overloaded { 1.0 } else { 0.0 });
+    }
+}
+
+/// Evaluates whether the receiver is overloaded
+///
+/// # Arguments
+/// * `thresholds` - Thresholds after which the receiver is considered overloaded
+/// * `total_queue_depth` - Total number of items waiting in per-device queues
+/// * `cpu_load` - One minute load average per CPU core, if available
+pub fn evaluate_overload(
+    thresholds: LoadSheddingThresholds,
+    total_queue_depth: usize,
+    cpu_load: Option<f64>,
+) -> bool {
+    let queue_overloaded = thresholds
+        .queue_depth
+        .is_some_and(|threshold| total_queue_depth > threshold);
+    let cpu_overloaded = match (thresholds.cpu_load, cpu_load) {
+        (Some(threshold), Some(cpu_load)) => cpu_load > threshold,
+        _ => false,
+    };
+
+    return queue_overloaded || cpu_overloaded;
+}
+
+/// Reads the one minute load average per CPU core
+///
+/// Only available on Linux, returns [None] elsewhere.
+fn read_cpu_load() -> Option<f64> {
+    let load_average = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let one_minute_load = load_average
+        .split_whitespace()
+        .next()?
+        .parse::<f64>()
+        .ok()?;
+    let cores = std::thread::available_parallelism().ok()?.get();
+
+    return Some(one_minute_load / cores as f64);
+}
@@ -1,23 +1,53 @@
+mod admin;
+mod batching;
+mod completeness;
+mod config;
+mod connection_limits;
+mod device_auth;
+mod device_stats;
+mod invariants;
+mod load_shedding;
+mod metrics;
+mod misinstallation;
+mod probe;
+mod processing;
+mod retention;
+mod retry;
+mod shutdown;
+mod spoofing;
+mod synthetic;
 mod telematics_cache;
 mod teltonika;
 mod utils;
 
-use log::info;
-use std::{error::Error, path::Path};
-use tokio::net::TcpListener;
+use log::{error, info, warn};
+use std::{
+    error::Error,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
 
 use crate::{
-    teltonika::connection::TeltonikaConnection,
-    utils::{read_env_variable, read_optional_env_variable},
+    config::get_config,
+    connection_limits::ConnectionLimiter,
+    teltonika::{
+        connection::{registry, TeltonikaConnection},
+        device_family::DeviceFamily,
+        udp::TeltonikaUdpListener,
+    },
+    utils::{api, cache_directory},
 };
 
-/// Default card remove threshold in milliseconds
-const DEFAULT_CARD_REMOVE_THRESHOLD: u16 = 15_000;
-const BASE_FILE_PATH_ENV_KEY: &str = "BASE_FILE_PATH";
-const WRITE_TO_FILE_ENV_KEY: &str = "WRITE_TO_FILE";
-const CARD_REMOVE_THRESHOLD_ENV_KEY: &str = "CARD_REMOVE_THRESHOLD";
-const VEHICLE_MANAGEMENT_SERVICE_API_KEY_ENV_KEY: &str = "VEHICLE_MANAGEMENT_SERVICE_API_KEY";
-const API_BASE_URL_ENV_KEY: &str = "API_BASE_URL";
+/// Maximum time to wait for the truck cache warmup before accepting connections
+const TRUCK_CACHE_WARMUP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Name of the counter describing the number of failed connection accepts
+const ACCEPT_FAILURES_METRIC: &str = "receiver_accept_failures_total";
+/// Initial delay before accepting connections again after a failure
+const INITIAL_ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Maximum delay before accepting connections again after a failure
+const MAX_ACCEPT_RETRY_DELAY: Duration = Duration::from_secs(5);
 
 /// VP-Kuljetus Vehicle Data Receiver
 ///
@@ -26,17 +56,125 @@ const API_BASE_URL_ENV_KEY: &str = "API_BASE_URL";
 ///
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    env_logger::init();
-    let file_path: String = read_env_variable(BASE_FILE_PATH_ENV_KEY);
-    let write_to_file: bool = read_env_variable(WRITE_TO_FILE_ENV_KEY);
-    let card_remove_threshold: u16 = read_optional_env_variable(CARD_REMOVE_THRESHOLD_ENV_KEY)
-        .unwrap_or(DEFAULT_CARD_REMOVE_THRESHOLD);
+    if std::env::args().any(|arg| arg == config::PRINT_DEFAULT_CONFIG_FLAG) {
+        config::print_default_config();
+        return Ok(());
+    }
+    // The configuration is validated before anything else, so that an invalid value stops the receiver right away
+    config::init_config()?;
+    config::logger::init_logger();
+    teltonika::io_mappings::init_io_mappings()?;
+    tokio::spawn(config::start_reload_on_sighup());
+    let config = get_config();
+    let file_path = config.base_file_path.clone();
+    let write_to_file = config.write_to_file;
+
+    // Caches are written relative to the working directory unless raw frame captures are written to the base file path
+    let cache_base_path = match write_to_file {
+        true => PathBuf::from(&file_path),
+        false => PathBuf::from("."),
+    };
+    if let Err(err) =
+        cache_directory::check_cache_directory(&cache_base_path, config.min_cache_free_space_mb)
+    {
+        error!("{}", err);
+        return Err(err.into());
+    }
+
+    // API requests are run on a dedicated runtime only when a number of worker threads for it is configured
+    if let Some(api_runtime_worker_threads) = config.api.runtime_worker_threads {
+        api::init_api_runtime(api_runtime_worker_threads)?;
+        info!(
+            "Running API requests on a dedicated runtime with {} worker threads",
+            api_runtime_worker_threads
+        );
+    }
+
+    // Admin server is optional and only started when an address for it is configured
+    if let Some(admin_server_address) = &config.listeners.admin_server_address {
+        tokio::spawn(admin::start_admin_server(
+            admin_server_address.clone(),
+            cache_base_path.clone(),
+        ));
+    }
+
+    // Statistics summary can be disabled by setting the interval to zero
+    let statistics_summary_interval = config.monitoring.statistics_summary_interval;
+    if !statistics_summary_interval.is_zero() {
+        tokio::spawn(metrics::summary::start_summary_logger(
+            statistics_summary_interval,
+        ));
+    }
+
+    // Completeness report is enabled only when the expected interval between records is configured
+    if let Some(expected_interval_seconds) =
+        config.monitoring.completeness_expected_interval_seconds
+    {
+        tokio::spawn(completeness::start_completeness_reporter(
+            expected_interval_seconds.into(),
+        ));
+    }
+
+    // Raw frame captures are purged only when they are written, setting the retention to zero keeps them indefinitely
+    let raw_captures_retention_days = config.raw_captures_retention_days;
+    if write_to_file && raw_captures_retention_days > 0 {
+        tokio::spawn(retention::start_retention_job(
+            PathBuf::from(&file_path),
+            raw_captures_retention_days,
+        ));
+    }
 
-    // This is retrieved from the environment on-demand but we want to restrict starting the software if the environment variable is not set
-    read_env_variable::<String>(VEHICLE_MANAGEMENT_SERVICE_API_KEY_ENV_KEY);
+    // Cached events are retried on a schedule unless the interval is set to zero
+    let cache_retry_interval = retry::get_retry_interval();
+    if !cache_retry_interval.is_zero() {
+        tokio::spawn(retry::start_retry_scheduler(cache_retry_interval));
+    }
+
+    // Truck IDs of connected devices are refreshed on a schedule unless the interval is set to zero
+    let truck_id_refresh_interval = config.api.truck_id_refresh_interval;
+    if !truck_id_refresh_interval.is_zero() {
+        tokio::spawn(registry::start_truck_id_refresh(truck_id_refresh_interval));
+    }
+
+    // Synthetic devices can't be configured without a sandbox API, so that their data is never sent to production
+    let synthetic_routes = synthetic::get_synthetic_devices().get_routing_overrides();
+    if !synthetic_routes.is_empty() {
+        info!(
+            "Routing {} synthetic devices to a sandbox API",
+            synthetic_routes.len()
+        );
+    }
+
+    // Spoofing detection is enabled only when the window for it is configured
+    if let Some(spoofing_detection_window) = config.spoofing_detection_window_seconds {
+        spoofing::init_spoofing_detection(
+            spoofing_detection_window.into(),
+            config.quarantine_suspected_spoofing,
+        );
+    }
+
+    // Device authentication is enabled only when the tokens of the devices are configured
+    if let Some(device_auth_tokens) = &config.device_auth_tokens {
+        device_auth::init_device_auth(device_auth_tokens.clone());
+    }
+
+    // Load shedding is enabled only when at least one of the thresholds is configured
+    let load_shedding_thresholds = config.load_shedding;
+    if load_shedding_thresholds.queue_depth.is_some() || load_shedding_thresholds.cpu_load.is_some()
+    {
+        tokio::spawn(load_shedding::start_load_monitor(load_shedding_thresholds));
+    }
+
+    // Truck cache is warmed up before accepting connections so that reconnecting devices don't look up their trucks one by one
+    if config.api.truck_cache_warmup {
+        match tokio::time::timeout(TRUCK_CACHE_WARMUP_TIMEOUT, api::warm_up_truck_cache()).await {
+            Ok(Ok(cached_trucks)) => info!("Warmed up truck cache with {} trucks", cached_trucks),
+            Ok(Err(err)) => warn!("Failed to warm up truck cache: {}", err),
+            Err(_) => warn!("Truck cache warmup timed out"),
+        }
+    }
 
-    // Generated client gets the base URL from the environment variable itself but we want to restrict starting the software if the environment variable is not set
-    read_env_variable::<String>(API_BASE_URL_ENV_KEY);
+    let connection_limiter = ConnectionLimiter::new(config.listeners.connection_limits);
 
     let address = "0.0.0.0:8080";
 
@@ -44,49 +182,305 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     info!("Listening on: {}", address);
 
+    // Devices don't tell their model, so FMB devices are received on listeners of their own when addresses for them are configured
+    let fmb1xx_listener =
+        bind_device_family_listener(&config.listeners.fmb1xx_address, DeviceFamily::Fmb1xx).await?;
+    let fmb6xx_listener =
+        bind_device_family_listener(&config.listeners.fmb6xx_address, DeviceFamily::Fmb6xx).await?;
+
+    // Devices configured for UDP transport are received only when an address for the UDP listener is configured
+    if let Some(udp_address) = &config.listeners.udp_address {
+        let udp_socket = UdpSocket::bind(udp_address).await?;
+        if let Err(err) = config
+            .listeners
+            .udp_socket_options
+            .apply_to_udp(&udp_socket)
+        {
+            warn!("Failed to apply UDP socket options: {}", err);
+        }
+        info!("Listening for UDP datagrams on: {}", udp_address);
+        let udp_base_file_path = match write_to_file {
+            true => file_path.clone(),
+            false => "".to_string(),
+        };
+        tokio::spawn(async move {
+            if let Err(err) = TeltonikaUdpListener::new(udp_socket, Path::new(&udp_base_file_path))
+                .run()
+                .await
+            {
+                error!("UDP listener stopped: {}", err);
+            }
+        });
+    }
+
+    // Latency probe is enabled only when an interval for it is configured
+    if let Some(latency_probe_interval) = config.monitoring.latency_probe_interval {
+        let probe_address = format!("127.0.0.1:{}", listener.local_addr()?.port());
+        tokio::spawn(probe::start_latency_probe(
+            probe_address,
+            latency_probe_interval,
+        ));
+    }
+
+    let shutdown_signal = shutdown::wait_for_signal();
+    tokio::pin!(shutdown_signal);
+    let mut consecutive_accept_failures = 0;
     loop {
-        let (socket, _) = listener.accept().await?;
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted
+                .map(|(socket, peer_address)| (socket, peer_address, DeviceFamily::Fmc)),
+            accepted = accept_device_family_connection(&fmb1xx_listener, DeviceFamily::Fmb1xx) => accepted,
+            accepted = accept_device_family_connection(&fmb6xx_listener, DeviceFamily::Fmb6xx) => accepted,
+            _ = &mut shutdown_signal => break,
+        };
+        // Accept errors (e.g. running out of file descriptors) are usually transient, so they must not stop the listener
+        let (socket, peer_address, device_family) = match accepted {
+            Ok(accepted) => {
+                consecutive_accept_failures = 0;
+                accepted
+            }
+            Err(err) => {
+                consecutive_accept_failures += 1;
+                metrics::increment_counter(ACCEPT_FAILURES_METRIC, &[]);
+                let retry_delay = get_accept_retry_delay(consecutive_accept_failures);
+                error!(
+                    "Failed to accept connection: {}, retrying in {} ms",
+                    err,
+                    retry_delay.as_millis()
+                );
+                tokio::time::sleep(retry_delay).await;
+                continue;
+            }
+        };
+        // Connections exceeding the limits are closed by dropping the socket before anything is read from it
+        let connection_permit =
+            match connection_limiter.try_admit(peer_address.ip(), std::time::Instant::now()) {
+                Ok(connection_permit) => connection_permit,
+                Err(reason) => {
+                    connection_limits::record_rejected_connection(peer_address.ip(), reason);
+                    continue;
+                }
+            };
+        let socket_options = config.listeners.get_socket_options(device_family);
+        if let Err(err) = socket_options.apply(&socket) {
+            warn!("Failed to apply socket options: {}", err);
+        }
         let base_file_path = match write_to_file {
             true => file_path.clone(),
             false => "".to_string(),
         };
-        let card_remove_threshold = card_remove_threshold.clone();
-
+        // Tunables are read per connection, so that reloading the configuration applies them to new connections
+        let tunables = &get_config().tunables;
+        let card_remove_threshold = tunables.card_remove_threshold;
+        let ack_pipeline_depth = tunables.ack_pipeline_depth;
         tokio::spawn(async move {
-            if let Err(_) = TeltonikaConnection::handle_connection(
+            let _connection_permit = connection_permit;
+            if TeltonikaConnection::handle_connection(
                 socket,
+                Some(peer_address.ip()),
+                device_family,
                 Path::new(&base_file_path),
                 card_remove_threshold,
+                ack_pipeline_depth,
             )
             .await
+            .is_err()
             {
                 return;
             };
         });
     }
+
+    // Connections dispatch the records they have acknowledged before closing
+    drop(listener);
+    drop(fmb1xx_listener);
+    drop(fmb6xx_listener);
+    shutdown::shut_down_connections(&connection_limiter, config.listeners.shutdown_timeout).await;
+    shutdown::flush_batches().await;
+
+    return Ok(());
+}
+
+/// Binds the TCP listener of a device family if an address for it is configured
+///
+/// # Arguments
+/// * `address` - Address of the listener, if configured
+/// * `device_family` - Family of the devices connecting to the listener
+async fn bind_device_family_listener(
+    address: &Option<String>,
+    device_family: DeviceFamily,
+) -> std::io::Result<Option<TcpListener>> {
+    let Some(address) = address else {
+        return Ok(None);
+    };
+    let listener = TcpListener::bind(address).await?;
+    info!("Listening for {} devices on: {}", device_family, address);
+
+    return Ok(Some(listener));
+}
+
+/// Accepts a connection from the listener of a device family
+///
+/// Waits forever if the listener is not configured.
+///
+/// # Arguments
+/// * `listener` - Listener of the device family, if configured
+/// * `device_family` - Family of the devices connecting to the listener
+async fn accept_device_family_connection(
+    listener: &Option<TcpListener>,
+    device_family: DeviceFamily,
+) -> std::io::Result<(TcpStream, SocketAddr, DeviceFamily)> {
+    let Some(listener) = listener else {
+        return std::future::pending().await;
+    };
+    let (socket, peer_address) = listener.accept().await?;
+
+    return Ok((socket, peer_address, device_family));
+}
+
+/// Gets the delay before accepting connections again after consecutive failures
+///
+/// The delay is doubled for each consecutive failure up to [MAX_ACCEPT_RETRY_DELAY].
+///
+/// # Arguments
+/// * `consecutive_failures` - Number of consecutive accept failures
+///
+/// # Returns
+/// * `Duration` - Delay before the next accept
+fn get_accept_retry_delay(consecutive_failures: u32) -> Duration {
+    let multiplier = 2_u32.saturating_pow(consecutive_failures.saturating_sub(1));
+
+    return INITIAL_ACCEPT_RETRY_DELAY
+        .saturating_mul(multiplier)
+        .min(MAX_ACCEPT_RETRY_DELAY);
 }
 
 #[cfg(test)]
 mod tests {
     pub mod integration_tests;
+    pub mod replay_tests;
+    pub mod snapshot_tests;
     use crate::{
-        telematics_cache::Cacheable,
+        admin::OPENAPI_DOCUMENT,
+        batching::{ApiHealth, BatchBounds, EventBatch, BATCHED_EVENTS_METRIC},
+        completeness::build_completeness_report,
+        config::{
+            get_config, logger::LogFormat, telemetry, Config, ConfigError, ConfigFile,
+            DEFAULT_CONFIG,
+        },
+        connection_limits::{self, ConnectionLimiter, ConnectionLimits, RejectReason},
+        device_auth::{
+            init_device_auth, parse_device_auth_tokens, DeviceAuthResult, DeviceAuthenticator,
+            DEVICE_AUTH_FAILURES_METRIC,
+        },
+        device_stats::DeviceStatsStore,
+        get_accept_retry_delay,
+        invariants::{
+            PipelineInvariants, CACHE_DEPTH_INVARIANT, FRAME_RECORDS_INVARIANT,
+            INVARIANT_VIOLATIONS_METRIC,
+        },
+        load_shedding::{self, evaluate_overload, LoadSheddingThresholds},
+        metrics::{self, summary::StatisticsSnapshot},
+        misinstallation::{VinMismatchDetector, VIN_MISMATCH_METRIC},
+        probe::{self, PROBE_LATENCY_METRIC},
+        processing::{ProcessingControl, ProcessingMode},
+        retention::{purge_raw_captures, RAW_CAPTURES_STORE, RETENTION_PURGED_FILES_METRIC},
+        retry::{get_max_retry_attempts, retry_due, RetryBackoff},
+        shutdown::Shutdown,
+        spoofing::{FrameSource, SpoofingDetector, SPOOFING_SUSPECTED_METRIC},
+        synthetic::SyntheticDevices,
+        telematics_cache::{
+            cached_event::CachedEvent,
+            dead_letter_event::{DeadLetterEvent, DEAD_LETTER_EVENTS_METRIC},
+            failed_api_request::FailedApiRequest,
+            failed_event::FailedEvent,
+            get_cache_lock, get_device_cache_path, is_cache_lock_in_use, restore_cache, take_cache,
+            Cacheable, CORRUPTED_CACHE_FILES_METRIC,
+        },
+        teltonika::{
+            actions::{parse_device_actions, DeviceAction, OutputOperation},
+            commands::{get_command_channel, CommandError},
+            connection::{
+                lifecycle::DISCONNECTIONS_METRIC,
+                registry::{ConnectionRegistry, Transport},
+                TeltonikaConnection, DEVICE_BACKLOG_METRIC, IMEI_HANDSHAKE_TIMEOUTS_METRIC,
+            },
+            device_family::DeviceFamily,
+            drive_state_from_value,
+            events::{
+                axle_weight_event_handler::{AxleWeight, TruckAxleWeights},
+                ble_sensor_event_handler::{
+                    parse_ble_sensor_compartments, parse_ble_sensor_macs, BleSensorEventHandler,
+                    BleSensorReading, BleSensorReadings,
+                },
+                engine_hours_event_handler::TruckEngineHours,
+                engine_rpm_event_handler::TruckEngineRpm,
+                fault_code_event_handler::FaultRecord,
+                geofence_event_handler::{ZoneDirection, ZoneEvent},
+                harsh_driving_event_handler::{DriverBehaviorEvent, DriverBehaviorEventType},
+                teltonika_event_handlers::TeltonikaEventHandler,
+                AxleWeightEventHandler, DriverOneCardIdEventHandler,
+                DriverOneDriveStateEventHandler, EngineHoursEventHandler, EngineRpmEventHandler,
+                FaultCodeEventHandler, GeofenceEventHandler, HarshDrivingEventHandler,
+                SpeedEventHandler,
+            },
+            io_elements::{describe_io_element, get_io_element, IO_ELEMENTS},
+            io_mappings::{IoMapping, IoMappings},
+            messages::parse_datagram,
+            messages::{
+                build_codec12_command, check_message_length, parse_message, TeltonikaMessage,
+            },
+            records::{
+                teltonika_location_validator::{
+                    InvalidLocation, LocationValidationMode, INVALID_LOCATIONS_METRIC,
+                },
+                teltonika_odometer_validator::OdometerRejection,
+                teltonika_record_ordering::order_records,
+                teltonika_record_trigger::{RecordSubscription, RecordTrigger},
+                teltonika_timestamp_normalizer::parse_timestamp_offsets,
+                teltonika_timestamp_validator::{
+                    TimestampPolicy, DEVICE_CLOCK_SKEW_METRIC, INVALID_RECORD_TIMESTAMPS_METRIC,
+                },
+                FrameProvenance, RecordOrdering, TeltonikaGapDetector, TeltonikaLocationValidator,
+                TeltonikaOdometerReconciler, TeltonikaOdometerValidator, TeltonikaShiftTracker,
+                TeltonikaTimestampNormalizer, TeltonikaTimestampValidator,
+            },
+            udp::TeltonikaUdpListener,
+        },
         utils::{
+            api::{
+                build_api_client, get_idempotency_key, get_retry_delay, init_api_runtime,
+                run_api_request, warm_up_truck_cache, ApiClientSettings, TruckEventApi, VehicleApi,
+                VehicleApiError, VehicleApiErrorKind, API_REQUESTS_METRIC,
+            },
+            api_routing::{parse_routing_overrides, ApiRouting, RouteSelector},
             avl_frame_builder::*,
             avl_packet::*,
             avl_record_builder::avl_record_builder::*,
+            cache_directory::{check_cache_directory, CacheDirectoryError},
+            circuit_breaker::CircuitBreakers,
             get_vehicle_management_api_config,
             imei::{build_valid_imei_packet, get_random_imei_of_length, *},
+            log_throttle::{describe_suppressed, LogThrottle},
+            outbound_capture,
+            socket_options::SocketOptions,
             str_to_bytes,
             test_utils::{
-                driver_card_id_to_two_part_events, get_teltonika_records_handler, read_imei,
-                split_at_half, start_vehicle_management_mock, string_to_hex_string,
-                string_to_hex_to_dec,
+                build_udp_datagram, driver_card_id_to_two_part_events, get_received_requests,
+                get_teltonika_records_handler, read_imei, split_at_half,
+                start_vehicle_management_mock, string_to_hex_string, string_to_hex_to_dec,
+                vin_to_three_part_events,
             },
+            truck_cache::{
+                get_truck_cache, TruckCache, TruckCacheLookup, TRUCK_CACHE_EVICTIONS_METRIC,
+                TRUCK_CACHE_LOOKUPS_METRIC, TRUCK_LOOKUPS_COALESCED_METRIC,
+            },
+            validation::ValidatePayload,
         },
     };
-    use nom_teltonika::{parser, AVLEventIO, Priority};
-    use std::str::FromStr;
+    use chrono::TimeZone;
+    use nom_teltonika::{parser, AVLEventIO, AVLRecord, Codec, EventGenerationCause, Priority};
+    use std::{path::Path, str::FromStr};
     use vehicle_management_service::{
         apis::public_trucks_api::ListPublicTrucksParams,
         models::{
@@ -108,8 +502,8 @@ mod tests {
         let parsed_first_imei = read_imei_result_1.1.unwrap();
         let parsed_second_imei = read_imei_result_2.1.unwrap();
 
-        assert_eq!(is_first_imei_valid, true);
-        assert_eq!(is_second_imei_valid, true);
+        assert!(is_first_imei_valid);
+        assert!(is_second_imei_valid);
         assert_eq!(&parsed_first_imei, &generated_imei_1.clone());
         assert_eq!(&parsed_second_imei, &generated_imei_2.clone());
     }
@@ -123,7 +517,7 @@ mod tests {
         let is_imei_valid = read_imei_result.0;
         let parsed_imei = read_imei_result.1;
 
-        assert_eq!(is_imei_valid, false);
+        assert!(!is_imei_valid);
         assert_eq!(parsed_imei, None);
     }
 
@@ -340,10 +734,40 @@ mod tests {
         assert_eq!(10.0, first_cached_speed.unwrap().speed);
     }
 
+    #[tokio::test]
+    async fn test_harsh_driving_event_not_cached() {
+        let record_handler = get_teltonika_records_handler(None, None);
+        let record = AVLRecordBuilder::new()
+            .with_trigger_event_id(253)
+            .with_io_events(vec![
+                AVLEventIO {
+                    id: 253,
+                    value: nom_teltonika::AVLEventIOValue::U8(2),
+                },
+                AVLEventIO {
+                    id: 254,
+                    value: nom_teltonika::AVLEventIOValue::U8(45),
+                },
+                AVLEventIO {
+                    id: 18,
+                    value: nom_teltonika::AVLEventIOValue::U16(-420i16 as u16),
+                },
+            ])
+            .build();
+        let packet = AVLFrameBuilder::new().add_record(record.clone()).build();
+
+        record_handler.handle_records(packet.records).await;
+
+        // Driver behavior events have no API endpoint, so they aren't cached for the yet unknown truck
+        let base_cache_path = record_handler.get_base_cache_path();
+        let events_cache = DriverBehaviorEvent::read_from_file(base_cache_path.to_str().unwrap());
+        assert!(events_cache.is_empty());
+    }
+
     #[tokio::test]
     async fn test_send_cached_event() {
         start_vehicle_management_mock();
-        let mut record_handler = get_teltonika_records_handler(None, None);
+        let record_handler = get_teltonika_records_handler(None, None);
         let record = AVLRecordBuilder::new()
             .with_priority(Priority::High)
             .with_io_events(vec![AVLEventIO {
@@ -375,7 +799,7 @@ mod tests {
     #[tokio::test]
     async fn test_record_location_handling() {
         start_vehicle_management_mock();
-        let mut record_handler = get_teltonika_records_handler(None, None);
+        let record_handler = get_teltonika_records_handler(None, None);
         let record_1 = AVLRecordBuilder::new()
             .with_longitude(61.68779453479687)
             .with_latitude(27.27297030282335)
@@ -427,7 +851,7 @@ mod tests {
         let valid_driver_card_id = "1069619335000001".to_string();
         let valid_driver_card_id_2 = "1A696193350YZ001".to_string();
         start_vehicle_management_mock();
-        let mut record_handler = get_teltonika_records_handler(None, None);
+        let record_handler = get_teltonika_records_handler(None, None);
         let driver_card_events = driver_card_id_to_two_part_events(valid_driver_card_id.clone());
         let record = AVLRecordBuilder::new()
             .with_io_events(driver_card_events.to_vec())
@@ -448,7 +872,7 @@ mod tests {
             let base_cache_path = record_handler.get_base_cache_path();
             let driver_cards_cache =
                 TruckDriverCard::read_from_file(base_cache_path.to_str().unwrap());
-            let cached_driver_card_event = driver_cards_cache.get(0);
+            let cached_driver_card_event = driver_cards_cache.first();
             assert_eq!(1, driver_cards_cache.len());
             assert!(cached_driver_card_event.is_some());
             assert_eq!(
@@ -488,7 +912,7 @@ mod tests {
             let base_cache_path = record_handler.get_base_cache_path();
             let driver_cards_cache =
                 TruckDriverCard::read_from_file(base_cache_path.to_str().unwrap());
-            let cached_driver_card_event = driver_cards_cache.get(0);
+            let cached_driver_card_event = driver_cards_cache.first();
             assert_eq!(1, driver_cards_cache.len());
             assert!(cached_driver_card_event.is_some());
             let cached_driver_card_event = cached_driver_card_event.unwrap();
@@ -516,11 +940,45 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_duplicate_driver_card_suppression() {
+        let driver_card_id = "1069619335000001".to_string();
+        let record_handler = get_teltonika_records_handler(None, None);
+        let driver_card_events = driver_card_id_to_two_part_events(driver_card_id.clone());
+        let record = AVLRecordBuilder::new()
+            .with_io_events(driver_card_events.to_vec())
+            .add_io_event(AVLEventIO {
+                id: 187,
+                value: nom_teltonika::AVLEventIOValue::U8(1),
+            })
+            .with_trigger_event_id(187)
+            .build();
+        let packet = AVLFrameBuilder::new()
+            .with_records([record.clone(), record.clone()].to_vec())
+            .build();
+
+        record_handler.handle_records(packet.records).await;
+
+        {
+            let base_cache_path = record_handler.get_base_cache_path();
+            let driver_cards_cache =
+                TruckDriverCard::read_from_file(base_cache_path.to_str().unwrap());
+            assert_eq!(1, driver_cards_cache.len());
+        }
+        // Once the card has been removed, the same card is reported again
+        record_handler.reset_driver_one_card();
+        record_handler.handle_records(vec![record]).await;
+
+        let base_cache_path = record_handler.get_base_cache_path();
+        let driver_cards_cache = TruckDriverCard::read_from_file(base_cache_path.to_str().unwrap());
+        assert_eq!(2, driver_cards_cache.len());
+    }
+
     #[tokio::test]
     async fn test_driver_one_card_drive_state_handling() {
         let valid_driver_card_id = "1069619335000001".to_string();
         start_vehicle_management_mock();
-        let mut record_handler = get_teltonika_records_handler(None, None);
+        let record_handler = get_teltonika_records_handler(None, None);
         let driver_card_events = driver_card_id_to_two_part_events(valid_driver_card_id.clone());
         let record_1 = AVLRecordBuilder::new()
             .with_io_events(driver_card_events.to_vec())
@@ -539,7 +997,7 @@ mod tests {
             let base_cache_path = record_handler.get_base_cache_path();
             let driver_cards_cache =
                 TruckDriveState::read_from_file(base_cache_path.to_str().unwrap());
-            let cached_driver_card_event = driver_cards_cache.get(0);
+            let cached_driver_card_event = driver_cards_cache.first();
             assert_eq!(1, driver_cards_cache.len());
             assert!(cached_driver_card_event.is_some());
             let cached_driver_card_event = cached_driver_card_event.unwrap();
@@ -617,4 +1075,4417 @@ mod tests {
         assert_eq!(driver_card_id_msb_dec, 3544392526090811699);
         assert_eq!(driver_card_id_lsb_dec, 3689908453225017393);
     }
+
+    #[test]
+    fn test_configured_timestamp_offset() {
+        assert!(parse_timestamp_offsets("123456789012345=7200, invalid").is_err());
+        let offsets =
+            parse_timestamp_offsets("123456789012345=7200, 543210987654321=-3600").unwrap();
+        assert_eq!(2, offsets.len());
+        assert_eq!(Some(&7200), offsets.get("123456789012345"));
+        assert_eq!(Some(&-3600), offsets.get("543210987654321"));
+
+        let timestamp = chrono::Utc::now();
+        let mut records = vec![AVLRecordBuilder::new().with_timestamp(timestamp).build()];
+        let mut normalizer = TeltonikaTimestampNormalizer::with_configuration(
+            "123456789012345",
+            offsets.get("123456789012345").copied(),
+            false,
+        );
+        normalizer.normalize_records(&mut records);
+
+        assert_eq!(timestamp - chrono::Duration::hours(2), records[0].timestamp);
+    }
+
+    #[test]
+    fn test_detected_timestamp_offset() {
+        let timestamp = chrono::Utc::now();
+        let local_timestamp = timestamp + chrono::Duration::seconds(3 * 60 * 60 + 20);
+        let mut records = vec![AVLRecordBuilder::new()
+            .with_timestamp(local_timestamp)
+            .build()];
+        let mut normalizer =
+            TeltonikaTimestampNormalizer::with_configuration("123456789012345", None, true);
+        normalizer.normalize_records(&mut records);
+
+        assert_eq!(Some(chrono::Duration::hours(3)), normalizer.get_offset());
+        assert_eq!(
+            local_timestamp - chrono::Duration::hours(3),
+            records[0].timestamp
+        );
+
+        // Records without a future timestamp do not cause an offset to be detected
+        let mut records = vec![AVLRecordBuilder::new().with_timestamp(timestamp).build()];
+        let mut normalizer =
+            TeltonikaTimestampNormalizer::with_configuration("123456789012345", None, true);
+        normalizer.normalize_records(&mut records);
+
+        assert_eq!(None, normalizer.get_offset());
+        assert_eq!(timestamp, records[0].timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_end_of_shift_detection() {
+        let driver_card_id = "1069619335000001".to_string();
+        let started_at = chrono::Utc::now() - chrono::Duration::hours(8);
+        let mut shift_tracker = TeltonikaShiftTracker::new();
+        let shift_start = AVLRecordBuilder::new()
+            .with_timestamp(started_at)
+            .with_latitude(61.68779453479687)
+            .with_longitude(27.27297030282335)
+            .with_io_events(driver_card_id_to_two_part_events(driver_card_id.clone()).to_vec())
+            .add_io_events(vec![
+                AVLEventIO {
+                    id: 187,
+                    value: nom_teltonika::AVLEventIOValue::U8(1),
+                },
+                AVLEventIO {
+                    id: 239,
+                    value: nom_teltonika::AVLEventIOValue::U8(1),
+                },
+            ])
+            .build();
+        let ignition_off = AVLRecordBuilder::new()
+            .with_timestamp(started_at + chrono::Duration::hours(8))
+            .with_latitude(61.68779453479687)
+            .with_longitude(27.37297030282335)
+            .with_io_events(vec![AVLEventIO {
+                id: 239,
+                value: nom_teltonika::AVLEventIOValue::U8(0),
+            }])
+            .build();
+        let card_removed = AVLRecordBuilder::new()
+            .with_timestamp(started_at + chrono::Duration::minutes(8 * 60 + 5))
+            .with_latitude(61.68779453479687)
+            .with_longitude(27.37297030282335)
+            .with_io_events(vec![AVLEventIO {
+                id: 187,
+                value: nom_teltonika::AVLEventIOValue::U8(0),
+            }])
+            .build();
+
+        assert!(shift_tracker
+            .handle_records(&[ignition_off, shift_start])
+            .is_empty());
+        let summaries = shift_tracker.handle_records(&[card_removed]);
+
+        assert_eq!(1, summaries.len());
+        let summary = summaries.first().unwrap();
+        assert_eq!(485, summary.get_duration().num_minutes());
+        assert_eq!(Some(driver_card_id), summary.driver_card_id);
+        assert!((summary.distance_meters - 5_281.0).abs() < 10.0);
+        // Vehicle Management Service has no endpoint for shift summaries yet
+        assert_eq!(
+            VehicleApiErrorKind::Unsupported,
+            VehicleApi
+                .create_shift_summary("truck", summary.clone())
+                .await
+                .unwrap_err()
+                .kind
+        );
+    }
+
+    #[test]
+    fn test_queue_depth_high_water_mark() {
+        let labels = [("imei", "123456789012345"), ("queue", "test_queue")];
+        metrics::set_gauge_with_high_water_mark("test_queue_depth", &labels, 10.0);
+        metrics::set_gauge_with_high_water_mark("test_queue_depth", &labels, 3.0);
+
+        assert_eq!(Some(3.0), metrics::get_gauge("test_queue_depth", &labels));
+        assert_eq!(
+            Some(10.0),
+            metrics::get_gauge("test_queue_depth_high_water_mark", &labels)
+        );
+        assert!(metrics::render()
+            .contains("test_queue_depth{imei=\"123456789012345\",queue=\"test_queue\"} 3"));
+    }
+
+    #[test]
+    fn test_load_shedding_overload_evaluation() {
+        let thresholds = LoadSheddingThresholds {
+            queue_depth: Some(1_000),
+            cpu_load: Some(0.9),
+        };
+
+        assert!(!evaluate_overload(thresholds, 1_000, Some(0.5)));
+        assert!(evaluate_overload(thresholds, 1_001, Some(0.5)));
+        assert!(evaluate_overload(thresholds, 0, Some(1.5)));
+        assert!(!evaluate_overload(thresholds, 0, None));
+        assert!(!evaluate_overload(
+            LoadSheddingThresholds {
+                queue_depth: None,
+                cpu_load: None
+            },
+            usize::MAX,
+            Some(100.0)
+        ));
+
+        load_shedding::record_shed_event("test_shed_event");
+        load_shedding::record_shed_event("test_shed_event");
+        assert_eq!(
+            metrics::get_counter(
+                load_shedding::SHED_EVENTS_METRIC,
+                &[("event_type", "test_shed_event")]
+            ),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_socket_options() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let socket_options = SocketOptions {
+            nodelay: Some(true),
+            keepalive_time: Some(std::time::Duration::from_secs(30)),
+            keepalive_interval: Some(std::time::Duration::from_secs(10)),
+            recv_buffer_size: Some(65_536),
+            send_buffer_size: Some(65_536),
+        };
+
+        socket_options.apply(&client).unwrap();
+
+        let socket = socket2::SockRef::from(&client);
+        assert!(socket.nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        assert!(socket.recv_buffer_size().unwrap() >= 65_536);
+        assert!(socket.send_buffer_size().unwrap() >= 65_536);
+    }
+
+    #[test]
+    fn test_listener_socket_options() {
+        let config = Config::read(
+            &ConfigFile::parse(
+                r#"
+                API_BASE_URL = "http://localhost"
+                VEHICLE_MANAGEMENT_SERVICE_API_KEY = "API_KEY"
+                BASE_FILE_PATH = "."
+                WRITE_TO_FILE = false
+                TCP_NODELAY = true
+                TCP_KEEPALIVE_SECONDS = 60
+                TCP_RECV_BUFFER_SIZE = 65536
+                FMB1XX_TCP_KEEPALIVE_SECONDS = 30
+                FMB6XX_TCP_NODELAY = false
+                UDP_RECV_BUFFER_SIZE = 262144
+            "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let listeners = &config.listeners;
+
+        let fmc_options = listeners.get_socket_options(DeviceFamily::Fmc);
+        assert_eq!(Some(true), fmc_options.nodelay);
+        assert_eq!(
+            Some(std::time::Duration::from_secs(60)),
+            fmc_options.keepalive_time
+        );
+
+        // Options a listener doesn't override fall back to the global ones
+        let fmb1xx_options = listeners.get_socket_options(DeviceFamily::Fmb1xx);
+        assert_eq!(Some(true), fmb1xx_options.nodelay);
+        assert_eq!(
+            Some(std::time::Duration::from_secs(30)),
+            fmb1xx_options.keepalive_time
+        );
+        assert_eq!(Some(65_536), fmb1xx_options.recv_buffer_size);
+
+        let fmb6xx_options = listeners.get_socket_options(DeviceFamily::Fmb6xx);
+        assert_eq!(Some(false), fmb6xx_options.nodelay);
+        assert_eq!(
+            Some(std::time::Duration::from_secs(60)),
+            fmb6xx_options.keepalive_time
+        );
+
+        assert_eq!(Some(262_144), listeners.udp_socket_options.recv_buffer_size);
+        assert_eq!(None, listeners.udp_socket_options.send_buffer_size);
+        assert_eq!(None, listeners.udp_socket_options.nodelay);
+    }
+
+    #[test]
+    fn test_accept_retry_delay() {
+        assert_eq!(get_accept_retry_delay(1).as_millis(), 100);
+        assert_eq!(get_accept_retry_delay(2).as_millis(), 200);
+        assert_eq!(get_accept_retry_delay(3).as_millis(), 400);
+        assert_eq!(get_accept_retry_delay(10).as_secs(), 5);
+        assert_eq!(get_accept_retry_delay(u32::MAX).as_secs(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_api_runtime() {
+        init_api_runtime(1).unwrap();
+
+        let thread_name =
+            run_api_request(async { std::thread::current().name().map(String::from) }).await;
+
+        assert_eq!(thread_name.as_deref(), Some("vehicle-management-api"));
+    }
+
+    #[tokio::test]
+    async fn test_connection_memory_cap() {
+        start_vehicle_management_mock();
+        let imei = get_random_imei_of_length(15);
+        let mut record_handler = get_teltonika_records_handler(
+            Some("F8C5BC38-0213-487D-A37A-553AC3A9D77F".to_string()),
+            Some(imei.clone()),
+        );
+        record_handler.set_max_memory_bytes(Some(1));
+        let record_1 = AVLRecordBuilder::new().with_angle(90).build();
+        let record_2 = AVLRecordBuilder::new().with_angle(180).build();
+
+        record_handler
+            .handle_records(vec![record_1, record_2])
+            .await;
+
+        let base_cache_path = record_handler.get_base_cache_path();
+        let locations_cache = TruckLocation::read_from_file(base_cache_path.to_str().unwrap());
+        assert_eq!(2, locations_cache.len());
+        assert_eq!(
+            2,
+            metrics::get_counter("receiver_memory_capped_records_total", &[("imei", &imei)])
+        );
+    }
+
+    #[test]
+    fn test_imei_validation() {
+        assert!(is_valid_imei("490154203237518"));
+        assert!(is_valid_imei("356307042441013"));
+        assert!(!is_valid_imei("490154203237519"));
+        assert!(!is_valid_imei("35630704244101"));
+        assert!(is_valid_imei("3563070424410101"));
+        assert!(!is_valid_imei("35630704244101A"));
+        assert!(!is_valid_imei(""));
+    }
+
+    #[test]
+    fn test_vehicle_api_error_mapping() {
+        let response_error = |status: u16| {
+            VehicleApiErrorKind::from(
+                vehicle_management_service::apis::Error::<()>::ResponseError(
+                    vehicle_management_service::apis::ResponseContent {
+                        status: reqwest::StatusCode::from_u16(status).unwrap(),
+                        content: "content".to_string(),
+                        entity: None,
+                    },
+                ),
+            )
+        };
+
+        assert_eq!(response_error(409), VehicleApiErrorKind::Conflict);
+        assert_eq!(response_error(404), VehicleApiErrorKind::NotFound);
+        assert_eq!(response_error(403), VehicleApiErrorKind::Unauthorized);
+        assert_eq!(
+            response_error(400),
+            VehicleApiErrorKind::Rejected {
+                status: 400,
+                content: "content".to_string()
+            }
+        );
+        assert_eq!(
+            response_error(503),
+            VehicleApiErrorKind::Server { status: 503 }
+        );
+        assert!(response_error(503).is_retryable());
+        assert!(!response_error(400).is_retryable());
+        assert!(VehicleApiErrorKind::Transport("timeout".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_failed_api_request_recording() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let base_cache_path = cache_dir.path().to_str().unwrap();
+        let error = VehicleApiError {
+            request_id: uuid::Uuid::new_v4(),
+            kind: VehicleApiErrorKind::Server { status: 503 },
+        };
+
+        let frame = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().build())
+            .build();
+        let provenance = FrameProvenance::new(
+            frame.crc16,
+            "c3f5a1e2-7b1d-4d7e-9f3a-2b6c8d0e4f11",
+            Some(std::net::IpAddr::from([10, 0, 0, 1])),
+        );
+
+        FailedApiRequest::record(
+            &error,
+            "truck_speed",
+            base_cache_path,
+            Some(provenance.clone()),
+        );
+
+        let failed_requests = FailedApiRequest::read_from_file(base_cache_path);
+        assert_eq!(1, failed_requests.len());
+        assert_eq!(error.request_id.to_string(), failed_requests[0].request_id);
+        assert_eq!("truck_speed", failed_requests[0].event_type);
+        assert_eq!(Some(provenance), failed_requests[0].provenance);
+        assert_eq!(
+            Some("10.0.0.1"),
+            failed_requests[0]
+                .provenance
+                .as_ref()
+                .and_then(|provenance| provenance.source_ip.as_deref())
+        );
+    }
+
+    #[test]
+    fn test_statistics_summary() {
+        let previous = StatisticsSnapshot {
+            connections: 1,
+            frames: 10,
+            records: 100,
+            bytes: 1_000,
+            parse_errors: 0,
+            api_failures: 2,
+        };
+        let current = StatisticsSnapshot {
+            connections: 3,
+            frames: 15,
+            records: 160,
+            bytes: 1_500,
+            parse_errors: 1,
+            api_failures: 2,
+        };
+
+        assert_eq!(
+            current.since(&previous),
+            StatisticsSnapshot {
+                connections: 2,
+                frames: 5,
+                records: 60,
+                bytes: 500,
+                parse_errors: 1,
+                api_failures: 0,
+            }
+        );
+
+        metrics::increment_counter(
+            "test_summary_total",
+            &[("operation", "a"), ("result", "failure")],
+        );
+        metrics::add_to_counter(
+            "test_summary_total",
+            &[("operation", "b"), ("result", "failure")],
+            2,
+        );
+        metrics::increment_counter(
+            "test_summary_total",
+            &[("operation", "a"), ("result", "success")],
+        );
+        assert_eq!(
+            3,
+            metrics::get_counter_total("test_summary_total", &[("result", "failure")])
+        );
+        assert_eq!(4, metrics::get_counter_total("test_summary_total", &[]));
+    }
+
+    #[test]
+    fn test_completeness_report() {
+        let window_end = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let gap_start = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 3, 0, 0).unwrap();
+        let gap_end = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 3, 30, 0).unwrap();
+        let received_intervals = (0..24 * 60)
+            .map(|minute| (window_end.timestamp() - minute * 60) / 60)
+            .filter(|interval| {
+                *interval < gap_start.timestamp() / 60 || *interval >= gap_end.timestamp() / 60
+            })
+            .collect::<std::collections::BTreeSet<i64>>();
+
+        let report = build_completeness_report("imei", &received_intervals, window_end, 60);
+
+        assert_eq!(1440, report.expected_intervals);
+        assert_eq!(1410, report.received_intervals);
+        assert_eq!(vec![(gap_start, gap_end)], report.gaps);
+        assert!((report.get_percentage() - 97.916).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_device_authentication() {
+        let authenticated_imei = get_random_imei_of_length(15);
+        let rejected_imei = get_random_imei_of_length(15);
+        let tokens = parse_device_auth_tokens(&format!(
+            "{}=secret, {}=other-secret",
+            authenticated_imei, rejected_imei
+        ))
+        .unwrap();
+        assert!(parse_device_auth_tokens("352093081452251").is_err());
+        assert!(parse_device_auth_tokens("352093081452251=").is_err());
+
+        let authenticator = DeviceAuthenticator::new(tokens.clone());
+        assert_eq!(
+            DeviceAuthResult::NotRequired,
+            authenticator.authenticate("unknown", None)
+        );
+        assert_eq!(
+            DeviceAuthResult::Authenticated,
+            authenticator.authenticate(&authenticated_imei, Some("secret"))
+        );
+        assert_eq!(
+            DeviceAuthResult::Rejected,
+            authenticator.authenticate(&authenticated_imei, Some("secrets"))
+        );
+        assert_eq!(
+            DeviceAuthResult::Rejected,
+            authenticator.authenticate(&authenticated_imei, None)
+        );
+        assert_eq!(
+            1,
+            metrics::get_counter(
+                DEVICE_AUTH_FAILURES_METRIC,
+                &[("imei", &authenticated_imei), ("reason", "missing")]
+            )
+        );
+
+        init_device_auth(tokens);
+        let token_message = |token: &str| {
+            let mut data = vec![0x0C, 0x01, 0x06];
+            data.extend_from_slice(&(token.len() as u32).to_be_bytes());
+            data.extend_from_slice(token.as_bytes());
+            data.push(0x01);
+            let mut message = vec![0, 0, 0, 0];
+            message.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            message.extend_from_slice(&data);
+            message.extend_from_slice(&(nom_teltonika::crc16(&data) as u32).to_be_bytes());
+            message
+        };
+        let frame = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().build())
+            .build();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let authenticated_stream = tokio_test::io::Builder::new()
+            .read(&build_valid_imei_packet(&authenticated_imei))
+            .write(b"\x01")
+            .read(&token_message("secret"))
+            .read(&frame.to_bytes())
+            .write(&(frame.records.len() as u32).to_be_bytes())
+            .build();
+        let result = TeltonikaConnection::handle_connection(
+            authenticated_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await;
+        assert!(result.is_ok());
+
+        let rejected_stream = tokio_test::io::Builder::new()
+            .read(&build_valid_imei_packet(&rejected_imei))
+            .write(b"\x01")
+            .read(&token_message("secret"))
+            .build();
+        let result = TeltonikaConnection::handle_connection(
+            rejected_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_codec12_messages() {
+        assert_eq!(
+            str_to_bytes("000000000000000F0C010500000007676574696E666F0100004312"),
+            build_codec12_command("getinfo")
+        );
+
+        let response = "INI:2019/7/22 7:22 RTC:2019/7/22 7:53";
+        let mut data = vec![0x0C, 0x01, 0x06];
+        data.extend_from_slice(&(response.len() as u32).to_be_bytes());
+        data.extend_from_slice(response.as_bytes());
+        data.push(0x01);
+        let mut buffer = vec![0, 0, 0, 0];
+        buffer.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        buffer.extend_from_slice(&data);
+        buffer.extend_from_slice(&(nom_teltonika::crc16(&data) as u32).to_be_bytes());
+
+        let frame = AVLFrameBuilder::new()
+            .add_record(
+                AVLRecordBuilder::new()
+                    .with_timestamp(chrono::Utc.timestamp_millis_opt(1_714_651_200_000).unwrap())
+                    .build(),
+            )
+            .build();
+        let frame_bytes = frame.to_bytes();
+        buffer.extend_from_slice(&frame_bytes[..10]);
+
+        match parse_message(&mut buffer).unwrap() {
+            Some(TeltonikaMessage::CommandResponse(parsed)) => assert_eq!(response, parsed),
+            other => panic!("Expected command response, got {:?}", other),
+        }
+        assert_eq!(&frame_bytes[..10], &buffer[..]);
+        assert!(parse_message(&mut buffer).unwrap().is_none());
+
+        buffer.extend_from_slice(&frame_bytes[10..]);
+        match parse_message(&mut buffer).unwrap() {
+            Some(TeltonikaMessage::Frame(parsed)) => assert_eq!(frame.records, parsed.records),
+            other => panic!("Expected frame, got {:?}", other),
+        }
+        assert!(buffer.is_empty());
+
+        let mut unsupported =
+            str_to_bytes("000000000000000F0E010500000007676574696E666F0100004312");
+        assert!(parse_message(&mut unsupported).is_err());
+        assert!(unsupported.is_empty());
+    }
+
+    #[test]
+    fn test_oversized_message_length() {
+        let mut oversized = vec![0, 0, 0, 0];
+        oversized.extend_from_slice(&u32::MAX.to_be_bytes());
+        oversized.push(0x08);
+
+        assert!(parse_message(&mut oversized).unwrap().is_none());
+        let err = check_message_length(&oversized, 1024).unwrap_err();
+        assert_eq!(std::io::ErrorKind::OutOfMemory, err.kind());
+
+        let frame_bytes = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().build())
+            .build()
+            .to_bytes();
+        assert!(check_message_length(&frame_bytes[..10], frame_bytes.len()).is_ok());
+        assert!(check_message_length(&frame_bytes[..10], frame_bytes.len() - 1).is_err());
+        assert!(check_message_length(&[0; 1025], 1024).is_err());
+    }
+
+    #[test]
+    fn test_gap_detector() {
+        let start = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let records_at = |minutes: &[i64]| {
+            minutes
+                .iter()
+                .map(|minute| {
+                    AVLRecordBuilder::new()
+                        .with_timestamp(start + chrono::Duration::minutes(*minute))
+                        .build()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut disabled_detector = TeltonikaGapDetector::with_configuration(None, 600);
+        assert!(disabled_detector
+            .handle_records(&records_at(&[0]))
+            .is_none());
+        assert!(disabled_detector
+            .handle_records(&records_at(&[60]))
+            .is_none());
+
+        let mut detector =
+            TeltonikaGapDetector::with_configuration(Some("getrecord".to_string()), 600);
+        assert!(detector.handle_records(&records_at(&[0, 1])).is_none());
+        assert!(detector.handle_records(&records_at(&[5, 6])).is_none());
+
+        let (gap, command) = detector.handle_records(&records_at(&[30, 31])).unwrap();
+        assert_eq!(start + chrono::Duration::minutes(6), gap.start);
+        assert_eq!(start + chrono::Duration::minutes(30), gap.end);
+        assert_eq!("getrecord", command);
+
+        assert!(detector.handle_records(&records_at(&[60])).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_latency_probe() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let temp_dir = tempfile::tempdir().unwrap();
+            TeltonikaConnection::handle_connection(
+                socket,
+                None,
+                DeviceFamily::default(),
+                temp_dir.path(),
+                1_000,
+                0,
+            )
+            .await
+            .unwrap();
+        });
+
+        probe::send_probe_frame(&address).await.unwrap();
+
+        let latency = metrics::get_gauge(PROBE_LATENCY_METRIC, &[]).unwrap();
+        assert!((0.0..5.0).contains(&latency));
+    }
+
+    #[tokio::test]
+    async fn test_frame_ack_write_retry() {
+        let imei = get_random_imei_of_length(15);
+        let frame = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().build())
+            .build();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mock_stream = tokio_test::io::Builder::new()
+            .read(&build_valid_imei_packet(&imei))
+            .write(b"\x01")
+            .read(&frame.to_bytes())
+            .write_error(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "Transient error",
+            ))
+            .write(&(frame.records.len() as u32).to_be_bytes())
+            .build();
+
+        let result = TeltonikaConnection::handle_connection(
+            mock_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_frame_ack_write_retries_exhausted() {
+        let imei = get_random_imei_of_length(15);
+        let frame = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().build())
+            .build();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut builder = tokio_test::io::Builder::new();
+        builder
+            .read(&build_valid_imei_packet(&imei))
+            .write(b"\x01")
+            .read(&frame.to_bytes());
+        // The first attempt and both retries fail
+        for _ in 0..3 {
+            builder.write_error(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "Broken pipe",
+            ));
+        }
+        let mock_stream = builder.build();
+        // The mock hands out the write errors only once the builder has released them
+        drop(builder);
+
+        let result = TeltonikaConnection::handle_connection(
+            mock_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await;
+
+        // The connection is closed without panicking its task
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_spoofing_detection() {
+        let first_source = std::net::IpAddr::from([10, 0, 0, 1]);
+        let second_source = std::net::IpAddr::from([10, 0, 0, 2]);
+        let received_at = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let detector = SpoofingDetector::new(300, false);
+
+        assert_eq!(
+            FrameSource::Trusted,
+            detector.check_frame_source("spoofed", first_source, received_at)
+        );
+        assert_eq!(
+            FrameSource::Suspected,
+            detector.check_frame_source(
+                "spoofed",
+                second_source,
+                received_at + chrono::Duration::seconds(60)
+            )
+        );
+        assert_eq!(
+            FrameSource::Trusted,
+            detector.check_frame_source(
+                "spoofed",
+                first_source,
+                received_at + chrono::Duration::seconds(600)
+            )
+        );
+        assert_eq!(
+            FrameSource::Trusted,
+            detector.check_frame_source(
+                "other",
+                second_source,
+                received_at + chrono::Duration::seconds(600)
+            )
+        );
+
+        let quarantining_detector = SpoofingDetector::new(300, true);
+        quarantining_detector.check_frame_source("spoofed", first_source, received_at);
+        for seconds in [60, 120] {
+            assert_eq!(
+                FrameSource::Quarantined,
+                quarantining_detector.check_frame_source(
+                    "spoofed",
+                    second_source,
+                    received_at + chrono::Duration::seconds(seconds)
+                )
+            );
+        }
+        assert_eq!(
+            FrameSource::Trusted,
+            quarantining_detector.check_frame_source(
+                "spoofed",
+                first_source,
+                received_at + chrono::Duration::seconds(180)
+            )
+        );
+        assert_eq!(
+            3,
+            metrics::get_counter(SPOOFING_SUSPECTED_METRIC, &[("imei", "spoofed")])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_payload_validation() {
+        let timestamp = chrono::Utc::now().timestamp();
+        let location = TruckLocation {
+            id: None,
+            timestamp,
+            latitude: 61.6885,
+            longitude: 27.2723,
+            heading: 90.0,
+        };
+        assert!(location.validate().is_ok());
+        assert!(TruckLocation {
+            latitude: 91.0,
+            ..location.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(TruckLocation {
+            heading: f64::NAN,
+            ..location.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(TruckLocation {
+            timestamp: timestamp + 2 * 24 * 60 * 60,
+            ..location.clone()
+        }
+        .validate()
+        .is_err());
+
+        let speed = TruckSpeed {
+            id: None,
+            timestamp,
+            speed: 80.0,
+        };
+        assert!(speed.validate().is_ok());
+        assert!(TruckSpeed {
+            speed: -1.0,
+            ..speed.clone()
+        }
+        .validate()
+        .is_err());
+
+        let driver_card = TruckDriverCard {
+            id: "1069619335000001".to_string(),
+            timestamp,
+        };
+        assert!(driver_card.validate().is_ok());
+        assert!(TruckDriverCard {
+            id: "10696193\u{0}".to_string(),
+            timestamp,
+        }
+        .validate()
+        .is_err());
+        assert!(TruckDriveState {
+            id: None,
+            timestamp: 0,
+            state: TruckDriveStateEnum::Drive,
+            driver_id: None,
+            driver_card_id: None,
+        }
+        .validate()
+        .is_err());
+
+        start_vehicle_management_mock();
+        let truck_id = uuid::Uuid::new_v4().to_string();
+        let error = VehicleApi
+            .create_truck_location(
+                &truck_id,
+                "imei",
+                TruckLocation {
+                    longitude: 181.0,
+                    ..location.clone()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(!error.kind.is_cacheable());
+        // Invalid payloads are not sent
+        assert!(get_received_requests(&truck_id).is_empty());
+    }
+
+    #[test]
+    fn test_outbound_capture() {
+        for speed in 0..25 {
+            outbound_capture::capture(
+                "test_capture",
+                uuid::Uuid::new_v4(),
+                "truck",
+                serde_json::json!({ "speed": speed, "driverCardId": "1069619335000001" }),
+                "success".to_string(),
+            );
+        }
+
+        let captured_requests = outbound_capture::get_captured_requests()
+            .remove("test_capture")
+            .unwrap();
+        assert_eq!(20, captured_requests.len());
+        assert_eq!(serde_json::json!(24), captured_requests[0].payload["speed"]);
+        assert_eq!(serde_json::json!(5), captured_requests[19].payload["speed"]);
+        assert_eq!(
+            serde_json::json!("************0001"),
+            captured_requests[0].payload["driverCardId"]
+        );
+    }
+
+    #[test]
+    fn test_truck_cache() {
+        let truck_cache = TruckCache::new(3600, 300, 100);
+        let now = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let truck_id = uuid::Uuid::new_v4();
+        let negative_hits_before =
+            metrics::get_counter(TRUCK_CACHE_LOOKUPS_METRIC, &[("result", "negative_hit")]);
+
+        assert_eq!(TruckCacheLookup::Miss, truck_cache.lookup("VIN1", now));
+        truck_cache.insert("VIN1", Some(truck_id), now);
+        truck_cache.insert("VIN2", None, now);
+
+        let later = now + chrono::Duration::minutes(10);
+        assert_eq!(
+            TruckCacheLookup::Hit(truck_id),
+            truck_cache.lookup("VIN1", later)
+        );
+        assert_eq!(
+            TruckCacheLookup::NegativeHit,
+            truck_cache.lookup("VIN2", now + chrono::Duration::minutes(1))
+        );
+        assert_eq!(TruckCacheLookup::Miss, truck_cache.lookup("VIN2", later));
+        assert_eq!(
+            TruckCacheLookup::Miss,
+            truck_cache.lookup("VIN1", now + chrono::Duration::hours(2))
+        );
+        assert!(
+            metrics::get_counter(TRUCK_CACHE_LOOKUPS_METRIC, &[("result", "negative_hit")])
+                > negative_hits_before
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truck_cache_coalesced_lookups() {
+        let truck_cache = TruckCache::new(3600, 300, 100);
+        let truck_id = uuid::Uuid::new_v4();
+        let fetches = std::sync::atomic::AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            Ok(Some(truck_id))
+        };
+        let coalesced_before = metrics::get_counter(TRUCK_LOOKUPS_COALESCED_METRIC, &[]);
+
+        // Concurrent lookups of the same VIN share a single fetch
+        let truck_ids = tokio::join!(
+            truck_cache.get_or_fetch("VIN1", fetch),
+            truck_cache.get_or_fetch("VIN1", fetch),
+            truck_cache.get_or_fetch("VIN1", fetch),
+        );
+        assert_eq!((Some(truck_id), Some(truck_id), Some(truck_id)), truck_ids);
+        assert_eq!(1, fetches.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(metrics::get_counter(TRUCK_LOOKUPS_COALESCED_METRIC, &[]) >= coalesced_before + 2);
+        assert_eq!(
+            Some(truck_id),
+            truck_cache.get_or_fetch("VIN1", fetch).await
+        );
+        assert_eq!(1, fetches.load(std::sync::atomic::Ordering::SeqCst));
+
+        // Failed fetches are not cached
+        assert_eq!(
+            None,
+            truck_cache.get_or_fetch("VIN2", || async { Err(()) }).await
+        );
+        assert_eq!(
+            Some(truck_id),
+            truck_cache.get_or_fetch("VIN2", fetch).await
+        );
+        assert_eq!(2, fetches.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_truck_cache_warmup() {
+        let _mock_server = start_vehicle_management_mock();
+
+        assert_eq!(1, warm_up_truck_cache().await.unwrap());
+        assert_eq!(
+            TruckCacheLookup::Hit(
+                uuid::Uuid::from_str("3ffaf18c-69e4-4f8a-9179-9aec5bc96e1c").unwrap()
+            ),
+            get_truck_cache().lookup("W1T96302X10704959", chrono::Utc::now())
+        );
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let imei = "352093081452251";
+        let speed = serde_json::json!({ "speed": 80.0, "timestamp": 1_714_651_200 });
+        let key = get_idempotency_key("create_truck_speed", imei, &speed);
+
+        assert_eq!(key, get_idempotency_key("create_truck_speed", imei, &speed));
+        // Events of the same second with different content are distinct
+        assert_ne!(
+            key,
+            get_idempotency_key(
+                "create_truck_speed",
+                imei,
+                &serde_json::json!({ "speed": 81.0, "timestamp": 1_714_651_200 })
+            )
+        );
+        assert_ne!(
+            key,
+            get_idempotency_key("create_truck_speed", "352093081452269", &speed)
+        );
+        assert_ne!(
+            key,
+            get_idempotency_key("create_truck_location", imei, &speed)
+        );
+    }
+
+    #[test]
+    fn test_codec13_messages() {
+        let response = [0x01, 0x02, 0xFF];
+        let mut data = vec![0x0D, 0x01, 0x05];
+        data.extend_from_slice(&(response.len() as u32 + 4).to_be_bytes());
+        data.extend_from_slice(&1_714_651_200_u32.to_be_bytes());
+        data.extend_from_slice(&response);
+        data.push(0x01);
+        let mut buffer = vec![0, 0, 0, 0];
+        buffer.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        buffer.extend_from_slice(&data);
+        buffer.extend_from_slice(&(nom_teltonika::crc16(&data) as u32).to_be_bytes());
+        let frame = AVLFrameBuilder::new()
+            .add_record(
+                AVLRecordBuilder::new()
+                    .with_timestamp(chrono::Utc.timestamp_millis_opt(1_714_651_200_000).unwrap())
+                    .build(),
+            )
+            .build();
+        buffer.extend_from_slice(&frame.to_bytes());
+
+        match parse_message(&mut buffer).unwrap() {
+            Some(TeltonikaMessage::BinaryCommandResponse { timestamp, data }) => {
+                assert_eq!(1_714_651_200, timestamp.timestamp());
+                assert_eq!(response.to_vec(), data);
+            }
+            other => panic!("Expected binary command response, got {:?}", other),
+        }
+        match parse_message(&mut buffer).unwrap() {
+            Some(TeltonikaMessage::Frame(parsed)) => assert_eq!(frame.records, parsed.records),
+            other => panic!("Expected frame, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_api_routing() {
+        let pilot_url = "https://pilot.example.com";
+        let overrides = parse_routing_overrides(&format!(
+            "imei:35209*=https://imei.example.com, truck:pilot-truck={}",
+            pilot_url
+        ))
+        .unwrap();
+        assert_eq!(
+            RouteSelector::Imei("35209*".to_string()),
+            overrides[0].selector
+        );
+        assert!(parse_routing_overrides("vin:ABC=https://example.com").is_err());
+        assert!(parse_routing_overrides("truck:pilot-truck").is_err());
+
+        let routing = ApiRouting::new(overrides);
+        assert_eq!(
+            Some(pilot_url.to_string()),
+            routing.get_base_url("pilot-truck")
+        );
+        assert_eq!(None, routing.get_base_url("imei-truck"));
+
+        routing.register_truck("352093081452251", "imei-truck");
+        routing.register_truck("356307042441013", "production-truck");
+        assert_eq!(
+            Some("https://imei.example.com".to_string()),
+            routing.get_base_url("imei-truck")
+        );
+        assert_eq!(None, routing.get_base_url("production-truck"));
+    }
+
+    #[test]
+    fn test_raw_captures_retention() {
+        let base_path = tempfile::tempdir().unwrap();
+        let device_path = base_path.path().join("352093081452251");
+        std::fs::create_dir_all(&device_path).unwrap();
+        for file_name in [
+            "2024-04-17.txt",
+            "2024-04-18.txt",
+            "2024-05-02.txt",
+            "notes.txt",
+        ] {
+            std::fs::write(device_path.join(file_name), "").unwrap();
+        }
+        let purged_before = metrics::get_counter(
+            RETENTION_PURGED_FILES_METRIC,
+            &[("store", RAW_CAPTURES_STORE)],
+        );
+
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+        assert_eq!(1, purge_raw_captures(base_path.path(), 14, today).unwrap());
+
+        assert!(!device_path.join("2024-04-17.txt").exists());
+        assert!(device_path.join("2024-04-18.txt").exists());
+        assert!(device_path.join("2024-05-02.txt").exists());
+        assert!(device_path.join("notes.txt").exists());
+        assert!(
+            metrics::get_counter(
+                RETENTION_PURGED_FILES_METRIC,
+                &[("store", RAW_CAPTURES_STORE)]
+            ) > purged_before
+        );
+    }
+
+    #[test]
+    fn test_admin_openapi_document() {
+        for path in [
+            "/metrics",
+            "/queues",
+            "/requests",
+            "/io-elements",
+            "/maintenance",
+            "/connections",
+            "/devices/processing",
+            "/devices/statistics.csv",
+            "/devices/{imei}/processing",
+            "/devices/{imei}/commands",
+            "/devices/{imei}/backlog",
+            "/devices/{imei}/cache/purge",
+            "/devices/{imei}/dead-letter-events",
+            "/actions",
+            "/devices/{imei}/actions/{action}",
+            "/openapi.yaml",
+        ] {
+            assert!(
+                OPENAPI_DOCUMENT.contains(&format!("\n  {}:\n", path)),
+                "Admin OpenAPI document is missing path {}",
+                path
+            );
+        }
+        for schema in [
+            "QueueDepth",
+            "CapturedRequest",
+            "IoElement",
+            "ProcessingMode",
+            "MaintenanceMode",
+            "CommandResponse",
+            "DeviceAction",
+            "DeadLetterEvent",
+            "ConnectionSummary",
+            "DeviceBacklog",
+        ] {
+            assert!(OPENAPI_DOCUMENT.contains(&format!("\n    {}:\n", schema)));
+        }
+    }
+
+    #[test]
+    fn test_io_elements() {
+        assert!(IO_ELEMENTS
+            .windows(2)
+            .all(|elements| elements[0].id < elements[1].id));
+        let ignition = get_io_element(239).unwrap();
+        assert_eq!("Ignition", ignition.name);
+        assert!(ignition.models.is_empty());
+        assert_eq!(Some("km/h"), get_io_element(191).unwrap().unit);
+        assert_eq!("239 (Ignition)", describe_io_element(239));
+        assert_eq!("9999", describe_io_element(9999));
+    }
+
+    #[test]
+    fn test_log_throttle() {
+        let log_throttle = LogThrottle::new(std::time::Duration::from_secs(300));
+        let now = std::time::Instant::now();
+
+        assert_eq!(Some(0), log_throttle.check("imei:no_handler:1", now));
+        assert_eq!(Some(0), log_throttle.check("imei:no_handler:2", now));
+        for seconds in 1..=3 {
+            assert_eq!(
+                None,
+                log_throttle.check(
+                    "imei:no_handler:1",
+                    now + std::time::Duration::from_secs(seconds)
+                )
+            );
+        }
+        assert_eq!(
+            Some(3),
+            log_throttle.check(
+                "imei:no_handler:1",
+                now + std::time::Duration::from_secs(300)
+            )
+        );
+        assert_eq!(" (3 similar messages suppressed)", describe_suppressed(3));
+        assert_eq!("", describe_suppressed(0));
+    }
+
+    #[test]
+    fn test_vin_mismatch_detection() {
+        let detector = VinMismatchDetector::default();
+        let imei = "352093081452251";
+        let mismatches_before = metrics::get_counter(VIN_MISMATCH_METRIC, &[("imei", imei)]);
+
+        assert_eq!(None, detector.check_vin(imei, "W1T96302X10704959"));
+        assert_eq!(None, detector.check_vin(imei, "W1T96302X10704959"));
+        assert_eq!(
+            None,
+            detector.check_vin("356307042441013", "YS2R4X20005399401")
+        );
+        assert_eq!(
+            Some("W1T96302X10704959".to_string()),
+            detector.check_vin(imei, "YS2R4X20005399401")
+        );
+        assert_eq!(None, detector.check_vin(imei, "YS2R4X20005399401"));
+        assert_eq!(
+            mismatches_before + 1,
+            metrics::get_counter(VIN_MISMATCH_METRIC, &[("imei", imei)])
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_imei_handshake_timeout() {
+        let (_client, server) = tokio::io::duplex(64);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let timeouts_before = metrics::get_counter(IMEI_HANDSHAKE_TIMEOUTS_METRIC, &[]);
+
+        let result = TeltonikaConnection::handle_connection(
+            server,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(metrics::get_counter(IMEI_HANDSHAKE_TIMEOUTS_METRIC, &[]) > timeouts_before);
+    }
+
+    #[tokio::test]
+    async fn test_device_backlog() {
+        let imei = get_random_imei_of_length(15);
+        let frame = AVLFrameBuilder::new()
+            .add_record(
+                AVLRecordBuilder::new()
+                    .with_timestamp(chrono::Utc::now() - chrono::Duration::hours(3))
+                    .build(),
+            )
+            .add_record(
+                AVLRecordBuilder::new()
+                    .with_timestamp(chrono::Utc::now() - chrono::Duration::hours(2))
+                    .build(),
+            )
+            .build();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mock_stream = tokio_test::io::Builder::new()
+            .read(&build_valid_imei_packet(&imei))
+            .write(b"\x01")
+            .read(&frame.to_bytes())
+            .write(&(frame.records.len() as u32).to_be_bytes())
+            .build();
+
+        TeltonikaConnection::handle_connection(
+            mock_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await
+        .unwrap();
+
+        let backlog = metrics::get_gauge(DEVICE_BACKLOG_METRIC, &[("imei", &imei)]).unwrap();
+        assert!((7_200.0..7_260.0).contains(&backlog));
+    }
+
+    #[test]
+    fn test_processing_control() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state_file = temp_dir.path().join("processing.json");
+        let processing_control = ProcessingControl::new(Some(state_file.clone()));
+        assert_eq!(
+            ProcessingMode::Active,
+            processing_control.get_mode("352093081452251")
+        );
+
+        processing_control
+            .set_mode("352093081452251", ProcessingMode::Paused)
+            .unwrap();
+        processing_control
+            .set_mode("356307042441013", ProcessingMode::Muted)
+            .unwrap();
+        processing_control
+            .set_mode("356307042441013", ProcessingMode::Active)
+            .unwrap();
+
+        let restored_control = ProcessingControl::new(Some(state_file));
+        assert_eq!(
+            ProcessingMode::Paused,
+            restored_control.get_mode("352093081452251")
+        );
+        assert_eq!(1, restored_control.get_modes().len());
+    }
+
+    #[test]
+    fn test_synthetic_devices() {
+        let sandbox_url = "https://sandbox.example.com";
+        let synthetic_devices =
+            SyntheticDevices::new(vec!["352093081452251".to_string()], sandbox_url.to_string());
+        assert!(synthetic_devices.is_synthetic("352093081452251"));
+        assert!(!synthetic_devices.is_synthetic("356307042441013"));
+
+        let routing = ApiRouting::new(synthetic_devices.get_routing_overrides());
+        routing.register_truck("352093081452251", "synthetic-truck");
+        assert_eq!(
+            Some(sandbox_url.to_string()),
+            routing.get_base_url("synthetic-truck")
+        );
+    }
+
+    #[test]
+    fn test_pipeline_invariants() {
+        let invariants = PipelineInvariants::default();
+        let get_violations = |invariant: &str| {
+            metrics::get_counter(INVARIANT_VIOLATIONS_METRIC, &[("invariant", invariant)])
+        };
+        let frame_violations_before = get_violations(FRAME_RECORDS_INVARIANT);
+        let cache_violations_before = get_violations(CACHE_DEPTH_INVARIANT);
+
+        assert!(invariants.check_frame_records("352093081452251", 3, 2, 1));
+        assert!(!invariants.check_frame_records("352093081452251", 3, 1, 1));
+
+        let cache_file_path = "/cache/352093081452251/truck_speed_cache.json";
+        assert!(invariants.check_cache_depth(cache_file_path, 5));
+        invariants.record_cache_write(cache_file_path, 2);
+        invariants.record_cache_write(cache_file_path, 3);
+        assert!(invariants.check_cache_depth(cache_file_path, 4));
+        invariants.record_cache_replace(cache_file_path, 0);
+        invariants.record_cache_write(cache_file_path, 0);
+        assert!(invariants.check_cache_depth(cache_file_path, 1));
+        assert!(!invariants.check_cache_depth(cache_file_path, 0));
+
+        assert!(get_violations(FRAME_RECORDS_INVARIANT) > frame_violations_before);
+        assert!(get_violations(CACHE_DEPTH_INVARIANT) > cache_violations_before);
+    }
+
+    #[test]
+    fn test_drive_state_from_value() {
+        assert_eq!(TruckDriveStateEnum::Rest, drive_state_from_value(0));
+        assert_eq!(TruckDriveStateEnum::Drive, drive_state_from_value(3));
+        assert_eq!(TruckDriveStateEnum::Error, drive_state_from_value(6));
+        assert_eq!(TruckDriveStateEnum::NotAvailable, drive_state_from_value(7));
+        // Reserved J1939 values
+        assert_eq!(TruckDriveStateEnum::NotAvailable, drive_state_from_value(4));
+        assert_eq!(TruckDriveStateEnum::NotAvailable, drive_state_from_value(5));
+        assert_eq!(
+            TruckDriveStateEnum::NotAvailable,
+            drive_state_from_value(42)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ack_pipelining() {
+        let imei = get_random_imei_of_length(15);
+        let first_frame = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().with_angle(90).build())
+            .build();
+        let second_frame = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().with_angle(180).build())
+            .add_record(AVLRecordBuilder::new().with_angle(270).build())
+            .build();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mock_stream = tokio_test::io::Builder::new()
+            .read(&build_valid_imei_packet(&imei))
+            .write(b"\x01")
+            .read(&first_frame.to_bytes())
+            .write(&(first_frame.records.len() as u32).to_be_bytes())
+            .read(&second_frame.to_bytes())
+            .write(&(second_frame.records.len() as u32).to_be_bytes())
+            .build();
+
+        TeltonikaConnection::handle_connection(
+            mock_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            2,
+        )
+        .await
+        .unwrap();
+
+        // Records of both frames are dispatched before the connection is closed
+        let cache_path = temp_dir.path().join(&imei);
+        let locations_cache = TruckLocation::read_from_file(cache_path.to_str().unwrap());
+        assert_eq!(3, locations_cache.len());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_reason() {
+        let imei = get_random_imei_of_length(15);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mock_stream = tokio_test::io::Builder::new()
+            .read(&build_valid_imei_packet(&imei))
+            .write(b"\x01")
+            .read(&[0, 0, 0, 0, 0, 0, 0, 4, 0x99, 1, 2, 3])
+            .build();
+        let parse_error_disconnections_before =
+            metrics::get_counter(DISCONNECTIONS_METRIC, &[("reason", "parse_error")]);
+
+        TeltonikaConnection::handle_connection(
+            mock_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            parse_error_disconnections_before + 1,
+            metrics::get_counter(DISCONNECTIONS_METRIC, &[("reason", "parse_error")])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_udp_listener() {
+        let imei = get_random_imei_of_length(15);
+        let frame = AVLFrameBuilder::new()
+            .add_record(AVLRecordBuilder::new().with_angle(90).build())
+            .add_record(AVLRecordBuilder::new().with_angle(180).build())
+            .build();
+        let datagram = build_udp_datagram(&imei, 0xCAFE, 7, &frame);
+        let parsed_datagram = parse_datagram(&datagram).unwrap();
+        assert_eq!(imei, parsed_datagram.imei);
+        assert_eq!(
+            vec![90, 180],
+            parsed_datagram
+                .records
+                .iter()
+                .map(|record| record.angle)
+                .collect::<Vec<u16>>()
+        );
+        assert!(parse_datagram(&datagram[..20]).is_err());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let server_socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_address = server_socket.local_addr().unwrap();
+        tokio::spawn(TeltonikaUdpListener::new(server_socket, temp_dir.path()).run());
+        let client_socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        client_socket
+            .send_to(&datagram, server_address)
+            .await
+            .unwrap();
+
+        let mut ack = [0u8; 16];
+        let ack_length = client_socket.recv(&mut ack).await.unwrap();
+        assert_eq!(
+            &[0x00, 0x05, 0xCA, 0xFE, 0x01, 0x07, 0x02],
+            &ack[..ack_length]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_driver_card_part() {
+        start_vehicle_management_mock();
+        let record_handler = get_teltonika_records_handler(None, None);
+        let valid_driver_card_events =
+            driver_card_id_to_two_part_events("1069619335000001".to_string());
+        let record = AVLRecordBuilder::new()
+            .add_io_event(AVLEventIO {
+                id: 195,
+                value: nom_teltonika::AVLEventIOValue::U64(0xFFFE_3130_3639_3631),
+            })
+            .add_io_event(valid_driver_card_events[1].clone())
+            .add_io_event(AVLEventIO {
+                id: 187,
+                value: nom_teltonika::AVLEventIOValue::U8(1),
+            })
+            .add_io_event(AVLEventIO {
+                id: 184,
+                value: nom_teltonika::AVLEventIOValue::U8(3),
+            })
+            .with_trigger_event_id(187)
+            .build();
+
+        record_handler.handle_records(vec![record]).await;
+
+        let base_cache_path = record_handler.get_base_cache_path().to_str().unwrap();
+        assert!(TruckDriverCard::read_from_file(base_cache_path).is_empty());
+        assert!(TruckDriveState::read_from_file(base_cache_path).is_empty());
+        let failed_events = FailedEvent::read_from_file(base_cache_path);
+        assert_eq!(2, failed_events.len());
+        assert!(failed_events
+            .iter()
+            .all(|failed_event| failed_event.event_id == 195
+                && failed_event.raw_bytes == "FFFE313036393631"));
+    }
+
+    #[test]
+    fn test_codec16_frames() {
+        let timestamp = chrono::Utc.timestamp_millis_opt(1_714_651_200_000).unwrap();
+        let frame = AVLFrameBuilder::new()
+            .with_codec(Codec::C16)
+            .add_record(
+                AVLRecordBuilder::new()
+                    .with_timestamp(timestamp)
+                    .with_trigger_event_id(10_348)
+                    .with_generation_type(EventGenerationCause::OnChange)
+                    .add_io_event(AVLEventIO {
+                        id: 10_348,
+                        value: nom_teltonika::AVLEventIOValue::U8(1),
+                    })
+                    .add_io_event(AVLEventIO {
+                        id: 239,
+                        value: nom_teltonika::AVLEventIOValue::U16(500),
+                    })
+                    .build(),
+            )
+            .build();
+        let mut buffer = frame.to_bytes();
+        assert_eq!(0x10, buffer[8]);
+
+        match parse_message(&mut buffer).unwrap() {
+            Some(TeltonikaMessage::Frame(parsed)) => {
+                assert_eq!(Codec::C16, parsed.codec);
+                assert_eq!(frame.records, parsed.records);
+                assert_eq!(
+                    Some(EventGenerationCause::OnChange),
+                    parsed.records[0].generation_type
+                );
+                // Archived frames are written back in the codec they were received in
+                assert_eq!(frame.to_bytes(), parsed.to_bytes());
+            }
+            other => panic!("Expected frame, got {:?}", other),
+        }
+        assert!(buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_command_channel() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let imei = get_random_imei_of_length(15);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_file_path = temp_dir.path().to_path_buf();
+        let (mut device, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(async move {
+            TeltonikaConnection::handle_connection(
+                server,
+                None,
+                DeviceFamily::default(),
+                &base_file_path,
+                1_000,
+                0,
+            )
+            .await
+        });
+        let command_channel = get_command_channel();
+        assert_eq!(
+            Err(CommandError::NotConnected),
+            command_channel.enqueue(&imei, "getinfo")
+        );
+
+        device
+            .write_all(&build_valid_imei_packet(&imei))
+            .await
+            .unwrap();
+        let mut approval = [0u8; 1];
+        device.read_exact(&mut approval).await.unwrap();
+        assert_eq!(
+            Err(CommandError::InvalidCommand),
+            command_channel.enqueue(&imei, " ")
+        );
+        // Connection registers itself after approving the IMEI
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while command_channel.enqueue(&imei, "getinfo") == Err(CommandError::NotConnected) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let expected_command = build_codec12_command("getinfo");
+        let mut command = vec![0u8; expected_command.len()];
+        device.read_exact(&mut command).await.unwrap();
+        assert_eq!(expected_command, command);
+
+        let response = "INI:2019/7/22 7:22 RTC:2019/7/22 7:53";
+        let mut data = vec![0x0C, 0x01, 0x06];
+        data.extend_from_slice(&(response.len() as u32).to_be_bytes());
+        data.extend_from_slice(response.as_bytes());
+        data.push(0x01);
+        let mut response_frame = vec![0, 0, 0, 0];
+        response_frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        response_frame.extend_from_slice(&data);
+        response_frame.extend_from_slice(&(nom_teltonika::crc16(&data) as u32).to_be_bytes());
+        device.write_all(&response_frame).await.unwrap();
+        drop(device);
+        connection.await.unwrap().unwrap();
+
+        let responses = command_channel.get_responses(&imei);
+        assert_eq!(1, responses.len());
+        assert_eq!(Some("getinfo"), responses[0].command.as_deref());
+        assert_eq!(response, responses[0].response);
+        // Closed connections are unregistered
+        assert_eq!(
+            Err(CommandError::NotConnected),
+            command_channel.enqueue(&imei, "getinfo")
+        );
+    }
+
+    #[test]
+    fn test_default_config() {
+        let source_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut directories = vec![source_dir.clone()];
+        let mut env_keys = Vec::new();
+        while let Some(directory) = directories.pop() {
+            for entry in std::fs::read_dir(directory).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    // Tests are configured separately
+                    if path != source_dir.join("tests") {
+                        directories.push(path);
+                    }
+                } else if path.extension().is_some_and(|extension| extension == "rs") {
+                    let source = std::fs::read_to_string(&path).unwrap();
+                    env_keys.extend(
+                        source
+                            .split("_ENV_KEY: &str =")
+                            .skip(1)
+                            .filter_map(|rest| rest.split('"').nth(1))
+                            .filter(|key| key.chars().all(|c| c.is_ascii_uppercase() || c == '_'))
+                            .map(|key| key.to_string()),
+                    );
+                }
+            }
+        }
+
+        assert!(env_keys.len() > 30);
+        for env_key in env_keys {
+            assert!(
+                DEFAULT_CONFIG.contains(&format!("\n{}=", env_key))
+                    || DEFAULT_CONFIG.contains(&format!("\n# {}=", env_key)),
+                "Default configuration is missing {}",
+                env_key
+            );
+        }
+    }
+
+    /// Fake API recording the events sent by the event handlers
+    #[derive(Default)]
+    struct FakeTruckEventApi {
+        sent_events: std::sync::Arc<std::sync::Mutex<Vec<(String, serde_json::Value)>>>,
+        error: Option<VehicleApiErrorKind>,
+    }
+
+    impl FakeTruckEventApi {
+        fn send<P: serde::Serialize>(
+            &self,
+            truck_id: &str,
+            payload: P,
+        ) -> Result<(), VehicleApiError> {
+            if let Some(kind) = &self.error {
+                return Err(VehicleApiError {
+                    request_id: uuid::Uuid::new_v4(),
+                    kind: kind.clone(),
+                });
+            }
+            self.sent_events
+                .lock()
+                .unwrap()
+                .push((truck_id.to_string(), serde_json::to_value(payload).unwrap()));
+            Ok(())
+        }
+    }
+
+    impl TruckEventApi for FakeTruckEventApi {
+        async fn create_truck_driver_card(
+            &self,
+            truck_id: &str,
+            _imei: &str,
+            truck_driver_card: TruckDriverCard,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, truck_driver_card)
+        }
+
+        async fn create_truck_speed(
+            &self,
+            truck_id: &str,
+            _imei: &str,
+            truck_speed: TruckSpeed,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, truck_speed)
+        }
+
+        async fn create_drive_state(
+            &self,
+            truck_id: &str,
+            _imei: &str,
+            truck_drive_state: TruckDriveState,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, truck_drive_state)
+        }
+
+        async fn create_driver_behavior_event(
+            &self,
+            truck_id: &str,
+            driver_behavior_event: DriverBehaviorEvent,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, driver_behavior_event)
+        }
+
+        async fn create_ble_sensor_readings(
+            &self,
+            truck_id: &str,
+            ble_sensor_readings: BleSensorReadings,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, ble_sensor_readings)
+        }
+
+        async fn create_truck_axle_weights(
+            &self,
+            truck_id: &str,
+            truck_axle_weights: TruckAxleWeights,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, truck_axle_weights)
+        }
+
+        async fn create_truck_engine_hours(
+            &self,
+            truck_id: &str,
+            truck_engine_hours: TruckEngineHours,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, truck_engine_hours)
+        }
+
+        async fn create_truck_engine_rpm(
+            &self,
+            truck_id: &str,
+            truck_engine_rpm: TruckEngineRpm,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, truck_engine_rpm)
+        }
+
+        async fn create_fault_record(
+            &self,
+            truck_id: &str,
+            fault_record: FaultRecord,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, fault_record)
+        }
+
+        async fn create_zone_event(
+            &self,
+            truck_id: &str,
+            zone_event: ZoneEvent,
+        ) -> Result<(), VehicleApiError> {
+            self.send(truck_id, zone_event)
+        }
+    }
+
+    #[test]
+    fn test_speed_event_handler_process_event_data() {
+        let handler = SpeedEventHandler::<FakeTruckEventApi>::default();
+        for (value, expected_speed) in [
+            (nom_teltonika::AVLEventIOValue::U8(0), 0.0),
+            (nom_teltonika::AVLEventIOValue::U8(80), 80.0),
+            (nom_teltonika::AVLEventIOValue::U16(255), 255.0),
+            (nom_teltonika::AVLEventIOValue::U32(90), 90.0),
+        ] {
+            let event = AVLEventIO { id: 191, value };
+            assert_eq!(
+                Some(TruckSpeed {
+                    id: None,
+                    speed: expected_speed,
+                    timestamp: 1_714_651_200,
+                }),
+                handler
+                    .process_event_data(
+                        RecordTrigger::Periodic,
+                        &[&event],
+                        1_714_651_200,
+                        (0.0, 0.0),
+                        "imei"
+                    )
+                    .unwrap()
+            );
+        }
+        assert_eq!(
+            None,
+            handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[],
+                    1_714_651_200,
+                    (0.0, 0.0),
+                    "imei"
+                )
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ble_sensor_signed_temperatures() {
+        let read_temperature = |handler: &BleSensorEventHandler<FakeTruckEventApi>,
+                                event: AVLEventIO| {
+            handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&event],
+                    1_714_651_200,
+                    (0.0, 0.0),
+                    "imei",
+                )
+                .unwrap()
+                .unwrap()
+                .readings[0]
+                .temperature
+        };
+
+        // EYE sensors send hundredths of °C as 16-bit values
+        let mut handler = BleSensorEventHandler::<FakeTruckEventApi>::default();
+        for (value, expected_temperature) in [
+            (nom_teltonika::AVLEventIOValue::U16(-2550i16 as u16), -25.5),
+            (nom_teltonika::AVLEventIOValue::U16(-1i16 as u16), -0.01),
+            (nom_teltonika::AVLEventIOValue::U16(2550), 25.5),
+        ] {
+            let event = AVLEventIO { id: 25, value };
+            assert_eq!(
+                Some(expected_temperature),
+                read_temperature(&handler, event)
+            );
+        }
+
+        // Dallas 1-Wire sensors send tenths of °C as 32-bit values
+        handler.set_temperature_events(vec![72, 73, 74, 75], 10.0);
+        for (value, expected_temperature) in [
+            (nom_teltonika::AVLEventIOValue::U32(-255i32 as u32), -25.5),
+            (nom_teltonika::AVLEventIOValue::U32(255), 25.5),
+            (nom_teltonika::AVLEventIOValue::U16(-255i16 as u16), -25.5),
+            (nom_teltonika::AVLEventIOValue::U8(-5i8 as u8), -0.5),
+        ] {
+            let event = AVLEventIO { id: 72, value };
+            assert_eq!(
+                Some(expected_temperature),
+                read_temperature(&handler, event)
+            );
+        }
+    }
+
+    #[test]
+    fn test_drive_state_event_handler_process_event_data() {
+        let handler = DriverOneDriveStateEventHandler::with_api(FakeTruckEventApi::default());
+        let driver_card_id = "1069619335000001".to_string();
+        let driver_card_events = driver_card_id_to_two_part_events(driver_card_id.clone());
+        for (value, expected_state) in [
+            (0, TruckDriveStateEnum::Rest),
+            (1, TruckDriveStateEnum::DriverAvailable),
+            (2, TruckDriveStateEnum::Work),
+            (3, TruckDriveStateEnum::Drive),
+            (5, TruckDriveStateEnum::NotAvailable),
+            (6, TruckDriveStateEnum::Error),
+            (7, TruckDriveStateEnum::NotAvailable),
+            (8, TruckDriveStateEnum::NotAvailable),
+        ] {
+            let state_event = AVLEventIO {
+                id: 184,
+                value: nom_teltonika::AVLEventIOValue::U8(value),
+            };
+            let events = [&state_event, &driver_card_events[0], &driver_card_events[1]];
+            assert_eq!(
+                Some(TruckDriveState {
+                    id: None,
+                    timestamp: 1_714_651_200,
+                    state: expected_state,
+                    driver_id: None,
+                    driver_card_id: Some(driver_card_id.clone()),
+                }),
+                handler
+                    .process_event_data(
+                        RecordTrigger::Periodic,
+                        &events,
+                        1_714_651_200,
+                        (0.0, 0.0),
+                        "imei"
+                    )
+                    .unwrap(),
+                "Unexpected drive state for value {}",
+                value
+            );
+        }
+
+        let state_event = AVLEventIO {
+            id: 184,
+            value: nom_teltonika::AVLEventIOValue::U8(3),
+        };
+        let no_card_events = [
+            AVLEventIO {
+                id: 195,
+                value: nom_teltonika::AVLEventIOValue::U64(0),
+            },
+            AVLEventIO {
+                id: 196,
+                value: nom_teltonika::AVLEventIOValue::U64(0),
+            },
+        ];
+        assert_eq!(
+            None,
+            handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&state_event, &no_card_events[0], &no_card_events[1]],
+                    1_714_651_200,
+                    (0.0, 0.0),
+                    "imei"
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&driver_card_events[0], &driver_card_events[1]],
+                    1_714_651_200,
+                    (0.0, 0.0),
+                    "imei"
+                )
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_driver_card_event_handler_process_event_data() {
+        let handler = DriverOneCardIdEventHandler::with_api(FakeTruckEventApi::default());
+        let driver_card_id = "1069619335000001".to_string();
+        let driver_card_events = driver_card_id_to_two_part_events(driver_card_id.clone());
+        let events = [&driver_card_events[0], &driver_card_events[1]];
+        let expected_driver_card = TruckDriverCard {
+            id: driver_card_id.clone(),
+            timestamp: 1_714_651_200,
+        };
+        for _ in 0..2 {
+            assert_eq!(
+                Some(expected_driver_card.clone()),
+                handler
+                    .process_event_data(
+                        RecordTrigger::Eventual(187),
+                        &events,
+                        1_714_651_200,
+                        (0.0, 0.0),
+                        "imei"
+                    )
+                    .unwrap()
+            );
+        }
+        // Only records triggered by the driver card presence are handled
+        let subscription = handler.get_record_subscription();
+        assert!(subscription.matches(RecordTrigger::Eventual(187)));
+        assert!(!subscription.matches(RecordTrigger::Periodic));
+        assert!(!subscription.matches(RecordTrigger::Eventual(196)));
+
+        // Filtering skips the card already reported until the card is reset
+        assert!(handler
+            .filter_event_data(expected_driver_card.clone(), "imei")
+            .is_some());
+        assert!(handler
+            .filter_event_data(expected_driver_card.clone(), "imei")
+            .is_none());
+        handler.reset_last_driver_card_id();
+        assert!(handler
+            .filter_event_data(expected_driver_card, "imei")
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_event_handler_send_events() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_cache_path: Box<Path> = temp_dir.path().into();
+        let speed_event = AVLEventIO {
+            id: 191,
+            value: nom_teltonika::AVLEventIOValue::U8(80),
+        };
+        let api = FakeTruckEventApi::default();
+        let sent_events = api.sent_events.clone();
+        let handler = SpeedEventHandler::with_api(api, None);
+        handler
+            .handle_events(
+                RecordTrigger::Periodic,
+                vec![&speed_event],
+                1_714_651_200,
+                (0.0, 0.0),
+                Some("truck".to_string()),
+                base_cache_path.clone(),
+                "imei",
+                None,
+            )
+            .await;
+        handler
+            .handle_events(
+                RecordTrigger::Periodic,
+                vec![&speed_event],
+                1_714_651_260,
+                (0.0, 0.0),
+                None,
+                base_cache_path.clone(),
+                "imei",
+                None,
+            )
+            .await;
+        let expected_speed = TruckSpeed {
+            id: None,
+            speed: 80.0,
+            timestamp: 1_714_651_200,
+        };
+        assert_eq!(
+            vec![(
+                "truck".to_string(),
+                serde_json::to_value(expected_speed).unwrap()
+            )],
+            *sent_events.lock().unwrap()
+        );
+        // Events of a yet unknown truck are cached
+        let cache_path = base_cache_path.to_str().unwrap();
+        assert_eq!(1, TruckSpeed::read_from_file(cache_path).len());
+
+        let failing_handler = SpeedEventHandler::with_api(
+            FakeTruckEventApi {
+                error: Some(VehicleApiErrorKind::Server { status: 503 }),
+                ..Default::default()
+            },
+            None,
+        );
+        failing_handler
+            .handle_events(
+                RecordTrigger::Periodic,
+                vec![&speed_event],
+                1_714_651_320,
+                (0.0, 0.0),
+                Some("truck".to_string()),
+                base_cache_path.clone(),
+                "imei",
+                None,
+            )
+            .await;
+        assert_eq!(2, TruckSpeed::read_from_file(cache_path).len());
+    }
+
+    #[tokio::test]
+    async fn test_record_ordering() {
+        let start = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let records = [
+            (Priority::Low, 1, 90),
+            (Priority::Panic, 3, 180),
+            (Priority::High, 2, 270),
+            (Priority::Low, 0, 0),
+            (Priority::High, 1, 45),
+        ]
+        .into_iter()
+        .map(|(priority, minute, angle)| {
+            AVLRecordBuilder::new()
+                .with_priority(priority)
+                .with_timestamp(start + chrono::Duration::minutes(minute))
+                .with_angle(angle)
+                .build()
+        })
+        .collect::<Vec<_>>();
+        let get_angles = |records: &[AVLRecord]| {
+            records
+                .iter()
+                .map(|record| record.angle)
+                .collect::<Vec<_>>()
+        };
+
+        for (ordering, expected_angles) in [
+            (RecordOrdering::Received, vec![90, 180, 270, 0, 45]),
+            (RecordOrdering::Timestamp, vec![0, 90, 45, 270, 180]),
+            (RecordOrdering::Priority, vec![180, 45, 270, 0, 90]),
+        ] {
+            let mut ordered_records = records.clone();
+            order_records(&mut ordered_records, ordering);
+            assert_eq!(expected_angles, get_angles(&ordered_records));
+        }
+        assert_eq!(
+            Ok(RecordOrdering::Priority),
+            RecordOrdering::from_str("priority")
+        );
+        assert!(RecordOrdering::from_str("newest").is_err());
+
+        // Records of an unknown truck are cached in the order they are handled
+        let mut record_handler = get_teltonika_records_handler(None, None);
+        record_handler.set_record_ordering(RecordOrdering::Priority);
+        record_handler.handle_records(records).await;
+        let base_cache_path = record_handler.get_base_cache_path();
+        let locations_cache = TruckLocation::read_from_file(base_cache_path.to_str().unwrap());
+        assert_eq!(
+            vec![180.0, 45.0, 270.0, 0.0, 90.0],
+            locations_cache
+                .iter()
+                .map(|location| location.heading)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_odometer_reconciliation() {
+        let start = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        // Each step of 0.1 degrees of longitude is about 5281 meters at this latitude
+        let records = [
+            (0, Some(1_000_000)),
+            (1, None),
+            (2, Some(1_010_560)),
+            (3, Some(1_025_000)),
+            (4, Some(1_040_000)),
+        ]
+        .into_iter()
+        .map(|(step, odometer)| {
+            AVLRecordBuilder::new()
+                .with_timestamp(start + chrono::Duration::minutes(step * 5))
+                .with_latitude(61.68779453479687)
+                .with_longitude(27.27297030282335 + step as f64 * 0.1)
+                .with_io_events(
+                    odometer
+                        .map(|odometer| AVLEventIO {
+                            id: 87,
+                            value: nom_teltonika::AVLEventIOValue::U32(odometer),
+                        })
+                        .into_iter()
+                        .collect(),
+                )
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+        let mut disabled_reconciler = TeltonikaOdometerReconciler::with_configuration(
+            None,
+            10_000.0,
+            TeltonikaOdometerValidator::with_configuration(Some(5_000), 60),
+        );
+        assert!(disabled_reconciler.handle_records(&records).is_empty());
+
+        let mut reconciler = TeltonikaOdometerReconciler::with_configuration(
+            Some(10.0),
+            10_000.0,
+            TeltonikaOdometerValidator::with_configuration(Some(5_000), 60),
+        );
+        assert!(reconciler.handle_records(&records[..3]).is_empty());
+        let discrepancies = reconciler.handle_records(&records[3..]);
+
+        assert_eq!(1, discrepancies.len());
+        let discrepancy = discrepancies.first().unwrap();
+        assert_eq!(
+            start + chrono::Duration::minutes(10),
+            discrepancy.started_at
+        );
+        assert_eq!(start + chrono::Duration::minutes(20), discrepancy.ended_at);
+        assert_eq!(29_440.0, discrepancy.odometer_distance_meters);
+        assert!((discrepancy.gps_distance_meters - 10_562.0).abs() < 20.0);
+        assert!(discrepancy.get_deviation_percent() > 170.0);
+        assert_eq!("Total Mileage", get_io_element(87).unwrap().name);
+        // Vehicle Management Service has no endpoint for odometer discrepancies yet
+        assert_eq!(
+            VehicleApiErrorKind::Unsupported,
+            VehicleApi
+                .create_odometer_discrepancy("truck", discrepancy.clone())
+                .await
+                .unwrap_err()
+                .kind
+        );
+    }
+
+    #[test]
+    fn test_config_file() {
+        let config = ConfigFile::parse(
+            r#"
+                BASE_FILE_PATH = "/var/lib/receiver"
+                WRITE_TO_FILE = true
+                ACK_WRITE_RETRIES = 3
+                LOAD_SHEDDING_CPU_THRESHOLD = 0.9
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some("/var/lib/receiver"), config.get("BASE_FILE_PATH"));
+        assert_eq!(Some("true"), config.get("WRITE_TO_FILE"));
+        assert_eq!(Some("3"), config.get("ACK_WRITE_RETRIES"));
+        assert_eq!(Some("0.9"), config.get("LOAD_SHEDDING_CPU_THRESHOLD"));
+        assert_eq!(None, config.get("RUST_LOG"));
+        assert!(matches!(
+            ConfigFile::parse("SYNTHETIC_IMEIS = [\"123456789012345\"]"),
+            Err(ConfigError::UnsupportedValue(key)) if key == "SYNTHETIC_IMEIS"
+        ));
+        assert!(matches!(
+            ConfigFile::parse("BASE_FILE_PATH=/var/lib/receiver"),
+            Err(ConfigError::Parse(_))
+        ));
+
+        let config_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(
+            config_file.path(),
+            "BASE_FILE_PATH = \"/var/lib/receiver\"\nRUST_LOG = \"debug\"\nACK_WRITE_RETRIES = 2\n",
+        )
+        .unwrap();
+        let reloaded_config = ConfigFile::load(config_file.path()).unwrap();
+
+        assert_eq!(
+            vec![
+                "ACK_WRITE_RETRIES",
+                "LOAD_SHEDDING_CPU_THRESHOLD",
+                "RUST_LOG",
+                "WRITE_TO_FILE"
+            ],
+            config.get_changed_keys(&reloaded_config)
+        );
+        assert!(matches!(
+            ConfigFile::load(&config_file.path().with_extension("missing")),
+            Err(ConfigError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_typed_config() {
+        let required = r#"
+            API_BASE_URL = "http://localhost"
+            VEHICLE_MANAGEMENT_SERVICE_API_KEY = "API_KEY"
+            BASE_FILE_PATH = "/var/lib/receiver"
+            WRITE_TO_FILE = true
+        "#;
+        let config = Config::read(&ConfigFile::parse(required).unwrap()).unwrap();
+        assert_eq!("/var/lib/receiver", config.base_file_path);
+        assert!(config.write_to_file);
+        assert_eq!(Some(5), config.api.circuit_breaker_failure_threshold);
+        assert_eq!(2, config.tunables.ack_write_retries);
+        assert_eq!(None, config.tunables.event_batch_bounds);
+
+        let config = Config::read(
+            &ConfigFile::parse(&format!(
+                "{}\nACK_WRITE_RETRIES = 4\nEVENT_BATCH_WINDOW_SECONDS = 5\nEVENT_BATCH_DEGRADED_SIZE = 10\nCONNECTION_IDLE_TIMEOUT_SECONDS = 0",
+                required
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(4, config.tunables.ack_write_retries);
+        assert_eq!(None, config.tunables.connection_idle_timeout);
+        assert_eq!(
+            Some(BatchBounds {
+                window: std::time::Duration::from_secs(5),
+                max_size: 100,
+                degraded_window: std::time::Duration::from_secs(5),
+                degraded_max_size: 100,
+            }),
+            config.tunables.event_batch_bounds
+        );
+
+        // Invalid values stop the receiver on startup instead of failing on first use
+        assert!(matches!(
+            Config::read(&ConfigFile::parse("WRITE_TO_FILE = true").unwrap()),
+            Err(ConfigError::Missing(key)) if key == "BASE_FILE_PATH"
+        ));
+        assert!(matches!(
+            Config::read(
+                &ConfigFile::parse(&format!("{}\nACK_WRITE_RETRIES = \"often\"", required))
+                    .unwrap()
+            ),
+            Err(ConfigError::InvalidValue { key, value }) if key == "ACK_WRITE_RETRIES" && value == "often"
+        ));
+        assert!(matches!(
+            Config::read(
+                &ConfigFile::parse(&format!("{}\nBLE_SENSOR_MACS = \"352093081452251:1\"", required))
+                    .unwrap()
+            ),
+            Err(ConfigError::InvalidSetting { key, .. }) if key == "BLE_SENSOR_MACS"
+        ));
+        assert!(matches!(
+            Config::read(
+                &ConfigFile::parse(&format!("{}\nSYNTHETIC_IMEIS = \"123456789012345\"", required))
+                    .unwrap()
+            ),
+            Err(ConfigError::Missing(key)) if key == "SYNTHETIC_API_BASE_URL"
+        ));
+    }
+
+    #[test]
+    fn test_device_stats_csv_export() {
+        let store = DeviceStatsStore::default();
+        let today = chrono::Utc::now().date_naive();
+        let yesterday = today - chrono::Duration::days(1);
+        store.update(today, "356307042441013", |stats| {
+            stats.frames += 2;
+            stats.records += 10;
+        });
+        store.update(today, "356307042441013", |stats| stats.ack_failures += 1);
+        store.update(yesterday, "356307042441014", |stats| {
+            stats.parse_errors += 1
+        });
+        store.update(today, "35630704244101,5", |stats| stats.frames += 1);
+
+        assert_eq!(
+            format!(
+                "date,imei,frames,records,parse_errors,ack_failures\n\
+                {yesterday},356307042441014,0,0,1,0\n\
+                {today},\"35630704244101,5\",1,0,0,0\n\
+                {today},356307042441013,2,10,0,1\n"
+            ),
+            store.to_csv()
+        );
+
+        // Statistics older than the retention are dropped
+        store.update(
+            today + chrono::Duration::days(30),
+            "356307042441013",
+            |stats| stats.frames += 1,
+        );
+        assert_eq!(
+            format!(
+                "date,imei,frames,records,parse_errors,ack_failures\n\
+                {today},\"35630704244101,5\",1,0,0,0\n\
+                {today},356307042441013,2,10,0,1\n\
+                {},356307042441013,1,0,0,0\n",
+                today + chrono::Duration::days(30)
+            ),
+            store.to_csv()
+        );
+    }
+
+    #[test]
+    fn test_cache_directory_check() {
+        let base_dir = tempfile::tempdir().unwrap();
+        let cache_dir = base_dir.path().join("cache");
+
+        assert!(check_cache_directory(&cache_dir, 0).is_ok());
+        assert!(cache_dir.is_dir());
+        assert_eq!(0, std::fs::read_dir(&cache_dir).unwrap().count());
+        assert!(matches!(
+            check_cache_directory(&cache_dir, u64::MAX),
+            Err(CacheDirectoryError::InsufficientSpace { .. })
+        ));
+
+        let cache_file = base_dir.path().join("cache.json");
+        std::fs::write(&cache_file, "[]").unwrap();
+        assert!(matches!(
+            check_cache_directory(&cache_file, 0),
+            Err(CacheDirectoryError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            check_cache_directory(&cache_file.join("cache"), 0),
+            Err(CacheDirectoryError::NotCreatable(..))
+        ));
+    }
+
+    #[test]
+    fn test_device_actions() {
+        let actions = parse_device_actions(
+            "unlock_cargo_door=DOUT2:pulse:3, beacon_on=DOUT1:on,beacon_off=DOUT4:off",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["beacon_off", "beacon_on", "unlock_cargo_door"],
+            actions.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(&DeviceAction {
+                name: "unlock_cargo_door".to_string(),
+                output: 2,
+                operation: OutputOperation::Pulse(3),
+            }),
+            actions.get("unlock_cargo_door")
+        );
+        assert_eq!(
+            "setdigout ?1 0 3",
+            actions["unlock_cargo_door"].to_command()
+        );
+        assert_eq!("setdigout 1", actions["beacon_on"].to_command());
+        assert_eq!("setdigout ???0", actions["beacon_off"].to_command());
+        assert_eq!(
+            serde_json::json!({"name": "unlock_cargo_door", "output": 2, "operation": {"type": "pulse", "seconds": 3}}),
+            serde_json::to_value(&actions["unlock_cargo_door"]).unwrap()
+        );
+
+        for invalid_actions in [
+            "unlock_cargo_door",
+            "unlock_cargo_door=DOUT5:on",
+            "unlock_cargo_door=DOUT0:on",
+            "unlock_cargo_door=DIN2:on",
+            "unlock_cargo_door=DOUT2:pulse",
+            "unlock_cargo_door=DOUT2:pulse:0",
+            "unlock_cargo_door=DOUT2:toggle",
+        ] {
+            assert!(
+                parse_device_actions(invalid_actions).is_err(),
+                "{} should be invalid",
+                invalid_actions
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_api_operations() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_cache_path: Box<Path> = temp_dir.path().into();
+        let cache_path = base_cache_path.to_str().unwrap();
+        let driver_behavior_event = DriverBehaviorEvent {
+            timestamp: 1_714_651_200,
+            event_type: DriverBehaviorEventType::HarshBraking,
+            value: None,
+            duration: None,
+            axis_x: None,
+            axis_y: None,
+            axis_z: None,
+        };
+        let unsupported_requests_count = metrics::get_counter(
+            API_REQUESTS_METRIC,
+            &[
+                ("operation", "create_driver_behavior_event"),
+                ("result", "unsupported"),
+            ],
+        );
+        let error = VehicleApi
+            .create_driver_behavior_event("truck", driver_behavior_event.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(VehicleApiErrorKind::Unsupported, error.kind);
+        assert!(!error.kind.is_retryable());
+        assert!(!error.kind.is_cacheable());
+        assert_eq!(
+            unsupported_requests_count + 1,
+            metrics::get_counter(
+                API_REQUESTS_METRIC,
+                &[
+                    ("operation", "create_driver_behavior_event"),
+                    ("result", "unsupported"),
+                ],
+            )
+        );
+
+        // Events of unsupported operations are neither cached nor recorded as failed requests
+        let handler = HarshDrivingEventHandler::with_api(VehicleApi);
+        let type_event = AVLEventIO {
+            id: 253,
+            value: nom_teltonika::AVLEventIOValue::U8(2),
+        };
+        handler
+            .handle_events(
+                RecordTrigger::Eventual(253),
+                vec![&type_event],
+                1_714_651_200,
+                (0.0, 0.0),
+                Some("truck".to_string()),
+                base_cache_path.clone(),
+                "imei",
+                None,
+            )
+            .await;
+        assert!(DriverBehaviorEvent::read_from_file(cache_path).is_empty());
+        assert!(FailedApiRequest::read_from_file(cache_path).is_empty());
+
+        // Events without an endpoint aren't cached while the truck is not yet known either
+        handler
+            .handle_events(
+                RecordTrigger::Eventual(253),
+                vec![&type_event],
+                1_714_651_200,
+                (0.0, 0.0),
+                None,
+                base_cache_path.clone(),
+                "imei",
+                None,
+            )
+            .await;
+        assert!(DriverBehaviorEvent::read_from_file(cache_path).is_empty());
+
+        // Cached events of unsupported operations are dropped when purging the cache
+        CachedEvent::new(driver_behavior_event)
+            .write_to_file(cache_path)
+            .unwrap();
+        handler
+            .purge_cache("truck".to_string(), base_cache_path.clone(), "imei")
+            .await;
+        assert!(DriverBehaviorEvent::read_from_file(cache_path).is_empty());
+    }
+
+    #[test]
+    fn test_harsh_driving_event_handler_process_event_data() {
+        let handler = HarshDrivingEventHandler::with_api(FakeTruckEventApi::default());
+        let value_event = AVLEventIO {
+            id: 254,
+            value: nom_teltonika::AVLEventIOValue::U8(123),
+        };
+        let duration_event = AVLEventIO {
+            id: 243,
+            value: nom_teltonika::AVLEventIOValue::U16(850),
+        };
+        let axis_events = [(17, 1_230i16), (18, -56), (19, -1_002)].map(|(id, value)| AVLEventIO {
+            id,
+            value: nom_teltonika::AVLEventIOValue::U16(value as u16),
+        });
+        for (value, expected_type) in [
+            (1, DriverBehaviorEventType::HarshAcceleration),
+            (2, DriverBehaviorEventType::HarshBraking),
+            (3, DriverBehaviorEventType::HarshCornering),
+        ] {
+            let type_event = AVLEventIO {
+                id: 253,
+                value: nom_teltonika::AVLEventIOValue::U8(value),
+            };
+            let events = [
+                &type_event,
+                &value_event,
+                &duration_event,
+                &axis_events[0],
+                &axis_events[1],
+                &axis_events[2],
+            ];
+            assert_eq!(
+                Some(DriverBehaviorEvent {
+                    timestamp: 1_714_651_200,
+                    event_type: expected_type,
+                    value: Some(1.23),
+                    duration: Some(850),
+                    axis_x: Some(1_230),
+                    axis_y: Some(-56),
+                    axis_z: Some(-1_002),
+                }),
+                handler
+                    .process_event_data(
+                        RecordTrigger::Eventual(253),
+                        &events,
+                        1_714_651_200,
+                        (0.0, 0.0),
+                        "imei"
+                    )
+                    .unwrap()
+            );
+            // Devices not configured to send the optional values only send the type
+            assert_eq!(
+                Some(DriverBehaviorEvent {
+                    timestamp: 1_714_651_200,
+                    event_type: expected_type,
+                    value: None,
+                    duration: None,
+                    axis_x: None,
+                    axis_y: None,
+                    axis_z: None,
+                }),
+                handler
+                    .process_event_data(
+                        RecordTrigger::Eventual(253),
+                        &[&type_event],
+                        1_714_651_200,
+                        (0.0, 0.0),
+                        "imei"
+                    )
+                    .unwrap()
+            );
+        }
+
+        let unknown_type_event = AVLEventIO {
+            id: 253,
+            value: nom_teltonika::AVLEventIOValue::U8(4),
+        };
+        let error = handler
+            .process_event_data(
+                RecordTrigger::Eventual(253),
+                &[&unknown_type_event],
+                1_714_651_200,
+                (0.0, 0.0),
+                "imei",
+            )
+            .unwrap_err();
+        assert_eq!(253, error.event_id);
+        assert_eq!(vec![4], error.raw_bytes);
+    }
+
+    #[test]
+    fn test_failed_event_bounds() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_cache_path = temp_dir.path().to_str().unwrap();
+        let error = crate::teltonika::EventDecodeError {
+            event_id: 10_358,
+            raw_bytes: vec![0xAB; 100_000],
+            reason: format!("Invalid value\n{}", "x".repeat(1_000)),
+        };
+
+        FailedEvent::record(&error, "speed\r\n", 1_714_651_200, base_cache_path, None);
+        FailedEvent::record(
+            &crate::teltonika::EventDecodeError {
+                event_id: 195,
+                raw_bytes: vec![0xFF, 0xFE],
+                reason: "Driver card part is not valid UTF-8".to_string(),
+            },
+            "truck_driver_card",
+            1_714_651_200,
+            base_cache_path,
+            None,
+        );
+
+        let failed_events = FailedEvent::read_from_file(base_cache_path);
+        assert_eq!(2, failed_events.len());
+        assert_eq!("AB".repeat(1024), failed_events[0].raw_bytes);
+        assert_eq!(Some(100_000), failed_events[0].truncated_raw_bytes_length);
+        assert_eq!("speed\u{FFFD}\u{FFFD}", failed_events[0].event_type);
+        assert_eq!(256, failed_events[0].error.chars().count());
+        assert!(failed_events[0].error.starts_with("Invalid value\u{FFFD}x"));
+        assert_eq!("FFFE", failed_events[1].raw_bytes);
+        assert_eq!(None, failed_events[1].truncated_raw_bytes_length);
+        assert_eq!(
+            "Driver card part is not valid UTF-8",
+            failed_events[1].error
+        );
+    }
+
+    #[test]
+    fn test_record_trigger() {
+        let periodic_record = AVLRecordBuilder::new().build();
+        let eventual_record = AVLRecordBuilder::new().with_trigger_event_id(187).build();
+        let codec16_periodic_record = AVLRecordBuilder::new()
+            .with_trigger_event_id(10_348)
+            .with_generation_type(EventGenerationCause::Periodical)
+            .build();
+        let codec16_eventual_record = AVLRecordBuilder::new()
+            .with_trigger_event_id(10_348)
+            .with_generation_type(EventGenerationCause::OnChange)
+            .build();
+
+        assert_eq!(
+            RecordTrigger::Periodic,
+            RecordTrigger::from_record(&periodic_record)
+        );
+        assert_eq!(
+            RecordTrigger::Eventual(187),
+            RecordTrigger::from_record(&eventual_record)
+        );
+        assert_eq!(
+            RecordTrigger::Periodic,
+            RecordTrigger::from_record(&codec16_periodic_record)
+        );
+        assert_eq!(
+            RecordTrigger::Eventual(10_348),
+            RecordTrigger::from_record(&codec16_eventual_record)
+        );
+
+        for (subscription, expected_matches) in [
+            (RecordSubscription::All, [true, true, true]),
+            (RecordSubscription::Periodic, [true, false, false]),
+            (RecordSubscription::Eventual, [false, true, true]),
+            (RecordSubscription::EventualOf(187), [false, true, false]),
+        ] {
+            assert_eq!(
+                expected_matches,
+                [
+                    RecordTrigger::Periodic,
+                    RecordTrigger::Eventual(187),
+                    RecordTrigger::Eventual(253)
+                ]
+                .map(|trigger| subscription.matches(trigger))
+            );
+        }
+    }
+
+    #[test]
+    fn test_cache_file_recovery() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_cache_path = temp_dir.path().to_str().unwrap();
+        let cache_file_path = temp_dir.path().join(TruckSpeed::FILE_PATH);
+        for timestamp in 0..3 {
+            TruckSpeed {
+                id: None,
+                speed: 80.0,
+                timestamp,
+            }
+            .write_to_file(base_cache_path)
+            .unwrap();
+        }
+        // Writes don't leave temporary files behind
+        assert_eq!(1, std::fs::read_dir(temp_dir.path()).unwrap().count());
+        assert_eq!(3, TruckSpeed::read_from_file(base_cache_path).len());
+
+        // Cache file truncated in the middle of the last item, e.g. by a crash during writing
+        let contents = std::fs::read_to_string(&cache_file_path).unwrap();
+        std::fs::write(&cache_file_path, &contents[..contents.len() - 10]).unwrap();
+        let corrupted_files_before = metrics::get_counter(
+            CORRUPTED_CACHE_FILES_METRIC,
+            &[("cache", "truck_speed_cache")],
+        );
+
+        let recovered_speeds = TruckSpeed::read_from_file(base_cache_path);
+        assert_eq!(
+            vec![0, 1],
+            recovered_speeds
+                .iter()
+                .map(|speed| speed.timestamp)
+                .collect::<Vec<i64>>()
+        );
+        assert_eq!(
+            corrupted_files_before + 1,
+            metrics::get_counter(
+                CORRUPTED_CACHE_FILES_METRIC,
+                &[("cache", "truck_speed_cache")]
+            )
+        );
+        // Corrupted file is kept and the cache is rewritten with the recovered items
+        let corrupted_files = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|file_name| file_name.starts_with("truck_speed_cache.json.corrupted-"))
+            .collect::<Vec<String>>();
+        assert_eq!(1, corrupted_files.len());
+        assert_eq!(
+            contents[..contents.len() - 10],
+            std::fs::read_to_string(temp_dir.path().join(&corrupted_files[0])).unwrap()
+        );
+        assert_eq!(
+            recovered_speeds,
+            TruckSpeed::read_from_file(base_cache_path)
+        );
+
+        // Nothing can be recovered from a file which isn't a JSON array
+        std::fs::write(&cache_file_path, "{\"speed\":").unwrap();
+        assert!(TruckSpeed::read_from_file(base_cache_path).is_empty());
+    }
+
+    #[test]
+    fn test_cache_depth() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_cache_path = temp_dir.path().to_str().unwrap();
+        let cache_file_path = temp_dir.path().join(TruckSpeed::FILE_PATH);
+        std::fs::write(&cache_file_path, "[{\"speed\":80.0,\"timestamp\":0}]").unwrap();
+
+        // Depth of a cache not accessed yet is read from the file
+        assert_eq!(1, TruckSpeed::get_cache_depth(base_cache_path));
+        for timestamp in 1..3 {
+            CachedEvent::new(TruckSpeed {
+                id: None,
+                speed: 80.0,
+                timestamp,
+            })
+            .write_to_file(base_cache_path)
+            .unwrap();
+        }
+        assert_eq!(3, TruckSpeed::get_cache_depth(base_cache_path));
+
+        // Depth is kept in memory instead of reading the file again
+        std::fs::write(&cache_file_path, "[]").unwrap();
+        assert_eq!(3, TruckSpeed::get_cache_depth(base_cache_path));
+
+        CachedEvent::<TruckSpeed>::write_all_to_file(base_cache_path, &[]).unwrap();
+        assert_eq!(0, TruckSpeed::get_cache_depth(base_cache_path));
+    }
+
+    #[test]
+    fn test_device_cache_path() {
+        let base_path = Path::new("/var/lib/receiver");
+        for (imei, expected_directory) in [
+            ("356307042441013", "356307042441013"),
+            ("../356307042441013", "%2E%2E%2F356307042441013"),
+            ("/tmp", "%2Ftmp"),
+            ("35 63", "35%2063"),
+            ("3ä", "3%C3%A4"),
+            ("", "_"),
+        ] {
+            let device_cache_path = get_device_cache_path(base_path, imei);
+            assert_eq!(base_path.join(expected_directory), device_cache_path);
+            assert_eq!(Some(base_path), device_cache_path.parent());
+        }
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let now = chrono::Utc::now();
+        let mut retry_backoff = RetryBackoff::with_configuration(
+            std::time::Duration::from_secs(60),
+            std::time::Duration::from_secs(300),
+        );
+        assert!(retry_backoff.is_due(now));
+
+        // Each failure doubles the backoff up to the maximum
+        for expected_backoff in [60, 120, 240, 300, 300] {
+            retry_backoff.record_failure(now);
+            let next_retry_at = now + chrono::Duration::seconds(expected_backoff);
+            assert_eq!(Some(next_retry_at), retry_backoff.get_next_retry_at());
+            assert!(!retry_backoff.is_due(next_retry_at - chrono::Duration::seconds(1)));
+            assert!(retry_backoff.is_due(next_retry_at));
+        }
+
+        // Many failures don't overflow the backoff
+        for _ in 0..100 {
+            retry_backoff.record_failure(now);
+        }
+        assert_eq!(
+            Some(now + chrono::Duration::seconds(300)),
+            retry_backoff.get_next_retry_at()
+        );
+
+        retry_backoff.record_success();
+        assert_eq!(None, retry_backoff.get_next_retry_at());
+        assert!(retry_backoff.is_due(now));
+        retry_backoff.record_failure(now);
+        assert_eq!(
+            Some(now + chrono::Duration::seconds(60)),
+            retry_backoff.get_next_retry_at()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_cached_events_backoff() {
+        start_vehicle_management_mock();
+        let mut record_handler = get_teltonika_records_handler(None, None);
+        let mut retry_backoff = RetryBackoff::with_configuration(
+            std::time::Duration::from_secs(60),
+            std::time::Duration::from_secs(300),
+        );
+        retry_backoff.record_failure(chrono::Utc::now());
+        record_handler.set_retry_backoff(retry_backoff);
+        let record = AVLRecordBuilder::new()
+            .with_io_events(vec![AVLEventIO {
+                id: 191,
+                value: nom_teltonika::AVLEventIOValue::U16(10),
+            }])
+            .build();
+        record_handler.handle_records(vec![record]).await;
+        let cached_events_count = record_handler.get_cached_events_count();
+        assert!(cached_events_count > 0);
+
+        // Cached events aren't retried before the backoff has passed
+        record_handler.set_truck_id(Some("F8C5BC38-0213-487D-A37A-553AC3A9D77F".to_string()));
+        record_handler.retry_cached_events().await;
+        assert_eq!(
+            cached_events_count,
+            record_handler.get_cached_events_count()
+        );
+
+        record_handler.set_retry_backoff(RetryBackoff::with_configuration(
+            std::time::Duration::from_secs(60),
+            std::time::Duration::from_secs(300),
+        ));
+        record_handler.retry_cached_events().await;
+        assert_eq!(0, record_handler.get_cached_events_count());
+    }
+
+    #[tokio::test]
+    async fn test_retry_disconnected_devices() {
+        start_vehicle_management_mock();
+        let registry = ConnectionRegistry::default();
+        let record_handler = std::sync::Arc::new(get_teltonika_records_handler(None, None));
+        let imei = record_handler.get_imei().to_string();
+        let record = AVLRecordBuilder::new()
+            .with_io_events(vec![AVLEventIO {
+                id: 191,
+                value: nom_teltonika::AVLEventIOValue::U16(10),
+            }])
+            .build();
+        record_handler.handle_records(vec![record]).await;
+        assert!(record_handler.get_cached_events_count() > 0);
+        registry.register(&imei, "tcp-1", Transport::Tcp, None, &record_handler);
+        registry.unregister(&imei, "tcp-1");
+        assert_eq!(1, registry.get_disconnected_records_handlers().len());
+
+        // Caches of a disconnected device are retried until they are empty
+        record_handler.set_truck_id(Some("F8C5BC38-0213-487D-A37A-553AC3A9D77F".to_string()));
+        retry_due(&registry).await;
+        assert_eq!(0, record_handler.get_cached_events_count());
+        assert!(registry.get_disconnected_records_handlers().is_empty());
+
+        // Reconnecting takes over retrying the caches
+        registry.register(&imei, "tcp-2", Transport::Tcp, None, &record_handler);
+        registry.unregister(&imei, "tcp-2");
+        registry.register(&imei, "tcp-3", Transport::Tcp, None, &record_handler);
+        assert!(registry.get_disconnected_records_handlers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_lock() {
+        let record_handler = std::sync::Arc::new(get_teltonika_records_handler(None, None));
+        let base_cache_path = record_handler.get_base_cache_path().to_path_buf();
+        let cache_lock = get_cache_lock(&base_cache_path);
+        let cache_guard = cache_lock.lock().await;
+
+        // Caching waits for a purge holding the lock of the device caches to finish
+        let caching_handler = record_handler.clone();
+        let caching = tokio::spawn(async move {
+            caching_handler
+                .cache_records(&[AVLRecordBuilder::new()
+                    .with_timestamp(chrono::Utc::now() - chrono::Duration::minutes(1))
+                    .build()])
+                .await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!caching.is_finished());
+        assert_eq!(
+            0,
+            TruckLocation::read_from_file(base_cache_path.to_str().unwrap()).len()
+        );
+
+        drop(cache_guard);
+        assert_eq!(1, caching.await.unwrap());
+        assert_eq!(
+            1,
+            TruckLocation::read_from_file(base_cache_path.to_str().unwrap()).len()
+        );
+
+        // Purges take the cache and put the failed items back in front of the items cached while sending
+        let taken_locations = take_cache::<TruckLocation>(&base_cache_path).await.unwrap();
+        assert_eq!(1, taken_locations.len());
+        assert!(cache_lock.try_lock().is_ok());
+        record_handler
+            .cache_records(&[AVLRecordBuilder::new().build()])
+            .await;
+        restore_cache(&base_cache_path, taken_locations)
+            .await
+            .unwrap();
+        let locations = TruckLocation::read_from_file(base_cache_path.to_str().unwrap());
+        assert_eq!(2, locations.len());
+        assert!(locations[0].timestamp < locations[1].timestamp);
+
+        // Locks are dropped once not in use
+        drop(cache_lock);
+        assert!(!is_cache_lock_in_use(&base_cache_path));
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_events() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_cache_path: Box<Path> = temp_dir.path().into();
+        let cache_path = base_cache_path.to_str().unwrap();
+        let truck_speed = TruckSpeed {
+            id: None,
+            speed: 80.0,
+            timestamp: 1_714_651_200,
+        };
+        let handler = SpeedEventHandler::with_api(
+            FakeTruckEventApi {
+                error: Some(VehicleApiErrorKind::Rejected {
+                    status: 400,
+                    content: "Invalid speed".to_string(),
+                }),
+                ..Default::default()
+            },
+            None,
+        );
+
+        // Caches written before the attempts were counted are read with zero attempts
+        truck_speed.write_to_file(cache_path).unwrap();
+        assert_eq!(
+            0,
+            CachedEvent::<TruckSpeed>::read_from_file(cache_path)[0].attempts
+        );
+        handler
+            .purge_cache("truck".to_string(), base_cache_path.clone(), "imei")
+            .await;
+        let cache = CachedEvent::<TruckSpeed>::read_from_file(cache_path);
+        assert_eq!(1, cache.len());
+        assert_eq!(1, cache[0].attempts);
+        assert_eq!(1, TruckSpeed::read_from_file(cache_path).len());
+
+        // Events reaching the maximum attempts are moved to the dead-letter events
+        let dead_letter_events_count =
+            metrics::get_counter(DEAD_LETTER_EVENTS_METRIC, &[("event_type", "truck_speed")]);
+        let max_attempts = get_max_retry_attempts().unwrap();
+        CachedEvent::write_all_to_file(
+            cache_path,
+            &[CachedEvent {
+                event: truck_speed.clone(),
+                attempts: max_attempts - 1,
+            }],
+        )
+        .unwrap();
+        handler
+            .purge_cache("truck".to_string(), base_cache_path.clone(), "imei")
+            .await;
+        assert!(CachedEvent::<TruckSpeed>::read_from_file(cache_path).is_empty());
+        let dead_letter_events = DeadLetterEvent::read_from_file(cache_path);
+        assert_eq!(1, dead_letter_events.len());
+        assert_eq!("truck_speed", dead_letter_events[0].event_type);
+        assert_eq!(
+            serde_json::to_value(&truck_speed).unwrap(),
+            dead_letter_events[0].payload
+        );
+        assert_eq!(max_attempts, dead_letter_events[0].attempts);
+        assert_eq!(
+            "request rejected with status code 400: Invalid speed",
+            dead_letter_events[0].error
+        );
+        assert_eq!(
+            dead_letter_events_count + 1,
+            metrics::get_counter(DEAD_LETTER_EVENTS_METRIC, &[("event_type", "truck_speed")])
+        );
+    }
+
+    #[test]
+    fn test_connection_registry() {
+        let registry = ConnectionRegistry::default();
+        let records_handler = std::sync::Arc::new(get_teltonika_records_handler(
+            Some("truck".to_string()),
+            Some("356307042441013".to_string()),
+        ));
+        let tcp_connection = registry.register(
+            "356307042441013",
+            "tcp-1",
+            Transport::Tcp,
+            Some("10.0.0.1".parse().unwrap()),
+            &records_handler,
+        );
+        registry.register(
+            "356307042441012",
+            "udp-1",
+            Transport::Udp,
+            None,
+            &records_handler,
+        );
+        tcp_connection.record_frame();
+        tcp_connection.record_frame();
+
+        let summaries = registry.get_summaries();
+        assert_eq!(
+            vec!["356307042441012", "356307042441013"],
+            summaries
+                .iter()
+                .map(|summary| summary.imei.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2, summaries[1].frames_received);
+        assert_eq!(Transport::Tcp, summaries[1].transport);
+        assert_eq!(Some("truck".to_string()), summaries[1].truck_id);
+        assert_eq!(2, registry.get_records_handlers().len());
+
+        // Closing a replaced connection keeps the registration of the reconnected device
+        registry.register(
+            "356307042441013",
+            "tcp-2",
+            Transport::Tcp,
+            None,
+            &records_handler,
+        );
+        registry.unregister("356307042441013", "tcp-1");
+        assert_eq!(
+            0,
+            registry
+                .get_connection("356307042441013")
+                .unwrap()
+                .get_summary()
+                .frames_received
+        );
+        registry.unregister("356307042441013", "tcp-2");
+        assert!(registry.get_connection("356307042441013").is_none());
+        assert_eq!(1, registry.get_summaries().len());
+
+        let cache_depths = records_handler.get_cache_depths();
+        assert_eq!(
+            Some(&0),
+            cache_depths.get(TruckLocation::FILE_PATH),
+            "Location cache is missing from {:?}",
+            cache_depths
+        );
+        assert_eq!(Some(&0), cache_depths.get(TruckSpeed::FILE_PATH));
+    }
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!(Ok(LogFormat::Text), "text".parse());
+        assert_eq!(Ok(LogFormat::Json), "json".parse());
+        assert!("JSON".parse::<LogFormat>().is_err());
+        assert_eq!(LogFormat::Text, LogFormat::default());
+    }
+
+    #[test]
+    fn test_trace_context_propagation() {
+        use opentelemetry::trace::{TraceContextExt, TracerProvider};
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        opentelemetry::global::set_text_map_propagator(
+            opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+        );
+        let tracer = opentelemetry_sdk::trace::TracerProvider::builder()
+            .build()
+            .tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(telemetry::get_telemetry_layer(tracer));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let connection_span = tracing::info_span!("connection");
+            let _connection_guard = connection_span.enter();
+            let frame_span = tracing::info_span!("frame");
+            let _frame_guard = frame_span.enter();
+            let api_request_span = tracing::info_span!("api_request");
+            let _api_request_guard = api_request_span.enter();
+
+            let mut headers = reqwest::header::HeaderMap::new();
+            telemetry::inject_trace_context(&mut headers);
+
+            let span_context = api_request_span.context().span().span_context().clone();
+            let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+            assert_eq!(
+                format!(
+                    "00-{}-{}-01",
+                    span_context.trace_id(),
+                    span_context.span_id()
+                ),
+                traceparent
+            );
+            // Frames are traced separately from their connections
+            assert_eq!(
+                span_context.trace_id(),
+                frame_span.context().span().span_context().trace_id()
+            );
+            assert_ne!(
+                span_context.trace_id(),
+                connection_span.context().span().span_context().trace_id()
+            );
+        });
+    }
+
+    #[test]
+    fn test_connection_limits() {
+        let now = std::time::Instant::now();
+        let first_ip: std::net::IpAddr = "10.0.0.1".parse().unwrap();
+        let second_ip: std::net::IpAddr = "10.0.0.2".parse().unwrap();
+        let connection_limiter = ConnectionLimiter::new(ConnectionLimits {
+            max_connections: Some(3),
+            max_connections_per_ip: Some(2),
+            max_accepts_per_second: None,
+        });
+
+        let first_permit = connection_limiter.try_admit(first_ip, now).unwrap();
+        let _second_permit = connection_limiter.try_admit(first_ip, now).unwrap();
+        assert_eq!(
+            Some(RejectReason::MaxConnectionsPerIp),
+            connection_limiter.try_admit(first_ip, now).err()
+        );
+        let _third_permit = connection_limiter.try_admit(second_ip, now).unwrap();
+        assert_eq!(
+            Some(RejectReason::MaxConnections),
+            connection_limiter.try_admit(second_ip, now).err()
+        );
+        // Closing a connection frees its slot
+        drop(first_permit);
+        assert!(connection_limiter.try_admit(first_ip, now).is_ok());
+
+        let connection_limiter = ConnectionLimiter::new(ConnectionLimits {
+            max_connections: None,
+            max_connections_per_ip: None,
+            max_accepts_per_second: Some(2),
+        });
+        assert!(connection_limiter.try_admit(first_ip, now).is_ok());
+        assert!(connection_limiter.try_admit(second_ip, now).is_ok());
+        assert_eq!(
+            Some(RejectReason::AcceptRate),
+            connection_limiter.try_admit(first_ip, now).err()
+        );
+        let later = now + std::time::Duration::from_millis(500);
+        assert!(connection_limiter.try_admit(first_ip, later).is_ok());
+        assert!(connection_limiter.try_admit(first_ip, later).is_err());
+
+        connection_limits::record_rejected_connection(first_ip, RejectReason::AcceptRate);
+        assert!(
+            metrics::get_counter(
+                connection_limits::REJECTED_CONNECTIONS_METRIC,
+                &[("reason", "accept_rate")]
+            ) >= 1
+        );
+    }
+
+    #[test]
+    fn test_truck_cache_capacity() {
+        let truck_cache = TruckCache::new(3600, 300, 3);
+        let now = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let truck_id = uuid::Uuid::new_v4();
+        let evictions_before = metrics::get_counter(TRUCK_CACHE_EVICTIONS_METRIC, &[]);
+
+        truck_cache.insert("VIN1", Some(truck_id), now);
+        truck_cache.insert("VIN2", None, now);
+        truck_cache.insert("VIN3", Some(truck_id), now + chrono::Duration::minutes(1));
+        // Updating a cached VIN doesn't evict anything
+        truck_cache.insert("VIN3", Some(truck_id), now + chrono::Duration::minutes(2));
+        assert_eq!(3, truck_cache.get_cached_count());
+
+        // Expired negative entry is evicted first
+        let later = now + chrono::Duration::minutes(10);
+        truck_cache.insert("VIN4", Some(truck_id), later);
+        assert_eq!(3, truck_cache.get_cached_count());
+        assert_eq!(TruckCacheLookup::Miss, truck_cache.lookup("VIN2", now));
+        assert_eq!(
+            TruckCacheLookup::Hit(truck_id),
+            truck_cache.lookup("VIN1", later)
+        );
+
+        // Entry cached the longest ago is evicted when none have expired
+        truck_cache.insert("VIN5", None, later);
+        assert_eq!(3, truck_cache.get_cached_count());
+        assert_eq!(TruckCacheLookup::Miss, truck_cache.lookup("VIN1", later));
+        assert_eq!(
+            TruckCacheLookup::Hit(truck_id),
+            truck_cache.lookup("VIN3", later)
+        );
+        assert_eq!(
+            TruckCacheLookup::NegativeHit,
+            truck_cache.lookup("VIN5", later)
+        );
+        assert!(metrics::get_counter(TRUCK_CACHE_EVICTIONS_METRIC, &[]) >= evictions_before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_truck_id() {
+        let old_truck_id = uuid::Uuid::new_v4().to_string();
+        let new_truck_id = uuid::Uuid::new_v4();
+        let records_handler = get_teltonika_records_handler(
+            Some(old_truck_id.clone()),
+            Some(get_random_imei_of_length(15)),
+        );
+
+        // Truck can't be refreshed before the VIN is received
+        assert!(!records_handler.refresh_truck_id().await);
+
+        records_handler.set_truck_vin(Some("REFRESHTRUCKVIN01".to_string()));
+        get_truck_cache().insert("REFRESHTRUCKVIN01", Some(new_truck_id), chrono::Utc::now());
+        assert!(records_handler.refresh_truck_id().await);
+        assert_eq!(
+            Some(new_truck_id.to_string()),
+            records_handler.get_truck_id()
+        );
+        assert!(!records_handler.refresh_truck_id().await);
+
+        // Truck is kept when the VIN no longer has a truck
+        get_truck_cache().insert("REFRESHTRUCKVIN01", None, chrono::Utc::now());
+        assert!(!records_handler.refresh_truck_id().await);
+        assert_eq!(
+            Some(new_truck_id.to_string()),
+            records_handler.get_truck_id()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let shutdown = std::sync::Arc::new(Shutdown::new());
+        let waiting_shutdown = shutdown.clone();
+        let waiting = tokio::spawn(async move { waiting_shutdown.wait().await });
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        shutdown.request();
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        // Waiting after shutting down is requested returns immediately
+        tokio::time::timeout(std::time::Duration::from_secs(1), shutdown.wait())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_frame_rejection() {
+        let rejected_frames_before =
+            metrics::get_counter(load_shedding::REJECTED_FRAMES_METRIC, &[]);
+
+        load_shedding::record_frame_queued();
+        load_shedding::record_frame_queued();
+        assert!(load_shedding::should_reject_frame(Some(2)));
+        assert!(!load_shedding::should_reject_frame(Some(usize::MAX)));
+        assert!(!load_shedding::should_reject_frame(None));
+        load_shedding::record_frame_dispatched();
+        load_shedding::record_frame_dispatched();
+
+        load_shedding::record_rejected_frame();
+        assert!(
+            metrics::get_counter(load_shedding::REJECTED_FRAMES_METRIC, &[])
+                > rejected_frames_before
+        );
+    }
+
+    #[test]
+    fn test_adaptive_batching() {
+        let api_health = ApiHealth::new(std::time::Duration::from_secs(2));
+        assert_eq!(0.0, api_health.get_degradation());
+
+        // Degradation grows with the latency...
+        for _ in 0..10 {
+            api_health.record_request(std::time::Duration::from_secs(1), true);
+        }
+        let slow_degradation = api_health.get_degradation();
+        assert!(slow_degradation > 0.4 && slow_degradation < 0.5);
+        // ...and the error rate of the API requests
+        for _ in 0..10 {
+            api_health.record_request(std::time::Duration::from_secs(1), false);
+        }
+        assert_eq!(1.0, api_health.get_degradation());
+        // ...and shrinks back once the API recovers
+        for _ in 0..30 {
+            api_health.record_request(std::time::Duration::from_millis(100), true);
+        }
+        assert!(api_health.get_degradation() < 0.1);
+
+        let bounds = BatchBounds {
+            window: std::time::Duration::from_secs(10),
+            max_size: 100,
+            degraded_window: std::time::Duration::from_secs(60),
+            degraded_max_size: 500,
+        };
+        assert_eq!((std::time::Duration::from_secs(10), 100), bounds.scale(0.0));
+        assert_eq!((std::time::Duration::from_secs(35), 300), bounds.scale(0.5));
+        assert_eq!((std::time::Duration::from_secs(60), 500), bounds.scale(1.0));
+        assert_eq!((std::time::Duration::from_secs(60), 500), bounds.scale(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_event_batching() {
+        let start = std::time::Instant::now();
+        let batch = EventBatch::new(std::time::Duration::from_secs(10), 3);
+        assert_eq!(None, batch.push(1, start));
+        assert_eq!(
+            None,
+            batch.push(2, start + std::time::Duration::from_secs(1))
+        );
+        assert!(batch
+            .take_due(start + std::time::Duration::from_secs(9))
+            .is_empty());
+        // Batches are submitted when their window has elapsed since their first event
+        assert_eq!(
+            vec![1, 2],
+            batch.take_due(start + std::time::Duration::from_secs(10))
+        );
+        assert_eq!(
+            None,
+            batch.push(3, start + std::time::Duration::from_secs(11))
+        );
+        assert_eq!(
+            Some(vec![3, 4]),
+            batch.push(4, start + std::time::Duration::from_secs(21))
+        );
+        // ...or when they are full
+        assert_eq!(
+            None,
+            batch.push(5, start + std::time::Duration::from_secs(22))
+        );
+        assert_eq!(
+            None,
+            batch.push(6, start + std::time::Duration::from_secs(22))
+        );
+        assert_eq!(
+            Some(vec![5, 6, 7]),
+            batch.push(7, start + std::time::Duration::from_secs(22))
+        );
+        assert_eq!(
+            None,
+            batch.push(8, start + std::time::Duration::from_secs(23))
+        );
+        assert_eq!(vec![8], batch.take_all());
+        assert!(batch.take_all().is_empty());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_cache_path: Box<Path> = temp_dir.path().into();
+        let cache_path = base_cache_path.to_str().unwrap();
+        let api = FakeTruckEventApi::default();
+        let sent_events = api.sent_events.clone();
+        let handler = SpeedEventHandler::with_api(
+            api,
+            Some(EventBatch::new(std::time::Duration::from_secs(3600), 2)),
+        );
+        let batched_speeds_count =
+            metrics::get_counter(BATCHED_EVENTS_METRIC, &[("event_type", "truck_speed")]);
+        for (speed, timestamp) in [
+            (80, 1_714_651_200),
+            (81, 1_714_651_210),
+            (82, 1_714_651_220),
+        ] {
+            let speed_event = AVLEventIO {
+                id: 191,
+                value: nom_teltonika::AVLEventIOValue::U8(speed),
+            };
+            handler
+                .handle_events(
+                    RecordTrigger::Periodic,
+                    vec![&speed_event],
+                    timestamp,
+                    (0.0, 0.0),
+                    Some("truck".to_string()),
+                    base_cache_path.clone(),
+                    "imei",
+                    None,
+                )
+                .await;
+        }
+        // The first two speeds filled a batch and the third one waits for the next batch
+        assert_eq!(2, sent_events.lock().unwrap().len());
+        handler
+            .flush_batch("truck".to_string(), base_cache_path.clone(), "imei", false)
+            .await;
+        assert_eq!(2, sent_events.lock().unwrap().len());
+        handler
+            .flush_batch("truck".to_string(), base_cache_path.clone(), "imei", true)
+            .await;
+        assert_eq!(
+            vec![80.0, 81.0, 82.0],
+            sent_events
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, speed)| speed["speed"].as_f64().unwrap())
+                .collect::<Vec<f64>>()
+        );
+        assert_eq!(
+            batched_speeds_count + 3,
+            metrics::get_counter(BATCHED_EVENTS_METRIC, &[("event_type", "truck_speed")])
+        );
+
+        // Speeds of a batch failing to be sent are cached
+        let failing_handler = SpeedEventHandler::with_api(
+            FakeTruckEventApi {
+                error: Some(VehicleApiErrorKind::Server { status: 503 }),
+                ..Default::default()
+            },
+            Some(EventBatch::new(std::time::Duration::from_secs(3600), 2)),
+        );
+        let speed_event = AVLEventIO {
+            id: 191,
+            value: nom_teltonika::AVLEventIOValue::U8(80),
+        };
+        failing_handler
+            .handle_events(
+                RecordTrigger::Periodic,
+                vec![&speed_event],
+                1_714_651_230,
+                (0.0, 0.0),
+                Some("truck".to_string()),
+                base_cache_path.clone(),
+                "imei",
+                None,
+            )
+            .await;
+        assert!(TruckSpeed::read_from_file(cache_path).is_empty());
+        failing_handler
+            .flush_batch("truck".to_string(), base_cache_path.clone(), "imei", true)
+            .await;
+        assert_eq!(1, TruckSpeed::read_from_file(cache_path).len());
+    }
+
+    #[test]
+    fn test_api_circuit_breaker() {
+        let now = std::time::Instant::now();
+        let open_duration = std::time::Duration::from_secs(30);
+        let circuit_breakers = CircuitBreakers::new(Some(2), open_duration);
+        circuit_breakers.record_failure("create_truck_location", now);
+        assert!(circuit_breakers.try_acquire("create_truck_location", now));
+        // Requests reaching the API reset the consecutive failures
+        circuit_breakers.record_success("create_truck_location");
+        circuit_breakers.record_failure("create_truck_location", now);
+        assert!(circuit_breakers.try_acquire("create_truck_location", now));
+        circuit_breakers.record_failure("create_truck_location", now);
+        assert!(!circuit_breakers.try_acquire("create_truck_location", now));
+        assert!(circuit_breakers.try_acquire("create_truck_speed", now));
+
+        // A single probe is let through after the circuit has been open long enough
+        let probe_at = now + open_duration;
+        assert!(!circuit_breakers.try_acquire(
+            "create_truck_location",
+            probe_at - std::time::Duration::from_secs(1)
+        ));
+        assert!(circuit_breakers.try_acquire("create_truck_location", probe_at));
+        assert!(!circuit_breakers.try_acquire("create_truck_location", probe_at));
+        circuit_breakers.record_failure("create_truck_location", probe_at);
+        assert!(!circuit_breakers.try_acquire(
+            "create_truck_location",
+            probe_at + std::time::Duration::from_secs(29)
+        ));
+        let probe_at = probe_at + open_duration;
+        assert!(circuit_breakers.try_acquire("create_truck_location", probe_at));
+        circuit_breakers.record_success("create_truck_location");
+        assert!(circuit_breakers.try_acquire("create_truck_location", probe_at));
+        assert!(circuit_breakers.try_acquire("create_truck_location", probe_at));
+
+        let disabled_circuit_breakers = CircuitBreakers::new(None, open_duration);
+        for _ in 0..10 {
+            disabled_circuit_breakers.record_failure("create_truck_location", now);
+        }
+        assert!(disabled_circuit_breakers.try_acquire("create_truck_location", now));
+
+        // Delays of the retries are jittered and grow exponentially
+        for attempt in 1..=3 {
+            let delay = get_retry_delay(attempt);
+            let max_delay = std::time::Duration::from_millis(200 * 2_u64.pow(attempt - 1));
+            assert!(delay >= max_delay / 2 && delay <= max_delay);
+        }
+        assert!(VehicleApiErrorKind::Rejected {
+            status: 429,
+            content: String::new()
+        }
+        .is_retryable());
+        assert!(!VehicleApiErrorKind::CircuitOpen.is_retryable());
+        assert!(VehicleApiErrorKind::CircuitOpen.is_cacheable());
+
+        // Short-circuited requests don't count as attempts of cached events
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_path = temp_dir.path().to_str().unwrap();
+        let cached_event = CachedEvent::new(TruckSpeed {
+            id: None,
+            speed: 80.0,
+            timestamp: 1_714_651_200,
+        });
+        let short_circuited = VehicleApiError {
+            request_id: uuid::Uuid::new_v4(),
+            kind: VehicleApiErrorKind::CircuitOpen,
+        };
+        let cached_event = cached_event
+            .record_failed_attempt(&short_circuited, cache_path, "imei")
+            .unwrap();
+        assert_eq!(0, cached_event.attempts);
+    }
+
+    #[tokio::test]
+    async fn test_api_client_settings() {
+        let settings = get_config().api.client;
+        assert_eq!(std::time::Duration::from_secs(5), settings.connect_timeout);
+        assert_eq!(std::time::Duration::from_secs(30), settings.read_timeout);
+
+        let mock_server = httpmock::MockServer::start_async().await;
+        mock_server
+            .mock_async(|when, then| {
+                when.path("/hung");
+                then.status(200).delay(std::time::Duration::from_secs(5));
+            })
+            .await;
+        let client = build_api_client(&ApiClientSettings {
+            read_timeout: std::time::Duration::from_millis(100),
+            ..settings
+        });
+        let started_at = std::time::Instant::now();
+        let result = client.get(mock_server.url("/hung")).send().await;
+        // A hung API fails the request instead of stalling it
+        assert!(result.unwrap_err().is_timeout());
+        assert!(started_at.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_odometer_validation() {
+        let start = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let at = |seconds: i64| start + chrono::Duration::seconds(seconds);
+        let mut validator = TeltonikaOdometerValidator::with_configuration(Some(5_000), 60);
+        assert_eq!(Ok(()), validator.validate(at(0), 1_000_000));
+        assert_eq!(Ok(()), validator.validate(at(60), 1_004_000));
+        assert_eq!(
+            Err(OdometerRejection::Decrease {
+                previous_meters: 1_004_000
+            }),
+            validator.validate(at(120), 1_003_000)
+        );
+        assert_eq!(
+            Err(OdometerRejection::Jump {
+                previous_meters: 1_004_000,
+                max_delta_meters: 5_000
+            }),
+            validator.validate(at(90), 1_900_000)
+        );
+        // The allowed delta grows with the time elapsed since the previous reading
+        assert_eq!(Ok(()), validator.validate(at(660), 1_050_000));
+        // Older readings are validated against the previous reading without replacing it
+        assert_eq!(Ok(()), validator.validate(at(600), 1_049_000));
+        assert!(validator.validate(at(600), 1_051_000).is_err());
+        assert_eq!(Ok(()), validator.validate(at(720), 1_053_000));
+
+        // The reading after too many rejected readings in a row is accepted as the new baseline
+        for step in 1..=10 {
+            assert!(validator.validate(at(720 + step), 10_000).is_err());
+        }
+        assert_eq!(Ok(()), validator.validate(at(731), 10_000));
+        assert_eq!(Ok(()), validator.validate(at(791), 11_000));
+
+        let mut jumps_allowed = TeltonikaOdometerValidator::with_configuration(None, 60);
+        assert_eq!(Ok(()), jumps_allowed.validate(at(0), 1_000_000));
+        assert_eq!(Ok(()), jumps_allowed.validate(at(1), 9_000_000));
+        assert!(jumps_allowed.validate(at(2), 1_000_000).is_err());
+
+        // Rejected readings are left out of the reconciliation
+        let records = [(0, 1_000_000), (1, 9_000_000), (2, 1_010_560)]
+            .into_iter()
+            .map(|(step, odometer)| {
+                AVLRecordBuilder::new()
+                    .with_timestamp(start + chrono::Duration::minutes(step * 5))
+                    .with_latitude(61.68779453479687)
+                    .with_longitude(27.27297030282335 + step as f64 * 0.1)
+                    .with_io_events(vec![AVLEventIO {
+                        id: 87,
+                        value: nom_teltonika::AVLEventIOValue::U32(odometer),
+                    }])
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let mut reconciler = TeltonikaOdometerReconciler::with_configuration(
+            Some(10.0),
+            10_000.0,
+            TeltonikaOdometerValidator::with_configuration(Some(5_000), 60),
+        );
+        assert!(reconciler.handle_records(&records).is_empty());
+        let rejected_readings = reconciler.take_rejected_readings();
+        assert_eq!(1, rejected_readings.len());
+        assert_eq!(9_000_000, rejected_readings[0].odometer_meters);
+        assert_eq!("jump", rejected_readings[0].rejection.as_label());
+        assert!(reconciler.take_rejected_readings().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_location_validation() {
+        let start = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let record = |seconds: i64, longitude: f64, satellites: u8, speed: u16, hdop: u16| {
+            AVLRecordBuilder::new()
+                .with_timestamp(start + chrono::Duration::seconds(seconds))
+                .with_latitude(61.68779453479687)
+                .with_longitude(longitude)
+                .with_satellites(satellites)
+                .with_speed(speed)
+                .with_io_events(vec![AVLEventIO {
+                    id: 182,
+                    value: nom_teltonika::AVLEventIOValue::U16(hdop),
+                }])
+                .build()
+        };
+        assert_eq!(
+            Ok(LocationValidationMode::Drop),
+            LocationValidationMode::from_str("drop")
+        );
+        assert!(LocationValidationMode::from_str("reject").is_err());
+
+        let mut validator = TeltonikaLocationValidator::with_configuration(
+            LocationValidationMode::Drop,
+            3,
+            Some(5.0),
+            200.0,
+        );
+        assert_eq!(Ok(()), validator.validate(&record(0, 27.27, 8, 80, 10)));
+        assert_eq!(
+            Err(InvalidLocation::Position),
+            validator.validate(&AVLRecordBuilder::new().with_satellites(8).build())
+        );
+        assert_eq!(
+            Err(InvalidLocation::Satellites(2)),
+            validator.validate(&record(10, 27.27, 2, 80, 10))
+        );
+        assert_eq!(
+            Err(InvalidLocation::Hdop(9.9)),
+            validator.validate(&record(10, 27.27, 8, 80, 99))
+        );
+        assert_eq!(
+            Err(InvalidLocation::Speed(250.0)),
+            validator.validate(&record(10, 27.27, 8, 250, 10))
+        );
+        // About 5281 meters in 10 seconds implies a speed of about 1900 km/h
+        assert!(matches!(
+            validator.validate(&record(10, 27.37, 8, 80, 10)),
+            Err(InvalidLocation::Speed(_))
+        ));
+        assert_eq!(Ok(()), validator.validate(&record(600, 27.37, 8, 80, 10)));
+
+        let mut disabled = TeltonikaLocationValidator::with_configuration(
+            LocationValidationMode::Off,
+            3,
+            Some(5.0),
+            200.0,
+        );
+        assert_eq!(Ok(()), disabled.validate(&AVLRecordBuilder::new().build()));
+
+        // Invalid locations are dropped or only flagged
+        for (mode, expected_locations) in [
+            (LocationValidationMode::Drop, 1),
+            (LocationValidationMode::Flag, 2),
+        ] {
+            let imei = get_random_imei_of_length(15);
+            let mut record_handler = get_teltonika_records_handler(None, Some(imei.clone()));
+            record_handler.set_location_validator(TeltonikaLocationValidator::with_configuration(
+                mode, 3, None, 200.0,
+            ));
+            record_handler
+                .handle_records(vec![
+                    record(0, 27.27, 8, 80, 10),
+                    record(10, 27.27, 0, 0, 10),
+                ])
+                .await;
+            let base_cache_path = record_handler.get_base_cache_path();
+            let locations_cache = TruckLocation::read_from_file(base_cache_path.to_str().unwrap());
+            assert_eq!(expected_locations, locations_cache.len());
+            assert_eq!(
+                1,
+                metrics::get_counter(
+                    INVALID_LOCATIONS_METRIC,
+                    &[
+                        ("imei", &imei),
+                        ("reason", "satellites"),
+                        ("action", mode.as_label())
+                    ]
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_record_timestamp_validation() {
+        let now = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let records = || {
+            [
+                chrono::Utc.timestamp_opt(0, 0).unwrap(),
+                now - chrono::Duration::days(1),
+                now + chrono::Duration::seconds(60),
+                now + chrono::Duration::days(365),
+            ]
+            .into_iter()
+            .map(|timestamp| AVLRecordBuilder::new().with_timestamp(timestamp).build())
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            Ok(TimestampPolicy::Clamp),
+            TimestampPolicy::from_str("clamp")
+        );
+        assert!(TimestampPolicy::from_str("drop").is_err());
+
+        let imei = get_random_imei_of_length(15);
+        let validator = TeltonikaTimestampValidator::with_configuration(
+            TimestampPolicy::Reject,
+            30 * 24 * 60 * 60,
+            300,
+        );
+        let mut rejected_records = records();
+        assert_eq!(
+            2,
+            validator.validate_records(&imei, &mut rejected_records, now)
+        );
+        assert_eq!(
+            vec![
+                now - chrono::Duration::days(1),
+                now + chrono::Duration::seconds(60)
+            ],
+            rejected_records
+                .iter()
+                .map(|record| record.timestamp)
+                .collect::<Vec<_>>()
+        );
+        for direction in ["past", "future"] {
+            assert_eq!(
+                1,
+                metrics::get_counter(
+                    INVALID_RECORD_TIMESTAMPS_METRIC,
+                    &[
+                        ("imei", &imei),
+                        ("direction", direction),
+                        ("action", "rejected")
+                    ]
+                )
+            );
+        }
+        assert_eq!(
+            Some(365.0 * 24.0 * 60.0 * 60.0),
+            metrics::get_gauge(DEVICE_CLOCK_SKEW_METRIC, &[("imei", &imei)])
+        );
+
+        // Clamped records keep their place in the frame
+        let validator = TeltonikaTimestampValidator::with_configuration(
+            TimestampPolicy::Clamp,
+            30 * 24 * 60 * 60,
+            300,
+        );
+        let mut clamped_records = records();
+        assert_eq!(
+            0,
+            validator.validate_records(&imei, &mut clamped_records, now)
+        );
+        assert_eq!(
+            vec![
+                now,
+                now - chrono::Duration::days(1),
+                now + chrono::Duration::seconds(60),
+                now
+            ],
+            clamped_records
+                .iter()
+                .map(|record| record.timestamp)
+                .collect::<Vec<_>>()
+        );
+
+        let validator = TeltonikaTimestampValidator::with_configuration(TimestampPolicy::Off, 0, 0);
+        let mut unvalidated_records = records();
+        assert_eq!(
+            0,
+            validator.validate_records(&imei, &mut unvalidated_records, now)
+        );
+        assert_eq!(records().len(), unvalidated_records.len());
+
+        let mut record_handler = get_teltonika_records_handler(None, None);
+        record_handler.set_timestamp_validator(TeltonikaTimestampValidator::with_configuration(
+            TimestampPolicy::Reject,
+            30 * 24 * 60 * 60,
+            300,
+        ));
+        let mut handled_records = vec![
+            AVLRecordBuilder::new().build(),
+            AVLRecordBuilder::new()
+                .with_timestamp(chrono::Utc.timestamp_opt(0, 0).unwrap())
+                .build(),
+        ];
+        assert_eq!(
+            1,
+            record_handler.validate_record_timestamps(&mut handled_records)
+        );
+        assert_eq!(1, handled_records.len());
+    }
+
+    #[tokio::test]
+    async fn test_device_families() {
+        let vin = "W1T96302X10704959";
+        let can_vin_event = AVLEventIO {
+            id: 256,
+            value: nom_teltonika::AVLEventIOValue::Variable(vin.as_bytes().to_vec()),
+        };
+        assert_eq!(191, DeviceFamily::Fmc.get_io_mapping().vehicle_speed);
+        assert_eq!(191, DeviceFamily::Fmb6xx.get_io_mapping().vehicle_speed);
+        assert_eq!(81, DeviceFamily::Fmb1xx.get_io_mapping().vehicle_speed);
+        assert_eq!(
+            vec![81],
+            SpeedEventHandler::<VehicleApi>::new(DeviceFamily::Fmb1xx).get_event_ids()
+        );
+        assert_eq!(
+            &["FMB120", "FMB125", "FMB130", "FMB140"],
+            get_io_element(256).unwrap().models
+        );
+
+        // FMB1xx devices send the VIN in a single event, others in three parts
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fmb1xx_handler = crate::teltonika::records::TeltonikaRecordsHandler::new(
+            temp_dir.path(),
+            None,
+            get_random_imei_of_length(15),
+            DeviceFamily::Fmb1xx,
+        );
+        let fmc_handler = get_teltonika_records_handler(None, None);
+        let can_vin_records = vec![AVLRecordBuilder::new()
+            .with_io_events(vec![can_vin_event])
+            .build()];
+        let tachograph_vin_records = vec![AVLRecordBuilder::new()
+            .with_io_events(vin_to_three_part_events(vin.to_string()).to_vec())
+            .build()];
+        assert_eq!(
+            Some(vin.to_string()),
+            fmb1xx_handler.get_truck_vin_from_records(&can_vin_records)
+        );
+        assert_eq!(
+            None,
+            fmb1xx_handler.get_truck_vin_from_records(&tachograph_vin_records)
+        );
+        assert_eq!(
+            None,
+            fmc_handler.get_truck_vin_from_records(&can_vin_records)
+        );
+        assert_eq!(
+            Some(vin.to_string()),
+            fmc_handler.get_truck_vin_from_records(&tachograph_vin_records)
+        );
+
+        // Speeds of FMB1xx devices are read from the CAN bus
+        fmb1xx_handler
+            .handle_records(vec![AVLRecordBuilder::new()
+                .with_io_events(vec![
+                    AVLEventIO {
+                        id: 81,
+                        value: nom_teltonika::AVLEventIOValue::U8(72),
+                    },
+                    AVLEventIO {
+                        id: 191,
+                        value: nom_teltonika::AVLEventIOValue::U8(0),
+                    },
+                ])
+                .build()])
+            .await;
+        let speeds_cache = TruckSpeed::read_from_file(temp_dir.path().to_str().unwrap());
+        assert_eq!(
+            vec![72.0],
+            speeds_cache
+                .iter()
+                .map(|speed| speed.speed)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_io_mappings() {
+        let io_mappings = IoMappings::load(None).unwrap();
+        assert_eq!(
+            &IoMapping {
+                vehicle_speed: 81,
+                vin: vec![256],
+                odometer: 87,
+                temperatures: vec![25, 26, 27, 28],
+                temperature_divisor: 100.0,
+            },
+            io_mappings.get_for_family(DeviceFamily::Fmb1xx)
+        );
+        assert_eq!(
+            vec![233, 234, 235],
+            io_mappings.get_for_family(DeviceFamily::Fmc).vin
+        );
+        assert_eq!(None, io_mappings.get("fmb920"));
+
+        // Models of the file replace or add to the embedded ones
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("io_mappings.toml");
+        std::fs::write(
+            &path,
+            "[fmc]\nvehicle_speed = 24\nvin = [233, 234, 235]\nodometer = 16\n\n[fmb920]\nvehicle_speed = 24\nvin = []\nodometer = 16\ntemperatures = [72, 73, 74, 75]\ntemperature_divisor = 10.0\n",
+        )
+        .unwrap();
+        let io_mappings = IoMappings::load(Some(&path)).unwrap();
+        assert_eq!(
+            24,
+            io_mappings.get_for_family(DeviceFamily::Fmc).vehicle_speed
+        );
+        assert_eq!(16, io_mappings.get("fmb920").unwrap().odometer);
+        assert_eq!(
+            vec![72, 73, 74, 75],
+            io_mappings.get("fmb920").unwrap().temperatures
+        );
+        // Temperatures default to the BLE temperatures of EYE sensors
+        assert_eq!(
+            (vec![25, 26, 27, 28], 100.0),
+            (
+                io_mappings
+                    .get_for_family(DeviceFamily::Fmc)
+                    .temperatures
+                    .clone(),
+                io_mappings
+                    .get_for_family(DeviceFamily::Fmc)
+                    .temperature_divisor
+            )
+        );
+        assert_eq!(
+            81,
+            io_mappings
+                .get_for_family(DeviceFamily::Fmb1xx)
+                .vehicle_speed
+        );
+
+        // Keys of a model other than the temperatures are required
+        std::fs::write(&path, "[fmc]\nvehicle_speed = 24\n").unwrap();
+        assert!(matches!(
+            IoMappings::load(Some(&path)),
+            Err(ConfigError::Parse(_))
+        ));
+        assert!(matches!(
+            IoMappings::load(Some(&temp_dir.path().join("missing.toml"))),
+            Err(ConfigError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_ble_sensor_readings() {
+        let handler = BleSensorEventHandler::<FakeTruckEventApi>::default();
+        let events = [
+            AVLEventIO {
+                id: 25,
+                value: nom_teltonika::AVLEventIOValue::U16(-525i16 as u16),
+            },
+            AVLEventIO {
+                id: 86,
+                value: nom_teltonika::AVLEventIOValue::U16(456),
+            },
+            AVLEventIO {
+                id: 10808,
+                value: nom_teltonika::AVLEventIOValue::U8(0),
+            },
+            // Sensor of slot 2 is not found
+            AVLEventIO {
+                id: 26,
+                value: nom_teltonika::AVLEventIOValue::U16(3000),
+            },
+            AVLEventIO {
+                id: 10810,
+                value: nom_teltonika::AVLEventIOValue::U8(1),
+            },
+        ];
+        let readings = handler
+            .process_event_data(
+                RecordTrigger::Periodic,
+                &events.iter().collect::<Vec<&AVLEventIO>>(),
+                1_714_651_200,
+                (0.0, 0.0),
+                "imei",
+            )
+            .unwrap();
+        assert_eq!(
+            Some(BleSensorReadings {
+                timestamp: 1_714_651_200,
+                readings: vec![
+                    BleSensorReading {
+                        slot: 1,
+                        sensor_mac: None,
+                        compartment: None,
+                        temperature: Some(-5.25),
+                        humidity: Some(45.6),
+                        door_open: Some(true),
+                    },
+                    BleSensorReading {
+                        slot: 3,
+                        sensor_mac: None,
+                        compartment: None,
+                        temperature: None,
+                        humidity: None,
+                        door_open: Some(false),
+                    },
+                ],
+            }),
+            readings
+        );
+
+        // Records without BLE sensor events have no readings
+        let event = AVLEventIO {
+            id: 26,
+            value: nom_teltonika::AVLEventIOValue::U16(4000),
+        };
+        assert_eq!(
+            None,
+            handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&event],
+                    1_714_651_200,
+                    (0.0, 0.0),
+                    "imei",
+                )
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ble_sensor_readings_not_cached() {
+        let record_handler = get_teltonika_records_handler(None, None);
+        let record = AVLRecordBuilder::new()
+            .with_io_events(vec![AVLEventIO {
+                id: 25,
+                value: nom_teltonika::AVLEventIOValue::U16(2550),
+            }])
+            .build();
+        let packet = AVLFrameBuilder::new().add_record(record).build();
+
+        record_handler.handle_records(packet.records).await;
+
+        // BLE sensor readings have no API endpoint, so they aren't cached for the yet unknown truck
+        let base_cache_path = record_handler.get_base_cache_path();
+        let readings_cache = BleSensorReadings::read_from_file(base_cache_path.to_str().unwrap());
+        assert!(readings_cache.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ble_sensor_macs() {
+        let sensor_macs = parse_ble_sensor_macs(
+            "352093081452251:1=7c:d9:f4:01:02:03, 352093081452251:4=7C:D9:F4:0A:0B:0C",
+        )
+        .unwrap();
+        assert_eq!(2, sensor_macs.len());
+        assert_eq!(
+            Some(&"7C:D9:F4:01:02:03".to_string()),
+            sensor_macs.get(&("352093081452251".to_string(), 1))
+        );
+        assert_eq!(
+            Some(&"7C:D9:F4:0A:0B:0C".to_string()),
+            sensor_macs.get(&("352093081452251".to_string(), 4))
+        );
+        assert!(parse_ble_sensor_macs("").unwrap().is_empty());
+        assert!(parse_ble_sensor_macs("352093081452251:1").is_err());
+        assert!(parse_ble_sensor_macs("352093081452251=7C:D9:F4:01:02:03").is_err());
+        assert!(parse_ble_sensor_macs("352093081452251:5=7C:D9:F4:01:02:03").is_err());
+        assert!(parse_ble_sensor_macs("352093081452251:1=7C:D9:F4:01:02").is_err());
+        assert!(parse_ble_sensor_macs("352093081452251:1=7C:D9:F4:01:02:XY").is_err());
+    }
+
+    #[test]
+    fn test_parse_ble_sensor_compartments() {
+        let sensor_compartments =
+            parse_ble_sensor_compartments("352093081452251:1=front, 352093081452251:2= rear ")
+                .unwrap();
+        assert_eq!(2, sensor_compartments.len());
+        assert_eq!(
+            Some(&"front".to_string()),
+            sensor_compartments.get(&("352093081452251".to_string(), 1))
+        );
+        assert_eq!(
+            Some(&"rear".to_string()),
+            sensor_compartments.get(&("352093081452251".to_string(), 2))
+        );
+        assert!(parse_ble_sensor_compartments("").unwrap().is_empty());
+        assert!(parse_ble_sensor_compartments("352093081452251:1=").is_err());
+        assert!(parse_ble_sensor_compartments("352093081452251=front").is_err());
+        assert!(parse_ble_sensor_compartments("352093081452251:0=front").is_err());
+        // Readings serialized before compartments were configurable are read without a compartment
+        let reading: BleSensorReading =
+            serde_json::from_str("{\"slot\":1,\"temperature\":4.5}").unwrap();
+        assert_eq!(None, reading.compartment);
+    }
+
+    #[test]
+    fn test_axle_weights() {
+        let handler = AxleWeightEventHandler::<FakeTruckEventApi>::default();
+        let events = [
+            AVLEventIO {
+                id: 118,
+                value: nom_teltonika::AVLEventIOValue::U16(7_150),
+            },
+            AVLEventIO {
+                id: 119,
+                value: nom_teltonika::AVLEventIOValue::U16(11_420),
+            },
+            // Load of the third axle is not available
+            AVLEventIO {
+                id: 120,
+                value: nom_teltonika::AVLEventIOValue::U16(0xFFFF),
+            },
+        ];
+        let axle_weights = handler
+            .process_event_data(
+                RecordTrigger::Periodic,
+                &events.iter().collect::<Vec<&AVLEventIO>>(),
+                1_714_651_200,
+                (0.0, 0.0),
+                "imei",
+            )
+            .unwrap();
+        assert_eq!(
+            Some(TruckAxleWeights {
+                timestamp: 1_714_651_200,
+                axles: vec![
+                    AxleWeight {
+                        axle: 1,
+                        weight: 7_150.0,
+                    },
+                    AxleWeight {
+                        axle: 2,
+                        weight: 11_420.0,
+                    },
+                ],
+            }),
+            axle_weights
+        );
+    }
+
+    #[tokio::test]
+    async fn test_axle_weights_not_cached() {
+        let record_handler = get_teltonika_records_handler(None, None);
+        let record = AVLRecordBuilder::new()
+            .with_io_events(vec![AVLEventIO {
+                id: 118,
+                value: nom_teltonika::AVLEventIOValue::U16(7_150),
+            }])
+            .build();
+        let packet = AVLFrameBuilder::new().add_record(record).build();
+
+        record_handler.handle_records(packet.records).await;
+
+        // Axle weights have no API endpoint, so they aren't cached for the yet unknown truck
+        let base_cache_path = record_handler.get_base_cache_path();
+        let axle_weights_cache =
+            TruckAxleWeights::read_from_file(base_cache_path.to_str().unwrap());
+        assert!(axle_weights_cache.is_empty());
+    }
+
+    #[test]
+    fn test_speed_event_handler_gnss_fallback() {
+        let mut handler = SpeedEventHandler::<FakeTruckEventApi>::default();
+        assert_eq!(vec![191], handler.get_event_ids());
+        assert!(handler.get_optional_event_ids().is_empty());
+
+        handler.set_gnss_fallback(true);
+        assert!(handler.get_event_ids().is_empty());
+        assert_eq!(vec![191, 24], handler.get_optional_event_ids());
+        let tachograph_speed_event = AVLEventIO {
+            id: 191,
+            value: nom_teltonika::AVLEventIOValue::U8(78),
+        };
+        let gnss_speed_event = AVLEventIO {
+            id: 24,
+            value: nom_teltonika::AVLEventIOValue::U16(81),
+        };
+        // Tachograph speed is preferred when both are present
+        assert_eq!(
+            Some(TruckSpeed {
+                id: None,
+                speed: 78.0,
+                timestamp: 1_714_651_200,
+            }),
+            handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&gnss_speed_event, &tachograph_speed_event],
+                    1_714_651_200,
+                    (0.0, 0.0),
+                    "imei"
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            Some(TruckSpeed {
+                id: None,
+                speed: 81.0,
+                timestamp: 1_714_651_200,
+            }),
+            handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&gnss_speed_event],
+                    1_714_651_200,
+                    (0.0, 0.0),
+                    "imei"
+                )
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_engine_hours_and_rpm() {
+        let engine_hours_handler = EngineHoursEventHandler::<FakeTruckEventApi>::default();
+        let engine_rpm_handler = EngineRpmEventHandler::<FakeTruckEventApi>::default();
+        let read_engine_hours = |worktime: u32, timestamp: i64| {
+            let event = AVLEventIO {
+                id: 102,
+                value: nom_teltonika::AVLEventIOValue::U32(worktime),
+            };
+            let engine_hours = engine_hours_handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&event],
+                    timestamp,
+                    (0.0, 0.0),
+                    "imei",
+                )
+                .unwrap()
+                .unwrap();
+
+            return engine_hours_handler.filter_event_data(engine_hours, "imei");
+        };
+        let read_engine_rpm = |rpm: u16, timestamp: i64| {
+            let event = AVLEventIO {
+                id: 85,
+                value: nom_teltonika::AVLEventIOValue::U16(rpm),
+            };
+
+            return engine_rpm_handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &[&event],
+                    timestamp,
+                    (0.0, 0.0),
+                    "imei",
+                )
+                .unwrap();
+        };
+
+        assert_eq!(
+            Some(TruckEngineHours {
+                timestamp: 1_714_651_200,
+                engine_hours: 1_500.5,
+            }),
+            read_engine_hours(90_030, 1_714_651_200)
+        );
+        // Engine hours unchanged since the previous record are not reported again
+        assert_eq!(None, read_engine_hours(90_030, 1_714_651_230));
+        assert_eq!(
+            Some(TruckEngineRpm {
+                timestamp: 1_714_651_200,
+                rpm: 1_250,
+            }),
+            read_engine_rpm(1_250, 1_714_651_200)
+        );
+        assert_eq!(
+            Some(TruckEngineRpm {
+                timestamp: 1_714_651_230,
+                rpm: 1_310,
+            }),
+            read_engine_rpm(1_310, 1_714_651_230)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_engine_hours_and_rpm_not_cached() {
+        let record_handler = get_teltonika_records_handler(None, None);
+        let record = AVLRecordBuilder::new()
+            .with_io_events(vec![
+                AVLEventIO {
+                    id: 102,
+                    value: nom_teltonika::AVLEventIOValue::U32(90_030),
+                },
+                AVLEventIO {
+                    id: 85,
+                    value: nom_teltonika::AVLEventIOValue::U16(1_250),
+                },
+            ])
+            .build();
+        let packet = AVLFrameBuilder::new().add_record(record).build();
+
+        record_handler.handle_records(packet.records).await;
+
+        // Engine hours and engine speeds have no API endpoints, so they aren't cached for the yet unknown truck
+        let base_cache_path = record_handler.get_base_cache_path();
+        assert!(TruckEngineHours::read_from_file(base_cache_path.to_str().unwrap()).is_empty());
+        assert!(TruckEngineRpm::read_from_file(base_cache_path.to_str().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_fault_records() {
+        let handler = FaultCodeEventHandler::<FakeTruckEventApi>::default();
+        let read_fault_record = |count: u8, codes: Option<&str>, timestamp: i64| {
+            let mut events = vec![AVLEventIO {
+                id: 30,
+                value: nom_teltonika::AVLEventIOValue::U8(count),
+            }];
+            if let Some(codes) = codes {
+                events.push(AVLEventIO {
+                    id: 281,
+                    value: nom_teltonika::AVLEventIOValue::Variable(codes.as_bytes().to_vec()),
+                });
+            }
+
+            return handler
+                .process_event_data(
+                    RecordTrigger::Periodic,
+                    &events.iter().collect::<Vec<&AVLEventIO>>(),
+                    timestamp,
+                    (0.0, 0.0),
+                    "imei",
+                )
+                .map(|fault_record| {
+                    fault_record
+                        .and_then(|fault_record| handler.filter_event_data(fault_record, "imei"))
+                });
+        };
+
+        assert_eq!(
+            Some(FaultRecord {
+                timestamp: 1_714_651_200,
+                active_count: 2,
+                codes: vec!["P0420".to_string(), "P0171".to_string()],
+            }),
+            read_fault_record(2, Some("P0420, p0171"), 1_714_651_200).unwrap()
+        );
+        // Unchanged codes are not reported again
+        assert_eq!(
+            None,
+            read_fault_record(2, Some("P0420,P0171"), 1_714_651_230).unwrap()
+        );
+        assert_eq!(
+            Some(FaultRecord {
+                timestamp: 1_714_651_260,
+                active_count: 0,
+                codes: Vec::new(),
+            }),
+            read_fault_record(0, None, 1_714_651_260).unwrap()
+        );
+        let decode_error = read_fault_record(1, Some("P04"), 1_714_651_290).unwrap_err();
+        assert_eq!(281, decode_error.event_id);
+    }
+
+    #[tokio::test]
+    async fn test_fault_records_not_cached() {
+        let record_handler = get_teltonika_records_handler(None, None);
+        let record = AVLRecordBuilder::new()
+            .with_io_events(vec![
+                AVLEventIO {
+                    id: 30,
+                    value: nom_teltonika::AVLEventIOValue::U8(1),
+                },
+                AVLEventIO {
+                    id: 281,
+                    value: nom_teltonika::AVLEventIOValue::Variable(b"P0420".to_vec()),
+                },
+            ])
+            .build();
+        let packet = AVLFrameBuilder::new().add_record(record).build();
+
+        record_handler.handle_records(packet.records).await;
+
+        // Fault records have no API endpoint, so they aren't cached for the yet unknown truck
+        let base_cache_path = record_handler.get_base_cache_path();
+        assert!(FaultRecord::read_from_file(base_cache_path.to_str().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_zone_events() {
+        let handler = GeofenceEventHandler::<FakeTruckEventApi>::default();
+        let geozone_events = [
+            AVLEventIO {
+                id: 155,
+                value: nom_teltonika::AVLEventIOValue::U8(0),
+            },
+            AVLEventIO {
+                id: 156,
+                value: nom_teltonika::AVLEventIOValue::U8(1),
+            },
+        ];
+        let read_zone_event = |trigger: RecordTrigger| {
+            return handler
+                .process_event_data(
+                    trigger,
+                    &geozone_events.iter().collect::<Vec<&AVLEventIO>>(),
+                    1_714_651_200,
+                    (61.6885, 27.2723),
+                    "imei",
+                )
+                .unwrap();
+        };
+
+        assert_eq!(
+            Some(ZoneEvent {
+                timestamp: 1_714_651_200,
+                zone_id: 2,
+                direction: ZoneDirection::Enter,
+                latitude: 61.6885,
+                longitude: 27.2723,
+            }),
+            read_zone_event(RecordTrigger::Eventual(156))
+        );
+        // Periodic records including the state of the geozones are not zone events
+        assert_eq!(None, read_zone_event(RecordTrigger::Periodic));
+    }
+
+    #[tokio::test]
+    async fn test_zone_events_not_cached() {
+        let record_handler = get_teltonika_records_handler(None, None);
+        let record = AVLRecordBuilder::new()
+            .with_trigger_event_id(156)
+            .with_io_events(vec![AVLEventIO {
+                id: 156,
+                value: nom_teltonika::AVLEventIOValue::U8(1),
+            }])
+            .build();
+        let packet = AVLFrameBuilder::new().add_record(record).build();
+
+        record_handler.handle_records(packet.records).await;
+
+        // Zone events have no API endpoint, so they aren't cached for the yet unknown truck
+        let base_cache_path = record_handler.get_base_cache_path();
+        assert!(ZoneEvent::read_from_file(base_cache_path.to_str().unwrap()).is_empty());
+    }
 }
@@ -0,0 +1,197 @@
+//! In-process metrics registry
+//!
+//! Metrics are kept in memory and rendered in Prometheus text exposition format by the admin server.
+pub mod summary;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Mutex, OnceLock},
+};
+
+/// Suffix of the gauges tracking the high-water mark of another gauge
+pub const HIGH_WATER_MARK_SUFFIX: &str = "_high_water_mark";
+
+/// Labels of a metric as key-value pairs
+pub type Labels = Vec<(String, String)>;
+
+/// Registry for counters and gauges
+#[derive(Default)]
+struct Metrics {
+    counters: Mutex<BTreeMap<(String, Labels), u64>>,
+    gauges: Mutex<BTreeMap<(String, Labels), f64>>,
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// Gets the global metrics registry
+fn get_metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::default)
+}
+
+/// Converts label references to owned [Labels]
+fn to_labels(labels: &[(&str, &str)]) -> Labels {
+    labels
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Increments a counter by one
+///
+/// # Arguments
+/// * `name` - Name of the counter
+/// * `labels` - Labels of the counter
+pub fn increment_counter(name: &str, labels: &[(&str, &str)]) {
+    add_to_counter(name, labels, 1);
+}
+
+/// Increments a counter by the given amount
+///
+/// # Arguments
+/// * `name` - Name of the counter
+/// * `labels` - Labels of the counter
+/// * `amount` - Amount to increment by
+pub fn add_to_counter(name: &str, labels: &[(&str, &str)], amount: u64) {
+    let mut counters = get_metrics().counters.lock().unwrap();
+    *counters
+        .entry((name.to_string(), to_labels(labels)))
+        .or_insert(0) += amount;
+}
+
+/// Gets the current value of a counter
+///
+/// # Arguments
+/// * `name` - Name of the counter
+/// * `labels` - Labels of the counter
+#[cfg(test)]
+pub fn get_counter(name: &str, labels: &[(&str, &str)]) -> u64 {
+    let counters = get_metrics().counters.lock().unwrap();
+    return counters
+        .get(&(name.to_string(), to_labels(labels)))
+        .copied()
+        .unwrap_or(0);
+}
+
+/// Gets the sum of all counters with the given name and matching labels
+///
+/// # Arguments
+/// * `name` - Name of the counters
+/// * `labels` - Labels the counters must have, other labels are ignored
+pub fn get_counter_total(name: &str, labels: &[(&str, &str)]) -> u64 {
+    let counters = get_metrics().counters.lock().unwrap();
+    return counters
+        .iter()
+        .filter(|((counter_name, counter_labels), _)| {
+            counter_name == name
+                && labels.iter().all(|(key, value)| {
+                    counter_labels.iter().any(|(counter_key, counter_value)| {
+                        counter_key == key && counter_value == value
+                    })
+                })
+        })
+        .map(|(_, value)| value)
+        .sum();
+}
+
+/// Sets the value of a gauge
+///
+/// # Arguments
+/// * `name` - Name of the gauge
+/// * `labels` - Labels of the gauge
+/// * `value` - Value to set
+pub fn set_gauge(name: &str, labels: &[(&str, &str)], value: f64) {
+    let mut gauges = get_metrics().gauges.lock().unwrap();
+    gauges.insert((name.to_string(), to_labels(labels)), value);
+}
+
+/// Sets the value of a gauge and updates its high-water mark
+///
+/// The high-water mark is stored as a separate gauge with [HIGH_WATER_MARK_SUFFIX] appended to the name.
+///
+/// # Arguments
+/// * `name` - Name of the gauge
+/// * `labels` - Labels of the gauge
+/// * `value` - Value to set
+pub fn set_gauge_with_high_water_mark(name: &str, labels: &[(&str, &str)], value: f64) {
+    let mut gauges = get_metrics().gauges.lock().unwrap();
+    let labels = to_labels(labels);
+    let high_water_mark = gauges
+        .entry((format!("{name}{HIGH_WATER_MARK_SUFFIX}"), labels.clone()))
+        .or_insert(value);
+    *high_water_mark = high_water_mark.max(value);
+    gauges.insert((name.to_string(), labels), value);
+}
+
+/// Gets the current value of a gauge
+///
+/// # Arguments
+/// * `name` - Name of the gauge
+/// * `labels` - Labels of the gauge
+#[cfg(test)]
+pub fn get_gauge(name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+    let gauges = get_metrics().gauges.lock().unwrap();
+    return gauges.get(&(name.to_string(), to_labels(labels))).copied();
+}
+
+/// Gets all gauges with the given name
+///
+/// # Arguments
+/// * `name` - Name of the gauges
+///
+/// # Returns
+/// * `Vec<(Labels, f64)>` - Labels and values of the gauges
+pub fn get_gauges(name: &str) -> Vec<(Labels, f64)> {
+    let gauges = get_metrics().gauges.lock().unwrap();
+    return gauges
+        .iter()
+        .filter(|((gauge_name, _), _)| gauge_name == name)
+        .map(|((_, labels), value)| (labels.clone(), *value))
+        .collect();
+}
+
+/// Renders all metrics in Prometheus text exposition format
+pub fn render() -> String {
+    let mut output = String::new();
+    let counters = get_metrics().counters.lock().unwrap();
+    let mut previous_name: Option<&str> = None;
+    for ((name, labels), value) in counters.iter() {
+        if previous_name != Some(name) {
+            let _ = writeln!(output, "# TYPE {name} counter");
+            previous_name = Some(name);
+        }
+        let _ = writeln!(output, "{name}{} {value}", render_labels(labels));
+    }
+    drop(counters);
+
+    let gauges = get_metrics().gauges.lock().unwrap();
+    let mut previous_name: Option<&str> = None;
+    for ((name, labels), value) in gauges.iter() {
+        if previous_name != Some(name) {
+            let _ = writeln!(output, "# TYPE {name} gauge");
+            previous_name = Some(name);
+        }
+        let _ = writeln!(output, "{name}{} {value}", render_labels(labels));
+    }
+
+    return output;
+}
+
+/// Renders labels in Prometheus text exposition format
+fn render_labels(labels: &Labels) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let rendered_labels = labels
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "{key}=\"{}\"",
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+
+    return format!("{{{rendered_labels}}}");
+}
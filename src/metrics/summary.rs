@@ -0,0 +1,88 @@
+//! Periodic summary of the receiver statistics
+//!
+//! Makes log-based monitoring possible for sites without Prometheus.
+use std::time::Duration;
+
+use log::info;
+
+use crate::{
+    admin::QUEUE_DEPTH_METRIC,
+    teltonika::connection::{
+        BYTES_METRIC, CONNECTIONS_METRIC, FRAMES_METRIC, PARSE_ERRORS_METRIC, RECORDS_METRIC,
+    },
+    utils::api::API_REQUESTS_METRIC,
+};
+
+use super::{get_counter_total, get_gauges};
+
+/// Totals of the receiver statistics at a point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StatisticsSnapshot {
+    pub connections: u64,
+    pub frames: u64,
+    pub records: u64,
+    pub bytes: u64,
+    pub parse_errors: u64,
+    pub api_failures: u64,
+}
+
+impl StatisticsSnapshot {
+    /// Captures the current totals from the metrics registry
+    pub fn capture() -> Self {
+        StatisticsSnapshot {
+            connections: get_counter_total(CONNECTIONS_METRIC, &[]),
+            frames: get_counter_total(FRAMES_METRIC, &[]),
+            records: get_counter_total(RECORDS_METRIC, &[]),
+            bytes: get_counter_total(BYTES_METRIC, &[]),
+            parse_errors: get_counter_total(PARSE_ERRORS_METRIC, &[]),
+            api_failures: get_counter_total(API_REQUESTS_METRIC, &[("result", "failure")]),
+        }
+    }
+
+    /// Gets the statistics accumulated since a previous snapshot
+    ///
+    /// # Arguments
+    /// * `previous` - Previous snapshot
+    pub fn since(&self, previous: &StatisticsSnapshot) -> StatisticsSnapshot {
+        StatisticsSnapshot {
+            connections: self.connections.saturating_sub(previous.connections),
+            frames: self.frames.saturating_sub(previous.frames),
+            records: self.records.saturating_sub(previous.records),
+            bytes: self.bytes.saturating_sub(previous.bytes),
+            parse_errors: self.parse_errors.saturating_sub(previous.parse_errors),
+            api_failures: self.api_failures.saturating_sub(previous.api_failures),
+        }
+    }
+}
+
+/// Periodically logs a summary of the receiver statistics
+///
+/// # Arguments
+/// * `interval` - Interval between the summaries
+pub async fn start_summary_logger(interval: Duration) {
+    let mut previous = StatisticsSnapshot::capture();
+    let mut ticker = tokio::time::interval(interval);
+    // First tick completes immediately
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let current = StatisticsSnapshot::capture();
+        let summary = current.since(&previous);
+        let cache_backlog = get_gauges(QUEUE_DEPTH_METRIC)
+            .iter()
+            .map(|(_, depth)| *depth as u64)
+            .sum::<u64>();
+        info!(
+            "Summary of the last {} s: {} connections, {} frames, {} records, {} bytes, {} parse errors, {} API failures, {} cached items",
+            interval.as_secs(),
+            summary.connections,
+            summary.frames,
+            summary.records,
+            summary.bytes,
+            summary.parse_errors,
+            summary.api_failures,
+            cache_backlog
+        );
+        previous = current;
+    }
+}
@@ -0,0 +1,61 @@
+//! Detection of misinstalled devices
+//!
+//! Trucks are looked up by the VIN the device reads from the CAN bus, so a device moved to another vehicle silently
+//! starts sending data for the other truck. A VIN differing from the one previously received from the same IMEI is
+//! reported as a suspected misinstallation.
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use log::error;
+
+use crate::metrics;
+
+/// Name of the counter describing the number of VIN mismatches by IMEI
+pub const VIN_MISMATCH_METRIC: &str = "receiver_vin_mismatches_total";
+
+static DETECTOR: OnceLock<VinMismatchDetector> = OnceLock::new();
+
+/// Tracks the latest VIN received from each IMEI
+#[derive(Default)]
+pub struct VinMismatchDetector {
+    latest_vins: Mutex<HashMap<String, String>>,
+}
+
+impl VinMismatchDetector {
+    /// Checks the VIN received from a device
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `vin` - VIN received from the device
+    ///
+    /// # Returns
+    /// * Previous VIN of the device if it differs from the received one
+    pub fn check_vin(&self, imei: &str, vin: &str) -> Option<String> {
+        let mut latest_vins = self.latest_vins.lock().unwrap();
+        let previous_vin = latest_vins.insert(imei.to_string(), vin.to_string())?;
+        if previous_vin == vin {
+            return None;
+        }
+        error!(target: imei,
+            "Suspected misinstallation: VIN changed from [{}] to [{}]",
+            previous_vin,
+            vin
+        );
+        metrics::increment_counter(VIN_MISMATCH_METRIC, &[("imei", imei)]);
+
+        return Some(previous_vin);
+    }
+}
+
+/// Checks the VIN received from a device using the global detector
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `vin` - VIN received from the device
+pub fn check_vin(imei: &str, vin: &str) -> Option<String> {
+    return DETECTOR
+        .get_or_init(VinMismatchDetector::default)
+        .check_vin(imei, vin);
+}
@@ -0,0 +1,113 @@
+//! Synthetic end-to-end latency probe
+//!
+//! The probe periodically connects to the receiver itself as a device with [PROBE_IMEI] and sends a frame
+//! with a single record timestamped at the time of sending. Connections of the probe skip the API and
+//! report the time it took for the frame to pass through the pipeline instead.
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, warn};
+use nom_teltonika::{AVLFrame, AVLRecord, Codec, Priority};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{metrics, utils::avl_packet::AVLPacketToBytes};
+
+/// IMEI used by the probe connections
+pub const PROBE_IMEI: &str = "000000000000000";
+/// Name of the gauge describing the latency of the latest probe frame
+pub const PROBE_LATENCY_METRIC: &str = "receiver_probe_latency_seconds";
+/// Name of the counter describing the number of failed probes
+pub const PROBE_FAILURES_METRIC: &str = "receiver_probe_failures_total";
+
+/// Checks whether the connection is made by the probe
+///
+/// # Arguments
+/// * `imei` - IMEI of the connection
+pub fn is_probe_imei(imei: &str) -> bool {
+    return imei == PROBE_IMEI;
+}
+
+/// Records the latency of a frame sent by the probe
+///
+/// # Arguments
+/// * `records` - Records of the frame
+pub fn record_probe_frame(records: &[AVLRecord]) {
+    let Some(sent_at) = records.iter().map(|record| record.timestamp).max() else {
+        return;
+    };
+    let latency = (Utc::now() - sent_at).num_milliseconds().max(0) as f64 / 1000.0;
+    debug!("Probe frame passed the pipeline in {} s", latency);
+    metrics::set_gauge(PROBE_LATENCY_METRIC, &[], latency);
+}
+
+/// Starts sending probe frames to the receiver
+///
+/// # Arguments
+/// * `address` - Address of the receiver
+/// * `interval` - Interval between probes
+pub async fn start_latency_probe(address: String, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        match tokio::time::timeout(interval.period(), send_probe_frame(&address)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                warn!("Latency probe failed: {}", err);
+                metrics::increment_counter(PROBE_FAILURES_METRIC, &[]);
+            }
+            Err(_) => {
+                warn!("Latency probe timed out");
+                metrics::increment_counter(PROBE_FAILURES_METRIC, &[]);
+            }
+        }
+    }
+}
+
+/// Sends a single probe frame to the receiver and waits for its acknowledgement
+///
+/// # Arguments
+/// * `address` - Address of the receiver
+pub async fn send_probe_frame(address: &str) -> std::io::Result<()> {
+    let mut stream = TcpStream::connect(address).await?;
+
+    let mut imei_packet = (PROBE_IMEI.len() as u16).to_be_bytes().to_vec();
+    imei_packet.extend_from_slice(PROBE_IMEI.as_bytes());
+    stream.write_all(&imei_packet).await?;
+    if stream.read_u8().await? != 1 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "Probe IMEI was denied",
+        ));
+    }
+
+    let frame = AVLFrame {
+        codec: Codec::C8,
+        crc16: 0,
+        records: vec![AVLRecord {
+            timestamp: Utc::now(),
+            priority: Priority::Low,
+            longitude: 0.0,
+            latitude: 0.0,
+            altitude: 0,
+            angle: 0,
+            satellites: 0,
+            speed: 0,
+            trigger_event_id: 0,
+            generation_type: None,
+            io_events: vec![],
+        }],
+    };
+    stream.write_all(&frame.to_bytes()).await?;
+    let acknowledged_records = stream.read_u32().await?;
+    if acknowledged_records != frame.records.len() as u32 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Unexpected ACK of {} records", acknowledged_records),
+        ));
+    }
+
+    return Ok(());
+}
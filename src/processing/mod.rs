@@ -0,0 +1,163 @@
+//! Control of processing per device
+//!
+//! Processing of a device can be paused, buffering its data in the cache without sending it to the API,
+//! or muted, dropping its data altogether, e.g. for a test bench device sending garbage.
+//! Modes are persisted to the file in `PROCESSING_STATE_FILE` if set, so they survive restarts.
+//!
+//! Maintenance mode pauses the processing of all devices, e.g. during deployments of the Vehicle Management Service.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::get_config, metrics};
+
+/// Name of the counter describing the number of frames dropped from muted devices by IMEI
+pub const MUTED_FRAMES_METRIC: &str = "receiver_muted_frames_total";
+/// Name of the gauge describing whether maintenance mode is enabled
+pub const MAINTENANCE_MODE_METRIC: &str = "receiver_maintenance_mode";
+
+static PROCESSING_CONTROL: OnceLock<ProcessingControl> = OnceLock::new();
+static MAINTENANCE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Processing mode of a device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingMode {
+    /// Data is processed and sent to the API
+    #[default]
+    Active,
+    /// Data is processed and cached, but not sent to the API
+    Paused,
+    /// Frames are acknowledged and dropped
+    Muted,
+}
+
+/// Processing modes of the devices not in [ProcessingMode::Active]
+pub struct ProcessingControl {
+    state_file: Option<PathBuf>,
+    modes: Mutex<BTreeMap<String, ProcessingMode>>,
+}
+
+impl ProcessingControl {
+    /// Creates a new [ProcessingControl]
+    ///
+    /// # Arguments
+    /// * `state_file` - File to persist the modes to and to load them from if it exists
+    pub fn new(state_file: Option<PathBuf>) -> Self {
+        let modes = state_file
+            .as_ref()
+            .and_then(|state_file| fs::read_to_string(state_file).ok())
+            .and_then(|state| {
+                serde_json::from_str(&state)
+                    .map_err(|err| warn!("Ignoring invalid processing state file: {}", err))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        ProcessingControl {
+            state_file,
+            modes: Mutex::new(modes),
+        }
+    }
+
+    /// Gets the processing mode of a device
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    pub fn get_mode(&self, imei: &str) -> ProcessingMode {
+        return self
+            .modes
+            .lock()
+            .unwrap()
+            .get(imei)
+            .copied()
+            .unwrap_or_default();
+    }
+
+    /// Gets the processing modes of all devices not in [ProcessingMode::Active]
+    pub fn get_modes(&self) -> BTreeMap<String, ProcessingMode> {
+        return self.modes.lock().unwrap().clone();
+    }
+
+    /// Sets the processing mode of a device and persists the modes
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `mode` - Processing mode to set
+    pub fn set_mode(&self, imei: &str, mode: ProcessingMode) -> std::io::Result<()> {
+        let mut modes = self.modes.lock().unwrap();
+        match mode {
+            ProcessingMode::Active => modes.remove(imei),
+            _ => modes.insert(imei.to_string(), mode),
+        };
+        if let Some(state_file) = &self.state_file {
+            fs::write(state_file, serde_json::to_string(&*modes)?)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/// Gets the global processing control with the state file of the configuration
+pub fn get_processing_control() -> &'static ProcessingControl {
+    PROCESSING_CONTROL
+        .get_or_init(|| ProcessingControl::new(get_config().processing_state_file.clone()))
+}
+
+/// Records a frame dropped from a muted device
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+pub fn record_muted_frame(imei: &str) {
+    metrics::increment_counter(MUTED_FRAMES_METRIC, &[("imei", imei)]);
+}
+
+/// Gets the effective processing mode of a device
+///
+/// Devices not muted are paused while maintenance mode is enabled.
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+pub fn get_processing_mode(imei: &str) -> ProcessingMode {
+    let mode = get_processing_control().get_mode(imei);
+    if mode == ProcessingMode::Active && is_maintenance_mode() {
+        return ProcessingMode::Paused;
+    }
+
+    return mode;
+}
+
+/// Checks whether maintenance mode is enabled
+pub fn is_maintenance_mode() -> bool {
+    return MAINTENANCE_MODE.load(Ordering::Relaxed);
+}
+
+/// Enables or disables maintenance mode
+///
+/// While maintenance mode is enabled, data of all devices is cached instead of sent to the API.
+/// Caches are drained as usual once it is disabled.
+///
+/// # Arguments
+/// * `enabled` - Whether maintenance mode is enabled
+pub fn set_maintenance_mode(enabled: bool) {
+    if MAINTENANCE_MODE.swap(enabled, Ordering::Relaxed) != enabled {
+        info!(
+            "Maintenance mode {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+    metrics::set_gauge(
+        MAINTENANCE_MODE_METRIC,
+        &[],
+        if enabled { 1.0 } else { 0.0 },
+    );
+}
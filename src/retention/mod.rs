@@ -0,0 +1,89 @@
+//! Data retention
+//!
+//! Periodically purges stored data older than the retention period of its store.
+//! Raw frame captures written with `WRITE_TO_FILE` are the only data the receiver stores on disk,
+//! one file per device and day in `BASE_FILE_PATH/<IMEI>/<YYYY-MM-DD>.txt`.
+use std::{
+    fs::{read_dir, remove_file},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{NaiveDate, Utc};
+use log::{info, warn};
+
+use crate::metrics;
+
+/// Name of the store of raw frame captures
+pub const RAW_CAPTURES_STORE: &str = "raw_captures";
+/// Default retention period of raw frame captures in days
+pub const DEFAULT_RAW_CAPTURES_RETENTION_DAYS: u32 = 14;
+/// Name of the counter describing the number of files purged by store
+pub const RETENTION_PURGED_FILES_METRIC: &str = "receiver_retention_purged_files_total";
+/// Interval of the retention job
+const RETENTION_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Purges raw frame captures older than the retention period
+///
+/// # Arguments
+/// * `base_path` - Base path of the raw frame captures
+/// * `retention_days` - Number of days the captures are kept
+/// * `today` - Current date
+///
+/// # Returns
+/// * Number of purged files
+pub fn purge_raw_captures(
+    base_path: &Path,
+    retention_days: u32,
+    today: NaiveDate,
+) -> std::io::Result<usize> {
+    let oldest_kept_date = today - chrono::Duration::days(retention_days.into());
+    let mut purged_files = 0;
+    for device_dir in read_dir(base_path)? {
+        let device_dir = device_dir?.path();
+        if !device_dir.is_dir() {
+            continue;
+        }
+        for capture_file in read_dir(&device_dir)? {
+            let capture_file = capture_file?.path();
+            let capture_date = capture_file
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok());
+            match capture_date {
+                Some(capture_date) if capture_date < oldest_kept_date => {
+                    remove_file(&capture_file)?;
+                    purged_files += 1;
+                }
+                _ => continue,
+            }
+        }
+    }
+    metrics::add_to_counter(
+        RETENTION_PURGED_FILES_METRIC,
+        &[("store", RAW_CAPTURES_STORE)],
+        purged_files as u64,
+    );
+
+    return Ok(purged_files);
+}
+
+/// Starts the retention job purging raw frame captures
+///
+/// # Arguments
+/// * `base_path` - Base path of the raw frame captures
+/// * `retention_days` - Number of days the captures are kept
+pub async fn start_retention_job(base_path: PathBuf, retention_days: u32) {
+    let mut ticker = tokio::time::interval(RETENTION_JOB_INTERVAL);
+    loop {
+        ticker.tick().await;
+        match purge_raw_captures(&base_path, retention_days, Utc::now().date_naive()) {
+            Ok(0) => continue,
+            Ok(purged_files) => info!(
+                "Purged {} raw frame captures older than {} days",
+                purged_files, retention_days
+            ),
+            Err(err) => warn!("Failed to purge raw frame captures: {}", err),
+        }
+    }
+}
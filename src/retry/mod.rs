@@ -0,0 +1,148 @@
+//! Scheduled retries of cached events
+//!
+//! Events failing to be sent are cached per device and sent again when the caches of the device are purged. Purges
+//! back off exponentially while the events keep failing, so that an unavailable API isn't hammered with the whole
+//! backlog on every frame, and a background task retries the caches of connected devices on a schedule, so that the
+//! backlog is sent even when the devices send no new frames. The caches of disconnected devices are retried by the
+//! task until they are empty. Events failing to be sent too many times are moved to the dead-letter events of the
+//! device.
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    config::get_config,
+    metrics,
+    teltonika::connection::registry::{get_connection_registry, ConnectionRegistry},
+};
+
+/// Name of the counter describing the number of cache retries by result
+pub const CACHE_RETRIES_METRIC: &str = "receiver_cache_retries_total";
+
+/// Exponential backoff of the retries of the caches of a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryBackoff {
+    base: Duration,
+    max: Duration,
+    /// Number of consecutive failed retries
+    failures: u32,
+    next_retry_at: Option<DateTime<Utc>>,
+}
+
+impl RetryBackoff {
+    /// Creates a new [RetryBackoff] from the configuration
+    pub fn new() -> Self {
+        RetryBackoff::with_configuration(
+            get_retry_interval(),
+            get_config().tunables.cache_retry_max_backoff,
+        )
+    }
+
+    /// Creates a new [RetryBackoff]
+    ///
+    /// # Arguments
+    /// * `base` - Backoff after the first failed retry, doubled by each further failure
+    /// * `max` - Maximum backoff
+    pub fn with_configuration(base: Duration, max: Duration) -> Self {
+        RetryBackoff {
+            base,
+            max,
+            failures: 0,
+            next_retry_at: None,
+        }
+    }
+
+    /// Checks whether a retry is due
+    ///
+    /// # Arguments
+    /// * `now` - Current time
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        match self.next_retry_at {
+            Some(next_retry_at) => now >= next_retry_at,
+            None => true,
+        }
+    }
+
+    /// Records a retry sending all cached events
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.next_retry_at = None;
+        metrics::increment_counter(CACHE_RETRIES_METRIC, &[("result", "success")]);
+    }
+
+    /// Records a retry leaving events in the caches and schedules the next retry
+    ///
+    /// # Arguments
+    /// * `now` - Time of the retry
+    pub fn record_failure(&mut self, now: DateTime<Utc>) {
+        self.failures = self.failures.saturating_add(1);
+        let backoff = self
+            .base
+            .checked_mul(2_u32.pow(self.failures.min(32) - 1))
+            .map_or(self.max, |backoff| backoff.min(self.max));
+        self.next_retry_at = Some(now + chrono::Duration::from_std(backoff).unwrap());
+        metrics::increment_counter(CACHE_RETRIES_METRIC, &[("result", "failure")]);
+    }
+
+    /// Gets the time of the next retry, if backing off
+    pub fn get_next_retry_at(&self) -> Option<DateTime<Utc>> {
+        self.next_retry_at
+    }
+}
+
+impl Default for RetryBackoff {
+    fn default() -> Self {
+        RetryBackoff::new()
+    }
+}
+
+/// Retries the caches of the connected and disconnected devices whose retry is due
+///
+/// Batches of the connected devices whose window has elapsed are submitted first, so that the batches of devices
+/// sending no new frames aren't held back. Disconnected devices are released once their caches are empty, or right
+/// away if their truck is unknown, leaving the caches to be retried when the device reconnects.
+///
+/// # Arguments
+/// * `registry` - Registry of the devices
+pub async fn retry_due(registry: &ConnectionRegistry) {
+    for records_handler in registry.get_records_handlers() {
+        records_handler.flush_batches(false).await;
+        records_handler.retry_cached_events().await;
+    }
+    for records_handler in registry.get_disconnected_records_handlers() {
+        records_handler.retry_cached_events().await;
+        if records_handler.get_truck_id().is_none()
+            || records_handler.get_cached_events_count() == 0
+        {
+            registry
+                .release_disconnected_records_handler(records_handler.get_imei(), &records_handler);
+        }
+    }
+}
+
+/// Gets the interval of the scheduled retries configured in `CACHE_RETRY_INTERVAL_SECONDS`
+///
+/// Zero disables the scheduled retries, leaving the caches to be retried when the devices send frames.
+pub fn get_retry_interval() -> Duration {
+    return get_config().cache_retry_interval;
+}
+
+/// Gets the maximum number of failed attempts to send a cached event configured in `CACHE_MAX_RETRY_ATTEMPTS`
+///
+/// # Returns
+/// * The maximum attempts, or `None` if zero is configured to retry the events forever
+pub fn get_max_retry_attempts() -> Option<u32> {
+    return get_config().tunables.cache_max_retry_attempts;
+}
+
+/// Starts retrying the caches of the connected devices on a schedule
+///
+/// # Arguments
+/// * `interval` - Interval of the retries
+pub async fn start_retry_scheduler(interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        retry_due(get_connection_registry()).await;
+    }
+}
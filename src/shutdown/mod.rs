@@ -0,0 +1,117 @@
+//! Graceful shutdown of the receiver
+//!
+//! On `SIGTERM` or `SIGINT` the receiver stops accepting connections and asks the open connections to close. The
+//! connections stop reading frames, dispatch the records of the frames they have already acknowledged and close,
+//! while the receiver waits for them up to a timeout before exiting. Frames not acknowledged yet are kept by the
+//! devices and sent again when they reconnect. Batches of events still waiting to be submitted are submitted before
+//! exiting.
+use std::{sync::OnceLock, time::Duration};
+
+use log::{error, info, warn};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+
+use crate::{
+    connection_limits::ConnectionLimiter, teltonika::connection::registry::get_connection_registry,
+};
+
+/// Interval of checking whether the connections have closed
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static SHUTDOWN: OnceLock<Shutdown> = OnceLock::new();
+
+/// State of shutting down shared by the listener and the connections
+pub struct Shutdown {
+    sender: watch::Sender<bool>,
+}
+
+impl Shutdown {
+    /// Creates a new [Shutdown] not requested yet
+    pub fn new() -> Self {
+        Shutdown {
+            sender: watch::channel(false).0,
+        }
+    }
+
+    /// Requests shutting down
+    pub fn request(&self) {
+        self.sender.send_replace(true);
+    }
+
+    /// Waits until shutting down is requested
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as self, so waiting can't fail
+        let _ = receiver.wait_for(|requested| *requested).await;
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown::new()
+    }
+}
+
+/// Gets the global shutdown state
+pub fn get_shutdown() -> &'static Shutdown {
+    SHUTDOWN.get_or_init(Shutdown::new)
+}
+
+/// Waits for `SIGTERM` or `SIGINT`
+pub async fn wait_for_signal() {
+    let (mut terminates, mut interrupts) = match (
+        signal(SignalKind::terminate()),
+        signal(SignalKind::interrupt()),
+    ) {
+        (Ok(terminates), Ok(interrupts)) => (terminates, interrupts),
+        (Err(err), _) | (_, Err(err)) => {
+            error!(
+                "Failed to listen for termination signals, graceful shutdown is disabled: {}",
+                err
+            );
+            return std::future::pending().await;
+        }
+    };
+    tokio::select! {
+        _ = terminates.recv() => info!("Received SIGTERM, shutting down"),
+        _ = interrupts.recv() => info!("Received SIGINT, shutting down"),
+    }
+}
+
+/// Shuts down the open connections
+///
+/// # Arguments
+/// * `connection_limiter` - Limiter holding the permits of the open connections
+/// * `timeout` - Maximum time to wait for the connections to close
+pub async fn shut_down_connections(connection_limiter: &ConnectionLimiter, timeout: Duration) {
+    get_shutdown().request();
+    let open_connections = connection_limiter.get_connections_count();
+    info!(
+        "Waiting up to {} seconds for {} connections to close",
+        timeout.as_secs(),
+        open_connections
+    );
+    let closed = tokio::time::timeout(timeout, async {
+        while connection_limiter.get_connections_count() > 0 {
+            tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+        }
+    })
+    .await;
+    match closed {
+        Ok(()) => info!("All connections closed"),
+        Err(_) => warn!(
+            "{} connections still open after {} seconds, exiting anyway",
+            connection_limiter.get_connections_count(),
+            timeout.as_secs()
+        ),
+    }
+}
+
+/// Submits the batches of the devices still registered, e.g. UDP devices having no connection to close
+pub async fn flush_batches() {
+    for records_handler in get_connection_registry().get_records_handlers() {
+        records_handler.flush_batches(true).await;
+    }
+}
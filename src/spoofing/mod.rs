@@ -0,0 +1,121 @@
+//! Detection of devices spoofing the IMEI of another device
+//!
+//! Devices are trusted by their IMEI alone, so frames for one IMEI arriving from two different source addresses
+//! within a short window are reported as suspected spoofing.
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+
+use crate::metrics;
+
+/// Name of the counter describing the number of frames suspected of IMEI spoofing by IMEI
+pub const SPOOFING_SUSPECTED_METRIC: &str = "receiver_spoofing_suspected_total";
+
+static DETECTOR: OnceLock<SpoofingDetector> = OnceLock::new();
+
+/// Result of checking the source of a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameSource {
+    /// The source is the same as the previous source of the IMEI or the previous frame is outside the window
+    Trusted,
+    /// Frames for the IMEI have been recently received from another source
+    Suspected,
+    /// Frames for the IMEI have been recently received from another source and the frame must be rejected
+    Quarantined,
+}
+
+/// Tracks the latest source address of each IMEI
+pub struct SpoofingDetector {
+    window: Duration,
+    quarantine: bool,
+    latest_sources: Mutex<HashMap<String, (IpAddr, DateTime<Utc>)>>,
+}
+
+impl SpoofingDetector {
+    /// Creates a new [SpoofingDetector]
+    ///
+    /// # Arguments
+    /// * `window_seconds` - Window within which frames from two sources are suspected
+    /// * `quarantine` - Whether frames from the newer source are rejected
+    pub fn new(window_seconds: i64, quarantine: bool) -> Self {
+        SpoofingDetector {
+            window: Duration::seconds(window_seconds),
+            quarantine,
+            latest_sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks the source of a frame
+    ///
+    /// Quarantined frames don't replace the trusted source of the IMEI.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `source` - Source address of the frame
+    /// * `received_at` - Time the frame was received
+    pub fn check_frame_source(
+        &self,
+        imei: &str,
+        source: IpAddr,
+        received_at: DateTime<Utc>,
+    ) -> FrameSource {
+        let mut latest_sources = self.latest_sources.lock().unwrap();
+        let result = match latest_sources.get(imei) {
+            Some((latest_source, latest_received_at))
+                if *latest_source != source && received_at - *latest_received_at < self.window =>
+            {
+                warn!(target: imei,
+                    "Suspected IMEI spoofing: frames received from [{}] and [{}] within {} seconds",
+                    latest_source,
+                    source,
+                    self.window.num_seconds()
+                );
+                metrics::increment_counter(SPOOFING_SUSPECTED_METRIC, &[("imei", imei)]);
+                match self.quarantine {
+                    true => FrameSource::Quarantined,
+                    false => FrameSource::Suspected,
+                }
+            }
+            _ => FrameSource::Trusted,
+        };
+        if result != FrameSource::Quarantined {
+            latest_sources.insert(imei.to_string(), (source, received_at));
+        }
+
+        return result;
+    }
+}
+
+/// Initializes the spoofing detection
+///
+/// # Arguments
+/// * `window_seconds` - Window within which frames from two sources are suspected
+/// * `quarantine` - Whether frames from the newer source are rejected
+pub fn init_spoofing_detection(window_seconds: i64, quarantine: bool) {
+    if DETECTOR
+        .set(SpoofingDetector::new(window_seconds, quarantine))
+        .is_err()
+    {
+        warn!("Spoofing detection is already initialized");
+    }
+}
+
+/// Checks the source of a frame
+///
+/// Frames are always trusted unless the spoofing detection has been initialized.
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `source` - Source address of the frame
+pub fn check_frame_source(imei: &str, source: IpAddr) -> FrameSource {
+    let Some(detector) = DETECTOR.get() else {
+        return FrameSource::Trusted;
+    };
+
+    return detector.check_frame_source(imei, source, Utc::now());
+}
@@ -0,0 +1,86 @@
+//! Synthetic devices
+//!
+//! Data of synthetic devices, e.g. a hardware simulator run against production as a canary, is processed fully
+//! but sent to a sandbox API and excluded from the receiver statistics.
+use std::sync::OnceLock;
+
+use crate::{
+    config::get_config,
+    metrics,
+    utils::api_routing::{RouteSelector, RoutingOverride},
+};
+
+/// Name of the counter describing the number of frames received from synthetic devices by IMEI
+pub const SYNTHETIC_FRAMES_METRIC: &str = "receiver_synthetic_frames_total";
+
+static SYNTHETIC_DEVICES: OnceLock<SyntheticDevices> = OnceLock::new();
+
+/// Synthetic devices and the sandbox API their data is sent to
+pub struct SyntheticDevices {
+    imeis: Vec<String>,
+    api_base_url: String,
+}
+
+impl SyntheticDevices {
+    /// Creates a new [SyntheticDevices]
+    ///
+    /// # Arguments
+    /// * `imeis` - IMEIs of the synthetic devices
+    /// * `api_base_url` - Base URL of the sandbox API
+    pub fn new(imeis: Vec<String>, api_base_url: String) -> Self {
+        SyntheticDevices {
+            imeis,
+            api_base_url,
+        }
+    }
+
+    /// Checks whether a device is synthetic
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    pub fn is_synthetic(&self, imei: &str) -> bool {
+        return self
+            .imeis
+            .iter()
+            .any(|synthetic_imei| synthetic_imei == imei);
+    }
+
+    /// Gets the overrides routing the requests of the synthetic devices to the sandbox API
+    pub fn get_routing_overrides(&self) -> Vec<RoutingOverride> {
+        return self
+            .imeis
+            .iter()
+            .map(|imei| RoutingOverride {
+                selector: RouteSelector::Imei(imei.clone()),
+                base_url: self.api_base_url.clone(),
+            })
+            .collect();
+    }
+}
+
+/// Gets the global synthetic devices of the configuration
+pub fn get_synthetic_devices() -> &'static SyntheticDevices {
+    SYNTHETIC_DEVICES.get_or_init(|| {
+        let config = get_config();
+        SyntheticDevices::new(
+            config.synthetic_imeis.clone(),
+            config.synthetic_api_base_url.clone().unwrap_or_default(),
+        )
+    })
+}
+
+/// Checks whether a device is synthetic
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+pub fn is_synthetic_imei(imei: &str) -> bool {
+    return get_synthetic_devices().is_synthetic(imei);
+}
+
+/// Records a frame received from a synthetic device
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+pub fn record_synthetic_frame(imei: &str) {
+    metrics::increment_counter(SYNTHETIC_FRAMES_METRIC, &[("imei", imei)]);
+}
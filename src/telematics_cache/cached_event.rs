@@ -0,0 +1,95 @@
+use log::error;
+use nom_teltonika::AVLRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    retry::get_max_retry_attempts,
+    utils::api::{VehicleApiError, VehicleApiErrorKind},
+};
+
+use super::{dead_letter_event::DeadLetterEvent, Cacheable};
+
+/// Event waiting in the cache of a device to be sent
+///
+/// The event is stored flattened alongside the number of failed attempts to send it from the cache, so that caches
+/// written before the attempts were counted are read with zero attempts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEvent<T> {
+    #[serde(flatten)]
+    pub event: T,
+    /// Number of failed attempts to send the event from the cache
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub attempts: u32,
+}
+
+impl<T> CachedEvent<T>
+where
+    T: Cacheable + Serialize,
+{
+    /// Creates a new [CachedEvent] not attempted to be sent from the cache yet
+    ///
+    /// # Arguments
+    /// * `event` - Event to cache
+    pub fn new(event: T) -> Self {
+        CachedEvent { event, attempts: 0 }
+    }
+
+    /// Records a failed attempt to send the event from the cache
+    ///
+    /// Events exceeding the maximum attempts configured in `CACHE_MAX_RETRY_ATTEMPTS` are moved to the dead-letter
+    /// events of the device instead of being retried forever. Requests not sent due to an open circuit breaker are not
+    /// counted as attempts.
+    ///
+    /// # Arguments
+    /// * `error` - Error of the failed attempt
+    /// * `base_cache_path` - The base path to the cache directory
+    /// * `imei` - IMEI of the device
+    ///
+    /// # Returns
+    /// * The event to keep in the cache, or `None` if it was moved to the dead-letter events
+    pub fn record_failed_attempt(
+        mut self,
+        error: &VehicleApiError,
+        base_cache_path: &str,
+        imei: &str,
+    ) -> Option<Self> {
+        // Requests short-circuited by an open circuit breaker weren't attempted
+        if error.kind == VehicleApiErrorKind::CircuitOpen {
+            return Some(self);
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        match get_max_retry_attempts() {
+            Some(max_attempts) if self.attempts >= max_attempts => {
+                let event_type = T::FILE_PATH.trim_end_matches("_cache.json");
+                error!(target: imei,
+                    "Failed to send {} event {} times, last {}. Moving it to dead-letter events.",
+                    event_type,
+                    self.attempts,
+                    error
+                );
+                DeadLetterEvent::record(
+                    event_type,
+                    &self.event,
+                    self.attempts,
+                    error,
+                    base_cache_path,
+                );
+                return None;
+            }
+            _ => return Some(self),
+        }
+    }
+}
+
+/// Checks whether no attempts were made, to leave them out of the cache file
+fn is_zero(attempts: &u32) -> bool {
+    *attempts == 0
+}
+
+impl<T: Cacheable> Cacheable for CachedEvent<T> {
+    const FILE_PATH: &'static str = T::FILE_PATH;
+
+    fn from_teltonika_record(record: &AVLRecord) -> Option<Self> {
+        T::from_teltonika_record(record).map(|event| CachedEvent { event, attempts: 0 })
+    }
+}
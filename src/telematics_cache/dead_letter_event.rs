@@ -0,0 +1,73 @@
+use chrono::Utc;
+use nom_teltonika::AVLRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::{metrics, utils::api::VehicleApiError};
+
+use super::{failed_event::sanitize_text, Cacheable};
+
+/// Maximum number of dead-letter events kept in the cache of a device
+const MAX_DEAD_LETTER_EVENTS: usize = 1000;
+/// Name of the counter describing the number of events moved to the dead-letter events by event type
+pub const DEAD_LETTER_EVENTS_METRIC: &str = "receiver_dead_letter_events_total";
+
+/// Cached event that failed to be sent the maximum number of attempts
+///
+/// Stored with its payload and the error of the last attempt, so that permanently failing payloads can be inspected
+/// instead of being retried forever.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterEvent {
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub attempts: u32,
+    pub request_id: String,
+    pub error: String,
+    pub dead_lettered_at: i64,
+}
+
+impl DeadLetterEvent {
+    /// Records a dead-letter event to the cache
+    ///
+    /// Only the latest [MAX_DEAD_LETTER_EVENTS] are kept.
+    ///
+    /// # Arguments
+    /// * `event_type` - Type of the event
+    /// * `event` - Event that failed to be sent
+    /// * `attempts` - Number of failed attempts to send the event from the cache
+    /// * `error` - Error of the last attempt
+    /// * `base_cache_path` - The base path to the cache directory
+    pub fn record<T: Serialize>(
+        event_type: &str,
+        event: &T,
+        attempts: u32,
+        error: &VehicleApiError,
+        base_cache_path: &str,
+    ) {
+        let dead_letter_event = DeadLetterEvent {
+            event_type: event_type.to_string(),
+            payload: serde_json::to_value(event).unwrap_or_default(),
+            attempts,
+            request_id: error.request_id.to_string(),
+            error: sanitize_text(&error.kind.to_string()),
+            dead_lettered_at: Utc::now().timestamp(),
+        };
+        let mut dead_letter_events = Self::read_from_file(base_cache_path);
+        dead_letter_events.push(dead_letter_event);
+        let excess_count = dead_letter_events
+            .len()
+            .saturating_sub(MAX_DEAD_LETTER_EVENTS);
+        dead_letter_events.drain(..excess_count);
+
+        Self::write_all_to_file(base_cache_path, &dead_letter_events)
+            .expect("Error caching dead-letter event");
+        metrics::increment_counter(DEAD_LETTER_EVENTS_METRIC, &[("event_type", event_type)]);
+    }
+}
+
+impl Cacheable for DeadLetterEvent {
+    const FILE_PATH: &'static str = "dead_letter_events.json";
+
+    fn from_teltonika_record(_record: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
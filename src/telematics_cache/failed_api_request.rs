@@ -0,0 +1,67 @@
+use chrono::Utc;
+use nom_teltonika::AVLRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::{teltonika::records::FrameProvenance, utils::api::VehicleApiError};
+
+use super::Cacheable;
+
+/// Maximum number of failed API requests kept in the cache of a device
+const MAX_FAILED_API_REQUESTS: usize = 1000;
+
+/// Failed Vehicle Management Service API request
+///
+/// Stored alongside the cached events so that failures can be cross-referenced with the logs of Vehicle Management Service by the request ID
+/// and traced back to the frame the event was produced from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedApiRequest {
+    pub request_id: String,
+    pub event_type: String,
+    pub error: String,
+    pub failed_at: i64,
+    #[serde(default)]
+    pub provenance: Option<FrameProvenance>,
+}
+
+impl FailedApiRequest {
+    /// Records a failed API request to the cache
+    ///
+    /// Only the latest [MAX_FAILED_API_REQUESTS] are kept.
+    ///
+    /// # Arguments
+    /// * `error` - Error of the failed request
+    /// * `event_type` - Type of the event that failed to be sent
+    /// * `base_cache_path` - The base path to the cache directory
+    /// * `provenance` - Provenance of the frame the event was produced from, if known
+    pub fn record(
+        error: &VehicleApiError,
+        event_type: &str,
+        base_cache_path: &str,
+        provenance: Option<FrameProvenance>,
+    ) {
+        let failed_request = FailedApiRequest {
+            request_id: error.request_id.to_string(),
+            event_type: event_type.to_string(),
+            error: error.kind.to_string(),
+            failed_at: Utc::now().timestamp(),
+            provenance,
+        };
+        let mut failed_requests = Self::read_from_file(base_cache_path);
+        failed_requests.push(failed_request);
+        let excess_count = failed_requests
+            .len()
+            .saturating_sub(MAX_FAILED_API_REQUESTS);
+        failed_requests.drain(..excess_count);
+
+        Self::write_all_to_file(base_cache_path, &failed_requests)
+            .expect("Error caching failed API request");
+    }
+}
+
+impl Cacheable for FailedApiRequest {
+    const FILE_PATH: &'static str = "failed_api_requests.json";
+
+    fn from_teltonika_record(_record: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
@@ -0,0 +1,102 @@
+use chrono::Utc;
+use nom_teltonika::AVLRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::teltonika::{records::FrameProvenance, EventDecodeError};
+
+use super::Cacheable;
+
+/// Maximum number of failed events kept in the cache of a device
+const MAX_FAILED_EVENTS: usize = 1000;
+/// Maximum number of raw bytes stored for a failed event
+const MAX_RAW_BYTES: usize = 1024;
+/// Maximum number of characters stored for the event type and error of a failed event
+const MAX_TEXT_LENGTH: usize = 256;
+
+/// Event that failed to decode
+///
+/// Stored with the raw bytes of the event, so that values produced by field devices can be analyzed later.
+/// Raw bytes and texts are bounded, so that a pathological event, such as a huge variable length IO element,
+/// can't bloat the cache of the device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedEvent {
+    pub event_type: String,
+    pub event_id: u16,
+    pub raw_bytes: String,
+    /// Number of raw bytes of the event if they were truncated to [MAX_RAW_BYTES]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated_raw_bytes_length: Option<usize>,
+    pub error: String,
+    pub timestamp: i64,
+    pub failed_at: i64,
+    #[serde(default)]
+    pub provenance: Option<FrameProvenance>,
+}
+
+impl FailedEvent {
+    /// Records a failed event to the cache
+    ///
+    /// Only the latest [MAX_FAILED_EVENTS] are kept.
+    ///
+    /// # Arguments
+    /// * `error` - Error decoding the event
+    /// * `event_type` - Type of the event that failed to decode
+    /// * `timestamp` - Timestamp of the record of the event
+    /// * `base_cache_path` - The base path to the cache directory
+    /// * `provenance` - Provenance of the frame the event was received in, if known
+    pub fn record(
+        error: &EventDecodeError,
+        event_type: &str,
+        timestamp: i64,
+        base_cache_path: &str,
+        provenance: Option<FrameProvenance>,
+    ) {
+        let raw_bytes = &error.raw_bytes[..error.raw_bytes.len().min(MAX_RAW_BYTES)];
+        let failed_event = FailedEvent {
+            event_type: sanitize_text(event_type),
+            event_id: error.event_id,
+            raw_bytes: raw_bytes
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect(),
+            truncated_raw_bytes_length: (raw_bytes.len() < error.raw_bytes.len())
+                .then_some(error.raw_bytes.len()),
+            error: sanitize_text(&error.reason),
+            timestamp,
+            failed_at: Utc::now().timestamp(),
+            provenance,
+        };
+        let mut failed_events = Self::read_from_file(base_cache_path);
+        failed_events.push(failed_event);
+        let excess_count = failed_events.len().saturating_sub(MAX_FAILED_EVENTS);
+        failed_events.drain(..excess_count);
+
+        Self::write_all_to_file(base_cache_path, &failed_events)
+            .expect("Error caching failed event");
+    }
+}
+
+/// Sanitizes a text stored for a failed or dead-letter event
+///
+/// Control characters are replaced and the text is truncated to [MAX_TEXT_LENGTH] characters.
+///
+/// # Arguments
+/// * `text` - Text to sanitize
+pub(super) fn sanitize_text(text: &str) -> String {
+    return text
+        .chars()
+        .take(MAX_TEXT_LENGTH)
+        .map(|character| match character.is_control() {
+            true => char::REPLACEMENT_CHARACTER,
+            false => character,
+        })
+        .collect();
+}
+
+impl Cacheable for FailedEvent {
+    const FILE_PATH: &'static str = "failed_events.json";
+
+    fn from_teltonika_record(_record: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
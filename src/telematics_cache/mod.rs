@@ -1,11 +1,169 @@
+pub mod cached_event;
+pub mod dead_letter_event;
+pub mod failed_api_request;
+pub mod failed_event;
+
+use crate::{invariants, metrics};
+use chrono::Utc;
+use log::error;
 use nom_teltonika::AVLRecord;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::create_dir_all,
-    io::{BufReader, Write},
-    path::Path,
+    collections::HashMap,
+    fmt::Write as _,
+    fs::{create_dir_all, rename, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, Weak},
 };
 
+/// Name of the counter describing the number of corrupted cache files recovered by cache
+pub const CORRUPTED_CACHE_FILES_METRIC: &str = "receiver_corrupted_cache_files_total";
+
+/// Locks of the cache directories of the devices by path
+///
+/// Only weak handles are kept, so that the lock of a device is dropped once no cache write or purge holds it.
+static CACHE_LOCKS: OnceLock<Mutex<HashMap<PathBuf, Weak<tokio::sync::Mutex<()>>>>> =
+    OnceLock::new();
+/// Number of items in each cache file by path, kept up to date as the caches are read and written
+static CACHE_DEPTHS: OnceLock<Mutex<HashMap<String, usize>>> = OnceLock::new();
+
+/// Gets the lock of the cache directory of a device
+///
+/// Caching an event and purging a cache both read the whole cache file and write it back. The lock is held while
+/// the cache file is read and written, so that a purge running in the retry scheduler doesn't overwrite the events
+/// cached by the connection of the device, and vice versa. Purges don't hold the lock while sending, see [take_cache]
+/// and [restore_cache].
+///
+/// # Arguments
+/// * `base_cache_path` - Cache directory of the device
+pub fn get_cache_lock(base_cache_path: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    let mut cache_locks = CACHE_LOCKS.get_or_init(Default::default).lock().unwrap();
+    if let Some(cache_lock) = cache_locks.get(base_cache_path).and_then(Weak::upgrade) {
+        return cache_lock;
+    }
+    cache_locks.retain(|_, cache_lock| cache_lock.strong_count() > 0);
+    let cache_lock = Arc::new(tokio::sync::Mutex::new(()));
+    cache_locks.insert(base_cache_path.to_path_buf(), Arc::downgrade(&cache_lock));
+
+    return cache_lock;
+}
+
+/// Checks whether the lock of the cache directory of a device is in use
+///
+/// # Arguments
+/// * `base_cache_path` - Cache directory of the device
+#[cfg(test)]
+pub fn is_cache_lock_in_use(base_cache_path: &Path) -> bool {
+    return CACHE_LOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .get(base_cache_path)
+        .is_some_and(|cache_lock| cache_lock.strong_count() > 0);
+}
+
+/// Takes all items out of a cache of a device to be sent
+///
+/// The cache is read and emptied while holding the lock of the device caches, which is released before the items
+/// are sent, so that caching new events of the device doesn't wait for the API. Items failing to send are put back
+/// with [restore_cache].
+///
+/// # Arguments
+/// * `base_cache_path` - Cache directory of the device
+///
+/// # Returns
+/// * Items of the cache
+pub async fn take_cache<T>(base_cache_path: &Path) -> Result<Vec<T>, std::io::Error>
+where
+    T: Cacheable + Serialize + for<'a> Deserialize<'a>,
+{
+    let cache_lock = get_cache_lock(base_cache_path);
+    let _cache_guard = cache_lock.lock().await;
+    let base_cache_path = base_cache_path.to_str().unwrap();
+    let items = T::read_from_file(base_cache_path);
+    if !items.is_empty() {
+        T::write_all_to_file(base_cache_path, &[])?;
+    }
+
+    return Ok(items);
+}
+
+/// Puts items taken with [take_cache] back in front of the items cached while they were being sent
+///
+/// # Arguments
+/// * `base_cache_path` - Cache directory of the device
+/// * `items` - Items failed to send
+pub async fn restore_cache<T>(base_cache_path: &Path, items: Vec<T>) -> Result<(), std::io::Error>
+where
+    T: Cacheable + Serialize + for<'a> Deserialize<'a>,
+{
+    if items.is_empty() {
+        return Ok(());
+    }
+    let cache_lock = get_cache_lock(base_cache_path);
+    let _cache_guard = cache_lock.lock().await;
+    let base_cache_path = base_cache_path.to_str().unwrap();
+    let mut restored_items = items;
+    restored_items.extend(T::read_from_file(base_cache_path));
+
+    return T::write_all_to_file(base_cache_path, &restored_items);
+}
+
+/// Gets the number of items in a cache file, if known
+///
+/// # Arguments
+/// * `cache_file_path` - Path of the cache file
+fn get_known_cache_depth(cache_file_path: &str) -> Option<usize> {
+    return CACHE_DEPTHS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .get(cache_file_path)
+        .copied();
+}
+
+/// Records the number of items in a cache file
+///
+/// # Arguments
+/// * `cache_file_path` - Path of the cache file
+/// * `depth` - Number of items in the cache file
+fn record_cache_depth(cache_file_path: &str, depth: usize) {
+    CACHE_DEPTHS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .insert(cache_file_path.to_string(), depth);
+}
+
+/// Gets the cache directory of a device
+///
+/// Each device has its own directory under the base path, so that the caches of different devices are never mixed
+/// and purging the caches of a device doesn't touch the others. IMEIs are read from the devices as arbitrary
+/// characters, so characters other than ASCII letters, digits and `-` are percent-encoded to keep the directory
+/// inside the base path, e.g. `../1` becomes `%2E%2E%2F1`.
+///
+/// # Arguments
+/// * `base_path` - Base path of the caches
+/// * `imei` - IMEI of the device
+pub fn get_device_cache_path(base_path: &Path, imei: &str) -> PathBuf {
+    let mut directory_name = String::with_capacity(imei.len());
+    for character in imei.chars() {
+        if character.is_ascii_alphanumeric() || character == '-' {
+            directory_name.push(character);
+            continue;
+        }
+        for byte in character.to_string().bytes() {
+            let _ = write!(directory_name, "%{:02X}", byte);
+        }
+    }
+    if directory_name.is_empty() {
+        directory_name.push('_');
+    }
+
+    return base_path.join(directory_name);
+}
+
 /// Base trait for all cacheable telematics data
 pub trait Cacheable {
     /// File path to store the cache
@@ -41,18 +199,63 @@ pub trait Cacheable {
     where
         Self: Serialize + Sized + for<'a> Deserialize<'a> + Clone,
     {
-        let mut file = Self::get_cache_file_handle(base_cache_path);
         let mut existing_cache = Self::read_from_file(base_cache_path);
+        let existing_depth = existing_cache.len();
         existing_cache.push(self.clone());
-        let json = serde_json::to_string(&existing_cache).unwrap();
-        if let Err(_) = file.set_len(0) {
-            panic!("Error truncating cache file!");
-        };
-        return file.write_all(json.as_bytes());
+        Self::replace_file_contents(base_cache_path, &existing_cache)?;
+        let cache_file_path = format!("{}/{}", base_cache_path, Self::FILE_PATH);
+        record_cache_depth(&cache_file_path, existing_cache.len());
+        invariants::record_cache_write(&cache_file_path, existing_depth);
+
+        return Ok(());
+    }
+
+    /// Replaces the whole cache with the given items
+    ///
+    /// # Arguments
+    /// * `base_cache_path` - The base path to the cache directory
+    /// * `items` - Items of the cache
+    fn write_all_to_file(base_cache_path: &str, items: &[Self]) -> Result<(), std::io::Error>
+    where
+        Self: Serialize + Sized,
+    {
+        Self::replace_file_contents(base_cache_path, items)?;
+        let cache_file_path = format!("{}/{}", base_cache_path, Self::FILE_PATH);
+        record_cache_depth(&cache_file_path, items.len());
+        invariants::record_cache_replace(&cache_file_path, items.len());
+
+        return Ok(());
+    }
+
+    /// Replaces the contents of the cache file atomically
+    ///
+    /// The items are written to a temporary file, which is synced to disk and renamed over the cache file,
+    /// so that a crash in the middle of writing leaves either the previous or the new contents in place.
+    ///
+    /// # Arguments
+    /// * `base_cache_path` - The base path to the cache directory
+    /// * `items` - Items of the cache
+    fn replace_file_contents(base_cache_path: &str, items: &[Self]) -> Result<(), std::io::Error>
+    where
+        Self: Serialize + Sized,
+    {
+        create_dir_all(Path::new(&base_cache_path))?;
+        let cache_file_path = format!("{}/{}", base_cache_path, Self::FILE_PATH);
+        let temp_file_path = format!("{}.tmp", cache_file_path);
+        let mut temp_file = File::create(&temp_file_path)?;
+        temp_file.write_all(serde_json::to_string(items)?.as_bytes())?;
+        temp_file.sync_all()?;
+        rename(&temp_file_path, &cache_file_path)?;
+
+        return Ok(());
     }
 
     /// Reads the cache from a file
     ///
+    /// A cache file truncated e.g. by a crash of a previous version of the receiver is recovered by reading the items
+    /// preceding the damage. The damaged file is kept next to the cache for inspection and the cache is rewritten with
+    /// the recovered items.
+    ///
     /// # Arguments
     /// * `base_cache_path` - The base path to the cache directory
     ///
@@ -60,22 +263,105 @@ pub trait Cacheable {
     /// * A vector of cacheable objects
     fn read_from_file(base_cache_path: &str) -> Vec<Self>
     where
-        Self: Sized + for<'a> Deserialize<'a>,
+        Self: Sized + Serialize + for<'a> Deserialize<'a>,
     {
-        let file = Self::get_cache_file_handle(&base_cache_path);
-        let reader = BufReader::new(file);
+        let mut contents = String::new();
+        if let Err(err) = Self::get_cache_file_handle(base_cache_path).read_to_string(&mut contents)
+        {
+            error!(
+                "Failed to read cache file [{}/{}]: {}",
+                base_cache_path,
+                Self::FILE_PATH,
+                err
+            );
+            return Vec::new();
+        }
+        let cache_file_path = format!("{}/{}", base_cache_path, Self::FILE_PATH);
+        if contents.trim().is_empty() {
+            record_cache_depth(&cache_file_path, 0);
+            return Vec::new();
+        }
+        let parse_error = match serde_json::from_str::<Vec<Self>>(&contents) {
+            Ok(items) => {
+                record_cache_depth(&cache_file_path, items.len());
+                return items;
+            }
+            Err(err) => err,
+        };
+
+        let corrupted_file_path = format!(
+            "{}.corrupted-{}",
+            cache_file_path,
+            Utc::now().timestamp_millis()
+        );
+        let items = recover_items(&contents);
+        error!(
+            "Cache file [{}] is corrupted: {}. Recovered {} items, keeping the corrupted file in [{}]",
+            cache_file_path,
+            parse_error,
+            items.len(),
+            corrupted_file_path
+        );
+        metrics::increment_counter(
+            CORRUPTED_CACHE_FILES_METRIC,
+            &[("cache", Self::FILE_PATH.trim_end_matches(".json"))],
+        );
+        let recovery_result = std::fs::copy(&cache_file_path, &corrupted_file_path)
+            .and_then(|_| Self::write_all_to_file(base_cache_path, &items));
+        if let Err(err) = recovery_result {
+            error!(
+                "Failed to rewrite corrupted cache file [{}]: {}",
+                cache_file_path, err
+            );
+        }
 
-        return serde_json::from_reader(reader).unwrap_or_else(|_| Vec::new());
+        return items;
     }
 
-    /// Clears the cache file
+    /// Gets the number of items in the cache
+    ///
+    /// The depth is kept in memory as the cache is read and written, so the file is read only if the cache hasn't
+    /// been accessed since the receiver was started.
     ///
     /// # Arguments
     /// * `base_cache_path` - The base path to the cache directory
-    fn clear_cache(base_cache_path: &str) {
-        let file = Self::get_cache_file_handle(base_cache_path);
-        if let Err(_) = file.set_len(0) {
-            panic!("Error truncating cache file!");
+    fn get_cache_depth(base_cache_path: &str) -> usize
+    where
+        Self: Sized + Serialize + for<'a> Deserialize<'a>,
+    {
+        let cache_file_path = format!("{}/{}", base_cache_path, Self::FILE_PATH);
+        if let Some(depth) = get_known_cache_depth(&cache_file_path) {
+            return depth;
+        }
+
+        return Self::read_from_file(base_cache_path).len();
+    }
+}
+
+/// Recovers the items preceding the damage from a JSON array of cache items
+///
+/// # Arguments
+/// * `contents` - Contents of a damaged cache file
+///
+/// # Returns
+/// * Items up to the first incomplete or invalid item
+fn recover_items<T: for<'a> Deserialize<'a>>(contents: &str) -> Vec<T> {
+    let mut items = Vec::new();
+    let Some(mut rest) = contents.trim_start().strip_prefix('[') else {
+        return items;
+    };
+    loop {
+        let mut item_stream = serde_json::Deserializer::from_str(rest).into_iter::<T>();
+        let Some(Ok(item)) = item_stream.next() else {
+            return items;
+        };
+        items.push(item);
+        let Some(next) = rest[item_stream.byte_offset()..]
+            .trim_start()
+            .strip_prefix(',')
+        else {
+            return items;
         };
+        rest = next;
     }
 }
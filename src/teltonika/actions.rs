@@ -0,0 +1,110 @@
+//! Named device actions mapped to digital output commands
+//!
+//! Operators trigger business-level actions such as `unlock_cargo_door` instead of remembering which digital output
+//! of the device is wired to what. Actions are configured in `DEVICE_ACTIONS` and sent to the devices as `setdigout`
+//! commands through the [command channel](super::commands).
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use serde::Serialize;
+
+use crate::config::get_config;
+
+/// Number of digital outputs on the devices with the most outputs
+const MAX_DIGITAL_OUTPUTS: u8 = 4;
+
+static DEVICE_ACTIONS: OnceLock<BTreeMap<String, DeviceAction>> = OnceLock::new();
+
+/// Operation applied to a digital output
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type", content = "seconds")]
+pub enum OutputOperation {
+    /// Output is switched on
+    On,
+    /// Output is switched off
+    Off,
+    /// Output is switched on for the given number of seconds
+    Pulse(u32),
+}
+
+/// Named action controlling a digital output of a device
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceAction {
+    pub name: String,
+    /// Number of the digital output, starting from 1
+    pub output: u8,
+    pub operation: OutputOperation,
+}
+
+impl DeviceAction {
+    /// Builds the `setdigout` command performing the action
+    ///
+    /// Other outputs are marked with `?`, so that the devices leave them unchanged.
+    pub fn to_command(&self) -> String {
+        let index = usize::from(self.output - 1);
+        let mut states = vec!["?"; index + 1];
+        states[index] = match self.operation {
+            OutputOperation::Off => "0",
+            OutputOperation::On | OutputOperation::Pulse(_) => "1",
+        };
+        let mut command = format!("setdigout {}", states.concat());
+        if let OutputOperation::Pulse(seconds) = self.operation {
+            let mut timeouts = vec!["0".to_string(); index + 1];
+            timeouts[index] = seconds.to_string();
+            command.push(' ');
+            command.push_str(&timeouts.join(" "));
+        }
+
+        return command;
+    }
+}
+
+/// Parses device actions
+///
+/// Actions are separated by commas and consist of a name and a digital output operation separated by `=`,
+/// e.g. `unlock_cargo_door=DOUT2:pulse:3,beacon_on=DOUT1:on,beacon_off=DOUT1:off`.
+///
+/// # Arguments
+/// * `actions` - Device actions to parse
+pub fn parse_device_actions(actions: &str) -> Result<BTreeMap<String, DeviceAction>, String> {
+    return actions
+        .split(',')
+        .map(str::trim)
+        .filter(|action| !action.is_empty())
+        .map(|action| {
+            let (name, definition) = action
+                .split_once('=')
+                .ok_or(format!("Missing output operation in [{}]", action))?;
+            let mut parts = definition.split(':');
+            let output = parts
+                .next()
+                .and_then(|output| output.strip_prefix("DOUT"))
+                .and_then(|output| output.parse::<u8>().ok())
+                .filter(|output| (1..=MAX_DIGITAL_OUTPUTS).contains(output))
+                .ok_or(format!("Invalid digital output in [{}]", action))?;
+            let operation = match (parts.next(), parts.next(), parts.next()) {
+                (Some("on"), None, None) => OutputOperation::On,
+                (Some("off"), None, None) => OutputOperation::Off,
+                (Some("pulse"), Some(seconds), None) => seconds
+                    .parse()
+                    .ok()
+                    .filter(|seconds| *seconds > 0)
+                    .map(OutputOperation::Pulse)
+                    .ok_or(format!("Invalid pulse duration in [{}]", action))?,
+                _ => return Err(format!("Invalid output operation in [{}]", action)),
+            };
+            Ok((
+                name.to_string(),
+                DeviceAction {
+                    name: name.to_string(),
+                    output,
+                    operation,
+                },
+            ))
+        })
+        .collect();
+}
+
+/// Gets the device actions of the configuration by name
+pub fn get_device_actions() -> &'static BTreeMap<String, DeviceAction> {
+    DEVICE_ACTIONS.get_or_init(|| get_config().device_actions.clone())
+}
@@ -0,0 +1,208 @@
+//! Codec 12 GPRS command channel to the connected devices
+//!
+//! Live device connections register themselves by IMEI, so that commands such as `getinfo` or `setdigout` can be
+//! enqueued for them through the admin API. Devices answer the commands in the order they were sent, so responses are
+//! paired with the oldest command waiting for a response and kept in memory for reading back.
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::metrics;
+
+/// Maximum number of commands waiting to be sent to a device
+const COMMAND_QUEUE_SIZE: usize = 16;
+/// Maximum number of responses kept per device
+const MAX_COMMAND_RESPONSES: usize = 20;
+/// Maximum length of a command, as devices don't accept longer GPRS commands
+const MAX_COMMAND_LENGTH: usize = 256;
+/// Name of the counter describing the number of commands sent to the devices
+pub const COMMANDS_SENT_METRIC: &str = "receiver_commands_sent_total";
+
+static COMMAND_CHANNEL: OnceLock<CommandChannel> = OnceLock::new();
+
+/// Error enqueueing a command
+#[derive(Debug, PartialEq)]
+pub enum CommandError {
+    /// Command is empty or too long
+    InvalidCommand,
+    /// Device is not connected
+    NotConnected,
+    /// Too many commands are already waiting to be sent to the device
+    QueueFull,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::InvalidCommand => write!(
+                f,
+                "Command must be between 1 and {} characters",
+                MAX_COMMAND_LENGTH
+            ),
+            CommandError::NotConnected => write!(f, "Device is not connected"),
+            CommandError::QueueFull => write!(f, "Too many commands waiting to be sent"),
+        }
+    }
+}
+
+/// Response of a device to a command
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandResponse {
+    /// Command the response was paired with, if it was sent by this receiver
+    pub command: Option<String>,
+    pub response: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Connection of a device registered for receiving commands
+struct RegisteredConnection {
+    connection_id: String,
+    sender: mpsc::Sender<String>,
+    sent_commands: VecDeque<String>,
+}
+
+/// Registry of the live device connections and the responses they have received
+pub struct CommandChannel {
+    connections: Mutex<HashMap<String, RegisteredConnection>>,
+    responses: Mutex<HashMap<String, VecDeque<CommandResponse>>>,
+}
+
+impl CommandChannel {
+    /// Creates a new [CommandChannel]
+    pub fn new() -> Self {
+        CommandChannel {
+            connections: Mutex::new(HashMap::new()),
+            responses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers a connection of a device
+    ///
+    /// A device reconnecting replaces its previous connection, whose queued commands are dropped.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `connection_id` - ID of the connection
+    ///
+    /// # Returns
+    /// * Receiver of the commands to send to the device
+    pub fn register(&self, imei: &str, connection_id: &str) -> mpsc::Receiver<String> {
+        let (sender, receiver) = mpsc::channel(COMMAND_QUEUE_SIZE);
+        self.connections.lock().unwrap().insert(
+            imei.to_string(),
+            RegisteredConnection {
+                connection_id: connection_id.to_string(),
+                sender,
+                sent_commands: VecDeque::new(),
+            },
+        );
+
+        return receiver;
+    }
+
+    /// Unregisters a closed connection of a device
+    ///
+    /// The registration is kept if the device has already reconnected.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `connection_id` - ID of the closed connection
+    pub fn unregister(&self, imei: &str, connection_id: &str) {
+        let mut connections = self.connections.lock().unwrap();
+        if connections
+            .get(imei)
+            .is_some_and(|connection| connection.connection_id == connection_id)
+        {
+            connections.remove(imei);
+        }
+    }
+
+    /// Enqueues a command to be sent to a connected device
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `command` - Command to send, e.g. `getinfo`
+    pub fn enqueue(&self, imei: &str, command: &str) -> Result<(), CommandError> {
+        let command = command.trim();
+        if command.is_empty() || command.len() > MAX_COMMAND_LENGTH {
+            return Err(CommandError::InvalidCommand);
+        }
+        let connections = self.connections.lock().unwrap();
+        let Some(connection) = connections.get(imei) else {
+            return Err(CommandError::NotConnected);
+        };
+        match connection.sender.try_send(command.to_string()) {
+            Ok(()) => {
+                info!(target: imei, "Enqueued command [{}]", command);
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => Err(CommandError::QueueFull),
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(CommandError::NotConnected),
+        }
+    }
+
+    /// Records a command written to a device, so that its response can be paired with it
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `command` - Command written to the device
+    pub fn record_sent_command(&self, imei: &str, command: &str) {
+        metrics::increment_counter(COMMANDS_SENT_METRIC, &[]);
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(imei) {
+            if connection.sent_commands.len() == MAX_COMMAND_RESPONSES {
+                connection.sent_commands.pop_front();
+            }
+            connection.sent_commands.push_back(command.to_string());
+        }
+    }
+
+    /// Records a response received from a device
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `response` - Response received from the device
+    pub fn record_response(&self, imei: &str, response: &str) {
+        let command = self
+            .connections
+            .lock()
+            .unwrap()
+            .get_mut(imei)
+            .and_then(|connection| connection.sent_commands.pop_front());
+        let mut responses = self.responses.lock().unwrap();
+        let device_responses = responses.entry(imei.to_string()).or_default();
+        if device_responses.len() == MAX_COMMAND_RESPONSES {
+            device_responses.pop_back();
+        }
+        device_responses.push_front(CommandResponse {
+            command,
+            response: response.to_string(),
+            received_at: Utc::now(),
+        });
+    }
+
+    /// Gets the latest responses received from a device, latest first
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    pub fn get_responses(&self, imei: &str) -> Vec<CommandResponse> {
+        return self
+            .responses
+            .lock()
+            .unwrap()
+            .get(imei)
+            .map(|responses| responses.iter().cloned().collect())
+            .unwrap_or_default();
+    }
+}
+
+/// Gets the global command channel
+pub fn get_command_channel() -> &'static CommandChannel {
+    COMMAND_CHANNEL.get_or_init(CommandChannel::new)
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::Serialize;
+
+use crate::metrics;
+
+/// Name of the counter describing the number of closed device connections by reason
+pub const DISCONNECTIONS_METRIC: &str = "receiver_disconnections_total";
+
+/// Reason for a device connection being closed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectReason {
+    /// Device closed the connection
+    ClientReset,
+    /// Device didn't send anything within the idle timeout
+    IdleTimeout,
+    /// Device closed the connection after sending a frame that failed to parse
+    ParseError,
+    /// Connection was closed for suspected IMEI spoofing
+    Quarantined,
+    /// Connection was closed for shutting down the receiver
+    Shutdown,
+    /// Device sent a message exceeding the memory cap of the connection
+    MemoryCap,
+    /// Reading from or writing to the device failed
+    Error,
+}
+
+impl DisconnectReason {
+    /// Gets the name of the reason used in metrics and events
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DisconnectReason::ClientReset => "client_reset",
+            DisconnectReason::IdleTimeout => "idle_timeout",
+            DisconnectReason::ParseError => "parse_error",
+            DisconnectReason::Quarantined => "quarantined",
+            DisconnectReason::Shutdown => "shutdown",
+            DisconnectReason::MemoryCap => "memory_cap",
+            DisconnectReason::Error => "error",
+        }
+    }
+}
+
+/// Lifecycle event of a device connection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ConnectionLifecycleEvent {
+    /// Device connected and sent a valid IMEI
+    Connected {
+        imei: String,
+        connection_id: String,
+        timestamp: DateTime<Utc>,
+    },
+    /// Connection of the device was closed
+    Disconnected {
+        imei: String,
+        connection_id: String,
+        truck_id: Option<String>,
+        timestamp: DateTime<Utc>,
+        connected_seconds: i64,
+        reason: DisconnectReason,
+    },
+}
+
+/// Emits a lifecycle event of a device connection
+///
+/// Vehicle Management Service doesn't yet provide an endpoint for connectivity history,
+/// so the events are emitted as structured log events for the time being.
+///
+/// # Arguments
+/// * `event` - Event to emit
+pub fn emit_lifecycle_event(event: &ConnectionLifecycleEvent) {
+    let imei = match event {
+        ConnectionLifecycleEvent::Connected { imei, .. } => imei,
+        ConnectionLifecycleEvent::Disconnected { imei, reason, .. } => {
+            metrics::increment_counter(DISCONNECTIONS_METRIC, &[("reason", reason.as_str())]);
+            imei
+        }
+    };
+    info!(target: imei,
+        "Connection lifecycle event: {}",
+        serde_json::to_string(event).unwrap_or_default()
+    );
+}
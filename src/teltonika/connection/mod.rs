@@ -1,29 +1,163 @@
 use base64::Engine;
 use chrono::{Datelike, Utc};
 use log::{debug, error, info, warn};
-use nom_teltonika::{AVLRecord, TeltonikaStream};
+use nom_teltonika::{AVLFrame, AVLRecord, TeltonikaStream};
 use std::{
     fs::{create_dir_all, File, OpenOptions},
     io::Write,
-    path::{Path, PathBuf},
+    net::IpAddr,
+    path::Path,
+    sync::Arc,
 };
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    sync::{mpsc, OwnedSemaphorePermit, Semaphore},
+};
+use tracing::{info_span, instrument, Instrument, Span};
+
+pub mod lifecycle;
+pub mod registry;
+
+use lifecycle::{ConnectionLifecycleEvent, DisconnectReason};
+use registry::{get_connection_registry, ActiveConnection, Transport};
 
-use crate::utils::{
-    api::{delete_truck_driver_card_by_id, get_truck_driver_card_id, get_truck_id_by_vin},
-    avl_packet::AVLPacketToBytes,
+use crate::{
+    completeness,
+    config::get_config,
+    device_auth::{self, DeviceAuthResult},
+    device_stats, invariants, load_shedding, metrics, misinstallation, probe,
+    processing::{self, ProcessingMode},
+    shutdown,
+    spoofing::{self, FrameSource},
+    synthetic,
+    telematics_cache::get_device_cache_path,
+    utils::{
+        api::{
+            delete_truck_driver_card_by_id, get_truck_driver_card_id, get_truck_id_by_vin,
+            VehicleApi,
+        },
+        api_routing::get_api_routing,
+        avl_packet::AVLPacketToBytes,
+        imei::is_valid_imei,
+        log_throttle,
+    },
 };
 
-use super::records::TeltonikaRecordsHandler;
+use super::{
+    commands::get_command_channel,
+    device_family::DeviceFamily,
+    messages::{build_codec12_command, check_message_length, parse_message, TeltonikaMessage},
+    records::{
+        teltonika_odometer_reconciler::ODOMETER_DISCREPANCIES_METRIC,
+        teltonika_odometer_validator::REJECTED_ODOMETER_READINGS_METRIC,
+        teltonika_records_handler::estimate_record_size, FrameProvenance, TeltonikaGapDetector,
+        TeltonikaOdometerReconciler, TeltonikaRecordsHandler, TeltonikaShiftTracker,
+        TeltonikaTimestampNormalizer,
+    },
+};
+
+/// Delay before retrying to write a frame ACK
+const ACK_WRITE_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+/// Name of the counter describing the number of accepted device connections
+pub const CONNECTIONS_METRIC: &str = "receiver_connections_total";
+/// Name of the counter describing the number of connections closed for not sending the IMEI in time
+pub const IMEI_HANDSHAKE_TIMEOUTS_METRIC: &str = "receiver_imei_handshake_timeouts_total";
+/// Name of the counter describing the number of received frames
+pub const FRAMES_METRIC: &str = "receiver_frames_total";
+/// Name of the counter describing the number of received records
+pub const RECORDS_METRIC: &str = "receiver_records_total";
+/// Name of the counter describing the number of received frame bytes
+pub const BYTES_METRIC: &str = "receiver_bytes_total";
+/// Name of the gauge describing how many seconds the newest record of the latest frame of a device is behind by IMEI
+pub const DEVICE_BACKLOG_METRIC: &str = "receiver_device_backlog_seconds";
+/// Name of the counter describing the number of frames that failed to parse
+pub const PARSE_ERRORS_METRIC: &str = "receiver_parse_errors_total";
+/// Name of the counter describing the number of frames whose ACK waited for room in the full dispatch queue
+pub const ACK_BACKPRESSURE_METRIC: &str = "receiver_ack_backpressure_total";
+/// Name of the metric counting connections closed for messages exceeding the memory cap
+pub const OVERSIZED_MESSAGES_METRIC: &str = "receiver_oversized_messages_total";
+/// Time the device has for presenting its authentication token after the IMEI handshake
+const DEVICE_AUTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// Size of the buffer for reading from the socket
+const RECEIVE_BUFFER_SIZE: usize = 2048;
+
+/// Dispatches the records of an acknowledged frame
+///
+/// Records with timestamps outside the window relative to server time are clamped or dropped first if so configured.
+/// Records of paused devices are cached, otherwise they are sent, the batches submitted if their window has elapsed and
+/// the caches retried if the truck is known and the retry is due.
+///
+/// # Arguments
+/// * `records_handler` - Records handler of the connection
+/// * `imei` - IMEI of the device
+/// * `records` - Records of the frame
+/// * `processing_mode` - Processing mode of the device when the frame was received
+/// * `provenance` - Provenance of the frame
+#[instrument(
+    name = "frame",
+    skip_all,
+    fields(frame_crc = provenance.frame_crc, records = records.len())
+)]
+pub async fn dispatch_records(
+    records_handler: &TeltonikaRecordsHandler,
+    imei: &str,
+    mut records: Vec<AVLRecord>,
+    processing_mode: ProcessingMode,
+    provenance: FrameProvenance,
+) {
+    let records_count = records.len();
+    records_handler.set_frame_provenance(Some(provenance));
+    let rejected_count = records_handler.validate_record_timestamps(&mut records);
+    if processing_mode == ProcessingMode::Paused {
+        debug!(target: imei, "Processing is paused, caching {} records", records.len());
+        let cached_count = records_handler.cache_records(&records).await;
+        invariants::check_frame_records(imei, records_count, cached_count, rejected_count);
+        records_handler.report_cache_depths();
+        return;
+    }
+
+    let handled_count = records_handler.handle_records(records).await;
+    invariants::check_frame_records(imei, records_count, handled_count, rejected_count);
+
+    records_handler.flush_batches(false).await;
+    records_handler.retry_cached_events().await;
+    records_handler.report_cache_depths();
+}
+
+/// Queue of the frames waiting to be dispatched in the background with the processing mode they were received in
+///
+/// Each frame holds its share of the memory budget of the connection until it has been dispatched.
+type DispatchQueue = mpsc::Sender<(
+    Vec<AVLRecord>,
+    ProcessingMode,
+    FrameProvenance,
+    Option<OwnedSemaphorePermit>,
+)>;
 
 pub struct TeltonikaConnection<S> {
     teltonika_stream: TeltonikaStream<S>,
     imei: String,
-    truck_id: Option<String>,
-    truck_vin: Option<String>,
-    records_handler: TeltonikaRecordsHandler,
+    connection_id: String,
+    peer_ip: Option<IpAddr>,
+    is_synthetic: bool,
+    records_handler: Arc<TeltonikaRecordsHandler>,
+    /// Registration of the connection while it's running
+    active_connection: Option<Arc<ActiveConnection>>,
+    timestamp_normalizer: TeltonikaTimestampNormalizer,
+    shift_tracker: TeltonikaShiftTracker,
+    odometer_reconciler: TeltonikaOdometerReconciler,
+    gap_detector: TeltonikaGapDetector,
+    read_buffer: Vec<u8>,
+    ack_write_retries: u32,
+    idle_timeout: Option<std::time::Duration>,
+    /// Number of frames queued across all connections at which received frames are rejected with a 0-record ACK
+    frame_reject_threshold: Option<usize>,
     card_remove_threshold: u16,
+    ack_pipeline_depth: usize,
+    /// Memory budget of the frames waiting to be dispatched in the background, if memory is capped
+    dispatch_budget: Option<Arc<Semaphore>>,
     driver_one_card_removed_at: Option<i64>,
+    driver_one_card_removal_reported: bool,
 }
 
 impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
@@ -32,22 +166,49 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
     /// # Arguments
     /// * `stream` - Stream to be passed for [`TeltonikaStream`]. Must implement [`AsyncWriteExt`] and [`AsyncReadExt`]
     /// * `imei` - IMEI of the device
+    /// * `peer_ip` - IP address of the device, if known
+    /// * `device_family` - Family of the device, set by the listener the device connected to
     /// * `base_file_path` - Base path for the log files
     /// * `card_remove_threshold` - Threshold for removing the driver card
+    /// * `ack_pipeline_depth` - Maximum number of acknowledged frames waiting to be dispatched in the background, 0 to dispatch each frame before reading the next one
     pub fn new(
         stream: TeltonikaStream<S>,
         imei: String,
+        peer_ip: Option<IpAddr>,
+        device_family: DeviceFamily,
         base_file_path: &Path,
         card_remove_threshold: u16,
+        ack_pipeline_depth: usize,
     ) -> Self {
+        let records_handler =
+            TeltonikaRecordsHandler::new(base_file_path, None, imei.clone(), device_family);
+        let dispatch_budget = records_handler
+            .get_max_memory_bytes()
+            .map(|max_memory_bytes| {
+                Arc::new(Semaphore::new(clamp_to_budget(max_memory_bytes) as usize))
+            });
+        let tunables = &get_config().tunables;
         TeltonikaConnection {
             teltonika_stream: stream,
-            records_handler: TeltonikaRecordsHandler::new(&base_file_path, None, imei.clone()),
+            records_handler: Arc::new(records_handler),
+            active_connection: None,
+            timestamp_normalizer: TeltonikaTimestampNormalizer::new(&imei),
+            shift_tracker: TeltonikaShiftTracker::new(),
+            odometer_reconciler: TeltonikaOdometerReconciler::new(device_family),
+            gap_detector: TeltonikaGapDetector::new(),
+            read_buffer: Vec::new(),
+            ack_write_retries: tunables.ack_write_retries,
+            idle_timeout: tunables.connection_idle_timeout,
+            frame_reject_threshold: tunables.frame_queue_reject_threshold,
+            is_synthetic: synthetic::is_synthetic_imei(&imei),
             imei,
-            truck_id: None,
-            truck_vin: None,
+            connection_id: uuid::Uuid::new_v4().to_string(),
+            peer_ip,
             card_remove_threshold,
+            ack_pipeline_depth,
+            dispatch_budget,
             driver_one_card_removed_at: None,
+            driver_one_card_removal_reported: false,
         }
     }
 
@@ -57,18 +218,56 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
     ///
     /// # Arguments
     /// * `stream` - Stream to be passed for [`TeltonikaStream`]. Must implement [`AsyncWriteExt`] and [`AsyncReadExt`]
+    /// * `peer_ip` - IP address of the device, if known
+    /// * `device_family` - Family of the device, set by the listener the device connected to
     /// * `base_file_path` - Base path for the log files
     /// * `card_remove_threshold` - Threshold for removing the driver card
+    /// * `ack_pipeline_depth` - Maximum number of acknowledged frames waiting to be dispatched in the background
+    ///
+    /// # Returns
+    /// * `Err` if the device was denied or the connection was closed after an error
     pub async fn handle_connection(
         stream: S,
+        peer_ip: Option<IpAddr>,
+        device_family: DeviceFamily,
         base_file_path: &Path,
         card_remove_threshold: u16,
+        ack_pipeline_depth: usize,
     ) -> Result<(), ()> {
         match Self::handle_imei(TeltonikaStream::new(stream)).await {
             Ok((stream, imei)) => {
-                let file_path = base_file_path.join(&imei);
-                let mut connection = Self::new(stream, imei, &file_path, card_remove_threshold);
-                connection.run(&file_path).await.expect("Failed to run");
+                if !synthetic::is_synthetic_imei(&imei) {
+                    metrics::increment_counter(CONNECTIONS_METRIC, &[]);
+                }
+                let file_path = get_device_cache_path(base_file_path, &imei);
+                let mut connection = Self::new(
+                    stream,
+                    imei,
+                    peer_ip,
+                    device_family,
+                    &file_path,
+                    card_remove_threshold,
+                    ack_pipeline_depth,
+                );
+                if !connection.authenticate_device().await {
+                    return Err(());
+                }
+                let span = info_span!(
+                    "connection",
+                    imei = %connection.imei,
+                    connection_id = %connection.connection_id,
+                    transport = "tcp",
+                    device_family = %device_family,
+                    truck_id = tracing::field::Empty,
+                );
+                // Errors such as exhausted ACK write retries close the connection instead of panicking its task
+                if let Err(err) = connection.run(&file_path).instrument(span).await {
+                    warn!(target: connection.log_target(), "Closing connection after error: {}", err);
+                    if let Err(err) = connection.teltonika_stream.inner_mut().shutdown().await {
+                        debug!(target: connection.log_target(), "Failed to shut down connection: {}", err);
+                    }
+                    return Err(());
+                }
                 Ok(())
             }
             Err(_) => Err(()),
@@ -78,14 +277,38 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
     /// Handles the IMEI of the Teltonika Telematics device
     ///
     /// Whether the IMEI is valid, the server will send an approval message to the client.
+    /// IMEIs failing checksum validation are denied only if `VALIDATE_IMEI_CHECKSUMS` environment variable is enabled.
+    /// Connections not sending the IMEI within `IMEI_HANDSHAKE_TIMEOUT_SECONDS` are closed, so that idle connections don't hold tasks and sockets.
     ///
     /// # Arguments
     /// * `stream` - Teltonika stream
     async fn handle_imei(
         mut stream: TeltonikaStream<S>,
     ) -> Result<(TeltonikaStream<S>, String), ()> {
-        match stream.read_imei_async().await {
+        let config = get_config();
+        let imei_handshake_timeout = config.tunables.imei_handshake_timeout;
+        let Ok(imei_result) =
+            tokio::time::timeout(imei_handshake_timeout, stream.read_imei_async()).await
+        else {
+            debug!(
+                "Closing connection not sending IMEI within {} seconds",
+                imei_handshake_timeout.as_secs()
+            );
+            metrics::increment_counter(IMEI_HANDSHAKE_TIMEOUTS_METRIC, &[]);
+            return Err(());
+        };
+        match imei_result {
             Ok(imei) => {
+                if !is_valid_imei(&imei) {
+                    warn!(target: &imei, "IMEI failed checksum validation");
+                    if config.tunables.validate_imei_checksums {
+                        stream
+                            .write_imei_denial_async()
+                            .await
+                            .expect("Failed to write IMEI denial");
+                        return Err(());
+                    }
+                }
                 info!(target: &imei, "New client connected");
                 stream
                     .write_imei_approval_async()
@@ -116,12 +339,14 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
     /// It is possible that we sometimes receive false events of driver card removal,
     /// so we need to check whether is has actually been removed longer than the [`driver_card_removal_threshold`]
     ///
+    /// Removal is reported only once per removal, so frames received while the card stays removed don't cause further API calls.
+    ///
     /// # Arguments
     /// * `records` - Records to be checked for driver card removal events
-    async fn handle_driver_one_card_removal(&mut self, mut records: &mut Vec<AVLRecord>) {
+    async fn handle_driver_one_card_removal(&mut self, records: &mut [AVLRecord]) {
         if let Some((driver_one_card_present_in_frame, timestamp)) = self
             .records_handler
-            .get_driver_one_card_presence_from_records(&mut records)
+            .get_driver_one_card_presence_from_records(records)
         {
             let now = Utc::now().timestamp_millis();
             if !driver_one_card_present_in_frame {
@@ -129,18 +354,39 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
                     self.driver_one_card_removed_at = Some(now);
                     return;
                 };
+                if self.driver_one_card_removal_reported {
+                    return;
+                }
                 if now - card_removed_at > self.card_remove_threshold.into() {
-                    let Some(truck_id) = &self.truck_id else {
-                        warn!(target: self.log_target(), "Attempted to remove driver card from truck with no ID");
+                    let Some(truck_id) = self.records_handler.get_truck_id() else {
+                        if let Some(suppressed) = log_throttle::throttle(&format!(
+                            "{}:card_removal_without_truck",
+                            self.imei
+                        )) {
+                            warn!(target: self.log_target(),
+                                "Attempted to remove driver card from truck with no ID{}",
+                                log_throttle::describe_suppressed(suppressed)
+                            );
+                        }
                         return;
                     };
-                    let Some(driver_card_id) = get_truck_driver_card_id(truck_id.clone()).await
+                    self.records_handler.reset_driver_one_card();
+                    let Ok(driver_card_id) = get_truck_driver_card_id(truck_id.clone()).await
                     else {
                         return;
                     };
+                    let Some(driver_card_id) = driver_card_id else {
+                        self.driver_one_card_removal_reported = true;
+                        return;
+                    };
                     if let Some(timestamp) = timestamp {
-                        delete_truck_driver_card_by_id(truck_id.clone(), driver_card_id, timestamp)
-                            .await;
+                        self.driver_one_card_removal_reported = delete_truck_driver_card_by_id(
+                            truck_id.clone(),
+                            driver_card_id,
+                            timestamp,
+                        )
+                        .await
+                        .is_ok();
                     }
                 }
 
@@ -148,6 +394,254 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
             }
 
             self.driver_one_card_removed_at = None;
+            self.driver_one_card_removal_reported = false;
+        }
+    }
+
+    /// Handles the detection of probable ends of shifts
+    ///
+    /// Shift summaries are sent with [VehicleApi::create_shift_summary] for trucks already identified,
+    /// unless the processing of the device is paused. Vehicle Management Service has no endpoint for shift summaries
+    /// yet, so shifts are tracked only with the `pending-endpoints` feature.
+    ///
+    /// # Arguments
+    /// * `records` - Records to be checked for the end of shift
+    /// * `processing_mode` - Processing mode of the device
+    async fn handle_shift_summaries(
+        &mut self,
+        records: &[AVLRecord],
+        processing_mode: ProcessingMode,
+    ) {
+        if !cfg!(feature = "pending-endpoints") {
+            return;
+        }
+        for summary in self.shift_tracker.handle_records(records) {
+            let truck_id = self.records_handler.get_truck_id();
+            let Some(truck_id) = truck_id.filter(|_| processing_mode == ProcessingMode::Active)
+            else {
+                info!(target: self.log_target(),
+                    "Probable end of shift not sent for driver card [{}], started at {}, duration {} min",
+                    summary.driver_card_id.as_deref().unwrap_or("unknown"),
+                    summary.started_at,
+                    summary.get_duration().num_minutes()
+                );
+                continue;
+            };
+            if let Err(err) = VehicleApi.create_shift_summary(&truck_id, summary).await {
+                debug!(target: self.log_target(), "Shift summary not sent: {}", err);
+            }
+        }
+    }
+
+    /// Handles the reconciliation of the CAN odometer with the distance driven according to GPS
+    ///
+    /// Bogus odometer readings rejected by validation are logged and counted. Discrepancies are logged and counted.
+    /// Vehicle Management Service has no endpoint for discrepancies yet, so they are sent with
+    /// [VehicleApi::create_odometer_discrepancy] only with the `pending-endpoints` feature, for trucks already
+    /// identified unless the processing of the device is paused.
+    ///
+    /// # Arguments
+    /// * `records` - Records to be reconciled
+    /// * `processing_mode` - Processing mode of the device
+    async fn handle_odometer_discrepancies(
+        &mut self,
+        records: &[AVLRecord],
+        processing_mode: ProcessingMode,
+    ) {
+        let discrepancies = self.odometer_reconciler.handle_records(records);
+        for rejected_reading in self.odometer_reconciler.take_rejected_readings() {
+            let reason = rejected_reading.rejection.as_label();
+            metrics::increment_counter(
+                REJECTED_ODOMETER_READINGS_METRIC,
+                &[("imei", &self.imei), ("reason", reason)],
+            );
+            if let Some(suppressed) =
+                log_throttle::throttle(&format!("{}:rejected_odometer:{}", self.imei, reason))
+            {
+                warn!(target: self.log_target(),
+                    "Rejected odometer reading of {} m at {}: {}{}",
+                    rejected_reading.odometer_meters,
+                    rejected_reading.timestamp,
+                    rejected_reading.rejection,
+                    log_throttle::describe_suppressed(suppressed)
+                );
+            }
+        }
+        for discrepancy in discrepancies {
+            metrics::increment_counter(ODOMETER_DISCREPANCIES_METRIC, &[("imei", &self.imei)]);
+            warn!(target: self.log_target(),
+                "Odometer discrepancy for truck [{}] from {} to {}: odometer {:.1} km, GPS {:.1} km, deviation {:.1} %",
+                self.records_handler.get_truck_id().as_deref().unwrap_or("unknown"),
+                discrepancy.started_at,
+                discrepancy.ended_at,
+                discrepancy.odometer_distance_meters / 1000.0,
+                discrepancy.gps_distance_meters / 1000.0,
+                discrepancy.get_deviation_percent()
+            );
+            if !cfg!(feature = "pending-endpoints") {
+                continue;
+            }
+            let truck_id = self.records_handler.get_truck_id();
+            let Some(truck_id) = truck_id.filter(|_| processing_mode == ProcessingMode::Active)
+            else {
+                continue;
+            };
+            if let Err(err) = VehicleApi
+                .create_odometer_discrepancy(&truck_id, discrepancy)
+                .await
+            {
+                debug!(target: self.log_target(), "Odometer discrepancy not sent: {}", err);
+            }
+        }
+    }
+
+    /// Handles the detection of gaps in the records
+    ///
+    /// If a gap is detected, a command is sent to the device to request the records stored in its memory.
+    ///
+    /// # Arguments
+    /// * `records` - Records to be checked for gaps
+    async fn handle_record_gaps(&mut self, records: &[AVLRecord]) -> std::io::Result<()> {
+        let Some((gap, command)) = self.gap_detector.handle_records(records) else {
+            return Ok(());
+        };
+        info!(target: &self.imei,
+            "Detected gap in records from {} to {}, requesting stored records with command [{}]",
+            gap.start,
+            gap.end,
+            command
+        );
+        let command = command.to_string();
+
+        return self.write_command(&command).await;
+    }
+
+    /// Writes a Codec 12 command to the device
+    ///
+    /// The command is recorded in the command channel, so that the response of the device can be paired with it.
+    ///
+    /// # Arguments
+    /// * `command` - Command to write, e.g. `getinfo`
+    async fn write_command(&mut self, command: &str) -> std::io::Result<()> {
+        let command_frame = build_codec12_command(command);
+        self.teltonika_stream
+            .inner_mut()
+            .write_all(&command_frame)
+            .await?;
+        debug!(target: self.log_target(), "Sent command [{}]", command);
+        get_command_channel().record_sent_command(&self.imei, command);
+
+        return Ok(());
+    }
+
+    /// Reads the next message from the device within the idle timeout
+    ///
+    /// # Returns
+    /// * `None` if the device didn't send anything within the idle timeout
+    async fn read_message_within_idle_timeout(
+        &mut self,
+    ) -> Option<std::io::Result<TeltonikaMessage>> {
+        match self.idle_timeout {
+            Some(idle_timeout) => tokio::time::timeout(idle_timeout, self.read_message())
+                .await
+                .ok(),
+            None => Some(self.read_message().await),
+        }
+    }
+
+    /// Writes the ACK of a frame to the device
+    ///
+    /// Failed writes are retried `ACK_WRITE_RETRIES` times, so that a transient socket error doesn't leave the device
+    /// uncertain whether it should resend the frame.
+    ///
+    /// # Arguments
+    /// * `frame` - Frame to acknowledge, or `None` to reply with a 0-record ACK making the device send it again
+    async fn write_frame_ack(&mut self, frame: Option<&AVLFrame>) -> std::io::Result<()> {
+        let mut retries = 0;
+        loop {
+            match self.teltonika_stream.write_frame_ack_async(frame).await {
+                Ok(()) => return Ok(()),
+                Err(err) if retries < self.ack_write_retries => {
+                    retries += 1;
+                    warn!(target: self.log_target(),
+                        "Failed to write frame ACK: {}. Retrying ({}/{})...",
+                        err,
+                        retries,
+                        self.ack_write_retries
+                    );
+                    tokio::time::sleep(ACK_WRITE_RETRY_DELAY).await;
+                }
+                Err(err) => {
+                    error!(target: self.log_target(), "Failed to write frame ACK: {}", err);
+                    device_stats::record_ack_failure(&self.imei);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Authenticates the device with the pre-shared token configured for its IMEI
+    ///
+    /// Devices required to authenticate must send the token as their first message after the IMEI handshake.
+    /// Other devices are trusted by their IMEI alone and nothing is read from them.
+    ///
+    /// # Returns
+    /// * Whether the connection may continue
+    async fn authenticate_device(&mut self) -> bool {
+        if !device_auth::is_auth_required(&self.imei) {
+            return true;
+        }
+        let presented_token = match tokio::time::timeout(DEVICE_AUTH_TIMEOUT, self.read_message())
+            .await
+        {
+            Ok(Ok(TeltonikaMessage::CommandResponse(token))) => Some(token),
+            Ok(Ok(_)) => None,
+            Ok(Err(err)) => {
+                warn!(target: self.log_target(), "Failed to read authentication token: {}", err);
+                None
+            }
+            Err(_) => None,
+        };
+        let result = device_auth::authenticate(&self.imei, presented_token.as_deref());
+        if result == DeviceAuthResult::Rejected {
+            warn!(target: self.log_target(), "Closing connection of unauthenticated device");
+            return false;
+        }
+        info!(target: self.log_target(), "Device authenticated");
+
+        return true;
+    }
+
+    /// Reads the next message from the device
+    ///
+    /// Bytes following the message are kept for reading the next message.
+    /// If memory is capped, a message exceeding the cap is rejected with [std::io::ErrorKind::OutOfMemory]
+    /// as soon as its header has been received, before buffering the rest of it.
+    async fn read_message(&mut self) -> std::io::Result<TeltonikaMessage> {
+        loop {
+            if let Some(message) = parse_message(&mut self.read_buffer)? {
+                return Ok(message);
+            }
+            if let Some(max_memory_bytes) = self.records_handler.get_max_memory_bytes() {
+                if let Err(err) = check_message_length(&self.read_buffer, max_memory_bytes) {
+                    self.read_buffer.clear();
+                    return Err(err);
+                }
+            }
+            let mut receive_buffer = [0u8; RECEIVE_BUFFER_SIZE];
+            let bytes_read = self
+                .teltonika_stream
+                .inner_mut()
+                .read(&mut receive_buffer)
+                .await?;
+            if bytes_read == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "Connection closed",
+                ));
+            }
+            self.read_buffer
+                .extend_from_slice(&receive_buffer[..bytes_read]);
         }
     }
 
@@ -155,53 +649,304 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
         &self.imei
     }
 
+    /// Updates the backlog of the device from the records of a frame
+    ///
+    /// Codecs supported by the devices don't report the number of records left in the device memory.
+    /// Instead, devices send their stored records oldest first, so the age of the newest record in a frame tells how far behind the device is.
+    ///
+    /// # Arguments
+    /// * `records` - Records of the frame
+    fn update_device_backlog(&self, records: &[AVLRecord]) {
+        let Some(newest_timestamp) = records.iter().map(|record| record.timestamp).max() else {
+            return;
+        };
+        let backlog_seconds = (Utc::now() - newest_timestamp).num_seconds().max(0);
+        metrics::set_gauge(
+            DEVICE_BACKLOG_METRIC,
+            &[("imei", &self.imei)],
+            backlog_seconds as f64,
+        );
+    }
+
+    /// Starts the task dispatching the records of acknowledged frames in the background
+    ///
+    /// Up to `pipeline_depth` frames are queued for the task, so that the next frame can be read while the previous
+    /// ones are still being dispatched. Reading blocks while the queue is full.
+    ///
+    /// # Arguments
+    /// * `pipeline_depth` - Maximum number of frames waiting to be dispatched
+    fn start_dispatcher(
+        &self,
+        pipeline_depth: usize,
+    ) -> (DispatchQueue, tokio::task::JoinHandle<()>) {
+        let (sender, mut receiver) = mpsc::channel(pipeline_depth);
+        let records_handler = self.records_handler.clone();
+        let imei = self.imei.clone();
+        let dispatcher = tokio::spawn(
+            async move {
+                while let Some((records, processing_mode, provenance, budget_permit)) =
+                    receiver.recv().await
+                {
+                    dispatch_records(
+                        &records_handler,
+                        &imei,
+                        records,
+                        processing_mode,
+                        provenance,
+                    )
+                    .await;
+                    load_shedding::record_frame_dispatched();
+                    drop(budget_permit);
+                }
+            }
+            .instrument(Span::current()),
+        );
+
+        return (sender, dispatcher);
+    }
+
     /// Runs the connection with the Teltonika Telematics device
     ///
     /// This function will run the connection with the Teltonika Telematics device and handle the incoming frames.
     /// It will also write the data to the log file.
     ///
+    /// If the ACK pipeline depth is above 0, records are dispatched in the background
+    /// so that devices draining big on-board buffers aren't held back by the API. Otherwise each frame is dispatched before reading the next one.
+    ///
+    /// Lifecycle events are emitted when the connection is started and when it is closed.
+    /// While running, the connection is registered in the command channel for receiving commands and in the
+    /// connection registry for listing it in the admin API and retrying its caches in the background.
+    ///
     /// # Arguments
     /// * `base_log_file_path` - Base path for the log files
     async fn run(
         &mut self,
-        base_log_file_path: &PathBuf,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        base_log_file_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let start_of_connection = Utc::now();
+        lifecycle::emit_lifecycle_event(&ConnectionLifecycleEvent::Connected {
+            imei: self.imei.clone(),
+            connection_id: self.connection_id.clone(),
+            timestamp: start_of_connection,
+        });
         let mut file_handle = self.get_log_file_handle(base_log_file_path);
+        let mut commands = get_command_channel().register(&self.imei, &self.connection_id);
+        self.active_connection = Some(get_connection_registry().register(
+            &self.imei,
+            &self.connection_id,
+            Transport::Tcp,
+            self.peer_ip,
+            &self.records_handler,
+        ));
+        let mut dispatcher =
+            (self.ack_pipeline_depth > 0).then(|| self.start_dispatcher(self.ack_pipeline_depth));
+        let result = self
+            .run_frames(
+                base_log_file_path,
+                &mut file_handle,
+                start_of_connection,
+                dispatcher.as_ref().map(|(sender, _)| sender),
+                &mut commands,
+            )
+            .await;
+        get_command_channel().unregister(&self.imei, &self.connection_id);
+        get_connection_registry().unregister(&self.imei, &self.connection_id);
+        self.active_connection = None;
+        // Records already acknowledged are dispatched before the connection is closed
+        if let Some((sender, dispatcher)) = dispatcher.take() {
+            drop(sender);
+            if let Err(err) = dispatcher.await {
+                error!(target: self.log_target(), "Dispatching records failed: {}", err);
+            }
+        }
+        self.records_handler.flush_batches(true).await;
+        let disconnected_at = Utc::now();
+        lifecycle::emit_lifecycle_event(&ConnectionLifecycleEvent::Disconnected {
+            imei: self.imei.clone(),
+            connection_id: self.connection_id.clone(),
+            truck_id: self.records_handler.get_truck_id(),
+            timestamp: disconnected_at,
+            connected_seconds: (disconnected_at - start_of_connection).num_seconds(),
+            reason: *result.as_ref().unwrap_or(&DisconnectReason::Error),
+        });
+
+        return result.map(|_| ());
+    }
 
+    /// Reads and handles the frames of the connection until it is closed
+    ///
+    /// # Returns
+    /// * Reason for closing the connection
+    ///
+    /// # Arguments
+    /// * `base_log_file_path` - Base path for the log files
+    /// * `file_handle` - Handle of the log file
+    /// * `start_of_connection` - Time the connection was started
+    /// * `dispatch_queue` - Queue of the background dispatcher if records are dispatched in the background
+    /// * `commands` - Commands enqueued for the device
+    async fn run_frames(
+        &mut self,
+        base_log_file_path: &Path,
+        file_handle: &mut Option<File>,
+        start_of_connection: chrono::DateTime<Utc>,
+        dispatch_queue: Option<&DispatchQueue>,
+        commands: &mut mpsc::Receiver<String>,
+    ) -> Result<DisconnectReason, Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_message_failed_to_parse = false;
         loop {
             let start_of_loop = Utc::now();
             if start_of_loop.day() != start_of_connection.day() {
-                file_handle = self.get_log_file_handle(base_log_file_path);
+                *file_handle = self.get_log_file_handle(base_log_file_path);
             }
 
-            match self.teltonika_stream.read_frame_async().await {
-                Ok(mut frame) => {
+            let message = tokio::select! {
+                message = self.read_message_within_idle_timeout() => message,
+                Some(command) = commands.recv() => {
+                    self.write_command(&command).await?;
+                    continue;
+                }
+                _ = shutdown::get_shutdown().wait() => {
+                    info!(target: self.log_target(), "Closing connection for shutdown");
+                    return Ok(DisconnectReason::Shutdown);
+                }
+            };
+            let Some(message) = message else {
+                info!(target: self.log_target(),
+                    "Closing connection idle for {} seconds",
+                    self.idle_timeout.unwrap_or_default().as_secs()
+                );
+                return Ok(DisconnectReason::IdleTimeout);
+            };
+            let failed_to_parse =
+                matches!(&message, Err(err) if err.kind() == std::io::ErrorKind::InvalidData);
+            let previous_message_failed_to_parse =
+                std::mem::replace(&mut last_message_failed_to_parse, failed_to_parse);
+            match message {
+                Ok(TeltonikaMessage::CommandResponse(response)) => {
+                    info!(target: self.log_target(), "Received command response: {}", response);
+                    get_command_channel().record_response(&self.imei, &response);
+                }
+                Ok(TeltonikaMessage::BinaryCommandResponse { timestamp, data }) => {
+                    info!(target: self.log_target(),
+                        "Received binary command response at {}: {}",
+                        timestamp,
+                        data.iter().map(|byte| format!("{:02X}", byte)).collect::<String>()
+                    );
+                }
+                Ok(TeltonikaMessage::Frame(mut frame)) => {
+                    // Rejected frames are left unhandled altogether, as the device sends them again
+                    if !probe::is_probe_imei(&self.imei)
+                        && load_shedding::should_reject_frame(self.frame_reject_threshold)
+                    {
+                        load_shedding::record_rejected_frame();
+                        if let Some(suppressed) =
+                            log_throttle::throttle(&format!("{}:rejected_frame", self.imei))
+                        {
+                            warn!(target: self.log_target(),
+                                "Too many frames queued, rejecting frame with {} records{}",
+                                frame.records.len(),
+                                log_throttle::describe_suppressed(suppressed)
+                            );
+                        }
+                        self.write_frame_ack(None).await?;
+                        continue;
+                    }
+                    let provenance =
+                        FrameProvenance::new(frame.crc16, &self.connection_id, self.peer_ip);
+                    let frame_bytes = frame.to_bytes();
+                    self.write_data_to_log_file(file_handle, &frame_bytes);
+                    if let Some(active_connection) = &self.active_connection {
+                        active_connection.record_frame();
+                    }
+                    // Synthetic devices are excluded from the statistics
+                    if self.is_synthetic {
+                        synthetic::record_synthetic_frame(&self.imei);
+                    } else {
+                        metrics::increment_counter(FRAMES_METRIC, &[]);
+                        metrics::add_to_counter(RECORDS_METRIC, &[], frame.records.len() as u64);
+                        metrics::add_to_counter(BYTES_METRIC, &[], frame_bytes.len() as u64);
+                        device_stats::record_frame(&self.imei, frame.records.len());
+                    }
+                    self.timestamp_normalizer
+                        .normalize_records(&mut frame.records);
+                    if let Some(peer_ip) = self.peer_ip {
+                        if spoofing::check_frame_source(&self.imei, peer_ip)
+                            == FrameSource::Quarantined
+                        {
+                            warn!(target: self.log_target(),
+                                "Closing connection from [{}] quarantined for suspected IMEI spoofing",
+                                peer_ip
+                            );
+                            return Ok(DisconnectReason::Quarantined);
+                        }
+                    }
+                    if probe::is_probe_imei(&self.imei) {
+                        probe::record_probe_frame(&frame.records);
+                        invariants::check_frame_records(
+                            &self.imei,
+                            frame.records.len(),
+                            0,
+                            frame.records.len(),
+                        );
+                        self.write_frame_ack(Some(&frame)).await?;
+                        continue;
+                    }
+                    let processing_mode = processing::get_processing_mode(&self.imei);
+                    if processing_mode == ProcessingMode::Muted {
+                        processing::record_muted_frame(&self.imei);
+                        invariants::check_frame_records(
+                            &self.imei,
+                            frame.records.len(),
+                            0,
+                            frame.records.len(),
+                        );
+                        self.write_frame_ack(Some(&frame)).await?;
+                        continue;
+                    }
+                    if !self.is_synthetic {
+                        completeness::record_received(&self.imei, &frame.records);
+                        self.update_device_backlog(&frame.records);
+                    }
                     let records_count = frame.records.len();
-                    self.handle_driver_one_card_removal(&mut frame.records)
+                    self.handle_shift_summaries(&frame.records, processing_mode)
                         .await;
+                    self.handle_odometer_discrepancies(&frame.records, processing_mode)
+                        .await;
+                    if processing_mode == ProcessingMode::Active {
+                        self.handle_driver_one_card_removal(&mut frame.records)
+                            .await;
+                    }
 
-                    if let None = self.truck_vin {
-                        self.truck_vin = self
-                            .records_handler
-                            .get_truck_vin_from_records(&frame.records);
+                    let frame_vin = self
+                        .records_handler
+                        .get_truck_vin_from_records(&frame.records);
+                    if let Some(vin) = &frame_vin {
+                        misinstallation::check_vin(&self.imei, vin);
                     }
-                    if self.truck_id.is_none() && self.truck_vin.is_some() {
-                        let found_truck_id = get_truck_id_by_vin(&self.truck_vin).await;
-                        if found_truck_id.is_some() {
+                    if self.records_handler.get_truck_vin().is_none() {
+                        self.records_handler.set_truck_vin(frame_vin);
+                    }
+                    let truck_vin = self.records_handler.get_truck_vin();
+                    if self.records_handler.get_truck_id().is_none()
+                        && truck_vin.is_some()
+                        && !processing::is_maintenance_mode()
+                    {
+                        let found_truck_id = get_truck_id_by_vin(&truck_vin).await;
+                        if let Some(truck_id) = found_truck_id {
                             debug!(
                                 target: self.log_target(),
                                 "Found Truck ID [{}] for VIN [{}]",
-                                found_truck_id.clone().unwrap(),
-                                self.truck_vin.clone().unwrap()
+                                truck_id,
+                                truck_vin.clone().unwrap()
                             );
+                            get_api_routing().register_truck(&self.imei, &truck_id.to_string());
                             self.records_handler
-                                .set_truck_id(found_truck_id.clone().map(|id| id.to_string()));
-                            self.truck_id = found_truck_id.map(|id| id.to_string());
+                                .set_truck_id(found_truck_id.map(|id| id.to_string()));
+                            Span::current().record("truck_id", truck_id.to_string());
                         }
                     }
 
-                    if let Some(vin) = &self.truck_vin {
+                    if let Some(vin) = &truck_vin {
                         debug!(
                             target: self.log_target(),
                             "Received frame with {} records from VIN [{}]",
@@ -215,42 +960,118 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
                         );
                     }
 
-                    self.write_data_to_log_file(&mut file_handle, &frame.to_bytes());
+                    // Frame is acknowledged only once there is room for it in the queue and in the memory budget, so that
+                    // the device keeps the records while the dispatcher is behind and sends them again if the connection is closed
+                    let dispatch_permit = match dispatch_queue {
+                        Some(dispatch_queue) => {
+                            let budget_permit = match &self.dispatch_budget {
+                                Some(dispatch_budget) => {
+                                    // Frames larger than the whole budget wait for the budget to be free instead of forever
+                                    let frame_size = clamp_to_budget(
+                                        frame
+                                            .records
+                                            .iter()
+                                            .map(estimate_record_size)
+                                            .sum::<usize>()
+                                            .min(
+                                                self.records_handler
+                                                    .get_max_memory_bytes()
+                                                    .unwrap_or_default(),
+                                            ),
+                                    );
+                                    if dispatch_budget.available_permits() < frame_size as usize {
+                                        metrics::increment_counter(ACK_BACKPRESSURE_METRIC, &[]);
+                                    }
+                                    let Ok(budget_permit) = dispatch_budget
+                                        .clone()
+                                        .acquire_many_owned(frame_size)
+                                        .await
+                                    else {
+                                        error!(target: self.log_target(), "Memory budget closed, closing connection");
+                                        return Ok(DisconnectReason::Error);
+                                    };
+                                    Some(budget_permit)
+                                }
+                                None => None,
+                            };
+                            if dispatch_queue.capacity() == 0 {
+                                metrics::increment_counter(ACK_BACKPRESSURE_METRIC, &[]);
+                            }
+                            let Ok(dispatch_permit) = dispatch_queue.reserve().await else {
+                                error!(target: self.log_target(), "Records dispatcher stopped, closing connection");
+                                return Ok(DisconnectReason::Error);
+                            };
+                            Some((dispatch_permit, budget_permit))
+                        }
+                        None => None,
+                    };
 
-                    self.teltonika_stream
-                        .write_frame_ack_async(Some(&frame))
-                        .await?;
+                    self.write_frame_ack(Some(&frame)).await?;
 
-                    self.records_handler.handle_records(frame.records).await;
+                    if let Err(err) = self.handle_record_gaps(&frame.records).await {
+                        warn!(target: self.log_target(), "Failed to request stored records: {}", err);
+                    }
 
-                    if let Some(id) = &self.truck_id {
-                        info!(target: self.log_target(), "Purging cache for truck ID: [{}]...", id);
-                        self.records_handler.purge_cache().await;
+                    match dispatch_permit {
+                        Some((dispatch_permit, budget_permit)) => {
+                            load_shedding::record_frame_queued();
+                            dispatch_permit.send((
+                                frame.records,
+                                processing_mode,
+                                provenance,
+                                budget_permit,
+                            ));
+                        }
+                        None => {
+                            load_shedding::record_frame_queued();
+                            dispatch_records(
+                                &self.records_handler,
+                                &self.imei,
+                                frame.records,
+                                processing_mode,
+                                provenance,
+                            )
+                            .await;
+                            load_shedding::record_frame_dispatched();
+                        }
                     }
                 }
                 Err(err) => match err.kind() {
                     std::io::ErrorKind::ConnectionReset => {
                         info!(target: self.log_target(), "Client disconnected");
-                        break;
+                        if previous_message_failed_to_parse {
+                            return Ok(DisconnectReason::ParseError);
+                        }
+                        return Ok(DisconnectReason::ClientReset);
+                    }
+                    std::io::ErrorKind::OutOfMemory => {
+                        metrics::increment_counter(OVERSIZED_MESSAGES_METRIC, &[]);
+                        warn!(target: self.log_target(), "Closing connection: {}", err);
+                        return Ok(DisconnectReason::MemoryCap);
                     }
                     std::io::ErrorKind::InvalidData => {
-                        error!(target: self.log_target(),
-                            "Failed to parse frame from client: {}",
-                            err
-                        );
+                        metrics::increment_counter(PARSE_ERRORS_METRIC, &[]);
+                        device_stats::record_parse_error(&self.imei);
+                        if let Some(suppressed) =
+                            log_throttle::throttle(&format!("{}:parse_error", self.imei))
+                        {
+                            error!(target: self.log_target(),
+                                "Failed to parse frame from client: {}{}",
+                                err,
+                                log_throttle::describe_suppressed(suppressed)
+                            );
+                        }
                     }
                     _ => {
                         error!(target: self.log_target(),
                             "Unknown error when parsing frame from client: {}",
                             err
                         );
-                        break;
+                        return Ok(DisconnectReason::Error);
                     }
                 },
             }
         }
-
-        Ok(())
     }
 
     /// Write data to log file
@@ -279,10 +1100,12 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
     fn get_log_file_handle(&self, log_file_path: &Path) -> Option<File> {
         if cfg!(not(test)) && log_file_path.file_name().unwrap() != "" {
             let today = Utc::now().format("%Y-%m-%d").to_string();
-            create_dir_all(&log_file_path).expect(&format!(
-                "Failed to create log file directory `{:#?}`",
-                &log_file_path
-            ));
+            create_dir_all(log_file_path).unwrap_or_else(|_| {
+                panic!(
+                    "Failed to create log file directory `{:#?}`",
+                    &log_file_path
+                )
+            });
             return Some(
                 OpenOptions::new()
                     .read(true)
@@ -296,3 +1119,11 @@ impl<S: AsyncWriteExt + AsyncReadExt + Unpin> TeltonikaConnection<S> {
         return None;
     }
 }
+
+/// Clamps a number of bytes to the number of permits a frame can take from the memory budget of a connection
+///
+/// # Arguments
+/// * `bytes` - Number of bytes
+fn clamp_to_budget(bytes: usize) -> u32 {
+    return bytes.min(u32::MAX as usize) as u32;
+}
@@ -0,0 +1,236 @@
+//! Registry of the connected devices
+//!
+//! Devices register themselves with their records handler when they connect over TCP or send their first datagram
+//! over UDP, so that the admin API can list them and their backlogs, the caches of connected devices can be retried
+//! in the background and the trucks of connected devices can be refreshed in the background. The records handlers of
+//! disconnected devices are kept until the device reconnects or the caches of the device have been retried, so that the
+//! events cached before disconnecting are sent even if the device doesn't reconnect.
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::teltonika::records::TeltonikaRecordsHandler;
+
+static CONNECTION_REGISTRY: OnceLock<ConnectionRegistry> = OnceLock::new();
+
+/// Transport of a device connection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// Connection of a device
+pub struct ActiveConnection {
+    imei: String,
+    connection_id: String,
+    transport: Transport,
+    peer_ip: Option<IpAddr>,
+    connected_at: DateTime<Utc>,
+    frames_received: AtomicU64,
+    records_handler: Arc<TeltonikaRecordsHandler>,
+}
+
+impl ActiveConnection {
+    /// Records a frame or datagram received from the device
+    pub fn record_frame(&self) {
+        self.frames_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Gets the records handler of the device
+    pub fn get_records_handler(&self) -> &Arc<TeltonikaRecordsHandler> {
+        &self.records_handler
+    }
+
+    /// Gets a summary of the connection
+    pub fn get_summary(&self) -> ConnectionSummary {
+        ConnectionSummary {
+            imei: self.imei.clone(),
+            connection_id: self.connection_id.clone(),
+            transport: self.transport,
+            peer_ip: self.peer_ip,
+            truck_id: self.records_handler.get_truck_id(),
+            connected_at: self.connected_at,
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Summary of a device connection listed in the admin API
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConnectionSummary {
+    pub imei: String,
+    pub connection_id: String,
+    pub transport: Transport,
+    pub peer_ip: Option<IpAddr>,
+    pub truck_id: Option<String>,
+    pub connected_at: DateTime<Utc>,
+    pub frames_received: u64,
+}
+
+/// Registry of the connected devices by IMEI
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    connections: Mutex<HashMap<String, Arc<ActiveConnection>>>,
+    /// Records handlers of the disconnected devices whose caches are yet to be retried
+    disconnected_records_handlers: Mutex<HashMap<String, Arc<TeltonikaRecordsHandler>>>,
+}
+
+impl ConnectionRegistry {
+    /// Registers a connection of a device
+    ///
+    /// A device reconnecting replaces its previous connection.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `connection_id` - ID of the connection
+    /// * `transport` - Transport of the connection
+    /// * `peer_ip` - IP address of the device, if known
+    /// * `records_handler` - Records handler of the device
+    ///
+    /// # Returns
+    /// * The registered connection for recording the received frames
+    pub fn register(
+        &self,
+        imei: &str,
+        connection_id: &str,
+        transport: Transport,
+        peer_ip: Option<IpAddr>,
+        records_handler: &Arc<TeltonikaRecordsHandler>,
+    ) -> Arc<ActiveConnection> {
+        let connection = Arc::new(ActiveConnection {
+            imei: imei.to_string(),
+            connection_id: connection_id.to_string(),
+            transport,
+            peer_ip,
+            connected_at: Utc::now(),
+            frames_received: AtomicU64::new(0),
+            records_handler: records_handler.clone(),
+        });
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(imei.to_string(), connection.clone());
+        self.disconnected_records_handlers
+            .lock()
+            .unwrap()
+            .remove(imei);
+
+        return connection;
+    }
+
+    /// Unregisters a closed connection of a device
+    ///
+    /// The registration is kept if the device has already reconnected. Otherwise the records handler of the device
+    /// is kept for retrying its caches.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `connection_id` - ID of the closed connection
+    pub fn unregister(&self, imei: &str, connection_id: &str) {
+        let mut connections = self.connections.lock().unwrap();
+        if connections
+            .get(imei)
+            .is_some_and(|connection| connection.connection_id == connection_id)
+        {
+            if let Some(connection) = connections.remove(imei) {
+                self.disconnected_records_handlers
+                    .lock()
+                    .unwrap()
+                    .insert(imei.to_string(), connection.records_handler.clone());
+            }
+        }
+    }
+
+    /// Gets the connection of a device
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    pub fn get_connection(&self, imei: &str) -> Option<Arc<ActiveConnection>> {
+        self.connections.lock().unwrap().get(imei).cloned()
+    }
+
+    /// Gets the summaries of the connections ordered by IMEI
+    pub fn get_summaries(&self) -> Vec<ConnectionSummary> {
+        let mut summaries = self
+            .connections
+            .lock()
+            .unwrap()
+            .values()
+            .map(|connection| connection.get_summary())
+            .collect::<Vec<_>>();
+        summaries.sort_by(|a, b| a.imei.cmp(&b.imei));
+
+        return summaries;
+    }
+
+    /// Gets the records handlers of the connected devices
+    pub fn get_records_handlers(&self) -> Vec<Arc<TeltonikaRecordsHandler>> {
+        self.connections
+            .lock()
+            .unwrap()
+            .values()
+            .map(|connection| connection.records_handler.clone())
+            .collect()
+    }
+
+    /// Gets the records handlers of the disconnected devices whose caches are yet to be retried
+    pub fn get_disconnected_records_handlers(&self) -> Vec<Arc<TeltonikaRecordsHandler>> {
+        self.disconnected_records_handlers
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Releases the records handler of a disconnected device once its caches don't need to be retried anymore
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `records_handler` - Records handler to release, kept if the device has reconnected and disconnected again
+    pub fn release_disconnected_records_handler(
+        &self,
+        imei: &str,
+        records_handler: &Arc<TeltonikaRecordsHandler>,
+    ) {
+        let mut disconnected_records_handlers = self.disconnected_records_handlers.lock().unwrap();
+        if disconnected_records_handlers
+            .get(imei)
+            .is_some_and(|disconnected| Arc::ptr_eq(disconnected, records_handler))
+        {
+            disconnected_records_handlers.remove(imei);
+        }
+    }
+}
+
+/// Gets the global connection registry
+pub fn get_connection_registry() -> &'static ConnectionRegistry {
+    CONNECTION_REGISTRY.get_or_init(ConnectionRegistry::default)
+}
+
+/// Starts refreshing the truck IDs of the connected devices on a schedule
+///
+/// Truck IDs are looked up through the truck cache, so a changed truck is found once the cached VIN expires.
+///
+/// # Arguments
+/// * `interval` - Interval of the refreshes
+pub async fn start_truck_id_refresh(interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        for records_handler in get_connection_registry().get_records_handlers() {
+            records_handler.refresh_truck_id().await;
+        }
+    }
+}
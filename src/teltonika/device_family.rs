@@ -0,0 +1,35 @@
+use std::fmt;
+
+use super::io_mappings::{get_io_mappings, IoMapping};
+
+/// Family of Teltonika devices sharing the same IO element layout.
+///
+/// Devices don't tell their model, so the family is set by the listener the device connects to. The IO elements of the
+/// families are looked up from the [super::io_mappings] registry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DeviceFamily {
+    /// FMC234 and FMC650, reading the speed and VIN from the tachograph
+    #[default]
+    Fmc,
+    /// FMB1xx, e.g. FMB140, reading the speed and VIN from the CAN bus through an LV-CAN200 or ALL-CAN300 adapter
+    Fmb1xx,
+    /// FMB6xx, e.g. FMB640, having the same tachograph connection as FMC650
+    Fmb6xx,
+}
+
+impl DeviceFamily {
+    /// Gets the IO elements the devices of the family report their data in
+    pub fn get_io_mapping(&self) -> &'static IoMapping {
+        return get_io_mappings().get_for_family(*self);
+    }
+}
+
+impl fmt::Display for DeviceFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceFamily::Fmc => write!(f, "fmc"),
+            DeviceFamily::Fmb1xx => write!(f, "fmb1xx"),
+            DeviceFamily::Fmb6xx => write!(f, "fmb6xx"),
+        }
+    }
+}
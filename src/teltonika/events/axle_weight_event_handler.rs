@@ -0,0 +1,106 @@
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::{Deserialize, Serialize};
+
+use super::teltonika_event_handlers::TeltonikaEventHandler;
+use crate::{
+    telematics_cache::Cacheable,
+    teltonika::{avl_event_io_value_to_u64, records::RecordTrigger, EventDecodeError},
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
+};
+
+/// IDs of the axle load events of axles 1-5, in kg
+const AXLE_LOAD_EVENT_IDS: [u16; 5] = [118, 119, 120, 121, 122];
+/// Smallest value of a 2-byte CAN parameter reserved for errors and values not available
+const AXLE_LOAD_NOT_AVAILABLE_VALUE: u64 = 0xFB00;
+
+/// Weight on a single axle of a truck
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AxleWeight {
+    /// Number of the axle counted from the front, starting from 1
+    pub axle: u8,
+    /// Weight on the axle in kg
+    pub weight: f64,
+}
+
+/// Weights on the axles of a truck at a single point of time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TruckAxleWeights {
+    pub timestamp: i64,
+    pub axles: Vec<AxleWeight>,
+}
+
+/// Handler for the axle load events read from the CAN bus of the truck by FMC650 and FMB640.
+///
+/// Vehicle Management Service has no endpoint for axle weights yet, so the handler is registered only with the
+/// `pending-endpoints` feature.
+///
+/// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/FMC650_Teltonika_Data_Sending_Parameters_ID) for more detailed information.
+#[derive(Default)]
+pub struct AxleWeightEventHandler<A = VehicleApi> {
+    api: A,
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<TruckAxleWeights> for AxleWeightEventHandler<A> {
+    fn get_event_ids(&self) -> Vec<u16> {
+        Vec::new()
+    }
+
+    fn has_endpoint(&self) -> bool {
+        false
+    }
+
+    fn get_optional_event_ids(&self) -> Vec<u16> {
+        AXLE_LOAD_EVENT_IDS.to_vec()
+    }
+
+    async fn send_event(
+        &self,
+        event_data: &TruckAxleWeights,
+        truck_id: String,
+        _imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_truck_axle_weights(&truck_id, event_data.clone())
+            .await
+    }
+
+    fn process_event_data(
+        &self,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
+        timestamp: i64,
+        _position: (f64, f64),
+        _imei: &str,
+    ) -> Result<Option<TruckAxleWeights>, EventDecodeError> {
+        let axles = AXLE_LOAD_EVENT_IDS
+            .iter()
+            .enumerate()
+            .filter_map(|(index, id)| {
+                let event = events.iter().find(|event| event.id == *id)?;
+                let value = avl_event_io_value_to_u64(&event.value);
+                if value >= AXLE_LOAD_NOT_AVAILABLE_VALUE {
+                    return None;
+                }
+                Some(AxleWeight {
+                    axle: index as u8 + 1,
+                    weight: value as f64,
+                })
+            })
+            .collect::<Vec<AxleWeight>>();
+        if axles.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(TruckAxleWeights { timestamp, axles }))
+    }
+}
+
+impl Cacheable for TruckAxleWeights {
+    const FILE_PATH: &'static str = "axle_weights_cache.json";
+
+    fn from_teltonika_record(_: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
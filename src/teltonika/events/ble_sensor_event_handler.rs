@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::{Deserialize, Serialize};
+
+use super::teltonika_event_handlers::TeltonikaEventHandler;
+use crate::{
+    config::get_config,
+    telematics_cache::Cacheable,
+    teltonika::{
+        avl_event_io_value_to_i64, avl_event_io_value_to_u64, device_family::DeviceFamily,
+        records::RecordTrigger, EventDecodeError,
+    },
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
+};
+
+/// Number of sensor slots of a device
+const BLE_SENSOR_SLOTS: usize = 4;
+/// IDs of the BLE humidity events of sensor slots 1-4, in tenths of %
+const BLE_HUMIDITY_EVENT_IDS: [u16; 4] = [86, 104, 106, 108];
+/// IDs of the EYE magnet events of sensor slots 1-4, 1 when a magnetic field is detected
+const BLE_MAGNET_EVENT_IDS: [u16; 4] = [10808, 10809, 10810, 10811];
+/// Values sent in place of a measurement when the sensor is not found, its data fails to parse or it's in an abnormal state
+const BLE_SENSOR_ERROR_VALUES: [u64; 3] = [2000, 3000, 4000];
+
+/// Settings of the BLE sensor slots keyed by the IMEI of the device and the slot
+pub type SlotSettings = HashMap<(String, u8), String>;
+
+/// Reading of a single BLE sensor, such as a Teltonika EYE sensor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BleSensorReading {
+    /// Slot of the sensor in the device configuration, 1-4
+    pub slot: u8,
+    /// MAC address of the sensor, if configured for the slot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensor_mac: Option<String>,
+    /// Compartment of the trailer the sensor measures, if configured for the slot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compartment: Option<String>,
+    /// Temperature in °C
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Relative humidity in %
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<f64>,
+    /// Whether the door is open, i.e. the magnet mounted on it is away from the sensor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub door_open: Option<bool>,
+}
+
+/// Readings of the BLE sensors of a truck at a single point of time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BleSensorReadings {
+    pub timestamp: i64,
+    pub readings: Vec<BleSensorReading>,
+}
+
+/// Handler for the temperature, humidity and magnet events of BLE sensors, such as Teltonika EYE sensors paired with FMC234.
+///
+/// Devices send the measurements of up to four sensors in slots configured on the device. The temperatures are read from
+/// the IO elements of the device family as signed integers, so that temperatures below zero sent as unsigned values of
+/// the width of the IO element are read correctly, and converted to °C. The MAC addresses of the sensors
+/// are not sent, so they are associated with the slots of a device with `BLE_SENSOR_MACS`. The compartments of trailers
+/// measured by the sensors are likewise associated with the slots with `BLE_SENSOR_COMPARTMENTS`.
+///
+/// Vehicle Management Service has no endpoints for temperature, humidity or door state readings yet, so the handler
+/// is registered only with the `pending-endpoints` feature.
+///
+/// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/EYE_SENSOR_/_BTSMP1) for more detailed information.
+pub struct BleSensorEventHandler<A = VehicleApi> {
+    api: A,
+    temperature_event_ids: Vec<u16>,
+    temperature_divisor: f64,
+}
+
+impl<A: Default> BleSensorEventHandler<A> {
+    /// Creates a new [BleSensorEventHandler] for the temperature events of the given device family.
+    ///
+    /// # Arguments
+    /// * `device_family` - Family of the device
+    pub fn new(device_family: DeviceFamily) -> Self {
+        let io_mapping = device_family.get_io_mapping();
+        BleSensorEventHandler {
+            api: A::default(),
+            temperature_event_ids: io_mapping.temperatures.clone(),
+            temperature_divisor: io_mapping.temperature_divisor,
+        }
+    }
+}
+
+impl<A: Default> Default for BleSensorEventHandler<A> {
+    fn default() -> Self {
+        BleSensorEventHandler::new(DeviceFamily::default())
+    }
+}
+
+impl<A: TruckEventApi> BleSensorEventHandler<A> {
+    /// Sets the temperature events of the sensor slots and the divisor converting their values to °C.
+    #[cfg(test)]
+    pub fn set_temperature_events(
+        &mut self,
+        temperature_event_ids: Vec<u16>,
+        temperature_divisor: f64,
+    ) {
+        self.temperature_event_ids = temperature_event_ids;
+        self.temperature_divisor = temperature_divisor;
+    }
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<BleSensorReadings> for BleSensorEventHandler<A> {
+    fn get_event_ids(&self) -> Vec<u16> {
+        Vec::new()
+    }
+
+    fn has_endpoint(&self) -> bool {
+        false
+    }
+
+    fn get_optional_event_ids(&self) -> Vec<u16> {
+        let mut event_ids = self.temperature_event_ids.clone();
+        event_ids.extend(BLE_HUMIDITY_EVENT_IDS);
+        event_ids.extend(BLE_MAGNET_EVENT_IDS);
+
+        return event_ids;
+    }
+
+    async fn send_event(
+        &self,
+        event_data: &BleSensorReadings,
+        truck_id: String,
+        _imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_ble_sensor_readings(&truck_id, event_data.clone())
+            .await
+    }
+
+    fn process_event_data(
+        &self,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
+        timestamp: i64,
+        _position: (f64, f64),
+        imei: &str,
+    ) -> Result<Option<BleSensorReadings>, EventDecodeError> {
+        let find_event = |id: u16| events.iter().find(|event| event.id == id);
+        let find_value =
+            |id: u16| find_event(id).map(|event| avl_event_io_value_to_u64(&event.value));
+        let find_measurement = |id: u16| {
+            find_event(id).filter(|event| {
+                !BLE_SENSOR_ERROR_VALUES.contains(&avl_event_io_value_to_u64(&event.value))
+            })
+        };
+        let readings = (0..BLE_SENSOR_SLOTS)
+            .map(|index| {
+                let slot = index as u8 + 1;
+                BleSensorReading {
+                    slot,
+                    sensor_mac: get_ble_sensor_mac(imei, slot),
+                    compartment: get_ble_sensor_compartment(imei, slot),
+                    temperature: self
+                        .temperature_event_ids
+                        .get(index)
+                        .and_then(|id| find_measurement(*id))
+                        .map(|event| {
+                            avl_event_io_value_to_i64(&event.value) as f64
+                                / self.temperature_divisor
+                        }),
+                    humidity: find_value(BLE_HUMIDITY_EVENT_IDS[index])
+                        .filter(|value| !BLE_SENSOR_ERROR_VALUES.contains(value))
+                        .map(|value| value as f64 / 10.0),
+                    door_open: find_value(BLE_MAGNET_EVENT_IDS[index]).map(|value| value == 0),
+                }
+            })
+            .filter(|reading| {
+                reading.temperature.is_some()
+                    || reading.humidity.is_some()
+                    || reading.door_open.is_some()
+            })
+            .collect::<Vec<BleSensorReading>>();
+        if readings.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(BleSensorReadings {
+            timestamp,
+            readings,
+        }))
+    }
+}
+
+impl Cacheable for BleSensorReadings {
+    const FILE_PATH: &'static str = "ble_sensor_readings_cache.json";
+
+    fn from_teltonika_record(_: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
+
+/// Parses settings of the BLE sensor slots of devices
+///
+/// Settings are separated by commas and consist of the IMEI of the device and the slot of the sensor separated by `:`,
+/// and the value of the setting separated by `=`, e.g. `352093081452251:1=front`.
+///
+/// # Arguments
+/// * `slot_settings` - Settings to parse
+/// * `parse_value` - Function validating and normalizing the value of a setting
+fn parse_ble_sensor_slot_settings(
+    slot_settings: &str,
+    parse_value: impl Fn(&str) -> Result<String, String>,
+) -> Result<SlotSettings, String> {
+    return slot_settings
+        .split(',')
+        .map(str::trim)
+        .filter(|slot_setting| !slot_setting.is_empty())
+        .map(|slot_setting| {
+            let (sensor, value) = slot_setting
+                .split_once('=')
+                .ok_or(format!("Missing value in [{}]", slot_setting))?;
+            let (imei, slot) = sensor
+                .split_once(':')
+                .ok_or(format!("Missing slot in [{}]", sensor))?;
+            let slot = slot
+                .parse::<u8>()
+                .ok()
+                .filter(|slot| (1..=BLE_SENSOR_SLOTS as u8).contains(slot))
+                .ok_or(format!("Invalid slot [{}], expected 1-4", slot))?;
+            Ok(((imei.to_string(), slot), parse_value(value)?))
+        })
+        .collect();
+}
+
+/// Parses the MAC addresses of the BLE sensors of devices
+///
+/// Sensors are separated by commas and consist of the IMEI of the device and the slot of the sensor separated by `:`,
+/// and the MAC address of the sensor separated by `=`, e.g. `352093081452251:1=7C:D9:F4:01:02:03`.
+///
+/// # Arguments
+/// * `sensor_macs` - MAC addresses of the sensors to parse
+pub fn parse_ble_sensor_macs(sensor_macs: &str) -> Result<SlotSettings, String> {
+    return parse_ble_sensor_slot_settings(sensor_macs, |mac| {
+        let is_valid_mac = mac.split(':').count() == 6
+            && mac
+                .split(':')
+                .all(|part| part.len() == 2 && u8::from_str_radix(part, 16).is_ok());
+        if !is_valid_mac {
+            return Err(format!("Invalid MAC address [{}]", mac));
+        }
+        Ok(mac.to_uppercase())
+    });
+}
+
+/// Parses the compartments of trailers measured by the BLE sensors of devices
+///
+/// Compartments are separated by commas and consist of the IMEI of the device and the slot of the sensor separated by
+/// `:`, and the name of the compartment separated by `=`, e.g. `352093081452251:1=front`.
+///
+/// # Arguments
+/// * `sensor_compartments` - Compartments of the sensors to parse
+pub fn parse_ble_sensor_compartments(sensor_compartments: &str) -> Result<SlotSettings, String> {
+    return parse_ble_sensor_slot_settings(sensor_compartments, |compartment| {
+        let compartment = compartment.trim();
+        if compartment.is_empty() {
+            return Err("Missing compartment name".to_string());
+        }
+        Ok(compartment.to_string())
+    });
+}
+
+/// Gets the MAC address of the BLE sensor of a device slot configured in `BLE_SENSOR_MACS`
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `slot` - Slot of the sensor
+fn get_ble_sensor_mac(imei: &str, slot: u8) -> Option<String> {
+    return get_config()
+        .tunables
+        .ble_sensor_macs
+        .get(&(imei.to_string(), slot))
+        .cloned();
+}
+
+/// Gets the compartment measured by the BLE sensor of a device slot configured in `BLE_SENSOR_COMPARTMENTS`
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `slot` - Slot of the sensor
+fn get_ble_sensor_compartment(imei: &str, slot: u8) -> Option<String> {
+    return get_config()
+        .tunables
+        .ble_sensor_compartments
+        .get(&(imei.to_string(), slot))
+        .cloned();
+}
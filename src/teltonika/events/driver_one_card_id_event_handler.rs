@@ -1,72 +1,98 @@
+use std::sync::Mutex;
+
+use log::debug;
 use nom_teltonika::AVLEventIO;
-use vehicle_management_service::{
-    apis::{
-        trucks_api::{CreateTruckDriverCardError, CreateTruckDriverCardParams},
-        Error,
-    },
-    models::TruckDriverCard,
-};
+use vehicle_management_service::models::TruckDriverCard;
 
 use crate::{
-    telematics_cache::Cacheable, teltonika::driver_card_events_to_truck_driver_card,
-    utils::get_vehicle_management_api_config,
+    telematics_cache::Cacheable,
+    teltonika::{
+        driver_card_events_to_truck_driver_card,
+        records::{RecordSubscription, RecordTrigger},
+        EventDecodeError, DRIVER_ONE_CARD_PRESENCE_EVENT_ID,
+    },
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError, VehicleApiErrorKind},
 };
 
 use super::teltonika_event_handlers::TeltonikaEventHandler;
 
-pub struct DriverOneCardIdEventHandler;
+/// Handler for driver one card ID events.
+///
+/// Keeps track of the last reported driver card so that the same card is not reported again
+/// until it has been removed from the tachograph.
+#[derive(Default)]
+pub struct DriverOneCardIdEventHandler<A = VehicleApi> {
+    api: A,
+    last_driver_card_id: Mutex<Option<String>>,
+}
+
+impl<A: TruckEventApi> DriverOneCardIdEventHandler<A> {
+    /// Creates a new [DriverOneCardIdEventHandler] sending the events with the given API.
+    #[cfg(test)]
+    pub fn with_api(api: A) -> Self {
+        DriverOneCardIdEventHandler {
+            api,
+            last_driver_card_id: Mutex::new(None),
+        }
+    }
+
+    /// Forgets the last reported driver card so that the next inserted card is reported again.
+    pub fn reset_last_driver_card_id(&self) {
+        *self.last_driver_card_id.lock().unwrap() = None;
+    }
+}
 
-impl TeltonikaEventHandler<TruckDriverCard, Error<CreateTruckDriverCardError>>
-    for DriverOneCardIdEventHandler
-{
+impl<A: TruckEventApi> TeltonikaEventHandler<TruckDriverCard> for DriverOneCardIdEventHandler<A> {
     fn get_event_ids(&self) -> Vec<u16> {
         vec![195, 196]
     }
 
-    fn get_trigger_event_id(&self) -> Option<u16> {
-        Some(187)
+    fn get_record_subscription(&self) -> RecordSubscription {
+        RecordSubscription::EventualOf(DRIVER_ONE_CARD_PRESENCE_EVENT_ID)
     }
 
     async fn send_event(
         &self,
         event_data: &TruckDriverCard,
         truck_id: String,
-    ) -> Result<(), Error<CreateTruckDriverCardError>> {
-        let res = vehicle_management_service::apis::trucks_api::create_truck_driver_card(
-            &get_vehicle_management_api_config(),
-            CreateTruckDriverCardParams {
-                truck_id: truck_id.clone(),
-                truck_driver_card: event_data.clone(),
-            },
-        )
-        .await;
-        match res {
-            Ok(_) => Ok(()),
-            Err(error) => match &error {
-                // API returns a 409 if the truck already has a driver card. At least for now, swallow them silently and continue.
-                Error::ResponseError(err) => {
-                    if err.status.as_u16() == reqwest::StatusCode::CONFLICT {
-                        return Ok(());
-                    } else {
-                        return Err(error);
-                    }
-                }
-                _ => Err(error),
-            },
+        imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        match self
+            .api
+            .create_truck_driver_card(&truck_id, imei, event_data.clone())
+            .await
+        {
+            // API returns a 409 if the truck already has a driver card. At least for now, swallow them silently and continue.
+            Err(err) if err.kind == VehicleApiErrorKind::Conflict => Ok(()),
+            result => result,
         }
     }
 
     fn process_event_data(
         &self,
-        trigger_event_id: u16,
-        events: &Vec<&AVLEventIO>,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
         timestamp: i64,
-        _: &str,
+        _position: (f64, f64),
+        _imei: &str,
+    ) -> Result<Option<TruckDriverCard>, EventDecodeError> {
+        driver_card_events_to_truck_driver_card(timestamp, events)
+    }
+
+    fn filter_event_data(
+        &self,
+        driver_card: TruckDriverCard,
+        imei: &str,
     ) -> Option<TruckDriverCard> {
-        match trigger_event_id {
-            187 => driver_card_events_to_truck_driver_card(timestamp, events),
-            _ => None,
+        let mut last_driver_card_id = self.last_driver_card_id.lock().unwrap();
+        if last_driver_card_id.as_ref() == Some(&driver_card.id) {
+            debug!(target: imei, "Driver card [{}] has already been reported", driver_card.id);
+
+            return None;
         }
+        *last_driver_card_id = Some(driver_card.id.clone());
+
+        Some(driver_card)
     }
 }
 
@@ -1,25 +1,34 @@
 use log::debug;
 use nom_teltonika::{AVLEventIO, AVLRecord};
-use vehicle_management_service::{
-    apis::{
-        trucks_api::{CreateDriveStateError, CreateDriveStateParams},
-        Error,
-    },
-    models::{TruckDriveState, TruckDriveStateEnum},
-};
+use vehicle_management_service::models::{TruckDriveState, TruckDriveStateEnum};
 
 use crate::{
     telematics_cache::Cacheable,
-    teltonika::{driver_card_events_to_truck_driver_card, FromAVLEventIoValue},
-    utils::get_vehicle_management_api_config,
+    teltonika::{
+        driver_card_events_to_truck_driver_card, records::RecordTrigger, EventDecodeError,
+        FromAVLEventIoValue,
+    },
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
 };
 
 use super::teltonika_event_handlers::TeltonikaEventHandler;
 
-pub struct DriverOneDriveStateEventHandler;
+/// Handler for driver one drive state events.
+#[derive(Default)]
+pub struct DriverOneDriveStateEventHandler<A = VehicleApi> {
+    api: A,
+}
 
-impl TeltonikaEventHandler<TruckDriveState, Error<CreateDriveStateError>>
-    for DriverOneDriveStateEventHandler
+impl<A: TruckEventApi> DriverOneDriveStateEventHandler<A> {
+    /// Creates a new [DriverOneDriveStateEventHandler] sending the events with the given API.
+    #[cfg(test)]
+    pub fn with_api(api: A) -> Self {
+        DriverOneDriveStateEventHandler { api }
+    }
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<TruckDriveState>
+    for DriverOneDriveStateEventHandler<A>
 {
     fn get_event_ids(&self) -> Vec<u16> {
         vec![184, 195, 196]
@@ -29,41 +38,37 @@ impl TeltonikaEventHandler<TruckDriveState, Error<CreateDriveStateError>>
         &self,
         event_data: &TruckDriveState,
         truck_id: String,
-    ) -> Result<(), Error<CreateDriveStateError>> {
-        vehicle_management_service::apis::trucks_api::create_drive_state(
-            &get_vehicle_management_api_config(),
-            CreateDriveStateParams {
-                truck_id: truck_id.clone(),
-                truck_drive_state: event_data.clone(),
-            },
-        )
-        .await
+        imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_drive_state(&truck_id, imei, event_data.clone())
+            .await
     }
 
     fn process_event_data(
         &self,
-        _trigger_event_id: u16,
-        events: &Vec<&AVLEventIO>,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
         timestamp: i64,
+        _position: (f64, f64),
         imei: &str,
-    ) -> Option<TruckDriveState> {
-        let Some(driver_card) = driver_card_events_to_truck_driver_card(timestamp, events) else {
+    ) -> Result<Option<TruckDriveState>, EventDecodeError> {
+        let Some(driver_card) = driver_card_events_to_truck_driver_card(timestamp, events)? else {
             debug!(target: imei, "Driver card MSB or LSB was 0");
 
-            return None;
+            return Ok(None);
+        };
+        let Some(state_event) = events.iter().find(|event| event.id == 184) else {
+            return Ok(None);
         };
-        let state_event = events
-            .iter()
-            .find(|event| event.id == 184)
-            .expect("Driver one drive state event not found");
         let state = TruckDriveStateEnum::from_avl_event_io_value(&state_event.value);
-        Some(TruckDriveState {
+        Ok(Some(TruckDriveState {
             id: None,
             timestamp,
             state,
             driver_id: None,
             driver_card_id: Some(driver_card.id),
-        })
+        }))
     }
 }
 
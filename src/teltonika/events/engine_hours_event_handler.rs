@@ -0,0 +1,102 @@
+use std::sync::Mutex;
+
+use log::debug;
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::{Deserialize, Serialize};
+
+use super::teltonika_event_handlers::TeltonikaEventHandler;
+use crate::{
+    telematics_cache::Cacheable,
+    teltonika::{avl_event_io_value_to_u64, records::RecordTrigger, EventDecodeError},
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
+};
+
+/// The event ID for the total engine work time read from the CAN bus, in minutes
+const ENGINE_WORKTIME_EVENT_ID: u16 = 102;
+
+/// Total engine hours of a truck
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TruckEngineHours {
+    pub timestamp: i64,
+    /// Total engine hours in hours
+    pub engine_hours: f64,
+}
+
+/// Handler for engine work time events read from the CAN bus.
+///
+/// The engine work time is sent in nearly every record but changes only once a minute while the engine is running,
+/// so the handler keeps track of the last reported engine hours and skips unchanged values.
+///
+/// Vehicle Management Service has no endpoint for engine hours yet, so the handler is registered only with the
+/// `pending-endpoints` feature.
+#[derive(Default)]
+pub struct EngineHoursEventHandler<A = VehicleApi> {
+    api: A,
+    last_engine_hours: Mutex<Option<f64>>,
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<TruckEngineHours> for EngineHoursEventHandler<A> {
+    fn get_event_ids(&self) -> Vec<u16> {
+        vec![ENGINE_WORKTIME_EVENT_ID]
+    }
+
+    fn has_endpoint(&self) -> bool {
+        false
+    }
+
+    async fn send_event(
+        &self,
+        event_data: &TruckEngineHours,
+        truck_id: String,
+        _imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_truck_engine_hours(&truck_id, event_data.clone())
+            .await
+    }
+
+    fn process_event_data(
+        &self,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
+        timestamp: i64,
+        _position: (f64, f64),
+        _imei: &str,
+    ) -> Result<Option<TruckEngineHours>, EventDecodeError> {
+        let Some(event) = events.first() else {
+            return Ok(None);
+        };
+        Ok(Some(TruckEngineHours {
+            timestamp,
+            engine_hours: avl_event_io_value_to_u64(&event.value) as f64 / 60.0,
+        }))
+    }
+
+    fn filter_event_data(
+        &self,
+        engine_hours: TruckEngineHours,
+        imei: &str,
+    ) -> Option<TruckEngineHours> {
+        let mut last_engine_hours = self.last_engine_hours.lock().unwrap();
+        if *last_engine_hours == Some(engine_hours.engine_hours) {
+            debug!(target: imei,
+                "Engine hours {:.2} have already been reported",
+                engine_hours.engine_hours
+            );
+
+            return None;
+        }
+        *last_engine_hours = Some(engine_hours.engine_hours);
+
+        Some(engine_hours)
+    }
+}
+
+impl Cacheable for TruckEngineHours {
+    const FILE_PATH: &'static str = "truck_engine_hours_cache.json";
+
+    fn from_teltonika_record(_: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
@@ -0,0 +1,82 @@
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::{Deserialize, Serialize};
+
+use super::teltonika_event_handlers::TeltonikaEventHandler;
+use crate::{
+    telematics_cache::Cacheable,
+    teltonika::{avl_event_io_value_to_u64, records::RecordTrigger, EventDecodeError},
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
+};
+
+/// The event ID for the engine speed read from the CAN bus, in RPM
+const ENGINE_RPM_EVENT_ID: u16 = 85;
+
+/// Engine speed of a truck
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TruckEngineRpm {
+    pub timestamp: i64,
+    /// Engine speed in revolutions per minute
+    pub rpm: u32,
+}
+
+/// Handler for engine speed events read from the CAN bus.
+///
+/// Engine speeds are sent for nearly every record like speeds, so they may be dropped when the receiver is overloaded.
+///
+/// Vehicle Management Service has no endpoint for engine speeds yet, so the handler is registered only with the
+/// `pending-endpoints` feature.
+#[derive(Default)]
+pub struct EngineRpmEventHandler<A = VehicleApi> {
+    api: A,
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<TruckEngineRpm> for EngineRpmEventHandler<A> {
+    fn get_event_ids(&self) -> Vec<u16> {
+        vec![ENGINE_RPM_EVENT_ID]
+    }
+
+    fn has_endpoint(&self) -> bool {
+        false
+    }
+
+    fn is_sheddable(&self) -> bool {
+        true
+    }
+
+    async fn send_event(
+        &self,
+        event_data: &TruckEngineRpm,
+        truck_id: String,
+        _imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_truck_engine_rpm(&truck_id, event_data.clone())
+            .await
+    }
+
+    fn process_event_data(
+        &self,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
+        timestamp: i64,
+        _position: (f64, f64),
+        _imei: &str,
+    ) -> Result<Option<TruckEngineRpm>, EventDecodeError> {
+        let Some(event) = events.first() else {
+            return Ok(None);
+        };
+        Ok(Some(TruckEngineRpm {
+            timestamp,
+            rpm: avl_event_io_value_to_u64(&event.value) as u32,
+        }))
+    }
+}
+
+impl Cacheable for TruckEngineRpm {
+    const FILE_PATH: &'static str = "truck_engine_rpm_cache.json";
+
+    fn from_teltonika_record(_: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
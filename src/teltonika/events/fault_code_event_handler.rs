@@ -0,0 +1,151 @@
+use std::sync::Mutex;
+
+use log::debug;
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::{Deserialize, Serialize};
+
+use super::teltonika_event_handlers::TeltonikaEventHandler;
+use crate::{
+    telematics_cache::Cacheable,
+    teltonika::{
+        avl_event_io_value_to_be_bytes, avl_event_io_value_to_u64, records::RecordTrigger,
+        EventDecodeError,
+    },
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
+};
+
+/// The event ID for the number of active diagnostic trouble codes
+const DTC_COUNT_EVENT_ID: u16 = 30;
+/// The event ID for the active diagnostic trouble codes, as comma-separated text
+const FAULT_CODES_EVENT_ID: u16 = 281;
+
+/// Fault record of a truck, listing the diagnostic trouble codes active at the time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultRecord {
+    pub timestamp: i64,
+    /// Number of active diagnostic trouble codes
+    pub active_count: u64,
+    /// Active diagnostic trouble codes, e.g. `P0420`, if sent by the device
+    pub codes: Vec<String>,
+}
+
+/// Handler for diagnostic trouble code events read from the CAN bus.
+///
+/// The number of active codes is sent in nearly every record, so the handler keeps track of the last reported fault
+/// record and only reports changes, including all codes being cleared.
+///
+/// Vehicle Management Service has no endpoint for fault records yet, so the handler is registered only with the
+/// `pending-endpoints` feature.
+#[derive(Default)]
+pub struct FaultCodeEventHandler<A = VehicleApi> {
+    api: A,
+    last_fault_record: Mutex<Option<(u64, Vec<String>)>>,
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<FaultRecord> for FaultCodeEventHandler<A> {
+    fn get_event_ids(&self) -> Vec<u16> {
+        vec![DTC_COUNT_EVENT_ID]
+    }
+
+    fn has_endpoint(&self) -> bool {
+        false
+    }
+
+    fn get_optional_event_ids(&self) -> Vec<u16> {
+        vec![FAULT_CODES_EVENT_ID]
+    }
+
+    async fn send_event(
+        &self,
+        event_data: &FaultRecord,
+        truck_id: String,
+        _imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_fault_record(&truck_id, event_data.clone())
+            .await
+    }
+
+    fn process_event_data(
+        &self,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
+        timestamp: i64,
+        _position: (f64, f64),
+        _imei: &str,
+    ) -> Result<Option<FaultRecord>, EventDecodeError> {
+        let find_event = |id: u16| events.iter().find(|event| event.id == id);
+        let Some(count_event) = find_event(DTC_COUNT_EVENT_ID) else {
+            return Ok(None);
+        };
+        let codes = match find_event(FAULT_CODES_EVENT_ID) {
+            Some(codes_event) => parse_fault_codes(codes_event)?,
+            None => Vec::new(),
+        };
+
+        Ok(Some(FaultRecord {
+            timestamp,
+            active_count: avl_event_io_value_to_u64(&count_event.value),
+            codes,
+        }))
+    }
+
+    fn filter_event_data(&self, fault_record: FaultRecord, imei: &str) -> Option<FaultRecord> {
+        let mut last_fault_record = self.last_fault_record.lock().unwrap();
+        let fault_codes = (fault_record.active_count, fault_record.codes.clone());
+        if last_fault_record.as_ref() == Some(&fault_codes) {
+            debug!(target: imei,
+                "Fault codes [{}] have already been reported",
+                fault_record.codes.join(", ")
+            );
+
+            return None;
+        }
+        *last_fault_record = Some(fault_codes);
+
+        Some(fault_record)
+    }
+}
+
+impl Cacheable for FaultRecord {
+    const FILE_PATH: &'static str = "fault_record_cache.json";
+
+    fn from_teltonika_record(_: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
+
+/// Parses the diagnostic trouble codes of a fault codes event
+///
+/// Codes consist of a letter of the system (P, C, B or U) and four hexadecimal digits, separated by commas or whitespace.
+///
+/// # Arguments
+/// * `event` - Fault codes event
+fn parse_fault_codes(event: &AVLEventIO) -> Result<Vec<String>, EventDecodeError> {
+    let raw_bytes = avl_event_io_value_to_be_bytes(&event.value);
+    let decode_error = |reason: String| EventDecodeError {
+        event_id: event.id,
+        raw_bytes: raw_bytes.clone(),
+        reason,
+    };
+    let text = std::str::from_utf8(&raw_bytes)
+        .map_err(|_| decode_error("Fault codes are not valid text".to_string()))?;
+
+    return text
+        .split(|character: char| character == ',' || character.is_whitespace())
+        .filter(|code| !code.is_empty())
+        .map(|code| {
+            let code = code.to_uppercase();
+            let is_valid = code.len() == 5
+                && code.starts_with(['P', 'C', 'B', 'U'])
+                && code[1..]
+                    .chars()
+                    .all(|character| character.is_ascii_hexdigit());
+            if !is_valid {
+                return Err(decode_error(format!("Invalid fault code {}", code)));
+            }
+            Ok(code)
+        })
+        .collect();
+}
@@ -0,0 +1,141 @@
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::{Deserialize, Serialize};
+
+use super::teltonika_event_handlers::TeltonikaEventHandler;
+use crate::{
+    telematics_cache::Cacheable,
+    teltonika::{
+        avl_event_io_value_to_be_bytes, avl_event_io_value_to_u64,
+        records::{RecordSubscription, RecordTrigger},
+        EventDecodeError,
+    },
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
+};
+
+/// IDs of the events of geozones 1-5
+const GEOZONE_EVENT_IDS: [u16; 5] = [155, 156, 157, 158, 159];
+
+/// Direction of a truck crossing the border of a zone
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ZoneDirection {
+    Enter,
+    Exit,
+}
+
+impl ZoneDirection {
+    /// Gets the name of the direction used in logs and metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ZoneDirection::Enter => "enter",
+            ZoneDirection::Exit => "exit",
+        }
+    }
+}
+
+/// Event of a truck entering or exiting a zone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneEvent {
+    pub timestamp: i64,
+    /// Number of the geozone in the device configuration
+    pub zone_id: u8,
+    pub direction: ZoneDirection,
+    /// Latitude of the truck when crossing the border in degrees
+    pub latitude: f64,
+    /// Longitude of the truck when crossing the border in degrees
+    pub longitude: f64,
+}
+
+/// Handler for geofence events of the geozones configured on the devices.
+///
+/// Devices generate an eventual record when entering or exiting a geozone. Only the geozone triggering the record is
+/// reported, as the other geozones may be included in the record with their current state.
+///
+/// Vehicle Management Service has no endpoint for geofence events yet, so the handler is registered only with the
+/// `pending-endpoints` feature.
+///
+/// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/FMB_Geofencing) for more detailed information.
+#[derive(Default)]
+pub struct GeofenceEventHandler<A = VehicleApi> {
+    api: A,
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<ZoneEvent> for GeofenceEventHandler<A> {
+    fn get_event_ids(&self) -> Vec<u16> {
+        Vec::new()
+    }
+
+    fn has_endpoint(&self) -> bool {
+        false
+    }
+
+    fn get_optional_event_ids(&self) -> Vec<u16> {
+        GEOZONE_EVENT_IDS.to_vec()
+    }
+
+    fn get_record_subscription(&self) -> RecordSubscription {
+        RecordSubscription::Eventual
+    }
+
+    async fn send_event(
+        &self,
+        event_data: &ZoneEvent,
+        truck_id: String,
+        _imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_zone_event(&truck_id, event_data.clone())
+            .await
+    }
+
+    fn process_event_data(
+        &self,
+        trigger: RecordTrigger,
+        events: &[&AVLEventIO],
+        timestamp: i64,
+        position: (f64, f64),
+        _imei: &str,
+    ) -> Result<Option<ZoneEvent>, EventDecodeError> {
+        let RecordTrigger::Eventual(trigger_event_id) = trigger else {
+            return Ok(None);
+        };
+        let Some(zone_index) = GEOZONE_EVENT_IDS
+            .iter()
+            .position(|id| *id == trigger_event_id)
+        else {
+            return Ok(None);
+        };
+        let Some(event) = events.iter().find(|event| event.id == trigger_event_id) else {
+            return Ok(None);
+        };
+        let direction = match avl_event_io_value_to_u64(&event.value) {
+            0 => ZoneDirection::Exit,
+            1 => ZoneDirection::Enter,
+            _ => {
+                return Err(EventDecodeError {
+                    event_id: event.id,
+                    raw_bytes: avl_event_io_value_to_be_bytes(&event.value),
+                    reason: "Unknown geozone direction".to_string(),
+                })
+            }
+        };
+        let (latitude, longitude) = position;
+
+        Ok(Some(ZoneEvent {
+            timestamp,
+            zone_id: zone_index as u8 + 1,
+            direction,
+            latitude,
+            longitude,
+        }))
+    }
+}
+
+impl Cacheable for ZoneEvent {
+    const FILE_PATH: &'static str = "zone_event_cache.json";
+
+    fn from_teltonika_record(_: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
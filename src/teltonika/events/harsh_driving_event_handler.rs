@@ -0,0 +1,171 @@
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::{Deserialize, Serialize};
+
+use super::teltonika_event_handlers::TeltonikaEventHandler;
+use crate::{
+    telematics_cache::Cacheable,
+    teltonika::{
+        avl_event_io_value_to_be_bytes, avl_event_io_value_to_u64,
+        records::{RecordSubscription, RecordTrigger},
+        EventDecodeError,
+    },
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
+};
+
+/// ID of the green driving type event
+const GREEN_DRIVING_TYPE_EVENT_ID: u16 = 253;
+/// ID of the green driving value event
+const GREEN_DRIVING_VALUE_EVENT_ID: u16 = 254;
+/// ID of the green driving event duration event
+const GREEN_DRIVING_DURATION_EVENT_ID: u16 = 243;
+/// IDs of the accelerometer axis X, Y and Z events
+const AXIS_EVENT_IDS: [u16; 3] = [17, 18, 19];
+
+/// Type of a driver behavior event
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DriverBehaviorEventType {
+    HarshAcceleration,
+    HarshBraking,
+    HarshCornering,
+}
+
+impl DriverBehaviorEventType {
+    /// Gets the name of the event type used in logs and metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DriverBehaviorEventType::HarshAcceleration => "harsh_acceleration",
+            DriverBehaviorEventType::HarshBraking => "harsh_braking",
+            DriverBehaviorEventType::HarshCornering => "harsh_cornering",
+        }
+    }
+}
+
+/// Driver behavior event of a truck, such as harsh braking
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriverBehaviorEvent {
+    pub timestamp: i64,
+    #[serde(rename = "type")]
+    pub event_type: DriverBehaviorEventType,
+    /// Peak acceleration of the event in g
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// Duration of the event in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    /// Acceleration along the X axis of the device in mG
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub axis_x: Option<i16>,
+    /// Acceleration along the Y axis of the device in mG
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub axis_y: Option<i16>,
+    /// Acceleration along the Z axis of the device in mG
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub axis_z: Option<i16>,
+}
+
+/// Handler for green driving events describing harsh acceleration, braking and cornering.
+///
+/// Vehicle Management Service has no endpoint for driver behavior events yet, so the handler is registered only with the
+/// `pending-endpoints` feature.
+///
+/// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/FMB_Green_Driving) for more detailed information.
+#[derive(Default)]
+pub struct HarshDrivingEventHandler<A = VehicleApi> {
+    api: A,
+}
+
+impl<A: TruckEventApi> HarshDrivingEventHandler<A> {
+    /// Creates a new [HarshDrivingEventHandler] sending the events with the given API.
+    #[cfg(test)]
+    pub fn with_api(api: A) -> Self {
+        HarshDrivingEventHandler { api }
+    }
+}
+
+impl<A: TruckEventApi> TeltonikaEventHandler<DriverBehaviorEvent> for HarshDrivingEventHandler<A> {
+    fn get_event_ids(&self) -> Vec<u16> {
+        vec![GREEN_DRIVING_TYPE_EVENT_ID]
+    }
+
+    fn get_optional_event_ids(&self) -> Vec<u16> {
+        let mut event_ids = vec![
+            GREEN_DRIVING_VALUE_EVENT_ID,
+            GREEN_DRIVING_DURATION_EVENT_ID,
+        ];
+        event_ids.extend(AXIS_EVENT_IDS);
+
+        return event_ids;
+    }
+
+    fn get_record_subscription(&self) -> RecordSubscription {
+        RecordSubscription::EventualOf(GREEN_DRIVING_TYPE_EVENT_ID)
+    }
+
+    fn has_endpoint(&self) -> bool {
+        false
+    }
+
+    async fn send_event(
+        &self,
+        event_data: &DriverBehaviorEvent,
+        truck_id: String,
+        _imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_driver_behavior_event(&truck_id, event_data.clone())
+            .await
+    }
+
+    fn process_event_data(
+        &self,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
+        timestamp: i64,
+        _position: (f64, f64),
+        _imei: &str,
+    ) -> Result<Option<DriverBehaviorEvent>, EventDecodeError> {
+        let find_event = |id: u16| events.iter().find(|event| event.id == id);
+        let Some(type_event) = find_event(GREEN_DRIVING_TYPE_EVENT_ID) else {
+            return Ok(None);
+        };
+        let event_type = match avl_event_io_value_to_u64(&type_event.value) {
+            1 => DriverBehaviorEventType::HarshAcceleration,
+            2 => DriverBehaviorEventType::HarshBraking,
+            3 => DriverBehaviorEventType::HarshCornering,
+            _ => {
+                return Err(EventDecodeError {
+                    event_id: type_event.id,
+                    raw_bytes: avl_event_io_value_to_be_bytes(&type_event.value),
+                    reason: "Unknown green driving type".to_string(),
+                })
+            }
+        };
+        // Axis values are signed 16-bit integers sent as unsigned values
+        let [axis_x, axis_y, axis_z] = AXIS_EVENT_IDS.map(|id| {
+            find_event(id).map(|event| avl_event_io_value_to_u64(&event.value) as u16 as i16)
+        });
+
+        Ok(Some(DriverBehaviorEvent {
+            timestamp,
+            event_type,
+            value: find_event(GREEN_DRIVING_VALUE_EVENT_ID)
+                .map(|event| avl_event_io_value_to_u64(&event.value) as f64 / 100.0),
+            duration: find_event(GREEN_DRIVING_DURATION_EVENT_ID)
+                .map(|event| avl_event_io_value_to_u64(&event.value)),
+            axis_x,
+            axis_y,
+            axis_z,
+        }))
+    }
+}
+
+impl Cacheable for DriverBehaviorEvent {
+    const FILE_PATH: &'static str = "driver_behavior_event_cache.json";
+
+    fn from_teltonika_record(_: &AVLRecord) -> Option<Self> {
+        None
+    }
+}
@@ -1,9 +1,25 @@
+pub mod axle_weight_event_handler;
+pub mod ble_sensor_event_handler;
 pub mod driver_one_card_id_event_handler;
 pub mod driver_one_drive_state_event_handler;
+pub mod engine_hours_event_handler;
+pub mod engine_rpm_event_handler;
+pub mod fault_code_event_handler;
+pub mod geofence_event_handler;
+pub mod harsh_driving_event_handler;
 pub mod speed_event_handler;
 pub mod teltonika_event_handlers;
+pub mod vin_event_handler;
 
+pub use axle_weight_event_handler::AxleWeightEventHandler;
+pub use ble_sensor_event_handler::BleSensorEventHandler;
 pub use driver_one_card_id_event_handler::DriverOneCardIdEventHandler;
 pub use driver_one_drive_state_event_handler::DriverOneDriveStateEventHandler;
+pub use engine_hours_event_handler::EngineHoursEventHandler;
+pub use engine_rpm_event_handler::EngineRpmEventHandler;
+pub use fault_code_event_handler::FaultCodeEventHandler;
+pub use geofence_event_handler::GeofenceEventHandler;
+pub use harsh_driving_event_handler::HarshDrivingEventHandler;
 pub use speed_event_handler::SpeedEventHandler;
 pub use teltonika_event_handlers::TeltonikaEventHandlers;
+pub use vin_event_handler::VinEventHandler;
@@ -1,53 +1,139 @@
 use nom_teltonika::AVLEventIO;
-use vehicle_management_service::{
-    apis::{
-        trucks_api::{CreateTruckSpeedError, CreateTruckSpeedParams},
-        Error,
-    },
-    models::TruckSpeed,
-};
+use vehicle_management_service::models::TruckSpeed;
 
 use super::teltonika_event_handlers::TeltonikaEventHandler;
 use crate::{
-    telematics_cache::Cacheable, teltonika::avl_event_io_value_to_u64,
-    utils::get_vehicle_management_api_config,
+    batching::EventBatch,
+    config::get_config,
+    telematics_cache::Cacheable,
+    teltonika::{
+        avl_event_io_value_to_u64, device_family::DeviceFamily, records::RecordTrigger,
+        EventDecodeError,
+    },
+    utils::api::{TruckEventApi, VehicleApi, VehicleApiError},
 };
 
-pub struct SpeedEventHandler;
+/// The event ID for the speed calculated from the GNSS fixes of the device
+const GNSS_SPEED_EVENT_ID: u16 = 24;
+
+/// Handler for speed events.
+///
+/// Speeds are sent in batches if configured with `EVENT_BATCH_WINDOW_SECONDS`.
+/// The wheel-based speed is read from the event of the device family, i.e. the tachograph or the CAN bus.
+/// With `GNSS_SPEED_FALLBACK` enabled, the GNSS speed is sent for records without a wheel-based speed.
+pub struct SpeedEventHandler<A = VehicleApi> {
+    api: A,
+    batch: Option<EventBatch<TruckSpeed>>,
+    event_id: u16,
+    gnss_fallback: bool,
+}
+
+impl<A: Default> SpeedEventHandler<A> {
+    /// Creates a new [SpeedEventHandler] for the speed event of the given device family.
+    ///
+    /// # Arguments
+    /// * `device_family` - Family of the device
+    pub fn new(device_family: DeviceFamily) -> Self {
+        SpeedEventHandler {
+            api: A::default(),
+            batch: EventBatch::from_config(),
+            event_id: device_family.get_io_mapping().vehicle_speed,
+            gnss_fallback: get_config().tunables.gnss_speed_fallback,
+        }
+    }
+}
+
+impl<A: Default> Default for SpeedEventHandler<A> {
+    fn default() -> Self {
+        SpeedEventHandler::new(DeviceFamily::default())
+    }
+}
+
+impl<A: TruckEventApi> SpeedEventHandler<A> {
+    /// Creates a new [SpeedEventHandler] sending the events with the given API.
+    #[cfg(test)]
+    pub fn with_api(api: A, batch: Option<EventBatch<TruckSpeed>>) -> Self {
+        SpeedEventHandler {
+            api,
+            batch,
+            event_id: DeviceFamily::default().get_io_mapping().vehicle_speed,
+            gnss_fallback: false,
+        }
+    }
+
+    /// Sets whether the GNSS speed is sent for records without a wheel-based speed.
+    #[cfg(test)]
+    pub fn set_gnss_fallback(&mut self, gnss_fallback: bool) {
+        self.gnss_fallback = gnss_fallback;
+    }
+}
 
-impl TeltonikaEventHandler<TruckSpeed, Error<CreateTruckSpeedError>> for SpeedEventHandler {
+impl<A: TruckEventApi> TeltonikaEventHandler<TruckSpeed> for SpeedEventHandler<A> {
     fn get_event_ids(&self) -> Vec<u16> {
-        vec![191]
+        if self.gnss_fallback {
+            return Vec::new();
+        }
+
+        return vec![self.event_id];
+    }
+
+    fn get_optional_event_ids(&self) -> Vec<u16> {
+        if self.gnss_fallback {
+            return vec![self.event_id, GNSS_SPEED_EVENT_ID];
+        }
+
+        return Vec::new();
+    }
+
+    fn is_sheddable(&self) -> bool {
+        true
+    }
+
+    fn get_batch(&self) -> Option<&EventBatch<TruckSpeed>> {
+        self.batch.as_ref()
     }
 
     async fn send_event(
         &self,
         event_data: &TruckSpeed,
         truck_id: String,
-    ) -> Result<(), Error<CreateTruckSpeedError>> {
-        vehicle_management_service::apis::trucks_api::create_truck_speed(
-            &get_vehicle_management_api_config(),
-            CreateTruckSpeedParams {
-                truck_id,
-                truck_speed: event_data.clone(),
-            },
-        )
-        .await
+        imei: &str,
+    ) -> Result<(), VehicleApiError> {
+        self.api
+            .create_truck_speed(&truck_id, imei, event_data.clone())
+            .await
+    }
+
+    async fn send_events(
+        &self,
+        events: &[TruckSpeed],
+        truck_id: String,
+        imei: &str,
+    ) -> Vec<Result<(), VehicleApiError>> {
+        self.api
+            .create_truck_speeds(&truck_id, imei, events.to_vec())
+            .await
     }
 
     fn process_event_data(
         &self,
-        _trigger_event_id: u16,
-        events: &Vec<&AVLEventIO>,
+        _trigger: RecordTrigger,
+        events: &[&AVLEventIO],
         timestamp: i64,
+        _position: (f64, f64),
         _imei: &str,
-    ) -> Option<TruckSpeed> {
-        let event = events.first().expect("Received empty speed event");
-        Some(TruckSpeed {
+    ) -> Result<Option<TruckSpeed>, EventDecodeError> {
+        // Wheel-based speed is preferred over the less accurate GNSS speed
+        let find_event = |id: u16| events.iter().find(|event| event.id == id);
+        let Some(event) = find_event(self.event_id).or_else(|| find_event(GNSS_SPEED_EVENT_ID))
+        else {
+            return Ok(None);
+        };
+        Ok(Some(TruckSpeed {
             id: None,
             speed: avl_event_io_value_to_u64(&event.value) as f32,
             timestamp,
-        })
+        }))
     }
 }
 
@@ -1,15 +1,33 @@
 use super::{
-    driver_one_card_id_event_handler, driver_one_drive_state_event_handler, speed_event_handler,
+    axle_weight_event_handler, ble_sensor_event_handler, driver_one_card_id_event_handler,
+    driver_one_drive_state_event_handler, engine_hours_event_handler, engine_rpm_event_handler,
+    fault_code_event_handler, geofence_event_handler, harsh_driving_event_handler,
+    speed_event_handler,
+};
+use crate::{
+    batching::{self, EventBatch},
+    telematics_cache::{
+        cached_event::CachedEvent, failed_api_request::FailedApiRequest, failed_event::FailedEvent,
+        get_cache_lock, restore_cache, take_cache, Cacheable,
+    },
+    teltonika::{
+        records::{FrameProvenance, RecordSubscription, RecordTrigger},
+        EventDecodeError,
+    },
+    utils::{
+        api::{VehicleApiError, VehicleApiErrorKind},
+        log_throttle,
+    },
 };
-use crate::telematics_cache::Cacheable;
 use log::{debug, error};
 use nom_teltonika::AVLEventIO;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, path::Path};
+use std::{fmt::Debug, path::Path, time::Instant};
 
 /// Enumeration for Teltonika event handlers.
 ///
 /// This enumeration is used to store the different Teltonika event handlers and allow inheritance-like behavior.
+#[allow(clippy::enum_variant_names)]
 pub enum TeltonikaEventHandlers {
     SpeedEventHandler((speed_event_handler::SpeedEventHandler, String)),
     DriverOneCardIdEventHandler(
@@ -24,6 +42,18 @@ pub enum TeltonikaEventHandlers {
             String,
         ),
     ),
+    HarshDrivingEventHandler(
+        (
+            harsh_driving_event_handler::HarshDrivingEventHandler,
+            String,
+        ),
+    ),
+    BleSensorEventHandler((ble_sensor_event_handler::BleSensorEventHandler, String)),
+    AxleWeightEventHandler((axle_weight_event_handler::AxleWeightEventHandler, String)),
+    EngineHoursEventHandler((engine_hours_event_handler::EngineHoursEventHandler, String)),
+    EngineRpmEventHandler((engine_rpm_event_handler::EngineRpmEventHandler, String)),
+    FaultCodeEventHandler((fault_code_event_handler::FaultCodeEventHandler, String)),
+    GeofenceEventHandler((geofence_event_handler::GeofenceEventHandler, String)),
 }
 
 impl TeltonikaEventHandlers {
@@ -37,73 +67,398 @@ impl TeltonikaEventHandlers {
             TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, _)) => {
                 handler.get_event_ids()
             }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, _)) => {
+                handler.get_event_ids()
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, _)) => handler.get_event_ids(),
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, _)) => handler.get_event_ids(),
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, _)) => {
+                handler.get_event_ids()
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, _)) => handler.get_event_ids(),
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, _)) => handler.get_event_ids(),
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, _)) => handler.get_event_ids(),
+        }
+    }
+
+    /// Gets the IDs of the events passed to the handler when present.
+    pub fn get_optional_event_ids(&self) -> Vec<u16> {
+        match self {
+            TeltonikaEventHandlers::SpeedEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, _)) => {
+                handler.get_optional_event_ids()
+            }
+        }
+    }
+
+    /// Gets the records the handler opts into.
+    pub fn get_record_subscription(&self) -> RecordSubscription {
+        match self {
+            TeltonikaEventHandlers::SpeedEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, _)) => {
+                handler.get_record_subscription()
+            }
         }
     }
 
-    /// Gets the trigger event ID for the handler.
-    pub fn get_trigger_event_id(&self) -> Option<u16> {
+    /// Checks whether the events of the handler may be dropped when the receiver is overloaded.
+    pub fn is_sheddable(&self) -> bool {
+        match self {
+            TeltonikaEventHandlers::SpeedEventHandler((handler, _)) => handler.is_sheddable(),
+            TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, _)) => {
+                handler.is_sheddable()
+            }
+            TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, _)) => {
+                handler.is_sheddable()
+            }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, _)) => {
+                handler.is_sheddable()
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, _)) => handler.is_sheddable(),
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, _)) => handler.is_sheddable(),
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, _)) => handler.is_sheddable(),
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, _)) => handler.is_sheddable(),
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, _)) => handler.is_sheddable(),
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, _)) => handler.is_sheddable(),
+        }
+    }
+
+    /// Gets the file path of the cache used by the handler.
+    pub fn get_cache_file_path(&self) -> &'static str {
         match self {
             TeltonikaEventHandlers::SpeedEventHandler((handler, _)) => {
-                handler.get_trigger_event_id()
+                handler.get_cache_file_path()
             }
             TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, _)) => {
-                handler.get_trigger_event_id()
+                handler.get_cache_file_path()
             }
             TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, _)) => {
-                handler.get_trigger_event_id()
+                handler.get_cache_file_path()
+            }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, _)) => {
+                handler.get_cache_file_path()
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, _)) => {
+                handler.get_cache_file_path()
+            }
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, _)) => {
+                handler.get_cache_file_path()
+            }
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, _)) => {
+                handler.get_cache_file_path()
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, _)) => {
+                handler.get_cache_file_path()
+            }
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, _)) => {
+                handler.get_cache_file_path()
+            }
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, _)) => {
+                handler.get_cache_file_path()
+            }
+        }
+    }
+
+    /// Gets the number of events waiting in the cache of the handler.
+    pub fn get_cache_depth(&self, base_cache_path: &Path) -> usize {
+        match self {
+            TeltonikaEventHandlers::SpeedEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
+            }
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, _)) => {
+                handler.get_cache_depth(base_cache_path)
             }
         }
     }
 
     /// Handles a Teltonika event.
+    #[allow(clippy::too_many_arguments)]
     pub async fn handle_events(
         &self,
-        trigger_event_id: u16,
+        trigger: RecordTrigger,
         events: Vec<&AVLEventIO>,
         timestamp: i64,
+        position: (f64, f64),
         truck_id: Option<String>,
         base_cache_path: Box<Path>,
+        provenance: Option<FrameProvenance>,
     ) {
         match self {
             TeltonikaEventHandlers::SpeedEventHandler((handler, imei)) => {
                 handler
                     .handle_events(
-                        trigger_event_id,
+                        trigger,
                         events,
                         timestamp,
+                        position,
                         truck_id,
                         base_cache_path,
                         imei,
+                        provenance,
                     )
                     .await
             }
             TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, imei)) => {
                 handler
                     .handle_events(
-                        trigger_event_id,
+                        trigger,
                         events,
                         timestamp,
+                        position,
                         truck_id,
                         base_cache_path,
                         imei,
+                        provenance,
                     )
                     .await
             }
             TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, imei)) => {
                 handler
                     .handle_events(
-                        trigger_event_id,
+                        trigger,
+                        events,
+                        timestamp,
+                        position,
+                        truck_id,
+                        base_cache_path,
+                        imei,
+                        provenance,
+                    )
+                    .await
+            }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, imei)) => {
+                handler
+                    .handle_events(
+                        trigger,
+                        events,
+                        timestamp,
+                        position,
+                        truck_id,
+                        base_cache_path,
+                        imei,
+                        provenance,
+                    )
+                    .await
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, imei)) => {
+                handler
+                    .handle_events(
+                        trigger,
+                        events,
+                        timestamp,
+                        position,
+                        truck_id,
+                        base_cache_path,
+                        imei,
+                        provenance,
+                    )
+                    .await
+            }
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, imei)) => {
+                handler
+                    .handle_events(
+                        trigger,
+                        events,
+                        timestamp,
+                        position,
+                        truck_id,
+                        base_cache_path,
+                        imei,
+                        provenance,
+                    )
+                    .await
+            }
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, imei)) => {
+                handler
+                    .handle_events(
+                        trigger,
                         events,
                         timestamp,
+                        position,
                         truck_id,
                         base_cache_path,
                         imei,
+                        provenance,
+                    )
+                    .await
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, imei)) => {
+                handler
+                    .handle_events(
+                        trigger,
+                        events,
+                        timestamp,
+                        position,
+                        truck_id,
+                        base_cache_path,
+                        imei,
+                        provenance,
+                    )
+                    .await
+            }
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, imei)) => {
+                handler
+                    .handle_events(
+                        trigger,
+                        events,
+                        timestamp,
+                        position,
+                        truck_id,
+                        base_cache_path,
+                        imei,
+                        provenance,
+                    )
+                    .await
+            }
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, imei)) => {
+                handler
+                    .handle_events(
+                        trigger,
+                        events,
+                        timestamp,
+                        position,
+                        truck_id,
+                        base_cache_path,
+                        imei,
+                        provenance,
                     )
                     .await
             }
         }
     }
 
+    /// Submits the batch of the handler if its window has elapsed, or regardless of it if forced.
+    pub async fn flush_batch(&self, truck_id: String, base_cache_path: Box<Path>, force: bool) {
+        match self {
+            TeltonikaEventHandlers::SpeedEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, imei)) => {
+                handler
+                    .flush_batch(truck_id, base_cache_path, imei, force)
+                    .await
+            }
+        }
+    }
+
     /// Purges the cache.
     pub async fn purge_cache(&self, truck_id: String, base_cache_path: Box<Path>) {
         match self {
@@ -116,6 +471,27 @@ impl TeltonikaEventHandlers {
             TeltonikaEventHandlers::DriverOneDriveStateEventHandler((handler, imei)) => {
                 handler.purge_cache(truck_id, base_cache_path, imei).await
             }
+            TeltonikaEventHandlers::HarshDrivingEventHandler((handler, imei)) => {
+                handler.purge_cache(truck_id, base_cache_path, imei).await
+            }
+            TeltonikaEventHandlers::BleSensorEventHandler((handler, imei)) => {
+                handler.purge_cache(truck_id, base_cache_path, imei).await
+            }
+            TeltonikaEventHandlers::AxleWeightEventHandler((handler, imei)) => {
+                handler.purge_cache(truck_id, base_cache_path, imei).await
+            }
+            TeltonikaEventHandlers::EngineHoursEventHandler((handler, imei)) => {
+                handler.purge_cache(truck_id, base_cache_path, imei).await
+            }
+            TeltonikaEventHandlers::EngineRpmEventHandler((handler, imei)) => {
+                handler.purge_cache(truck_id, base_cache_path, imei).await
+            }
+            TeltonikaEventHandlers::FaultCodeEventHandler((handler, imei)) => {
+                handler.purge_cache(truck_id, base_cache_path, imei).await
+            }
+            TeltonikaEventHandlers::GeofenceEventHandler((handler, imei)) => {
+                handler.purge_cache(truck_id, base_cache_path, imei).await
+            }
         }
     }
 }
@@ -126,20 +502,23 @@ impl TeltonikaEventHandlers {
 ///
 /// # Type parameters
 /// * `T` - The type of the event data to send to the API or Cache.
-/// * `E` - The type of the error that can occur when sending the event to the API.
-pub trait TeltonikaEventHandler<T, E>
+pub trait TeltonikaEventHandler<T>
 where
     T: Cacheable + Serialize + for<'a> Deserialize<'a> + Clone + Debug,
-    E: Debug,
 {
     /// Gets the event ID for the handler.
     fn get_event_ids(&self) -> Vec<u16>;
 
-    /// Gets the trigger event ID for the handler.
+    /// Gets the IDs of the events passed to the handler in addition to the required events when present in the record.
+    fn get_optional_event_ids(&self) -> Vec<u16> {
+        Vec::new()
+    }
+
+    /// Gets the records the handler opts into.
     ///
-    /// If the trigger event ID is not the one that has triggered the record being processed (e.g. the records triggered event ID is 195 or 0 and the trigger event ID of the handler is 196), the record will be ignored.
-    fn get_trigger_event_id(&self) -> Option<u16> {
-        None
+    /// Records whose trigger doesn't match the subscription (e.g. a periodic record for a handler of eventual records triggered by event 187) are ignored by the handler.
+    fn get_record_subscription(&self) -> RecordSubscription {
+        RecordSubscription::All
     }
 
     /// Handles a Teltonika event.
@@ -147,75 +526,299 @@ where
     /// This method will process the event data, send it to the API and cache it if sending fails or truck id is not yet known.
     ///
     /// # Arguments
+    /// * `trigger` - Trigger of the record of the event.
     /// * `event` - The Teltonika event to handle.
     /// * `timestamp` - The timestamp of the event.
+    /// * `position` - Latitude and longitude of the record of the event.
     /// * `truck_id` - The truck ID of the event.
     /// * `base_cache_path` - The base path to the cache directory.
     /// * `imei` - The IMEI of the device.
+    /// * `provenance` - Provenance of the frame the event was received in, if known.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_events(
         &self,
-        trigger_event_id: u16,
+        trigger: RecordTrigger,
         events: Vec<&AVLEventIO>,
         timestamp: i64,
+        position: (f64, f64),
         truck_id: Option<String>,
         base_cache_path: Box<Path>,
         imei: &str,
+        provenance: Option<FrameProvenance>,
     ) {
-        let event_data = self.process_event_data(trigger_event_id, &events, timestamp, imei);
-        if event_data.is_none() {
+        let event_data = match self.process_event_data(trigger, &events, timestamp, position, imei)
+        {
+            Ok(Some(event_data)) => event_data,
+            Ok(None) => return,
+            Err(err) => {
+                error!(target: imei, "{}. Storing it as a failed event.", err);
+                FailedEvent::record(
+                    &err,
+                    T::FILE_PATH.trim_end_matches("_cache.json"),
+                    timestamp,
+                    base_cache_path.to_str().unwrap(),
+                    provenance,
+                );
+                return;
+            }
+        };
+        let Some(event_data) = self.filter_event_data(event_data, imei) else {
             return;
-        }
-        let event_data = event_data.unwrap();
+        };
         if let Some(truck_id) = truck_id {
+            if let Some(batch) = self.get_batch() {
+                if let Some(events) = batch.push(event_data, Instant::now()) {
+                    self.submit_batch(events, truck_id, base_cache_path, imei, provenance)
+                        .await;
+                }
+                return;
+            }
             debug!(target: imei, "Handling event for truck: {}", truck_id);
-            let send_event_result = self.send_event(&event_data, truck_id).await;
+            let send_event_result = self.send_event(&event_data, truck_id, imei).await;
             if let Err(e) = send_event_result {
-                error!(target: imei, "Error sending event: {:?}. Caching it for further use.", e);
-                self.cache_event_data(event_data, base_cache_path);
+                self.handle_send_error(e, event_data, &base_cache_path, imei, provenance)
+                    .await;
             }
+        } else if !self.has_endpoint() {
+            // The event would only be dropped when purging the cache once the truck is known
+            debug!(target: imei, "Dropping event without an API endpoint for yet unknown truck");
         } else {
             debug!(target: imei, "Caching event for yet unknown truck");
-            self.cache_event_data(event_data, base_cache_path);
+            self.cache_event_data(event_data, base_cache_path).await;
         };
     }
 
+    /// Handles an error sending event data to the API.
+    ///
+    /// The event data is cached for further use if the error is temporary, otherwise it's dropped.
+    ///
+    /// # Arguments
+    /// * `e` - The error sending the event data.
+    /// * `event_data` - The event data failed to be sent.
+    /// * `base_cache_path` - The base path to the cache directory.
+    /// * `imei` - The IMEI of the device.
+    /// * `provenance` - Provenance of the frame the event was received in, if known.
+    async fn handle_send_error(
+        &self,
+        e: VehicleApiError,
+        event_data: T,
+        base_cache_path: &Path,
+        imei: &str,
+        provenance: Option<FrameProvenance>,
+    ) {
+        // Unsupported operations fail for every event, so they are not recorded as failed requests
+        if e.kind == VehicleApiErrorKind::Unsupported {
+            debug!(target: imei, "Event not sent: {}. Dropping it.", e);
+            return;
+        }
+        FailedApiRequest::record(
+            &e,
+            T::FILE_PATH.trim_end_matches("_cache.json"),
+            base_cache_path.to_str().unwrap(),
+            provenance,
+        );
+        if !e.kind.is_cacheable() {
+            error!(target: imei, "Error sending event: {}. Dropping it.", e);
+            return;
+        }
+        let event_name = T::FILE_PATH.trim_end_matches("_cache.json");
+        if let Some(suppressed) =
+            log_throttle::throttle(&format!("{}:caching_event:{}", imei, event_name))
+        {
+            error!(target: imei,
+                "Error sending event: {}. Caching it for further use.{}",
+                e,
+                log_throttle::describe_suppressed(suppressed)
+            );
+        }
+        self.cache_event_data(event_data, base_cache_path.into())
+            .await;
+    }
+
+    /// Gets the batch collecting the events of the handler, if the events are sent in batches.
+    ///
+    /// Only events which are frequently sent should be batched.
+    fn get_batch(&self) -> Option<&EventBatch<T>> {
+        None
+    }
+
+    /// Submits a batch of events to the API, caching the events failed to be sent.
+    ///
+    /// # Arguments
+    /// * `events` - The events of the batch.
+    /// * `truck_id` - The truck ID of the events.
+    /// * `base_cache_path` - The base path to the cache directory.
+    /// * `imei` - The IMEI of the device.
+    /// * `provenance` - Provenance of the frame the last event was received in, if known.
+    async fn submit_batch(
+        &self,
+        events: Vec<T>,
+        truck_id: String,
+        base_cache_path: Box<Path>,
+        imei: &str,
+        provenance: Option<FrameProvenance>,
+    ) {
+        let event_name = T::FILE_PATH.trim_end_matches("_cache.json");
+        debug!(target: imei,
+            "Submitting batch of {} {} events for truck: {}",
+            events.len(),
+            event_name,
+            truck_id
+        );
+        batching::record_batch(event_name, events.len());
+        let results = self.send_events(&events, truck_id, imei).await;
+        for (event_data, result) in events.into_iter().zip(results) {
+            if let Err(e) = result {
+                self.handle_send_error(e, event_data, &base_cache_path, imei, provenance.clone())
+                    .await;
+            }
+        }
+    }
+
+    /// Submits the batch of the handler if its window has elapsed, or regardless of it if forced.
+    ///
+    /// # Arguments
+    /// * `truck_id` - The truck ID of the events.
+    /// * `base_cache_path` - The base path to the cache directory.
+    /// * `imei` - The IMEI of the device.
+    /// * `force` - Whether to submit the batch before its window has elapsed, e.g. when the connection closes.
+    async fn flush_batch(
+        &self,
+        truck_id: String,
+        base_cache_path: Box<Path>,
+        imei: &str,
+        force: bool,
+    ) {
+        let Some(batch) = self.get_batch() else {
+            return;
+        };
+        let events = if force {
+            batch.take_all()
+        } else {
+            batch.take_due(Instant::now())
+        };
+        if !events.is_empty() {
+            self.submit_batch(events, truck_id, base_cache_path, imei, None)
+                .await;
+        }
+    }
+
     /// Caches the event data.
     ///
     /// # Arguments
     /// * `event` - The Teltonika event to cache.
     /// * `timestamp` - The timestamp of the event.
     /// * `base_cache_path` - The base path to the cache directory.
-    fn cache_event_data(&self, event: T, base_cache_path: Box<Path>) {
-        let cache_result = event.write_to_file(base_cache_path.to_owned().to_str().unwrap());
+    async fn cache_event_data(&self, event: T, base_cache_path: Box<Path>) {
+        let cache_lock = get_cache_lock(&base_cache_path);
+        let _cache_guard = cache_lock.lock().await;
+        let cache_result =
+            CachedEvent::new(event).write_to_file(base_cache_path.to_owned().to_str().unwrap());
         if let Err(e) = cache_result {
             panic!("Error caching event: {:?}", e);
         }
     }
 
+    /// Checks whether Vehicle Management Service provides an endpoint for the events of the handler.
+    ///
+    /// Handlers of events without an endpoint are registered only with the `pending-endpoints` feature, and their events
+    /// are never cached.
+    fn has_endpoint(&self) -> bool {
+        true
+    }
+
+    /// Checks whether the events of the handler may be dropped when the receiver is overloaded.
+    ///
+    /// Only low-value events which are frequently sent should be sheddable.
+    fn is_sheddable(&self) -> bool {
+        false
+    }
+
+    /// Gets the file path of the cache used by the handler.
+    fn get_cache_file_path(&self) -> &'static str {
+        T::FILE_PATH
+    }
+
+    /// Gets the number of events waiting in the cache.
+    ///
+    /// # Arguments
+    /// * `base_cache_path` - The base path to the cache directory.
+    fn get_cache_depth(&self, base_cache_path: &Path) -> usize {
+        T::get_cache_depth(base_cache_path.to_str().unwrap())
+    }
+
     /// Sends the event data to the API.
     ///
     /// # Arguments
     /// * `event_data` - The event data to send.
     /// * `truck_id` - The truck ID of the event.
-    async fn send_event(&self, event_data: &T, truck_id: String) -> Result<(), E>;
+    /// * `imei` - The IMEI of the device.
+    async fn send_event(
+        &self,
+        event_data: &T,
+        truck_id: String,
+        imei: &str,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Sends a batch of event data to the API.
+    ///
+    /// Events are sent one by one by default. Handlers of batched events override this to submit the batch in bulk.
+    ///
+    /// # Arguments
+    /// * `events` - The event data to send.
+    /// * `truck_id` - The truck ID of the events.
+    /// * `imei` - The IMEI of the device.
+    ///
+    /// # Returns
+    /// * Result of sending each event in the order of the events.
+    async fn send_events(
+        &self,
+        events: &[T],
+        truck_id: String,
+        imei: &str,
+    ) -> Vec<Result<(), VehicleApiError>> {
+        let mut results = Vec::with_capacity(events.len());
+        for event_data in events {
+            results.push(self.send_event(event_data, truck_id.clone(), imei).await);
+        }
+
+        return results;
+    }
 
     /// Processes the event data.
     ///
+    /// Processing must not depend on the state of the handler, so that the payloads are fully determined by the events.
+    ///
     /// # Arguments
+    /// * `trigger` - Trigger of the record of the event.
     /// * `event` - The Teltonika event data to process.
     /// * `truck_id` - The truck ID of the event.
     /// * `timestamp` - The timestamp of the event.
+    /// * `position` - Latitude and longitude of the record of the event.
     /// * `imei` - The IMEI of the device.
     ///
     /// # Returns
-    /// * The processed event data.
+    /// * The processed event data, or an error if the events fail to decode.
     fn process_event_data(
         &self,
-        trigger_event_id: u16,
-        events: &Vec<&AVLEventIO>,
+        trigger: RecordTrigger,
+        events: &[&AVLEventIO],
         timestamp: i64,
+        position: (f64, f64),
         imei: &str,
-    ) -> Option<T>;
+    ) -> Result<Option<T>, EventDecodeError>;
+
+    /// Filters the processed event data based on the state of the handler, e.g. to skip events already reported.
+    ///
+    /// # Arguments
+    /// * `event_data` - The processed event data.
+    /// * `imei` - The IMEI of the device.
+    ///
+    /// # Returns
+    /// * The event data to send, or `None` to skip it.
+    fn filter_event_data(&self, event_data: T, _imei: &str) -> Option<T> {
+        Some(event_data)
+    }
 
     /// Purges the cache.
     ///
@@ -224,8 +827,12 @@ where
     /// * `base_cache_path` - The base path to the cache directory.
     /// * `imei` - The IMEI of the device.
     async fn purge_cache(&self, truck_id: String, base_cache_path: Box<Path>, imei: &str) {
-        let cache = T::read_from_file(base_cache_path.to_str().unwrap());
-        let mut failed_events: Vec<T> = Vec::new();
+        let cache = take_cache::<CachedEvent<T>>(&base_cache_path)
+            .await
+            .expect("Failed to write cache");
+        let cache_length = cache.len();
+        let mut failed_events_count = 0;
+        let mut failed_events: Vec<CachedEvent<T>> = Vec::new();
 
         let event_ids = self
             .get_event_ids()
@@ -239,29 +846,41 @@ where
             event_ids
         );
 
-        for cached_event in cache.iter() {
-            let sent_event = self.send_event(cached_event, truck_id.clone()).await;
-            if let Err(err) = sent_event {
-                debug!(target: imei,
-                    "Failed to send event: {:?}. Adding it to failed events.",
-                    err
-                );
-                failed_events.push(cached_event.clone());
+        for cached_event in cache.into_iter() {
+            let sent_event = self
+                .send_event(&cached_event.event, truck_id.clone(), imei)
+                .await;
+            match sent_event {
+                Err(err) if err.kind.is_cacheable() => {
+                    debug!(target: imei,
+                        "Failed to send event: {:?}. Adding it to failed events.",
+                        err
+                    );
+                    failed_events_count += 1;
+                    failed_events.extend(cached_event.record_failed_attempt(
+                        &err,
+                        base_cache_path.to_str().unwrap(),
+                        imei,
+                    ));
+                }
+                Err(err) if err.kind == VehicleApiErrorKind::Unsupported => {
+                    debug!(target: imei, "Cached event not sent: {}. Dropping it.", err);
+                }
+                Err(err) => {
+                    error!(target: imei, "Failed to send event: {}. Dropping it.", err);
+                }
+                Ok(()) => {}
             }
         }
-        let successful_events_count = cache.len() - failed_events.len();
+        let successful_events_count = cache_length - failed_events_count;
         debug!(target: imei,
             "Purged {} events for event ids: {} from cache with {} failures",
             successful_events_count,
             event_ids,
-            failed_events.len()
+            failed_events_count
         );
-        T::clear_cache(base_cache_path.to_str().unwrap());
-
-        for failed_event in failed_events.iter() {
-            failed_event
-                .write_to_file(base_cache_path.to_owned().to_str().unwrap())
-                .expect("Failed to write cache");
-        }
+        restore_cache(&base_cache_path, failed_events)
+            .await
+            .expect("Failed to write cache");
     }
 }
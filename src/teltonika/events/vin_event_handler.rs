@@ -0,0 +1,60 @@
+use nom_teltonika::{AVLEventIO, AVLEventIOValue};
+
+use crate::teltonika::{avl_event_io_value_to_be_bytes, device_family::DeviceFamily};
+
+/// Handler collecting the binary parts of a Teltonika VIN.
+///
+/// Unlike the other event handlers, the parts of the VIN may arrive in separate records, so the handler collects them across records
+/// and the combined VIN is only used for looking up the truck. Devices reading the VIN from the tachograph send it in three parts,
+/// while FMB1xx devices reading it from the CAN bus send it in a single variable length event.
+pub struct VinEventHandler {
+    event_ids: Vec<u16>,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+impl VinEventHandler {
+    /// Creates a new [VinEventHandler] for the VIN layout of the given device family.
+    ///
+    /// # Arguments
+    /// * `device_family` - Family of the device
+    pub fn new(device_family: DeviceFamily) -> Self {
+        let event_ids = device_family.get_io_mapping().vin.clone();
+        VinEventHandler {
+            parts: vec![None; event_ids.len()],
+            event_ids,
+        }
+    }
+
+    /// Handles a single event, storing it if it is a part of the VIN not yet received.
+    ///
+    /// # Arguments
+    /// * `event` - The event to handle
+    pub fn handle_event(&mut self, event: &AVLEventIO) {
+        if let Some(index) = self.event_ids.iter().position(|id| *id == event.id) {
+            Self::set_part(&mut self.parts[index], &event.value);
+        }
+    }
+
+    /// Checks if all parts of the VIN are present.
+    pub fn get_is_complete(&self) -> bool {
+        return self.parts.iter().all(Option::is_some);
+    }
+
+    /// Combines the binary parts of the VIN into the full string representation.
+    pub fn get_vin(&self) -> Option<String> {
+        if self.get_is_complete() {
+            let vin = self.parts.iter().flatten().flatten().copied().collect();
+
+            return Some(String::from_utf8(vin).unwrap());
+        }
+
+        return None;
+    }
+
+    /// Stores a part of the VIN if it is not yet received.
+    fn set_part(part: &mut Option<Vec<u8>>, value: &AVLEventIOValue) {
+        if part.is_none() {
+            *part = Some(avl_event_io_value_to_be_bytes(value));
+        }
+    }
+}
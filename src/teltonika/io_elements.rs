@@ -0,0 +1,162 @@
+use serde::Serialize;
+
+/// Teltonika IO element
+///
+/// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/FMC650_Teltonika_Data_Sending_Parameters_ID) for the full list of IO elements.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct IoElement {
+    pub id: u16,
+    pub name: &'static str,
+    pub unit: Option<&'static str>,
+    /// Device models supporting the element or empty if supported by all FM models
+    pub models: &'static [&'static str],
+}
+
+/// Device models with a tachograph connection
+const TACHOGRAPH_MODELS: &[&str] = &["FMB640", "FMC650", "FMM640"];
+/// FMB1xx device models reading the CAN bus through an LV-CAN200 or ALL-CAN300 adapter
+const CAN_ADAPTER_MODELS: &[&str] = &["FMB120", "FMB125", "FMB130", "FMB140"];
+
+/// Dictionary of the Teltonika IO elements, ordered by ID
+pub const IO_ELEMENTS: &[IoElement] = &[
+    io_element(1, "Digital Input 1", None),
+    io_element(2, "Digital Input 2", None),
+    io_element(3, "Digital Input 3", None),
+    io_element(9, "Analog Input 1", Some("mV")),
+    io_element(10, "Analog Input 2", Some("mV")),
+    io_element(11, "ICCID1", None),
+    io_element(12, "Fuel Used GPS", Some("l")),
+    io_element(13, "Fuel Rate GPS", Some("l/100km")),
+    io_element(16, "Total Odometer", Some("m")),
+    io_element(17, "Axis X", Some("mG")),
+    io_element(18, "Axis Y", Some("mG")),
+    io_element(19, "Axis Z", Some("mG")),
+    io_element(21, "GSM Signal", None),
+    io_element(24, "Speed", Some("km/h")),
+    io_element(25, "BLE Temperature 1", Some("0.01 °C")),
+    io_element(26, "BLE Temperature 2", Some("0.01 °C")),
+    io_element(27, "BLE Temperature 3", Some("0.01 °C")),
+    io_element(28, "BLE Temperature 4", Some("0.01 °C")),
+    io_element(30, "Number of DTC", None),
+    io_element(66, "External Voltage", Some("mV")),
+    io_element(67, "Battery Voltage", Some("mV")),
+    io_element(68, "Battery Current", Some("mA")),
+    io_element(69, "GNSS Status", None),
+    io_element(72, "Dallas Temperature 1", Some("0.1 °C")),
+    io_element(73, "Dallas Temperature 2", Some("0.1 °C")),
+    io_element(74, "Dallas Temperature 3", Some("0.1 °C")),
+    io_element(75, "Dallas Temperature 4", Some("0.1 °C")),
+    io_element(78, "iButton", None),
+    io_element(80, "Data Mode", None),
+    can_adapter_io_element(81, "Vehicle Speed", Some("km/h")),
+    io_element(85, "Engine RPM", Some("rpm")),
+    io_element(86, "BLE Humidity 1", Some("0.1 %")),
+    tachograph_io_element(87, "Total Mileage", Some("m")),
+    io_element(102, "Engine Worktime", Some("min")),
+    io_element(104, "BLE Humidity 2", Some("0.1 %")),
+    io_element(106, "BLE Humidity 3", Some("0.1 %")),
+    io_element(108, "BLE Humidity 4", Some("0.1 %")),
+    io_element(113, "Battery Level", Some("%")),
+    tachograph_io_element(118, "Axle 1 Load", Some("kg")),
+    tachograph_io_element(119, "Axle 2 Load", Some("kg")),
+    tachograph_io_element(120, "Axle 3 Load", Some("kg")),
+    tachograph_io_element(121, "Axle 4 Load", Some("kg")),
+    tachograph_io_element(122, "Axle 5 Load", Some("kg")),
+    io_element(155, "Geofence Zone 01", None),
+    io_element(156, "Geofence Zone 02", None),
+    io_element(157, "Geofence Zone 03", None),
+    io_element(158, "Geofence Zone 04", None),
+    io_element(159, "Geofence Zone 05", None),
+    io_element(179, "Digital Output 1", None),
+    io_element(180, "Digital Output 2", None),
+    io_element(181, "GNSS PDOP", None),
+    io_element(182, "GNSS HDOP", None),
+    tachograph_io_element(184, "Driver 1 Working State", None),
+    tachograph_io_element(187, "Driver 1 Card Presence", None),
+    tachograph_io_element(191, "Tachograph Vehicle Speed", Some("km/h")),
+    tachograph_io_element(195, "Driver 1 ID MSB", None),
+    tachograph_io_element(196, "Driver 1 ID LSB", None),
+    io_element(199, "Trip Odometer", Some("m")),
+    io_element(200, "Sleep Mode", None),
+    io_element(205, "GSM Cell ID", None),
+    io_element(206, "GSM Area Code", None),
+    tachograph_io_element(233, "VIN 1", None),
+    tachograph_io_element(234, "VIN 2", None),
+    tachograph_io_element(235, "VIN 3", None),
+    io_element(239, "Ignition", None),
+    io_element(240, "Movement", None),
+    io_element(241, "Active GSM Operator", None),
+    io_element(243, "Green Driving Event Duration", Some("ms")),
+    io_element(250, "Trip", None),
+    io_element(251, "Idling", None),
+    io_element(252, "Unplug", None),
+    io_element(253, "Green Driving Type", None),
+    io_element(254, "Green Driving Value", None),
+    io_element(255, "Overspeeding", Some("km/h")),
+    can_adapter_io_element(256, "VIN", None),
+    io_element(281, "Fault Codes", None),
+    io_element(10808, "EYE Magnet 1", None),
+    io_element(10809, "EYE Magnet 2", None),
+    io_element(10810, "EYE Magnet 3", None),
+    io_element(10811, "EYE Magnet 4", None),
+];
+
+/// Builds an [IoElement] supported by all FM models
+const fn io_element(id: u16, name: &'static str, unit: Option<&'static str>) -> IoElement {
+    IoElement {
+        id,
+        name,
+        unit,
+        models: &[],
+    }
+}
+
+/// Builds an [IoElement] supported by the models with a tachograph connection
+const fn tachograph_io_element(
+    id: u16,
+    name: &'static str,
+    unit: Option<&'static str>,
+) -> IoElement {
+    IoElement {
+        id,
+        name,
+        unit,
+        models: TACHOGRAPH_MODELS,
+    }
+}
+
+/// Builds an [IoElement] supported by the FMB1xx models with a CAN adapter
+const fn can_adapter_io_element(
+    id: u16,
+    name: &'static str,
+    unit: Option<&'static str>,
+) -> IoElement {
+    IoElement {
+        id,
+        name,
+        unit,
+        models: CAN_ADAPTER_MODELS,
+    }
+}
+
+/// Gets an IO element by ID
+///
+/// # Arguments
+/// * `id` - ID of the IO element
+pub fn get_io_element(id: u16) -> Option<&'static IoElement> {
+    return IO_ELEMENTS
+        .binary_search_by_key(&id, |element| element.id)
+        .ok()
+        .map(|index| &IO_ELEMENTS[index]);
+}
+
+/// Describes an IO element for log messages, e.g. `239 (Ignition)`
+///
+/// # Arguments
+/// * `id` - ID of the IO element
+pub fn describe_io_element(id: u16) -> String {
+    return match get_io_element(id) {
+        Some(element) => format!("{} ({})", id, element.name),
+        None => id.to_string(),
+    };
+}
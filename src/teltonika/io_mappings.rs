@@ -0,0 +1,123 @@
+//! Registry of the IO elements of the device models
+//!
+//! Device models report the same data in different IO elements, e.g. FMC650 reads the vehicle speed from the
+//! tachograph and FMB140 from the CAN bus. Instead of each handler matching the model, the IO elements of each model
+//! are looked up from a table embedded in the receiver, whose models can be replaced or added with a TOML file given
+//! in `IO_MAPPINGS_FILE`.
+use std::{collections::HashMap, path::Path, sync::OnceLock};
+
+use log::info;
+use serde::Deserialize;
+
+use crate::config::{get_config, ConfigError};
+
+use super::device_family::DeviceFamily;
+
+/// IO mappings of the supported device models
+const DEFAULT_IO_MAPPINGS: &str = include_str!("io_mappings.toml");
+
+static IO_MAPPINGS: OnceLock<IoMappings> = OnceLock::new();
+
+/// IO elements a device model reports its data in
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IoMapping {
+    /// IO element of the vehicle speed in km/h
+    pub vehicle_speed: u16,
+    /// IO elements of the parts of the VIN, in order
+    pub vin: Vec<u16>,
+    /// IO element of the total mileage in meters
+    pub odometer: u16,
+    /// IO elements of the temperatures of sensor slots 1-4, sent as signed integers
+    #[serde(default = "default_temperatures")]
+    pub temperatures: Vec<u16>,
+    /// Divisor converting the values of the temperature IO elements to °C
+    #[serde(default = "default_temperature_divisor")]
+    pub temperature_divisor: f64,
+}
+
+/// Gets the IO elements of the temperatures of Teltonika EYE sensors, used by models not configuring the temperatures
+fn default_temperatures() -> Vec<u16> {
+    return vec![25, 26, 27, 28];
+}
+
+/// Gets the divisor of the temperatures of Teltonika EYE sensors, sent in hundredths of °C
+fn default_temperature_divisor() -> f64 {
+    return 100.0;
+}
+
+/// IO mappings keyed by device model
+#[derive(Debug, Clone, PartialEq)]
+pub struct IoMappings {
+    mappings: HashMap<String, IoMapping>,
+}
+
+impl IoMappings {
+    /// Parses IO mappings from the contents of a TOML file with a table per device model
+    ///
+    /// # Arguments
+    /// * `contents` - Contents of the file
+    pub fn parse(contents: &str) -> Result<IoMappings, ConfigError> {
+        let mappings = toml::from_str(contents).map_err(ConfigError::Parse)?;
+
+        return Ok(IoMappings { mappings });
+    }
+
+    /// Loads the embedded IO mappings, with the models of the given file replacing or adding to them
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file, if any
+    pub fn load(path: Option<&Path>) -> Result<IoMappings, ConfigError> {
+        let mut io_mappings = IoMappings::parse(DEFAULT_IO_MAPPINGS)?;
+        if let Some(path) = path {
+            let contents = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+            io_mappings.merge(IoMappings::parse(&contents)?);
+        }
+
+        return Ok(io_mappings);
+    }
+
+    /// Replaces or adds the models of other IO mappings
+    ///
+    /// # Arguments
+    /// * `other` - IO mappings to merge
+    pub fn merge(&mut self, other: IoMappings) {
+        self.mappings.extend(other.mappings);
+    }
+
+    /// Gets the IO mapping of a device model
+    ///
+    /// # Arguments
+    /// * `model` - Device model
+    pub fn get(&self, model: &str) -> Option<&IoMapping> {
+        self.mappings.get(model)
+    }
+
+    /// Gets the IO mapping of a device family
+    ///
+    /// # Arguments
+    /// * `device_family` - Device family
+    pub fn get_for_family(&self, device_family: DeviceFamily) -> &IoMapping {
+        return self
+            .get(&device_family.to_string())
+            .expect("Embedded IO mappings cover all device families");
+    }
+}
+
+/// Initializes the global IO mappings from the embedded table and the file given in `IO_MAPPINGS_FILE`
+pub fn init_io_mappings() -> Result<(), ConfigError> {
+    let path = get_config().io_mappings_file.clone();
+    let io_mappings = IoMappings::load(path.as_deref().map(Path::new))?;
+    if let Some(path) = path {
+        info!("Loaded IO mappings from {}", path);
+    }
+    let _ = IO_MAPPINGS.set(io_mappings);
+
+    return Ok(());
+}
+
+/// Gets the global IO mappings, only the embedded ones if not initialized
+pub fn get_io_mappings() -> &'static IoMappings {
+    IO_MAPPINGS.get_or_init(|| {
+        IoMappings::parse(DEFAULT_IO_MAPPINGS).expect("Embedded IO mappings are valid")
+    })
+}
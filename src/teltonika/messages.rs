@@ -0,0 +1,275 @@
+//! Framing of the messages sent by Teltonika devices over TCP and UDP
+//!
+//! AVL data frames are parsed with [nom_teltonika], but the parser panics on codecs it doesn't fully support,
+//! so the codec is checked before handing the frame over and Codec 12 and Codec 13 command responses are parsed here.
+use std::io::{Error, ErrorKind};
+
+use chrono::{DateTime, TimeZone, Utc};
+use nom_teltonika::{
+    crc16,
+    parser::{tcp_frame, udp_datagram},
+    AVLDatagram, AVLFrame,
+};
+
+/// Length of the frame preamble and data length fields
+const FRAME_HEADER_LENGTH: usize = 8;
+/// Length of the CRC field at the end of the frame
+const FRAME_CRC_LENGTH: usize = 4;
+/// Length of the datagram length, packet ID, non-usable byte, AVL packet ID and IMEI length fields
+const DATAGRAM_HEADER_LENGTH: usize = 8;
+const CODEC_8: u8 = 0x08;
+const CODEC_8_EXT: u8 = 0x8E;
+const CODEC_16: u8 = 0x10;
+const CODEC_12: u8 = 0x0C;
+const CODEC_13: u8 = 0x0D;
+/// Codec 12 message type of a command sent by the server
+const CODEC_12_COMMAND_TYPE: u8 = 0x05;
+/// Codec 12 message type of a response sent by the device
+const CODEC_12_RESPONSE_TYPE: u8 = 0x06;
+/// Codec 13 message type of a response sent by the device
+const CODEC_13_RESPONSE_TYPE: u8 = 0x05;
+
+/// Message received from a Teltonika device
+#[derive(Debug)]
+pub enum TeltonikaMessage {
+    /// AVL data frame, which must be acknowledged
+    Frame(AVLFrame),
+    /// Response to a Codec 12 command
+    CommandResponse(String),
+    /// Binary response to a command in Codec 13
+    BinaryCommandResponse {
+        timestamp: DateTime<Utc>,
+        data: Vec<u8>,
+    },
+}
+
+/// Parses the next message from the buffer of received bytes
+///
+/// Parsed bytes are removed from the buffer while bytes of the following messages are kept.
+/// On errors the whole buffer is discarded.
+///
+/// # Arguments
+/// * `buffer` - Received bytes
+///
+/// # Returns
+/// * `Ok(None)` if the buffer doesn't yet contain a complete message
+pub fn parse_message(buffer: &mut Vec<u8>) -> Result<Option<TeltonikaMessage>, Error> {
+    if buffer.len() <= FRAME_HEADER_LENGTH {
+        return Ok(None);
+    }
+    let result = match buffer[FRAME_HEADER_LENGTH] {
+        CODEC_8 | CODEC_8_EXT | CODEC_16 => parse_avl_frame(buffer),
+        CODEC_12 => parse_codec12_response(buffer),
+        CODEC_13 => parse_codec13_response(buffer),
+        codec => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported codec 0x{:02X}", codec),
+        )),
+    };
+    if result.is_err() {
+        buffer.clear();
+    }
+
+    return result;
+}
+
+/// Checks that the message being received fits in the memory cap of the connection
+///
+/// The length declared in the header of the message is checked as soon as the header has been received,
+/// so that a message too large is rejected before its data is buffered.
+///
+/// # Arguments
+/// * `buffer` - Received bytes, starting with the message being received
+/// * `max_bytes` - Maximum number of bytes buffered for a message
+pub fn check_message_length(buffer: &[u8], max_bytes: usize) -> Result<(), Error> {
+    let declared_length = match buffer.get(4..FRAME_HEADER_LENGTH) {
+        Some(data_length) => {
+            let data_length = u32::from_be_bytes(data_length.try_into().unwrap()) as usize;
+            FRAME_HEADER_LENGTH + data_length + FRAME_CRC_LENGTH
+        }
+        None => 0,
+    };
+    let message_length = declared_length.max(buffer.len());
+    if message_length > max_bytes {
+        return Err(Error::new(
+            ErrorKind::OutOfMemory,
+            format!(
+                "Message of {} bytes exceeds the memory cap of {} bytes",
+                message_length, max_bytes
+            ),
+        ));
+    }
+
+    return Ok(());
+}
+
+/// Parses an AVL data frame from the buffer
+fn parse_avl_frame(buffer: &mut Vec<u8>) -> Result<Option<TeltonikaMessage>, Error> {
+    match tcp_frame(buffer) {
+        Ok((remaining, frame)) => {
+            let consumed = buffer.len() - remaining.len();
+            buffer.drain(..consumed);
+            Ok(Some(TeltonikaMessage::Frame(frame)))
+        }
+        Err(nom::Err::Incomplete(_)) => Ok(None),
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse frame: {:?}", err.code),
+        )),
+    }
+}
+
+/// Parses a Codec 12 command response from the buffer
+fn parse_codec12_response(buffer: &mut Vec<u8>) -> Result<Option<TeltonikaMessage>, Error> {
+    let Some(data) = get_frame_data(buffer)? else {
+        return Ok(None);
+    };
+    // Codec, quantity, type and size of the response
+    if data.len() < 7 || data[2] != CODEC_12_RESPONSE_TYPE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Invalid Codec 12 response",
+        ));
+    }
+    let response_length = u32::from_be_bytes([data[3], data[4], data[5], data[6]]) as usize;
+    let Some(response) = data.get(7..7 + response_length) else {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Invalid Codec 12 response length",
+        ));
+    };
+    let response = String::from_utf8_lossy(response).to_string();
+    let frame_length = FRAME_HEADER_LENGTH + data.len() + FRAME_CRC_LENGTH;
+    buffer.drain(..frame_length);
+
+    return Ok(Some(TeltonikaMessage::CommandResponse(response)));
+}
+
+/// Parses a Codec 13 command response from the buffer
+///
+/// Unlike Codec 12 responses, Codec 13 responses contain a timestamp and arbitrary binary data.
+fn parse_codec13_response(buffer: &mut Vec<u8>) -> Result<Option<TeltonikaMessage>, Error> {
+    let Some(data) = get_frame_data(buffer)? else {
+        return Ok(None);
+    };
+    // Codec, quantity, type, size of the response and timestamp
+    if data.len() < 11 || data[2] != CODEC_13_RESPONSE_TYPE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Invalid Codec 13 response",
+        ));
+    }
+    // Size of the response includes the timestamp
+    let response_length = u32::from_be_bytes([data[3], data[4], data[5], data[6]]) as usize;
+    let Some(response) = response_length
+        .checked_sub(4)
+        .and_then(|length| data.get(11..11 + length))
+    else {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Invalid Codec 13 response length",
+        ));
+    };
+    let timestamp = u32::from_be_bytes([data[7], data[8], data[9], data[10]]);
+    let message = TeltonikaMessage::BinaryCommandResponse {
+        timestamp: Utc.timestamp_opt(timestamp.into(), 0).unwrap(),
+        data: response.to_vec(),
+    };
+    let frame_length = FRAME_HEADER_LENGTH + data.len() + FRAME_CRC_LENGTH;
+    buffer.drain(..frame_length);
+
+    return Ok(Some(message));
+}
+
+/// Gets the data of a frame from the buffer after verifying its preamble and CRC
+///
+/// # Returns
+/// * `Ok(None)` if the buffer doesn't yet contain the whole frame
+fn get_frame_data(buffer: &[u8]) -> Result<Option<&[u8]>, Error> {
+    if buffer[..4] != [0, 0, 0, 0] {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid frame preamble"));
+    }
+    let data_length = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
+    if buffer.len() < FRAME_HEADER_LENGTH + data_length + FRAME_CRC_LENGTH {
+        return Ok(None);
+    }
+    let data = &buffer[FRAME_HEADER_LENGTH..FRAME_HEADER_LENGTH + data_length];
+    let crc_start = FRAME_HEADER_LENGTH + data_length;
+    let crc = u32::from_be_bytes([
+        buffer[crc_start],
+        buffer[crc_start + 1],
+        buffer[crc_start + 2],
+        buffer[crc_start + 3],
+    ]);
+    if crc != crc16(data) as u32 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid frame CRC"));
+    }
+
+    return Ok(Some(data));
+}
+
+/// Builds a Codec 12 command frame
+///
+/// # Arguments
+/// * `command` - Command to send to the device, e.g. `getinfo`
+///
+/// # Returns
+/// * Bytes of the frame
+pub fn build_codec12_command(command: &str) -> Vec<u8> {
+    let mut data = vec![CODEC_12, 1, CODEC_12_COMMAND_TYPE];
+    data.extend_from_slice(&(command.len() as u32).to_be_bytes());
+    data.extend_from_slice(command.as_bytes());
+    data.push(1);
+
+    let mut frame = vec![0, 0, 0, 0];
+    frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&data);
+    frame.extend_from_slice(&(crc16(&data) as u32).to_be_bytes());
+
+    return frame;
+}
+
+/// Parses an AVL data datagram received over UDP
+///
+/// # Arguments
+/// * `packet` - Received datagram
+pub fn parse_datagram(packet: &[u8]) -> Result<AVLDatagram, Error> {
+    if packet.len() <= DATAGRAM_HEADER_LENGTH {
+        return Err(Error::new(ErrorKind::InvalidData, "Datagram too short"));
+    }
+    let imei_length = u16::from_be_bytes([packet[6], packet[7]]) as usize;
+    let Some(codec) = packet.get(DATAGRAM_HEADER_LENGTH + imei_length) else {
+        return Err(Error::new(ErrorKind::InvalidData, "Datagram too short"));
+    };
+    if ![CODEC_8, CODEC_8_EXT, CODEC_16].contains(codec) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported codec 0x{:02X}", codec),
+        ));
+    }
+
+    match udp_datagram(packet) {
+        Ok((_, datagram)) => Ok(datagram),
+        Err(err) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse datagram: {:?}", err),
+        )),
+    }
+}
+
+/// Builds the acknowledgement of a datagram received over UDP
+///
+/// # Arguments
+/// * `datagram` - Datagram to acknowledge
+///
+/// # Returns
+/// * Bytes of the acknowledgement
+pub fn build_datagram_ack(datagram: &AVLDatagram) -> Vec<u8> {
+    let mut ack = vec![0, 5];
+    ack.extend_from_slice(&datagram.packet_id.to_be_bytes());
+    ack.push(1);
+    ack.push(datagram.avl_packet_id);
+    ack.push(datagram.records.len() as u8);
+
+    return ack;
+}
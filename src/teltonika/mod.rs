@@ -1,12 +1,23 @@
+pub mod actions;
+pub mod commands;
 pub mod connection;
+pub mod device_family;
 pub mod events;
+pub mod io_elements;
+pub mod io_mappings;
+pub mod messages;
 pub mod records;
+pub mod udp;
 use log::debug;
 use nom_teltonika::{AVLEventIO, AVLEventIOValue};
 use vehicle_management_service::models::{TruckDriveStateEnum, TruckDriverCard};
 
+use crate::teltonika::io_elements::describe_io_element;
+
 /// The event ID for the event describing driver one card presence in tachograph.
 const DRIVER_ONE_CARD_PRESENCE_EVENT_ID: u16 = 187;
+/// The event ID for the event describing ignition state.
+const IGNITION_EVENT_ID: u16 = 239;
 
 /// Converts an [AVLEventIOValue] to a big-endian byte vector.
 fn avl_event_io_value_to_be_bytes(value: &AVLEventIOValue) -> Vec<u8> {
@@ -15,7 +26,7 @@ fn avl_event_io_value_to_be_bytes(value: &AVLEventIOValue) -> Vec<u8> {
         AVLEventIOValue::U32(value) => value.to_be_bytes().to_vec(),
         AVLEventIOValue::U16(value) => value.to_be_bytes().to_vec(),
         AVLEventIOValue::U8(value) => value.to_be_bytes().to_vec(),
-        _ => Vec::new(),
+        AVLEventIOValue::Variable(value) => value.clone(),
     }
 }
 
@@ -30,6 +41,20 @@ fn avl_event_io_value_to_u64(value: &AVLEventIOValue) -> u64 {
     }
 }
 
+/// Converts an [AVLEventIOValue] holding a signed integer to an i64.
+///
+/// Devices send signed integers as unsigned values of the width of the IO element, so the value is sign-extended from
+/// its width, e.g. `U16(65281)` is -255.
+fn avl_event_io_value_to_i64(value: &AVLEventIOValue) -> i64 {
+    match value {
+        AVLEventIOValue::U64(value) => *value as i64,
+        AVLEventIOValue::U32(value) => *value as i32 as i64,
+        AVLEventIOValue::U16(value) => *value as i16 as i64,
+        AVLEventIOValue::U8(value) => *value as i8 as i64,
+        _ => 0,
+    }
+}
+
 /// Converts an [AVLEventIOValue] to a u8. Will panic if the value is not a u8.
 fn avl_event_io_value_to_u8(value: &AVLEventIOValue) -> u8 {
     match value {
@@ -38,63 +63,110 @@ fn avl_event_io_value_to_u8(value: &AVLEventIOValue) -> u8 {
     }
 }
 
+/// Error decoding the value of an event
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventDecodeError {
+    pub event_id: u16,
+    pub raw_bytes: Vec<u8>,
+    pub reason: String,
+}
+
+impl EventDecodeError {
+    /// Gets the raw bytes of the event hex-encoded
+    pub fn get_raw_bytes_hex(&self) -> String {
+        return self
+            .raw_bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+    }
+}
+
+impl std::fmt::Display for EventDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to decode event {}: {} (raw bytes {})",
+            describe_io_element(self.event_id),
+            self.reason,
+            self.get_raw_bytes_hex()
+        )
+    }
+}
+
 /// Converts a list of [AVLEventIO] to a [TruckDriverCard].
 ///
-/// If either the MSB or LSB part of the driver card is 0, it is considered invalid and None is returned.
+/// If either the MSB or LSB part of the driver card is 0 or missing, it is considered invalid and None is returned.
 /// TODO: Investigate if in the case of valid driver card id the length of MSB and LSB fields are always same.
 ///
 /// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/DriverID) for more detailed information.
+///
+/// # Returns
+/// * Error if a part of the driver card isn't valid ASCII or the ID isn't 16 characters long
 fn driver_card_events_to_truck_driver_card(
     timestamp: i64,
-    events: &Vec<&AVLEventIO>,
-) -> Option<TruckDriverCard> {
-    let Some(driver_card_msb_part) = driver_card_part_from_event(events, 195) else {
+    events: &[&AVLEventIO],
+) -> Result<Option<TruckDriverCard>, EventDecodeError> {
+    let Some(driver_card_msb_part) = driver_card_part_from_event(events, 195)? else {
         debug!("Driver card MSB part was 0");
 
-        return None;
+        return Ok(None);
     };
-    let Some(driver_card_lsb_part) = driver_card_part_from_event(events, 196) else {
-        debug!("Driver card MSB part was 0");
+    let Some(driver_card_lsb_part) = driver_card_part_from_event(events, 196)? else {
+        debug!("Driver card LSB part was 0");
 
-        return None;
+        return Ok(None);
     };
     let id = format!("{}{}", driver_card_msb_part, driver_card_lsb_part);
+    if id.len() != 16 {
+        return Err(EventDecodeError {
+            event_id: 195,
+            raw_bytes: id.into_bytes(),
+            reason: "Driver card ID is not 16 characters long".to_string(),
+        });
+    }
 
-    assert!(id.len() == 16);
-
-    return Some(TruckDriverCard { id, timestamp });
+    return Ok(Some(TruckDriverCard { id, timestamp }));
 }
 /// Converts a Driver Card part [AVLEventIO] to a String.
 ///
 /// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/DriverID) for more detailed information.
-fn driver_card_part_event_to_string(event: &AVLEventIO) -> String {
+///
+/// # Returns
+/// * Error if the part isn't valid UTF-8, which field devices occasionally produce
+fn driver_card_part_event_to_string(event: &AVLEventIO) -> Result<String, EventDecodeError> {
     let driver_one_card_part = avl_event_io_value_to_u64(&event.value)
         .to_be_bytes()
         .to_vec();
-    let Ok(part) = String::from_utf8(driver_one_card_part) else {
-        panic!("Invalid driver one card part data");
-    };
 
-    return part;
+    return String::from_utf8(driver_one_card_part).map_err(|err| EventDecodeError {
+        event_id: event.id,
+        raw_bytes: err.as_bytes().to_vec(),
+        reason: "Driver card part is not valid UTF-8".to_string(),
+    });
 }
 
 /// Returns a driver card part as String from a list of [AVLEventIO].
 ///
-/// If either the driver card part is 0, it is considered invalid and None is returned.
+/// If the driver card part is 0 or missing, it is considered invalid and None is returned.
 /// TODO: Investigate if in the case of valid driver card id the length of MSB and LSB fields are always same.
 ///
 /// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/DriverID) for more detailed information.
-fn driver_card_part_from_event(events: &Vec<&AVLEventIO>, event_id: u16) -> Option<String> {
-    let driver_card_part = events
-        .iter()
-        .find(|event| event.id == event_id)
-        .expect(&format!("Driver card part event not found {event_id}"));
+fn driver_card_part_from_event(
+    events: &[&AVLEventIO],
+    event_id: u16,
+) -> Result<Option<String>, EventDecodeError> {
+    let Some(driver_card_part) = events.iter().find(|event| event.id == event_id) else {
+        debug!("Driver card part event {} not found", event_id);
+
+        return Ok(None);
+    };
 
     if driver_card_part.value == AVLEventIOValue::U64(0) {
-        return None;
+        return Ok(None);
     }
 
-    return Some(driver_card_part_event_to_string(driver_card_part));
+    return driver_card_part_event_to_string(driver_card_part).map(Some);
 }
 
 /// Trait for converting an [AVLEventIOValue] to a value used by Vehicle Management API.
@@ -102,18 +174,46 @@ trait FromAVLEventIoValue {
     fn from_avl_event_io_value(value: &AVLEventIOValue) -> Self;
 }
 
+/// Drive state values of the tachograph working state events
+///
+/// The working state is the 3-bit SAE J1939 driver working state (SPN 1612 for driver 1, SPN 1613 for driver 2),
+/// which Teltonika devices report as is. Values 4 and 5 are reserved by J1939 and can't be sent by conforming
+/// tachographs.
+mod drive_state {
+    pub const REST: u8 = 0;
+    pub const DRIVER_AVAILABLE: u8 = 1;
+    pub const WORK: u8 = 2;
+    pub const DRIVE: u8 = 3;
+    pub const ERROR: u8 = 6;
+    pub const NOT_AVAILABLE: u8 = 7;
+}
+
+/// Converts a tachograph working state value to a [TruckDriveStateEnum]
+///
+/// Reserved and out-of-range values are reported as not available.
+///
+/// # Arguments
+/// * `value` - Working state value
+pub fn drive_state_from_value(value: u8) -> TruckDriveStateEnum {
+    match value {
+        drive_state::REST => TruckDriveStateEnum::Rest,
+        drive_state::DRIVER_AVAILABLE => TruckDriveStateEnum::DriverAvailable,
+        drive_state::WORK => TruckDriveStateEnum::Work,
+        drive_state::DRIVE => TruckDriveStateEnum::Drive,
+        drive_state::ERROR => TruckDriveStateEnum::Error,
+        drive_state::NOT_AVAILABLE => TruckDriveStateEnum::NotAvailable,
+        _ => {
+            debug!("Unknown drive state value {}", value);
+            TruckDriveStateEnum::NotAvailable
+        }
+    }
+}
+
 /// Implementation of [FromAVLEventIoValue] for [TruckDriveStateEnum].
 impl FromAVLEventIoValue for TruckDriveStateEnum {
     fn from_avl_event_io_value(value: &AVLEventIOValue) -> Self {
         match value {
-            AVLEventIOValue::U8(value) => match value {
-                0 => TruckDriveStateEnum::Rest,
-                1 => TruckDriveStateEnum::DriverAvailable,
-                2 => TruckDriveStateEnum::Work,
-                3 => TruckDriveStateEnum::Drive,
-                6 => TruckDriveStateEnum::Error,
-                _ => TruckDriveStateEnum::NotAvailable,
-            },
+            AVLEventIOValue::U8(value) => drive_state_from_value(*value),
             _ => TruckDriveStateEnum::NotAvailable,
         }
     }
@@ -1,5 +1,23 @@
+pub mod teltonika_frame_provenance;
+pub mod teltonika_gap_detector;
+pub mod teltonika_location_validator;
+pub mod teltonika_odometer_reconciler;
+pub mod teltonika_odometer_validator;
+pub mod teltonika_record_ordering;
+pub mod teltonika_record_trigger;
 pub mod teltonika_records_handler;
-pub mod teltonika_vin_handler;
+pub mod teltonika_shift_tracker;
+pub mod teltonika_timestamp_normalizer;
+pub mod teltonika_timestamp_validator;
 
+pub use teltonika_frame_provenance::FrameProvenance;
+pub use teltonika_gap_detector::TeltonikaGapDetector;
+pub use teltonika_location_validator::TeltonikaLocationValidator;
+pub use teltonika_odometer_reconciler::TeltonikaOdometerReconciler;
+pub use teltonika_odometer_validator::TeltonikaOdometerValidator;
+pub use teltonika_record_ordering::RecordOrdering;
+pub use teltonika_record_trigger::{RecordSubscription, RecordTrigger};
 pub use teltonika_records_handler::TeltonikaRecordsHandler;
-pub use teltonika_vin_handler::TeltonikaVinHandler;
+pub use teltonika_shift_tracker::TeltonikaShiftTracker;
+pub use teltonika_timestamp_normalizer::TeltonikaTimestampNormalizer;
+pub use teltonika_timestamp_validator::TeltonikaTimestampValidator;
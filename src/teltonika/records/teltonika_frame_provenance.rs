@@ -0,0 +1,32 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Provenance of a frame, tracing the data produced from it back to the exact frame received.
+///
+/// Frames are archived by IMEI and day, so the CRC and receive time identify the frame in the archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameProvenance {
+    pub connection_id: String,
+    pub source_ip: Option<String>,
+    pub frame_crc: u32,
+    pub received_at: DateTime<Utc>,
+}
+
+impl FrameProvenance {
+    /// Creates a new [FrameProvenance] for a frame received now.
+    ///
+    /// # Arguments
+    /// * `frame_crc` - CRC16 of the received frame. Datagrams received over UDP carry no CRC, so it is calculated on receive.
+    /// * `connection_id` - ID of the connection the frame was received from.
+    /// * `source_ip` - IP address the frame was received from, if known.
+    pub fn new(frame_crc: u32, connection_id: &str, source_ip: Option<IpAddr>) -> Self {
+        FrameProvenance {
+            connection_id: connection_id.to_string(),
+            source_ip: source_ip.map(|source_ip| source_ip.to_string()),
+            frame_crc,
+            received_at: Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Duration, Utc};
+use nom_teltonika::AVLRecord;
+
+use crate::config::get_config;
+
+/// Minimum time between history requests to a single device
+const GAP_RECOVERY_COOLDOWN_SECONDS: i64 = 60 * 60;
+
+/// Gap between records of a device
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordGap {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Detects gaps in record timestamps of a device
+///
+/// When a gap is detected, the configured Codec 12 command (`GAP_RECOVERY_COMMAND` environment variable) can be sent
+/// to the device to request the records stored in its memory. Detection is disabled when no command is configured.
+pub struct TeltonikaGapDetector {
+    command: Option<String>,
+    threshold: Duration,
+    latest_timestamp: Option<DateTime<Utc>>,
+    last_requested_at: Option<DateTime<Utc>>,
+}
+
+impl TeltonikaGapDetector {
+    /// Creates a new [TeltonikaGapDetector] from the configuration.
+    pub fn new() -> Self {
+        let tunables = &get_config().tunables;
+        Self::with_configuration(
+            tunables.gap_recovery_command.clone(),
+            tunables.gap_recovery_threshold_seconds,
+        )
+    }
+
+    /// Creates a new [TeltonikaGapDetector] with the given configuration.
+    ///
+    /// # Arguments
+    /// * `command` - Codec 12 command requesting the stored records from the device
+    /// * `threshold_seconds` - Shortest gap between records that triggers a history request
+    pub fn with_configuration(command: Option<String>, threshold_seconds: i64) -> Self {
+        TeltonikaGapDetector {
+            command,
+            threshold: Duration::seconds(threshold_seconds),
+            latest_timestamp: None,
+            last_requested_at: None,
+        }
+    }
+
+    /// Handles the records of a frame.
+    ///
+    /// # Arguments
+    /// * `records` - Records to handle
+    ///
+    /// # Returns
+    /// * The detected gap and the command to send to the device, if a history request should be made
+    pub fn handle_records(&mut self, records: &[AVLRecord]) -> Option<(RecordGap, &str)> {
+        let command = self.command.as_deref()?;
+        let earliest_timestamp = records.iter().map(|record| record.timestamp).min()?;
+        let latest_timestamp = records.iter().map(|record| record.timestamp).max()?;
+        let previous_timestamp = self.latest_timestamp.replace(
+            self.latest_timestamp.map_or(latest_timestamp, |timestamp| {
+                timestamp.max(latest_timestamp)
+            }),
+        )?;
+        if earliest_timestamp - previous_timestamp < self.threshold {
+            return None;
+        }
+        let now = Utc::now();
+        if self.last_requested_at.is_some_and(|requested_at| {
+            now - requested_at < Duration::seconds(GAP_RECOVERY_COOLDOWN_SECONDS)
+        }) {
+            return None;
+        }
+        self.last_requested_at = Some(now);
+
+        return Some((
+            RecordGap {
+                start: previous_timestamp,
+                end: earliest_timestamp,
+            },
+            command,
+        ));
+    }
+}
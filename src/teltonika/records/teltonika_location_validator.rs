@@ -0,0 +1,198 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use nom_teltonika::AVLRecord;
+
+use crate::{
+    config::get_config,
+    teltonika::avl_event_io_value_to_u64,
+    utils::geo::{haversine_distance_meters, is_valid_position},
+};
+
+/// The event ID for the horizontal dilution of precision of the fix, in tenths
+const GNSS_HDOP_EVENT_ID: u16 = 182;
+/// Name of the counter describing the number of invalid locations by reason and action
+pub const INVALID_LOCATIONS_METRIC: &str = "receiver_invalid_locations_total";
+
+/// Handling of locations failing validation
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LocationValidationMode {
+    /// Locations are not validated
+    #[default]
+    Off,
+    /// Invalid locations are sent, but logged and counted
+    Flag,
+    /// Invalid locations are dropped, logged and counted
+    Drop,
+}
+
+impl LocationValidationMode {
+    /// Gets the label of the action taken on invalid locations in metrics
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            LocationValidationMode::Off => "none",
+            LocationValidationMode::Flag => "flagged",
+            LocationValidationMode::Drop => "dropped",
+        }
+    }
+}
+
+impl FromStr for LocationValidationMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "off" => Ok(LocationValidationMode::Off),
+            "flag" => Ok(LocationValidationMode::Flag),
+            "drop" => Ok(LocationValidationMode::Drop),
+            _ => Err(format!(
+                "Unknown location validation [{}], expected off, flag or drop",
+                value
+            )),
+        }
+    }
+}
+
+/// Reason for a location failing validation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvalidLocation {
+    /// The coordinates are out of range or 0.0, 0.0 reported without a fix
+    Position,
+    /// The fix has too few satellites
+    Satellites(u8),
+    /// The horizontal dilution of precision of the fix is too high
+    Hdop(f64),
+    /// The speed reported by the device, or implied by the distance from the previous location, is implausible
+    Speed(f64),
+}
+
+impl InvalidLocation {
+    /// Gets the label of the reason in metrics
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            InvalidLocation::Position => "position",
+            InvalidLocation::Satellites(_) => "satellites",
+            InvalidLocation::Hdop(_) => "hdop",
+            InvalidLocation::Speed(_) => "speed",
+        }
+    }
+}
+
+impl fmt::Display for InvalidLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidLocation::Position => write!(f, "position is out of range or missing"),
+            InvalidLocation::Satellites(satellites) => {
+                write!(f, "fix has only {} satellites", satellites)
+            }
+            InvalidLocation::Hdop(hdop) => write!(f, "HDOP {:.1} is too high", hdop),
+            InvalidLocation::Speed(speed) => write!(f, "speed {:.0} km/h is implausible", speed),
+        }
+    }
+}
+
+/// Validator of the GNSS fixes of the records of a device.
+///
+/// When enabled with `LOCATION_VALIDATION`, a location is invalid if its coordinates are out of range or 0.0, 0.0, if
+/// its fix has fewer than `LOCATION_MIN_SATELLITES` satellites, if its HDOP (IO element 182) is above `LOCATION_MAX_HDOP`
+/// when both are known, or if the speed reported by the device or implied by the distance from the previous valid
+/// location exceeds `LOCATION_MAX_SPEED_KMH`.
+pub struct TeltonikaLocationValidator {
+    mode: LocationValidationMode,
+    min_satellites: u8,
+    max_hdop: Option<f64>,
+    max_speed_kmh: f64,
+    last_location: Option<(DateTime<Utc>, (f64, f64))>,
+}
+
+impl TeltonikaLocationValidator {
+    /// Creates a new [TeltonikaLocationValidator] from the configuration.
+    pub fn new() -> Self {
+        let tunables = &get_config().tunables;
+        Self::with_configuration(
+            tunables.location_validation,
+            tunables.location_min_satellites,
+            tunables.location_max_hdop,
+            tunables.location_max_speed_kmh,
+        )
+    }
+
+    /// Creates a new [TeltonikaLocationValidator] with the given configuration.
+    ///
+    /// # Arguments
+    /// * `mode` - Handling of invalid locations
+    /// * `min_satellites` - Minimum number of satellites of a valid fix
+    /// * `max_hdop` - Maximum HDOP of a valid fix, `None` to not check the HDOP
+    /// * `max_speed_kmh` - Maximum plausible speed in km/h
+    pub fn with_configuration(
+        mode: LocationValidationMode,
+        min_satellites: u8,
+        max_hdop: Option<f64>,
+        max_speed_kmh: f64,
+    ) -> Self {
+        TeltonikaLocationValidator {
+            mode,
+            min_satellites,
+            max_hdop,
+            max_speed_kmh,
+            last_location: None,
+        }
+    }
+
+    /// Gets the handling of invalid locations.
+    pub fn get_mode(&self) -> LocationValidationMode {
+        self.mode
+    }
+
+    /// Validates the location of a Teltonika [AVLRecord].
+    ///
+    /// Valid locations newer than the previous valid location replace it for checking the implied speed.
+    ///
+    /// # Arguments
+    /// * `record` - Record of the location
+    ///
+    /// # Returns
+    /// * The reason for the location being invalid, if invalid. Locations are always valid if validation is off.
+    pub fn validate(&mut self, record: &AVLRecord) -> Result<(), InvalidLocation> {
+        if self.mode == LocationValidationMode::Off {
+            return Ok(());
+        }
+        if !is_valid_position(record.latitude, record.longitude) {
+            return Err(InvalidLocation::Position);
+        }
+        if record.satellites < self.min_satellites {
+            return Err(InvalidLocation::Satellites(record.satellites));
+        }
+        let hdop = record
+            .io_events
+            .iter()
+            .find(|event| event.id == GNSS_HDOP_EVENT_ID)
+            .map(|event| avl_event_io_value_to_u64(&event.value) as f64 / 10.0);
+        if let (Some(hdop), Some(max_hdop)) = (hdop, self.max_hdop) {
+            if hdop > max_hdop {
+                return Err(InvalidLocation::Hdop(hdop));
+            }
+        }
+        if record.speed as f64 > self.max_speed_kmh {
+            return Err(InvalidLocation::Speed(record.speed as f64));
+        }
+        let position = (record.latitude, record.longitude);
+        if let Some((last_timestamp, last_position)) = self.last_location {
+            let elapsed_seconds = (record.timestamp - last_timestamp).num_seconds().abs();
+            if elapsed_seconds > 0 {
+                let implied_speed_kmh = haversine_distance_meters(last_position, position)
+                    / elapsed_seconds as f64
+                    * 3.6;
+                if implied_speed_kmh > self.max_speed_kmh {
+                    return Err(InvalidLocation::Speed(implied_speed_kmh));
+                }
+            }
+            if record.timestamp < last_timestamp {
+                return Ok(());
+            }
+        }
+        self.last_location = Some((record.timestamp, position));
+
+        return Ok(());
+    }
+}
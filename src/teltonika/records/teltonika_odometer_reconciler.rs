@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use nom_teltonika::AVLRecord;
+use serde::Serialize;
+
+use super::{teltonika_odometer_validator::RejectedOdometerReading, TeltonikaOdometerValidator};
+use crate::{
+    config::get_config,
+    teltonika::{avl_event_io_value_to_u64, device_family::DeviceFamily},
+    utils::geo::{haversine_distance_meters, is_valid_position},
+};
+
+/// Name of the counter describing the number of detected odometer discrepancies
+pub const ODOMETER_DISCREPANCIES_METRIC: &str = "receiver_odometer_discrepancies_total";
+
+/// Discrepancy between the distance reported by the odometer and the distance calculated from the GPS positions.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OdometerDiscrepancy {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub odometer_distance_meters: f64,
+    pub gps_distance_meters: f64,
+}
+
+impl OdometerDiscrepancy {
+    /// Gets the deviation of the odometer distance from the GPS distance in percent.
+    pub fn get_deviation_percent(&self) -> f64 {
+        (self.odometer_distance_meters - self.gps_distance_meters) / self.gps_distance_meters
+            * 100.0
+    }
+}
+
+/// State of the ongoing reconciliation window.
+struct ReconciliationWindow {
+    started_at: DateTime<Utc>,
+    start_odometer_meters: u64,
+    gps_distance_meters: f64,
+}
+
+/// Reconciler comparing the distance reported by the CAN odometer of a device to the distance calculated from its GPS positions.
+///
+/// Each time the GPS distance driven reaches `ODOMETER_RECONCILIATION_DISTANCE_METERS` (default 50 km), the odometer delta is compared to it
+/// and a discrepancy is reported if they diverge more than `ODOMETER_DISCREPANCY_THRESHOLD_PERCENT`. Reconciliation is disabled if the threshold is not set.
+///
+/// Odometer readings are validated first, and rejected readings are left out of the reconciliation.
+/// The odometer is read from the IO element of the device family.
+pub struct TeltonikaOdometerReconciler {
+    odometer_event_id: u16,
+    threshold_percent: Option<f64>,
+    reconciliation_distance_meters: f64,
+    validator: TeltonikaOdometerValidator,
+    rejected_readings: Vec<RejectedOdometerReading>,
+    window: Option<ReconciliationWindow>,
+    last_position: Option<(f64, f64)>,
+}
+
+impl TeltonikaOdometerReconciler {
+    /// Creates a new [TeltonikaOdometerReconciler] from the configuration.
+    ///
+    /// # Arguments
+    /// * `device_family` - Family of the device
+    pub fn new(device_family: DeviceFamily) -> Self {
+        let tunables = &get_config().tunables;
+        let mut reconciler = Self::with_configuration(
+            tunables.odometer_discrepancy_threshold_percent,
+            tunables.odometer_reconciliation_distance_meters,
+            TeltonikaOdometerValidator::new(),
+        );
+        reconciler.odometer_event_id = device_family.get_io_mapping().odometer;
+
+        return reconciler;
+    }
+
+    /// Creates a new [TeltonikaOdometerReconciler] with the given configuration.
+    ///
+    /// # Arguments
+    /// * `threshold_percent` - Deviation in percent above which a discrepancy is reported, `None` to disable the reconciliation
+    /// * `reconciliation_distance_meters` - GPS distance in meters driven between the reconciliations
+    /// * `validator` - Validator of the odometer readings
+    pub fn with_configuration(
+        threshold_percent: Option<f64>,
+        reconciliation_distance_meters: f64,
+        validator: TeltonikaOdometerValidator,
+    ) -> Self {
+        TeltonikaOdometerReconciler {
+            odometer_event_id: DeviceFamily::default().get_io_mapping().odometer,
+            threshold_percent,
+            reconciliation_distance_meters,
+            validator,
+            rejected_readings: Vec::new(),
+            window: None,
+            last_position: None,
+        }
+    }
+
+    /// Handles a list of Teltonika [AVLRecord]s.
+    ///
+    /// Odometer readings are validated even if the reconciliation is disabled, and the rejected readings are kept until
+    /// taken with [Self::take_rejected_readings].
+    ///
+    /// # Arguments
+    /// * `records` - Records to handle
+    ///
+    /// # Returns
+    /// * Discrepancies detected within the records.
+    pub fn handle_records(&mut self, records: &[AVLRecord]) -> Vec<OdometerDiscrepancy> {
+        let mut chronological_records = records.iter().collect::<Vec<&AVLRecord>>();
+        chronological_records.sort_by_key(|record| record.timestamp);
+
+        return chronological_records
+            .into_iter()
+            .filter_map(|record| {
+                let odometer_meters = self.get_valid_odometer_meters(record);
+                let threshold_percent = self.threshold_percent?;
+                self.handle_record(record, odometer_meters, threshold_percent)
+            })
+            .collect();
+    }
+
+    /// Takes the odometer readings rejected since the previous call.
+    pub fn take_rejected_readings(&mut self) -> Vec<RejectedOdometerReading> {
+        std::mem::take(&mut self.rejected_readings)
+    }
+
+    /// Gets the odometer reading of a Teltonika [AVLRecord] if it passes validation.
+    fn get_valid_odometer_meters(&mut self, record: &AVLRecord) -> Option<u64> {
+        let odometer_meters = record
+            .io_events
+            .iter()
+            .find(|event| event.id == self.odometer_event_id)
+            .map(|event| avl_event_io_value_to_u64(&event.value))?;
+        if let Err(rejection) = self.validator.validate(record.timestamp, odometer_meters) {
+            self.rejected_readings.push(RejectedOdometerReading {
+                timestamp: record.timestamp,
+                odometer_meters,
+                rejection,
+            });
+            return None;
+        }
+
+        return Some(odometer_meters);
+    }
+
+    /// Handles a single Teltonika [AVLRecord].
+    ///
+    /// # Arguments
+    /// * `record` - Record to handle
+    /// * `odometer_meters` - Validated odometer reading of the record, if any
+    /// * `threshold_percent` - Deviation in percent above which a discrepancy is reported
+    fn handle_record(
+        &mut self,
+        record: &AVLRecord,
+        odometer_meters: Option<u64>,
+        threshold_percent: f64,
+    ) -> Option<OdometerDiscrepancy> {
+        if is_valid_position(record.latitude, record.longitude) {
+            let position = (record.latitude, record.longitude);
+            if let (Some(last_position), Some(window)) = (self.last_position, self.window.as_mut())
+            {
+                window.gps_distance_meters += haversine_distance_meters(last_position, position);
+            }
+            self.last_position = Some(position);
+        }
+        let odometer_meters = odometer_meters?;
+        let window = self.window.get_or_insert(ReconciliationWindow {
+            started_at: record.timestamp,
+            start_odometer_meters: odometer_meters,
+            gps_distance_meters: 0.0,
+        });
+        if window.gps_distance_meters < self.reconciliation_distance_meters {
+            return None;
+        }
+        let discrepancy = OdometerDiscrepancy {
+            started_at: window.started_at,
+            ended_at: record.timestamp,
+            odometer_distance_meters: odometer_meters as f64 - window.start_odometer_meters as f64,
+            gps_distance_meters: window.gps_distance_meters,
+        };
+        self.window = Some(ReconciliationWindow {
+            started_at: record.timestamp,
+            start_odometer_meters: odometer_meters,
+            gps_distance_meters: 0.0,
+        });
+        if discrepancy.get_deviation_percent().abs() <= threshold_percent {
+            return None;
+        }
+
+        return Some(discrepancy);
+    }
+}
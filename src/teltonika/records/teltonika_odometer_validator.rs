@@ -0,0 +1,158 @@
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::get_config;
+
+/// Number of consecutive rejected readings after which the next reading is accepted as the new baseline
+const MAX_CONSECUTIVE_REJECTIONS: u32 = 10;
+/// Name of the counter describing the number of rejected odometer readings by reason
+pub const REJECTED_ODOMETER_READINGS_METRIC: &str = "receiver_rejected_odometer_readings_total";
+
+/// Reason for rejecting an odometer reading
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OdometerRejection {
+    /// The reading is lower than an earlier reading
+    Decrease { previous_meters: u64 },
+    /// The reading advanced more than allowed within the time since the previous reading
+    Jump {
+        previous_meters: u64,
+        max_delta_meters: u64,
+    },
+}
+
+impl OdometerRejection {
+    /// Gets the label of the reason in metrics
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            OdometerRejection::Decrease { .. } => "decrease",
+            OdometerRejection::Jump { .. } => "jump",
+        }
+    }
+}
+
+impl fmt::Display for OdometerRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OdometerRejection::Decrease { previous_meters } => {
+                write!(f, "decreased from {} m", previous_meters)
+            }
+            OdometerRejection::Jump {
+                previous_meters,
+                max_delta_meters,
+            } => write!(
+                f,
+                "jumped from {} m by more than {} m",
+                previous_meters, max_delta_meters
+            ),
+        }
+    }
+}
+
+/// Odometer reading rejected by the [TeltonikaOdometerValidator]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedOdometerReading {
+    pub timestamp: DateTime<Utc>,
+    pub odometer_meters: u64,
+    pub rejection: OdometerRejection,
+}
+
+/// Validator filtering out bogus readings of the CAN odometer of a device.
+///
+/// A reading is rejected if it is lower than the previous reading, or if it advanced more than `ODOMETER_MAX_DELTA_METERS`
+/// (default 5 km) per `ODOMETER_DELTA_WINDOW_SECONDS` (default 60 s) elapsed since the previous reading. After
+/// [MAX_CONSECUTIVE_REJECTIONS] rejected readings in a row, e.g. when the device is moved to another vehicle, the next
+/// reading is accepted as the new baseline.
+pub struct TeltonikaOdometerValidator {
+    max_delta_meters: Option<u64>,
+    delta_window: Duration,
+    last_reading: Option<(DateTime<Utc>, u64)>,
+    consecutive_rejections: u32,
+}
+
+impl TeltonikaOdometerValidator {
+    /// Creates a new [TeltonikaOdometerValidator] from the configuration.
+    pub fn new() -> Self {
+        let tunables = &get_config().tunables;
+        Self::with_configuration(
+            tunables.odometer_max_delta_meters,
+            tunables.odometer_delta_window_seconds,
+        )
+    }
+
+    /// Creates a new [TeltonikaOdometerValidator] with the given configuration.
+    ///
+    /// # Arguments
+    /// * `max_delta_meters` - Maximum distance in meters the odometer may advance per window, `None` to not reject jumps
+    /// * `delta_window_seconds` - Length of the window in seconds
+    pub fn with_configuration(max_delta_meters: Option<u64>, delta_window_seconds: i64) -> Self {
+        TeltonikaOdometerValidator {
+            max_delta_meters,
+            delta_window: Duration::seconds(delta_window_seconds.max(1)),
+            last_reading: None,
+            consecutive_rejections: 0,
+        }
+    }
+
+    /// Validates an odometer reading.
+    ///
+    /// Readings older than the previous accepted reading are validated against it but don't replace it.
+    ///
+    /// # Arguments
+    /// * `timestamp` - Timestamp of the record of the reading
+    /// * `odometer_meters` - Total mileage in meters
+    ///
+    /// # Returns
+    /// * The reason for rejecting the reading, if rejected
+    pub fn validate(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        odometer_meters: u64,
+    ) -> Result<(), OdometerRejection> {
+        let Some((last_timestamp, last_meters)) = self.last_reading else {
+            self.last_reading = Some((timestamp, odometer_meters));
+            return Ok(());
+        };
+        let is_later = timestamp >= last_timestamp;
+        let ((earlier_meters, later_meters), elapsed) = if is_later {
+            ((last_meters, odometer_meters), timestamp - last_timestamp)
+        } else {
+            ((odometer_meters, last_meters), last_timestamp - timestamp)
+        };
+        let rejection = if later_meters < earlier_meters {
+            Some(OdometerRejection::Decrease {
+                previous_meters: last_meters,
+            })
+        } else {
+            self.max_delta_meters.and_then(|max_delta_meters| {
+                let windows = (elapsed.num_seconds() as f64
+                    / self.delta_window.num_seconds() as f64)
+                    .ceil()
+                    .max(1.0);
+                let max_delta_meters = (max_delta_meters as f64 * windows) as u64;
+                (later_meters - earlier_meters > max_delta_meters).then_some(
+                    OdometerRejection::Jump {
+                        previous_meters: last_meters,
+                        max_delta_meters,
+                    },
+                )
+            })
+        };
+        match rejection {
+            Some(rejection) if self.consecutive_rejections < MAX_CONSECUTIVE_REJECTIONS => {
+                self.consecutive_rejections += 1;
+                return Err(rejection);
+            }
+            Some(_) => {
+                self.last_reading = Some((timestamp, odometer_meters));
+            }
+            None if is_later => {
+                self.last_reading = Some((timestamp, odometer_meters));
+            }
+            None => {}
+        }
+        self.consecutive_rejections = 0;
+
+        return Ok(());
+    }
+}
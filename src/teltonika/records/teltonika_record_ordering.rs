@@ -0,0 +1,62 @@
+use std::{cmp::Reverse, str::FromStr};
+
+use nom_teltonika::{AVLRecord, Priority};
+
+/// Order in which the records of a frame are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RecordOrdering {
+    /// Records are handled in the order the device sent them, which is oldest first.
+    #[default]
+    Received,
+    /// Records are handled by timestamp, oldest first.
+    Timestamp,
+    /// Panic and high priority records are handled before low priority records, each by timestamp.
+    ///
+    /// Devices draining stored records together with live data send big frames, and this gets the important records to the API first.
+    Priority,
+}
+
+impl FromStr for RecordOrdering {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "received" => Ok(RecordOrdering::Received),
+            "timestamp" => Ok(RecordOrdering::Timestamp),
+            "priority" => Ok(RecordOrdering::Priority),
+            _ => Err(format!(
+                "Unknown record ordering [{}], expected received, timestamp or priority",
+                value
+            )),
+        }
+    }
+}
+
+/// Orders the records of a frame.
+///
+/// Sorting is stable, so records with equal keys stay in the order they were received.
+///
+/// # Arguments
+/// * `records` - Records of the frame
+/// * `ordering` - Order to handle the records in
+pub fn order_records(records: &mut [AVLRecord], ordering: RecordOrdering) {
+    match ordering {
+        RecordOrdering::Received => (),
+        RecordOrdering::Timestamp => records.sort_by_key(|record| record.timestamp),
+        RecordOrdering::Priority => records.sort_by_key(|record| {
+            (
+                Reverse(get_priority_rank(&record.priority)),
+                record.timestamp,
+            )
+        }),
+    }
+}
+
+/// Gets the rank of a record priority, higher being more important.
+fn get_priority_rank(priority: &Priority) -> u8 {
+    match priority {
+        Priority::Low => 0,
+        Priority::High => 1,
+        Priority::Panic => 2,
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+use nom_teltonika::{AVLRecord, EventGenerationCause};
+
+/// What triggered the device to generate a record.
+///
+/// See [Teltonika Documentation](https://wiki.teltonika-gps.com/view/Codec#Codec_8) for more detailed information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordTrigger {
+    /// Record was generated by the data acquisition period of the device.
+    Periodic,
+    /// Record was generated by a change of the IO element with the given ID, e.g. a driver card being inserted.
+    Eventual(u16),
+}
+
+impl RecordTrigger {
+    /// Gets the trigger of a record.
+    ///
+    /// Codec 8 records with trigger event ID 0 are periodic, and Codec 16 records also tell the generation type explicitly.
+    ///
+    /// # Arguments
+    /// * `record` - Record to get the trigger of
+    pub fn from_record(record: &AVLRecord) -> Self {
+        match (&record.generation_type, record.trigger_event_id) {
+            (Some(EventGenerationCause::Periodical), _) | (_, 0) => RecordTrigger::Periodic,
+            (_, trigger_event_id) => RecordTrigger::Eventual(trigger_event_id),
+        }
+    }
+}
+
+impl fmt::Display for RecordTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordTrigger::Periodic => write!(f, "periodic"),
+            RecordTrigger::Eventual(event_id) => write!(f, "eventual ({})", event_id),
+        }
+    }
+}
+
+/// Records an event handler opts into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordSubscription {
+    /// Both periodic and eventual records.
+    #[default]
+    All,
+    /// Periodic records only, e.g. for sampling values at the data acquisition period.
+    #[allow(dead_code)]
+    Periodic,
+    /// Eventual records only, regardless of the IO element triggering them.
+    Eventual,
+    /// Eventual records triggered by the IO element with the given ID only, e.g. driver card presence.
+    EventualOf(u16),
+}
+
+impl RecordSubscription {
+    /// Checks whether a record with the given trigger is handled by the subscriber.
+    ///
+    /// # Arguments
+    /// * `trigger` - Trigger of the record
+    pub fn matches(&self, trigger: RecordTrigger) -> bool {
+        match (self, trigger) {
+            (RecordSubscription::All, _) => true,
+            (RecordSubscription::Periodic, RecordTrigger::Periodic) => true,
+            (RecordSubscription::Eventual, RecordTrigger::Eventual(_)) => true,
+            (
+                RecordSubscription::EventualOf(event_id),
+                RecordTrigger::Eventual(trigger_event_id),
+            ) => *event_id == trigger_event_id,
+            _ => false,
+        }
+    }
+}
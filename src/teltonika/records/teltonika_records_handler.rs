@@ -1,52 +1,162 @@
-use std::path::Path;
+use std::{
+    collections::BTreeMap,
+    mem::size_of,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
 
 use crate::{
-    telematics_cache::Cacheable,
+    admin::QUEUE_DEPTH_METRIC,
+    batching::{self, EventBatch},
+    config::get_config,
+    invariants,
+    load_shedding::{self, OVERLOAD_LOCATION_INTERVAL_SECONDS},
+    metrics, processing,
+    retry::RetryBackoff,
+    telematics_cache::{
+        cached_event::CachedEvent, failed_api_request::FailedApiRequest, get_cache_lock,
+        restore_cache, take_cache, Cacheable,
+    },
     teltonika::{
         avl_event_io_value_to_u8,
+        device_family::DeviceFamily,
         events::{
-            DriverOneCardIdEventHandler, DriverOneDriveStateEventHandler, SpeedEventHandler,
-            TeltonikaEventHandlers,
+            AxleWeightEventHandler, BleSensorEventHandler, DriverOneCardIdEventHandler,
+            DriverOneDriveStateEventHandler, EngineHoursEventHandler, EngineRpmEventHandler,
+            FaultCodeEventHandler, GeofenceEventHandler, HarshDrivingEventHandler,
+            SpeedEventHandler, TeltonikaEventHandlers, VinEventHandler,
+        },
+        io_elements::describe_io_element,
+        records::{
+            teltonika_location_validator::{LocationValidationMode, INVALID_LOCATIONS_METRIC},
+            teltonika_record_ordering::order_records,
+            FrameProvenance, RecordOrdering, RecordTrigger, TeltonikaLocationValidator,
+            TeltonikaTimestampValidator,
         },
         DRIVER_ONE_CARD_PRESENCE_EVENT_ID,
     },
-    utils::get_vehicle_management_api_config,
+    utils::{
+        api::{get_truck_id_by_vin, VehicleApi, VehicleApiError},
+        api_routing::get_api_routing,
+        log_throttle,
+    },
 };
 use chrono::{DateTime, Utc};
-use log::debug;
-use nom_teltonika::{AVLEventIO, AVLRecord};
-use vehicle_management_service::{
-    apis::trucks_api::CreateTruckLocationParams, models::TruckLocation,
-};
+use log::{debug, error, info};
+use nom_teltonika::{AVLEventIO, AVLEventIOValue, AVLRecord};
+use vehicle_management_service::models::TruckLocation;
 
-use super::TeltonikaVinHandler;
+/// Name of the gauge describing the approximate memory held by records waiting to be sent
+const CONNECTION_MEMORY_METRIC: &str = "receiver_connection_memory_bytes";
+/// Name of the counter describing the number of records moved to the cache due to the memory cap
+const MEMORY_CAPPED_RECORDS_METRIC: &str = "receiver_memory_capped_records_total";
+/// Name of the counter describing the number of truck IDs of connected devices changed by refreshing them
+pub const TRUCK_ID_CHANGES_METRIC: &str = "receiver_truck_id_changes_total";
 
 /// Handler for Teltonika records.
 pub struct TeltonikaRecordsHandler {
     base_cache_path: Box<Path>,
-    truck_id: Option<String>,
+    truck_id: Mutex<Option<String>>,
+    truck_vin: Mutex<Option<String>>,
+    frame_provenance: Mutex<Option<FrameProvenance>>,
     event_handlers: Vec<TeltonikaEventHandlers>,
     imei: String,
+    device_family: DeviceFamily,
+    last_location_timestamp: AtomicI64,
+    max_memory_bytes: Option<usize>,
+    record_ordering: RecordOrdering,
+    retry_backoff: Mutex<RetryBackoff>,
+    is_retrying: AtomicBool,
+    location_batch: Option<EventBatch<TruckLocation>>,
+    location_validator: Mutex<TeltonikaLocationValidator>,
+    timestamp_validator: TeltonikaTimestampValidator,
 }
 
 impl TeltonikaRecordsHandler {
     /// Creates a new [TeltonikaRecordsHandler].
-    pub fn new(base_cache_path: &Path, truck_id: Option<String>, imei: String) -> Self {
-        TeltonikaRecordsHandler {
-            base_cache_path: base_cache_path.into(),
-            truck_id,
-            event_handlers: vec![
-                TeltonikaEventHandlers::SpeedEventHandler((SpeedEventHandler, imei.clone())),
-                TeltonikaEventHandlers::DriverOneCardIdEventHandler((
-                    DriverOneCardIdEventHandler,
+    ///
+    /// Events are read from the IO elements of the device family, e.g. the speed and VIN from the CAN bus for FMB1xx devices.
+    /// Memory held by records waiting to be sent is capped by `MAX_CONNECTION_MEMORY_BYTES` environment variable if set.
+    /// Records of a frame are handled in the order set by `RECORD_ORDERING` environment variable.
+    /// Locations and speeds of a known truck are sent in batches if `EVENT_BATCH_WINDOW_SECONDS` environment variable is set.
+    /// Locations are validated as configured by `LOCATION_VALIDATION` environment variable.
+    /// Record timestamps are validated against server time as configured by `RECORD_TIMESTAMP_POLICY` environment variable.
+    /// Events Vehicle Management Service has no endpoints for are handled only with the `pending-endpoints` feature.
+    pub fn new(
+        base_cache_path: &Path,
+        truck_id: Option<String>,
+        imei: String,
+        device_family: DeviceFamily,
+    ) -> Self {
+        let tunables = &get_config().tunables;
+        let mut event_handlers = vec![
+            TeltonikaEventHandlers::SpeedEventHandler((
+                SpeedEventHandler::new(device_family),
+                imei.clone(),
+            )),
+            TeltonikaEventHandlers::DriverOneCardIdEventHandler((
+                DriverOneCardIdEventHandler::default(),
+                imei.clone(),
+            )),
+            TeltonikaEventHandlers::DriverOneDriveStateEventHandler((
+                DriverOneDriveStateEventHandler::default(),
+                imei.clone(),
+            )),
+        ];
+        // Events Vehicle Management Service has no endpoints for are decoded only with the `pending-endpoints` feature
+        if cfg!(feature = "pending-endpoints") {
+            event_handlers.extend([
+                TeltonikaEventHandlers::HarshDrivingEventHandler((
+                    HarshDrivingEventHandler::default(),
                     imei.clone(),
                 )),
-                TeltonikaEventHandlers::DriverOneDriveStateEventHandler((
-                    DriverOneDriveStateEventHandler,
+                TeltonikaEventHandlers::BleSensorEventHandler((
+                    BleSensorEventHandler::new(device_family),
                     imei.clone(),
                 )),
-            ],
+                TeltonikaEventHandlers::AxleWeightEventHandler((
+                    AxleWeightEventHandler::default(),
+                    imei.clone(),
+                )),
+                TeltonikaEventHandlers::EngineHoursEventHandler((
+                    EngineHoursEventHandler::default(),
+                    imei.clone(),
+                )),
+                TeltonikaEventHandlers::EngineRpmEventHandler((
+                    EngineRpmEventHandler::default(),
+                    imei.clone(),
+                )),
+                TeltonikaEventHandlers::FaultCodeEventHandler((
+                    FaultCodeEventHandler::default(),
+                    imei.clone(),
+                )),
+                TeltonikaEventHandlers::GeofenceEventHandler((
+                    GeofenceEventHandler::default(),
+                    imei.clone(),
+                )),
+            ]);
+        }
+
+        TeltonikaRecordsHandler {
+            base_cache_path: base_cache_path.into(),
+            truck_id: Mutex::new(truck_id),
+            truck_vin: Mutex::new(None),
+            frame_provenance: Mutex::new(None),
+            event_handlers,
             imei,
+            device_family,
+            last_location_timestamp: AtomicI64::new(0),
+            max_memory_bytes: tunables.max_connection_memory_bytes,
+            record_ordering: tunables.record_ordering,
+            retry_backoff: Mutex::new(RetryBackoff::new()),
+            is_retrying: AtomicBool::new(false),
+            location_batch: EventBatch::from_config(),
+            location_validator: Mutex::new(TeltonikaLocationValidator::new()),
+            timestamp_validator: TeltonikaTimestampValidator::new(),
         }
     }
 
@@ -54,54 +164,164 @@ impl TeltonikaRecordsHandler {
         &self.imei
     }
 
+    /// Gets the IMEI of the device.
+    pub fn get_imei(&self) -> &str {
+        &self.imei
+    }
+
     /// Gets the base cache path for the handler.
     #[cfg(test)]
     pub fn get_base_cache_path(&self) -> &Path {
         self.base_cache_path.as_ref()
     }
 
+    /// Gets the cap for memory held per connection, if set.
+    pub fn get_max_memory_bytes(&self) -> Option<usize> {
+        self.max_memory_bytes
+    }
+
+    /// Sets the cap for memory held by records waiting to be sent.
+    #[cfg(test)]
+    pub fn set_max_memory_bytes(&mut self, max_memory_bytes: Option<usize>) {
+        self.max_memory_bytes = max_memory_bytes;
+    }
+
+    /// Sets the backoff of the retries of the cached events.
+    #[cfg(test)]
+    pub fn set_retry_backoff(&mut self, retry_backoff: RetryBackoff) {
+        self.retry_backoff = Mutex::new(retry_backoff);
+    }
+
+    /// Sets the order in which the records of a frame are handled.
+    #[cfg(test)]
+    pub fn set_record_ordering(&mut self, record_ordering: RecordOrdering) {
+        self.record_ordering = record_ordering;
+    }
+
+    /// Sets the validator of the locations of the records.
+    #[cfg(test)]
+    pub fn set_location_validator(&mut self, location_validator: TeltonikaLocationValidator) {
+        self.location_validator = Mutex::new(location_validator);
+    }
+
+    /// Sets the validator of the record timestamps.
+    #[cfg(test)]
+    pub fn set_timestamp_validator(&mut self, timestamp_validator: TeltonikaTimestampValidator) {
+        self.timestamp_validator = timestamp_validator;
+    }
+
     /// Sets the truck ID for the handler.
     ///
     /// # Arguments
     /// * `truck_id` - The truck ID to set.
-    pub fn set_truck_id(&mut self, truck_id: Option<String>) {
-        self.truck_id = truck_id;
+    pub fn set_truck_id(&self, truck_id: Option<String>) {
+        *self.truck_id.lock().unwrap() = truck_id;
+    }
+
+    /// Sets the VIN of the truck the device is installed in.
+    ///
+    /// # Arguments
+    /// * `truck_vin` - The VIN to set.
+    pub fn set_truck_vin(&self, truck_vin: Option<String>) {
+        *self.truck_vin.lock().unwrap() = truck_vin;
+    }
+
+    /// Gets the VIN of the truck the device is installed in, if received yet.
+    pub fn get_truck_vin(&self) -> Option<String> {
+        return self.truck_vin.lock().unwrap().clone();
+    }
+
+    /// Refreshes the truck ID of the handler by looking it up by the VIN again.
+    ///
+    /// The association of a device with a truck may change during a long-lived connection, e.g. when the device is
+    /// moved to another vehicle, so the truck ID is swapped if the VIN now belongs to another truck. The truck ID is
+    /// kept if the VIN is not found, so that events aren't cached because of a failed lookup. Devices whose truck is
+    /// not known yet look it up when they send a frame instead.
+    ///
+    /// # Returns
+    /// * Whether the truck ID changed
+    pub async fn refresh_truck_id(&self) -> bool {
+        let Some(truck_id) = self.get_truck_id() else {
+            return false;
+        };
+        let truck_vin = self.get_truck_vin();
+        if truck_vin.is_none() || processing::is_maintenance_mode() {
+            return false;
+        }
+        let Some(found_truck_id) = get_truck_id_by_vin(&truck_vin)
+            .await
+            .map(|id| id.to_string())
+        else {
+            return false;
+        };
+        if found_truck_id == truck_id {
+            return false;
+        }
+        info!(target: self.log_target(),
+            "Truck ID of VIN [{}] changed from [{}] to [{}]",
+            truck_vin.unwrap_or_default(),
+            truck_id,
+            found_truck_id
+        );
+        get_api_routing().register_truck(&self.imei, &found_truck_id);
+        self.set_truck_id(Some(found_truck_id));
+        metrics::increment_counter(TRUCK_ID_CHANGES_METRIC, &[]);
+
+        return true;
+    }
+
+    /// Sets the provenance of the frame whose records are handled next.
+    ///
+    /// # Arguments
+    /// * `frame_provenance` - Provenance of the frame.
+    pub fn set_frame_provenance(&self, frame_provenance: Option<FrameProvenance>) {
+        *self.frame_provenance.lock().unwrap() = frame_provenance;
+    }
+
+    /// Gets the provenance of the frame whose records are handled.
+    fn get_frame_provenance(&self) -> Option<FrameProvenance> {
+        return self.frame_provenance.lock().unwrap().clone();
+    }
+
+    /// Gets the truck ID of the handler.
+    pub fn get_truck_id(&self) -> Option<String> {
+        return self.truck_id.lock().unwrap().clone();
+    }
+
+    /// Forgets the last reported driver one card.
+    ///
+    /// Should be called once the driver one card has been removed so that the next inserted card is reported again.
+    pub fn reset_driver_one_card(&self) {
+        for handler in self.event_handlers.iter() {
+            if let TeltonikaEventHandlers::DriverOneCardIdEventHandler((handler, _)) = handler {
+                handler.reset_last_driver_card_id();
+            }
+        }
     }
 
     /// Gets the truck VIN from a list of Teltonika [AVLRecord]s.
     ///
-    /// This method will iterate over the records and find the VIN parts. If all three parts are found, they will be combined into a single VIN according to Teltonika specification.
-    /// First VIN part has id 233, second 234 and third 235.
+    /// This method will pass the events of the records to a [VinEventHandler] until all parts of the VIN are found.
     ///
     /// # Arguments
     /// * `teltonika_records` - The list of [AVLRecord]s to get the VIN from.
     ///
     /// # Returns
-    /// * The combined VIN if all three parts are found, otherwise None.
-    pub fn get_truck_vin_from_records(&self, teltonika_records: &Vec<AVLRecord>) -> Option<String> {
-        let mut teltonika_vin = TeltonikaVinHandler::new();
+    /// * The combined VIN if all parts are found, otherwise None.
+    pub fn get_truck_vin_from_records(&self, teltonika_records: &[AVLRecord]) -> Option<String> {
+        let mut vin_handler = VinEventHandler::new(self.device_family);
 
         for record in teltonika_records.iter() {
             for event in record.io_events.iter() {
-                if teltonika_vin
-                    .get_teltonika_vin_event_ids()
-                    .contains(&event.id)
-                {
-                    match &event.id {
-                        233 => teltonika_vin.set_part_1(&event.value),
-                        234 => teltonika_vin.set_part_2(&event.value),
-                        235 => teltonika_vin.set_part_3(&event.value),
-                        _ => (),
-                    }
-                }
+                vin_handler.handle_event(event);
             }
-            // If we have all three parts, we can break the loop
-            if teltonika_vin.get_is_complete() {
+            // If we have all parts, we can break the loop
+            if vin_handler.get_is_complete() {
                 break;
             }
         }
 
-        return teltonika_vin.get_vin();
+        return vin_handler.get_vin();
     }
 
     /// Returns the driver one card presence from a list of Teltonika [AVLRecord]s.
@@ -113,12 +333,15 @@ impl TeltonikaRecordsHandler {
     /// * Tuple where first value is the driver one card presence and second value is the latest [AVLRecord] with the driver one card presence event.
     pub fn get_driver_one_card_presence_from_records(
         &self,
-        teltonika_records: &mut Vec<AVLRecord>,
+        teltonika_records: &mut [AVLRecord],
     ) -> Option<(bool, Option<DateTime<Utc>>)> {
-        teltonika_records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        teltonika_records.sort_by_key(|record| std::cmp::Reverse(record.timestamp));
         let driver_one_card_presence_records: Vec<&AVLRecord> = teltonika_records
             .iter()
-            .filter(|record| record.trigger_event_id == DRIVER_ONE_CARD_PRESENCE_EVENT_ID)
+            .filter(|record| {
+                RecordTrigger::from_record(record)
+                    == RecordTrigger::Eventual(DRIVER_ONE_CARD_PRESENCE_EVENT_ID)
+            })
             .collect();
         if let Some(latest_record) = driver_one_card_presence_records.first() {
             let latest_event = latest_record
@@ -126,23 +349,85 @@ impl TeltonikaRecordsHandler {
                 .iter()
                 .find(|event| event.id == DRIVER_ONE_CARD_PRESENCE_EVENT_ID);
 
-            return match latest_event {
-                Some(event) => Some((
+            return latest_event.map(|event| {
+                (
                     avl_event_io_value_to_u8(&event.value) == 1,
                     Some(latest_record.timestamp),
-                )),
-                None => None,
-            };
+                )
+            });
         }
 
         return None;
     }
 
+    /// Validates the timestamps of the records of a frame against server time.
+    ///
+    /// Records outside the window are clamped to server time or removed as configured.
+    ///
+    /// # Arguments
+    /// * `teltonika_records` - Records of the frame
+    ///
+    /// # Returns
+    /// * Number of removed records
+    pub fn validate_record_timestamps(&self, teltonika_records: &mut Vec<AVLRecord>) -> usize {
+        return self.timestamp_validator.validate_records(
+            &self.imei,
+            teltonika_records,
+            Utc::now(),
+        );
+    }
+
     /// Handles a list of Teltonika [AVLRecord]s.
-    pub async fn handle_records(&self, teltonika_records: Vec<AVLRecord>) {
+    ///
+    /// If the records would hold more memory than the configured cap while waiting to be sent,
+    /// the excess records are moved to the disk-backed cache right away and sent later when the cache is purged.
+    ///
+    /// # Returns
+    /// * Number of records sent or cached
+    pub async fn handle_records(&self, mut teltonika_records: Vec<AVLRecord>) -> usize {
+        let mut handled_count = 0;
+        // Records are ordered before applying the memory cap, so that the records handled first are kept in memory
+        order_records(&mut teltonika_records, self.record_ordering);
+        if let Some(max_memory_bytes) = self.max_memory_bytes {
+            let mut retained_bytes = 0;
+            let retained_count = teltonika_records
+                .iter()
+                .take_while(|record| {
+                    retained_bytes += estimate_record_size(record);
+                    retained_bytes <= max_memory_bytes
+                })
+                .count();
+            let excess_records = teltonika_records.split_off(retained_count);
+            if !excess_records.is_empty() {
+                debug!(target: self.log_target(),
+                    "Memory cap of {} bytes exceeded, caching {} records",
+                    max_memory_bytes,
+                    excess_records.len()
+                );
+                for record in excess_records.iter() {
+                    self.cache_record(record).await;
+                    handled_count += 1;
+                    metrics::increment_counter(
+                        MEMORY_CAPPED_RECORDS_METRIC,
+                        &[("imei", &self.imei)],
+                    );
+                }
+            }
+        }
+
+        let mut held_bytes = teltonika_records
+            .iter()
+            .map(estimate_record_size)
+            .sum::<usize>();
         for record in teltonika_records.iter() {
+            self.report_memory_usage(held_bytes);
             self.handle_record(record).await;
+            handled_count += 1;
+            held_bytes -= estimate_record_size(record);
         }
+        self.report_memory_usage(0);
+
+        return handled_count;
     }
 
     /// Handles a single Teltonika [AVLRecord].
@@ -150,124 +435,478 @@ impl TeltonikaRecordsHandler {
     /// This method will iterate over the known event handlers and pass appropriate events to them.
     pub async fn handle_record(&self, record: &AVLRecord) {
         self.handle_record_location(record).await;
-        debug!(target: self.log_target(), "Record trigger event ID: {}", record.trigger_event_id);
+        self.handle_record_events(record, self.get_truck_id()).await;
+    }
+
+    /// Caches Teltonika [AVLRecord]s without sending them to the Vehicle Management Service.
+    ///
+    /// # Arguments
+    /// * `teltonika_records` - Records to cache
+    ///
+    /// # Returns
+    /// * Number of cached records
+    pub async fn cache_records(&self, teltonika_records: &[AVLRecord]) -> usize {
+        let mut cached_count = 0;
+        for record in teltonika_records.iter() {
+            self.cache_record(record).await;
+            cached_count += 1;
+        }
+
+        return cached_count;
+    }
+
+    /// Caches a single Teltonika [AVLRecord] without sending it to the Vehicle Management Service.
+    async fn cache_record(&self, record: &AVLRecord) {
+        if self.validate_location(record) {
+            self.cache_location(TruckLocation::from_teltonika_record(record).unwrap())
+                .await;
+        }
+        self.handle_record_events(record, None).await;
+    }
+
+    /// Caches a location to be sent once the location cache is purged.
+    ///
+    /// # Arguments
+    /// * `location_data` - Location to cache
+    async fn cache_location(&self, location_data: TruckLocation) {
+        let cache_lock = get_cache_lock(&self.base_cache_path);
+        let _cache_guard = cache_lock.lock().await;
+        CachedEvent::new(location_data)
+            .write_to_file(self.base_cache_path.to_str().unwrap())
+            .expect("Error caching location");
+    }
+
+    /// Reports the approximate memory held by records waiting to be sent.
+    ///
+    /// # Arguments
+    /// * `held_bytes` - Approximate memory held in bytes
+    fn report_memory_usage(&self, held_bytes: usize) {
+        metrics::set_gauge_with_high_water_mark(
+            CONNECTION_MEMORY_METRIC,
+            &[("imei", &self.imei)],
+            held_bytes as f64,
+        );
+    }
+
+    /// Passes the events of a single Teltonika [AVLRecord] to the appropriate event handlers.
+    ///
+    /// # Arguments
+    /// * `record` - Record to handle
+    /// * `truck_id` - Truck ID to send the events for. Events are cached if not known.
+    async fn handle_record_events(&self, record: &AVLRecord, truck_id: Option<String>) {
+        let trigger = RecordTrigger::from_record(record);
+        debug!(target: self.log_target(), "Record trigger: {}", trigger);
         for handler in self.event_handlers.iter() {
-            let trigger_event_id = handler.get_trigger_event_id();
-            if trigger_event_id.is_some() && record.trigger_event_id != trigger_event_id.unwrap() {
+            if !handler.get_record_subscription().matches(trigger) {
                 continue;
             }
-            let events = handler
+            let mut events = handler
                 .get_event_ids()
                 .iter()
-                .map(|id| {
+                .flat_map(|id| {
                     record
                         .io_events
                         .iter()
                         .filter(|event| event.id == *id)
                         .collect::<Vec<&AVLEventIO>>()
                 })
-                .flatten()
                 .collect::<Vec<&AVLEventIO>>();
-            // If we don't have any events or the number of events is not the same as the number of event IDs, we skip the handler
-            if events.is_empty() || handler.get_event_ids().len() != events.len() {
+            // If the number of events is not the same as the number of event IDs, we skip the handler
+            if handler.get_event_ids().len() != events.len() {
+                continue;
+            }
+            events.extend(
+                record
+                    .io_events
+                    .iter()
+                    .filter(|event| handler.get_optional_event_ids().contains(&event.id)),
+            );
+            // Handlers without required events are passed records with any of their optional events
+            if events.is_empty() {
+                continue;
+            }
+            if handler.is_sheddable() && load_shedding::is_overloaded() {
+                load_shedding::record_shed_event(
+                    handler
+                        .get_cache_file_path()
+                        .trim_end_matches("_cache.json"),
+                );
                 continue;
             }
             handler
                 .handle_events(
-                    record.trigger_event_id,
+                    trigger,
                     events,
                     record.timestamp.timestamp(),
-                    self.truck_id.clone(),
+                    (record.latitude, record.longitude),
+                    truck_id.clone(),
                     self.base_cache_path.clone(),
+                    self.get_frame_provenance(),
                 )
                 .await;
         }
+        for event in record.io_events.iter() {
+            let is_handled = self.device_family.get_io_mapping().vin.contains(&event.id)
+                || self.event_handlers.iter().any(|handler| {
+                    handler.get_event_ids().contains(&event.id)
+                        || handler.get_optional_event_ids().contains(&event.id)
+                });
+            if is_handled {
+                continue;
+            }
+            if let Some(suppressed) =
+                log_throttle::throttle(&format!("{}:no_handler:{}", self.imei, event.id))
+            {
+                debug!(target: self.log_target(),
+                    "No handler found for event {}{}",
+                    describe_io_element(event.id),
+                    log_throttle::describe_suppressed(suppressed)
+                );
+            }
+        }
     }
     /// Purges the cache if Truck ID is known.
     pub async fn purge_cache(&self) {
-        if self.truck_id.is_none() {
+        let Some(truck_id) = self.get_truck_id() else {
             return;
-        }
+        };
 
-        self.purge_location_cache().await;
+        self.purge_location_cache(&truck_id).await;
 
         for handler in self.event_handlers.iter() {
             handler
-                .purge_cache(self.truck_id.clone().unwrap(), self.base_cache_path.clone())
+                .purge_cache(truck_id.clone(), self.base_cache_path.clone())
                 .await;
         }
     }
 
+    /// Retries sending the cached events if the truck is known and the retry is due.
+    ///
+    /// Retries back off exponentially while events are left in the caches after purging them.
+    /// A retry already in progress, e.g. a scheduled one while a frame is dispatched, is not started again.
+    pub async fn retry_cached_events(&self) {
+        if self.get_truck_id().is_none()
+            || !self.retry_backoff.lock().unwrap().is_due(Utc::now())
+            || self.get_cached_events_count() == 0
+        {
+            return;
+        }
+        self.retry_cached_events_now().await;
+    }
+
+    /// Retries sending the cached events regardless of the backoff, e.g. when forced through the admin API.
+    ///
+    /// # Returns
+    /// * Whether the events were retried, `false` if the truck is not known yet or a retry is already in progress
+    pub async fn retry_cached_events_now(&self) -> bool {
+        let Some(truck_id) = self.get_truck_id() else {
+            return false;
+        };
+        if self.is_retrying.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        let now = Utc::now();
+        info!(target: self.log_target(), "Purging cache for truck ID: [{}]...", truck_id);
+        self.purge_cache().await;
+        let remaining_count = self.get_cached_events_count();
+        let mut retry_backoff = self.retry_backoff.lock().unwrap();
+        if remaining_count > 0 {
+            retry_backoff.record_failure(now);
+            debug!(target: self.log_target(),
+                "{} events left in the cache, retrying at {}",
+                remaining_count,
+                retry_backoff.get_next_retry_at().unwrap_or(now)
+            );
+        } else {
+            retry_backoff.record_success();
+        }
+        self.is_retrying.store(false, Ordering::SeqCst);
+
+        return true;
+    }
+
+    /// Gets the number of events waiting in the caches of the device.
+    pub fn get_cached_events_count(&self) -> usize {
+        return self.get_cache_depths().values().sum();
+    }
+
+    /// Gets the number of events waiting in each cache of the device.
+    ///
+    /// # Returns
+    /// * Number of events by cache file path
+    pub fn get_cache_depths(&self) -> BTreeMap<&'static str, usize> {
+        let mut cache_depths = BTreeMap::new();
+        cache_depths.insert(
+            TruckLocation::FILE_PATH,
+            TruckLocation::get_cache_depth(self.base_cache_path.to_str().unwrap()),
+        );
+        for handler in self.event_handlers.iter() {
+            cache_depths.insert(
+                handler.get_cache_file_path(),
+                handler.get_cache_depth(&self.base_cache_path),
+            );
+        }
+
+        return cache_depths;
+    }
+
+    /// Reports the number of items waiting in each cache of the device as queue depth metrics.
+    pub fn report_cache_depths(&self) {
+        for (cache_file_path, depth) in self.get_cache_depths() {
+            self.report_cache_depth(cache_file_path, depth);
+        }
+    }
+
+    /// Reports the number of items waiting in a single cache.
+    ///
+    /// # Arguments
+    /// * `cache_file_path` - File path of the cache
+    /// * `depth` - Number of items in the cache
+    fn report_cache_depth(&self, cache_file_path: &str, depth: usize) {
+        invariants::check_cache_depth(
+            &format!(
+                "{}/{}",
+                self.base_cache_path.to_str().unwrap(),
+                cache_file_path
+            ),
+            depth,
+        );
+        metrics::set_gauge_with_high_water_mark(
+            QUEUE_DEPTH_METRIC,
+            &[
+                ("imei", &self.imei),
+                ("queue", cache_file_path.trim_end_matches(".json")),
+            ],
+            depth as f64,
+        );
+    }
+
     /// Handles a Teltonika [AVLRecord] location.
     ///
     /// Locations are separate from other events and are handled differently.
     /// This method will create a [CreateTruckLocationRequest] from the record and send it to the Vehicle Management Service or store in cache if truck ID is not yet known.
+    /// Locations failing validation are dropped if so configured.
+    /// While the receiver is overloaded, only one location per [OVERLOAD_LOCATION_INTERVAL_SECONDS] is handled.
     async fn handle_record_location(&self, record: &AVLRecord) {
+        if !self.validate_location(record) {
+            return;
+        }
+        let timestamp = record.timestamp.timestamp();
+        let last_location_timestamp = self.last_location_timestamp.load(Ordering::Relaxed);
+        if load_shedding::is_overloaded()
+            && timestamp.abs_diff(last_location_timestamp)
+                < OVERLOAD_LOCATION_INTERVAL_SECONDS as u64
+        {
+            load_shedding::record_shed_event("truck_location");
+            return;
+        }
+        self.last_location_timestamp
+            .store(timestamp, Ordering::Relaxed);
         let location_data = TruckLocation::from_teltonika_record(record).unwrap();
-        if let Some(truck_id) = self.truck_id.clone() {
+        if let Some(truck_id) = self.get_truck_id() {
+            if let Some(location_batch) = &self.location_batch {
+                if let Some(locations) = location_batch.push(location_data, Instant::now()) {
+                    self.submit_location_batch(locations, &truck_id).await;
+                }
+                return;
+            }
             debug!(target: self.log_target(), "Handling location for truck: {}", truck_id);
-            let result = vehicle_management_service::apis::trucks_api::create_truck_location(
-                &get_vehicle_management_api_config(),
-                CreateTruckLocationParams {
-                    truck_id,
-                    truck_location: location_data.clone(),
-                },
-            )
-            .await;
+            let result = VehicleApi
+                .create_truck_location(&truck_id, &self.imei, location_data.clone())
+                .await;
             if let Err(e) = result {
-                debug!(target: self.log_target(),
-                    "Error sending location: {:?}. Caching it for further use.",
-                    e
-                );
-                location_data
-                    .write_to_file(self.base_cache_path.to_str().unwrap())
-                    .expect("Error caching location");
+                self.handle_location_error(e, location_data).await;
             }
         } else {
             debug!(target: self.log_target(), "Caching location for yet unknown truck");
-            location_data
-                .write_to_file(self.base_cache_path.to_str().unwrap())
-                .expect("Error caching location");
+            self.cache_location(location_data).await;
         }
     }
 
-    /// Purges the location cache.
-    async fn purge_location_cache(&self) {
-        let cache = TruckLocation::read_from_file(self.base_cache_path.to_str().unwrap());
-        let mut failed_locations = Vec::new();
+    /// Validates the location of a Teltonika [AVLRecord].
+    ///
+    /// Invalid locations are logged and counted by reason.
+    ///
+    /// # Arguments
+    /// * `record` - Record of the location
+    ///
+    /// # Returns
+    /// * Whether the location should be handled, i.e. it's valid or invalid locations are only flagged
+    fn validate_location(&self, record: &AVLRecord) -> bool {
+        let mut location_validator = self.location_validator.lock().unwrap();
+        let Err(invalid_location) = location_validator.validate(record) else {
+            return true;
+        };
+        let mode = location_validator.get_mode();
+        metrics::increment_counter(
+            INVALID_LOCATIONS_METRIC,
+            &[
+                ("imei", &self.imei),
+                ("reason", invalid_location.as_label()),
+                ("action", mode.as_label()),
+            ],
+        );
+        if let Some(suppressed) = log_throttle::throttle(&format!(
+            "{}:invalid_location:{}",
+            self.imei,
+            invalid_location.as_label()
+        )) {
+            info!(target: self.log_target(),
+                "Invalid location at {} ({}, {}): {}, {}{}",
+                record.timestamp,
+                record.latitude,
+                record.longitude,
+                invalid_location,
+                mode.as_label(),
+                log_throttle::describe_suppressed(suppressed)
+            );
+        }
+
+        return mode != LocationValidationMode::Drop;
+    }
+
+    /// Handles an error sending a location to the API.
+    ///
+    /// The location is cached for further use if the error is temporary, otherwise it's dropped.
+    ///
+    /// # Arguments
+    /// * `e` - Error sending the location
+    /// * `location_data` - Location failed to be sent
+    async fn handle_location_error(&self, e: VehicleApiError, location_data: TruckLocation) {
+        FailedApiRequest::record(
+            &e,
+            "truck_location",
+            self.base_cache_path.to_str().unwrap(),
+            self.get_frame_provenance(),
+        );
+        if !e.kind.is_cacheable() {
+            error!(target: self.log_target(), "Error sending location: {}. Dropping it.", e);
+            return;
+        }
+        debug!(target: self.log_target(),
+            "Error sending location: {}. Caching it for further use.",
+            e
+        );
+        self.cache_location(location_data).await;
+    }
 
-        for cached_location in cache.iter() {
-            let result = vehicle_management_service::apis::trucks_api::create_truck_location(
-                &get_vehicle_management_api_config(),
-                CreateTruckLocationParams {
-                    truck_id: self.truck_id.clone().unwrap(),
-                    truck_location: cached_location.clone(),
-                },
-            )
+    /// Submits a batch of locations to the API, caching the locations failed to be sent.
+    ///
+    /// # Arguments
+    /// * `locations` - Locations of the batch
+    /// * `truck_id` - Truck ID to send the locations for
+    async fn submit_location_batch(&self, locations: Vec<TruckLocation>, truck_id: &str) {
+        debug!(target: self.log_target(),
+            "Submitting batch of {} locations for truck: {}",
+            locations.len(),
+            truck_id
+        );
+        batching::record_batch("truck_location", locations.len());
+        let results = VehicleApi
+            .create_truck_locations(truck_id, &self.imei, locations.clone())
             .await;
+        for (location_data, result) in locations.into_iter().zip(results) {
             if let Err(e) = result {
-                debug!(target: self.log_target(),
-                    "Error sending location: {:?}. Caching it for further use.",
-                    e
-                );
-                failed_locations.push(cached_location.clone());
+                self.handle_location_error(e, location_data).await;
             }
         }
-        let successful_locations_count = cache.len() - failed_locations.len();
+    }
+
+    /// Submits the batches of locations and events whose window has elapsed, or all batches if forced.
+    ///
+    /// # Arguments
+    /// * `force` - Whether to submit the batches before their window has elapsed, e.g. when the connection closes
+    pub async fn flush_batches(&self, force: bool) {
+        let Some(truck_id) = self.get_truck_id() else {
+            return;
+        };
+        if let Some(location_batch) = &self.location_batch {
+            let locations = if force {
+                location_batch.take_all()
+            } else {
+                location_batch.take_due(Instant::now())
+            };
+            if !locations.is_empty() {
+                self.submit_location_batch(locations, &truck_id).await;
+            }
+        }
+        for handler in self.event_handlers.iter() {
+            handler
+                .flush_batch(truck_id.clone(), self.base_cache_path.clone(), force)
+                .await;
+        }
+    }
+
+    /// Purges the location cache.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID to send the cached locations for.
+    async fn purge_location_cache(&self, truck_id: &str) {
+        let base_cache_path = self.base_cache_path.to_str().unwrap();
+        let cache = take_cache::<CachedEvent<TruckLocation>>(&self.base_cache_path)
+            .await
+            .expect("Error caching location");
+        let cache_length = cache.len();
+        let mut failed_locations_count = 0;
+        let mut failed_locations = Vec::new();
+
+        for cached_location in cache.into_iter() {
+            let result = VehicleApi
+                .create_truck_location(truck_id, &self.imei, cached_location.event.clone())
+                .await;
+            match result {
+                Err(e) if e.kind.is_cacheable() => {
+                    debug!(target: self.log_target(),
+                        "Error sending location: {:?}. Caching it for further use.",
+                        e
+                    );
+                    failed_locations_count += 1;
+                    failed_locations.extend(cached_location.record_failed_attempt(
+                        &e,
+                        base_cache_path,
+                        self.log_target(),
+                    ));
+                }
+                Err(e) => {
+                    error!(target: self.log_target(), "Error sending location: {}. Dropping it.", e);
+                }
+                Ok(()) => {}
+            }
+        }
+        let successful_locations_count = cache_length - failed_locations_count;
         debug!(target: self.log_target(),
             "Purged location cache of {} locations. {} failed to send.",
             successful_locations_count,
-            failed_locations.len()
+            failed_locations_count
         );
-        TruckLocation::clear_cache(&self.base_cache_path.to_str().unwrap());
-        for failed_location in failed_locations.iter() {
-            failed_location
-                .write_to_file(self.base_cache_path.to_str().unwrap())
-                .expect("Error caching location");
-        }
+        restore_cache(&self.base_cache_path, failed_locations)
+            .await
+            .expect("Error caching location");
     }
 }
 
+/// Estimates the memory held by a Teltonika [AVLRecord].
+///
+/// # Arguments
+/// * `record` - Record to estimate
+///
+/// # Returns
+/// * Approximate size of the record in bytes
+pub fn estimate_record_size(record: &AVLRecord) -> usize {
+    let variable_values_size = record
+        .io_events
+        .iter()
+        .map(|event| match &event.value {
+            AVLEventIOValue::Variable(value) => value.len(),
+            _ => 0,
+        })
+        .sum::<usize>();
+
+    return size_of::<AVLRecord>()
+        + record.io_events.len() * size_of::<AVLEventIO>()
+        + variable_values_size;
+}
+
 /// Implementation of [Cacheable] for [CreateTruckLocationRequest].
 impl Cacheable for TruckLocation {
     const FILE_PATH: &'static str = "truck_location_cache.json";
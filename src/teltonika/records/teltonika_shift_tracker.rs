@@ -0,0 +1,139 @@
+use chrono::{DateTime, Duration, Utc};
+use nom_teltonika::{AVLEventIO, AVLRecord};
+use serde::Serialize;
+
+use crate::{
+    teltonika::{
+        avl_event_io_value_to_u64, driver_card_events_to_truck_driver_card,
+        DRIVER_ONE_CARD_PRESENCE_EVENT_ID, IGNITION_EVENT_ID,
+    },
+    utils::geo::{haversine_distance_meters, is_valid_position},
+};
+
+/// Summary of a detected shift.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShiftSummary {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub distance_meters: f64,
+    pub driver_card_id: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl ShiftSummary {
+    /// Gets the duration of the shift.
+    pub fn get_duration(&self) -> Duration {
+        self.ended_at - self.started_at
+    }
+}
+
+/// State of an ongoing shift.
+struct Shift {
+    started_at: DateTime<Utc>,
+    distance_meters: f64,
+    last_position: Option<(f64, f64)>,
+    driver_card_id: Option<String>,
+}
+
+/// Tracker for detecting probable ends of shifts.
+///
+/// A shift starts when either the ignition is switched on or a driver card is inserted and
+/// it ends when both the ignition is switched off and the driver card has been removed.
+pub struct TeltonikaShiftTracker {
+    shift: Option<Shift>,
+    ignition_on: Option<bool>,
+    driver_card_present: Option<bool>,
+}
+
+impl TeltonikaShiftTracker {
+    /// Creates a new [TeltonikaShiftTracker].
+    pub fn new() -> Self {
+        TeltonikaShiftTracker {
+            shift: None,
+            ignition_on: None,
+            driver_card_present: None,
+        }
+    }
+
+    /// Handles a list of Teltonika [AVLRecord]s in chronological order.
+    ///
+    /// # Arguments
+    /// * `records` - Records to handle
+    ///
+    /// # Returns
+    /// * Summaries of the shifts that ended within the records.
+    pub fn handle_records(&mut self, records: &[AVLRecord]) -> Vec<ShiftSummary> {
+        let mut chronological_records = records.iter().collect::<Vec<&AVLRecord>>();
+        chronological_records.sort_by_key(|record| record.timestamp);
+
+        return chronological_records
+            .into_iter()
+            .filter_map(|record| self.handle_record(record))
+            .collect();
+    }
+
+    /// Handles a single Teltonika [AVLRecord].
+    fn handle_record(&mut self, record: &AVLRecord) -> Option<ShiftSummary> {
+        if let Some(ignition) = find_event(record, IGNITION_EVENT_ID) {
+            self.ignition_on = Some(avl_event_io_value_to_u64(&ignition.value) == 1);
+        }
+        if let Some(card_presence) = find_event(record, DRIVER_ONE_CARD_PRESENCE_EVENT_ID) {
+            self.driver_card_present = Some(avl_event_io_value_to_u64(&card_presence.value) == 1);
+        }
+
+        let shift = match self.shift.as_mut() {
+            Some(shift) => shift,
+            None => {
+                if self.ignition_on != Some(true) && self.driver_card_present != Some(true) {
+                    return None;
+                }
+                self.shift.insert(Shift {
+                    started_at: record.timestamp,
+                    distance_meters: 0.0,
+                    last_position: None,
+                    driver_card_id: None,
+                })
+            }
+        };
+
+        if is_valid_position(record.latitude, record.longitude) {
+            let position = (record.latitude, record.longitude);
+            if let Some(last_position) = shift.last_position {
+                shift.distance_meters += haversine_distance_meters(last_position, position);
+            }
+            shift.last_position = Some(position);
+        }
+        if let (Some(msb), Some(lsb)) = (find_event(record, 195), find_event(record, 196)) {
+            let events = vec![msb, lsb];
+            if let Ok(Some(driver_card)) =
+                driver_card_events_to_truck_driver_card(record.timestamp.timestamp(), &events)
+            {
+                shift.driver_card_id = Some(driver_card.id);
+            }
+        }
+
+        if self.ignition_on != Some(false) || self.driver_card_present != Some(false) {
+            return None;
+        }
+        let shift = self.shift.take()?;
+        let (latitude, longitude) = shift
+            .last_position
+            .unwrap_or((record.latitude, record.longitude));
+
+        return Some(ShiftSummary {
+            started_at: shift.started_at,
+            ended_at: record.timestamp,
+            distance_meters: shift.distance_meters,
+            driver_card_id: shift.driver_card_id,
+            latitude,
+            longitude,
+        });
+    }
+}
+
+/// Finds an [AVLEventIO] with the given ID from a record.
+fn find_event(record: &AVLRecord, event_id: u16) -> Option<&AVLEventIO> {
+    record.io_events.iter().find(|event| event.id == event_id)
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use log::info;
+use nom_teltonika::AVLRecord;
+
+use crate::config::get_config;
+
+/// Granularity of detected offsets. Time zone offsets are always multiples of 15 minutes.
+const DETECTED_OFFSET_GRANULARITY_SECONDS: i64 = 15 * 60;
+/// Largest offset that is considered to be caused by a local time RTC instead of a broken clock.
+const MAX_DETECTED_OFFSET_SECONDS: i64 = 14 * 60 * 60;
+
+/// Normalizes Teltonika record timestamps to UTC.
+///
+/// Some devices are configured with an RTC running in local time, which makes their records appear to be from the future.
+/// The offset of such device can either be configured with `DEVICE_TIMESTAMP_OFFSETS` environment variable
+/// (e.g. `356307042441013=7200,356307042441014=10800`) or detected from the first frame if `DETECT_TIMESTAMP_OFFSETS` is enabled.
+/// Offsets are given in seconds the device clock is ahead of UTC.
+pub struct TeltonikaTimestampNormalizer {
+    offset: Option<Duration>,
+    detect_offset: bool,
+    imei: String,
+}
+
+impl TeltonikaTimestampNormalizer {
+    /// Creates a new [TeltonikaTimestampNormalizer] from the configuration.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    pub fn new(imei: &str) -> Self {
+        let tunables = &get_config().tunables;
+
+        Self::with_configuration(
+            imei,
+            tunables.device_timestamp_offsets.get(imei).copied(),
+            tunables.detect_timestamp_offsets,
+        )
+    }
+
+    /// Creates a new [TeltonikaTimestampNormalizer] with the given configuration.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `offset_seconds` - Configured offset of the device clock in seconds
+    /// * `detect_offset` - Whether to detect the offset if it is not configured
+    pub fn with_configuration(
+        imei: &str,
+        offset_seconds: Option<i64>,
+        detect_offset: bool,
+    ) -> Self {
+        TeltonikaTimestampNormalizer {
+            offset: offset_seconds.map(Duration::seconds),
+            detect_offset,
+            imei: imei.to_string(),
+        }
+    }
+
+    /// Gets the offset currently applied to the records.
+    #[cfg(test)]
+    pub fn get_offset(&self) -> Option<Duration> {
+        self.offset
+    }
+
+    /// Normalizes the timestamps of the given records to UTC.
+    ///
+    /// If no offset is configured and detection is enabled, the offset is detected from the given records and used for the rest of the connection.
+    ///
+    /// # Arguments
+    /// * `records` - Records to normalize
+    pub fn normalize_records(&mut self, records: &mut [AVLRecord]) {
+        if self.offset.is_none() && self.detect_offset {
+            self.offset = detect_timestamp_offset(records);
+            if let Some(offset) = self.offset {
+                info!(
+                    target: &self.imei,
+                    "Detected device clock offset of {} seconds, normalizing timestamps to UTC",
+                    offset.num_seconds()
+                );
+            }
+        }
+        let Some(offset) = self.offset else {
+            return;
+        };
+        for record in records.iter_mut() {
+            record.timestamp -= offset;
+        }
+    }
+}
+
+/// Detects the offset of a device clock running in local time.
+///
+/// The offset is detected only when the latest record is ahead of server time by at least 15 minutes
+/// and rounded to the nearest 15 minutes as time zone offsets always are.
+///
+/// # Arguments
+/// * `records` - Records to detect the offset from
+///
+/// # Returns
+/// * `Option<Duration>` - Detected offset
+fn detect_timestamp_offset(records: &[AVLRecord]) -> Option<Duration> {
+    let latest_timestamp = records.iter().map(|record| record.timestamp).max()?;
+    let ahead_seconds = (latest_timestamp - Utc::now()).num_seconds();
+    if !(DETECTED_OFFSET_GRANULARITY_SECONDS..=MAX_DETECTED_OFFSET_SECONDS).contains(&ahead_seconds)
+    {
+        return None;
+    }
+    let rounded_offset = ((ahead_seconds as f64 / DETECTED_OFFSET_GRANULARITY_SECONDS as f64)
+        .round() as i64)
+        * DETECTED_OFFSET_GRANULARITY_SECONDS;
+
+    return Some(Duration::seconds(rounded_offset));
+}
+
+/// Parses per-IMEI timestamp offsets from a comma separated list of `IMEI=SECONDS` pairs.
+///
+/// # Arguments
+/// * `value` - Value to parse
+///
+/// # Returns
+/// * `HashMap<String, i64>` - Offsets in seconds by IMEI, or an error describing the first invalid entry
+pub fn parse_timestamp_offsets(value: &str) -> Result<HashMap<String, i64>, String> {
+    let mut offsets = HashMap::new();
+    for entry in value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let parsed_entry = entry
+            .split_once('=')
+            .and_then(|(imei, offset)| Some((imei.trim(), offset.trim().parse::<i64>().ok()?)));
+        match parsed_entry {
+            Some((imei, offset)) => {
+                offsets.insert(imei.to_string(), offset);
+            }
+            None => return Err(format!("invalid device timestamp offset entry [{}]", entry)),
+        }
+    }
+
+    return Ok(offsets);
+}
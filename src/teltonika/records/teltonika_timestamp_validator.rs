@@ -0,0 +1,160 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use nom_teltonika::AVLRecord;
+
+use crate::{config::get_config, metrics, utils::log_throttle};
+
+/// Name of the counter describing the number of records with timestamps outside the window by direction and action
+pub const INVALID_RECORD_TIMESTAMPS_METRIC: &str = "receiver_invalid_record_timestamps_total";
+/// Name of the gauge describing how many seconds the newest record of the latest frame of a device is ahead of server time
+pub const DEVICE_CLOCK_SKEW_METRIC: &str = "receiver_device_clock_skew_seconds";
+
+/// Handling of records with timestamps outside the window relative to server time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TimestampPolicy {
+    /// Records are handled regardless of their timestamps
+    #[default]
+    Off,
+    /// Timestamps outside the window are replaced with server time
+    Clamp,
+    /// Records with timestamps outside the window are dropped
+    Reject,
+}
+
+impl TimestampPolicy {
+    /// Gets the label of the action taken on records outside the window in metrics
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            TimestampPolicy::Off => "none",
+            TimestampPolicy::Clamp => "clamped",
+            TimestampPolicy::Reject => "rejected",
+        }
+    }
+}
+
+impl FromStr for TimestampPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "off" => Ok(TimestampPolicy::Off),
+            "clamp" => Ok(TimestampPolicy::Clamp),
+            "reject" => Ok(TimestampPolicy::Reject),
+            _ => Err(format!(
+                "Unknown record timestamp policy [{}], expected off, clamp or reject",
+                value
+            )),
+        }
+    }
+}
+
+/// Validator of Teltonika record timestamps against server time.
+///
+/// Devices losing their GNSS fix may send records timestamped in 1970 or far in the future. A record is outside the
+/// window if it's older than `RECORD_MAX_AGE_SECONDS` or ahead of server time by more than `RECORD_MAX_FUTURE_SECONDS`,
+/// and it's clamped or rejected as set by `RECORD_TIMESTAMP_POLICY`. Timestamps are validated after normalizing them
+/// with the [super::TeltonikaTimestampNormalizer].
+pub struct TeltonikaTimestampValidator {
+    policy: TimestampPolicy,
+    max_age: Duration,
+    max_future: Duration,
+}
+
+impl TeltonikaTimestampValidator {
+    /// Creates a new [TeltonikaTimestampValidator] from the configuration.
+    pub fn new() -> Self {
+        let tunables = &get_config().tunables;
+        Self::with_configuration(
+            tunables.record_timestamp_policy,
+            tunables.record_max_age_seconds,
+            tunables.record_max_future_seconds,
+        )
+    }
+
+    /// Creates a new [TeltonikaTimestampValidator] with the given configuration.
+    ///
+    /// # Arguments
+    /// * `policy` - Handling of records outside the window
+    /// * `max_age_seconds` - Maximum age of a record in seconds
+    /// * `max_future_seconds` - Maximum time in seconds a record may be ahead of server time
+    pub fn with_configuration(
+        policy: TimestampPolicy,
+        max_age_seconds: i64,
+        max_future_seconds: i64,
+    ) -> Self {
+        TeltonikaTimestampValidator {
+            policy,
+            max_age: Duration::seconds(max_age_seconds),
+            max_future: Duration::seconds(max_future_seconds),
+        }
+    }
+
+    /// Validates the timestamps of the records of a frame.
+    ///
+    /// Reports the skew of the device clock, i.e. how far the newest record is ahead of server time, and counts the
+    /// records outside the window by direction. Those records are clamped to server time or removed as set by the policy.
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `records` - Records of the frame
+    /// * `now` - Current server time
+    ///
+    /// # Returns
+    /// * Number of rejected records
+    pub fn validate_records(
+        &self,
+        imei: &str,
+        records: &mut Vec<AVLRecord>,
+        now: DateTime<Utc>,
+    ) -> usize {
+        let Some(newest_timestamp) = records.iter().map(|record| record.timestamp).max() else {
+            return 0;
+        };
+        metrics::set_gauge(
+            DEVICE_CLOCK_SKEW_METRIC,
+            &[("imei", imei)],
+            (newest_timestamp - now).num_seconds().max(0) as f64,
+        );
+        if self.policy == TimestampPolicy::Off {
+            return 0;
+        }
+        let records_count = records.len();
+        records.retain_mut(|record| {
+            let direction = if record.timestamp < now - self.max_age {
+                "past"
+            } else if record.timestamp > now + self.max_future {
+                "future"
+            } else {
+                return true;
+            };
+            metrics::increment_counter(
+                INVALID_RECORD_TIMESTAMPS_METRIC,
+                &[
+                    ("imei", imei),
+                    ("direction", direction),
+                    ("action", self.policy.as_label()),
+                ],
+            );
+            if let Some(suppressed) =
+                log_throttle::throttle(&format!("{}:invalid_record_timestamp", imei))
+            {
+                warn!(target: imei,
+                    "Record timestamp {} is too far in the {} of server time, {}{}",
+                    record.timestamp,
+                    direction,
+                    self.policy.as_label(),
+                    log_throttle::describe_suppressed(suppressed)
+                );
+            }
+            if self.policy == TimestampPolicy::Clamp {
+                record.timestamp = now;
+                return true;
+            }
+            return false;
+        });
+
+        return records_count - records.len();
+    }
+}
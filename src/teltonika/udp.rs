@@ -0,0 +1,212 @@
+//! Listener for Teltonika devices sending their data over UDP
+//!
+//! Devices in poor-coverage regions are configured for UDP transport, where each datagram carries the IMEI of the device
+//! and is acknowledged separately. Records of the datagrams are routed through the same pipeline as frames received over TCP.
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use log::{debug, error, warn};
+use nom_teltonika::crc16;
+use tokio::net::UdpSocket;
+use tracing::{info_span, Instrument};
+
+use crate::{
+    completeness, device_stats, invariants, metrics,
+    processing::{self, ProcessingMode},
+    synthetic,
+    telematics_cache::get_device_cache_path,
+    utils::{
+        api::get_truck_id_by_vin, api_routing::get_api_routing, imei::is_valid_imei, log_throttle,
+    },
+};
+
+use super::{
+    connection::{
+        dispatch_records,
+        registry::{get_connection_registry, ActiveConnection, Transport},
+        BYTES_METRIC, FRAMES_METRIC, PARSE_ERRORS_METRIC, RECORDS_METRIC,
+    },
+    device_family::DeviceFamily,
+    messages::{build_datagram_ack, parse_datagram},
+    records::{FrameProvenance, TeltonikaRecordsHandler, TeltonikaTimestampNormalizer},
+};
+
+/// Maximum size of a UDP datagram
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// State of a device sending its data over UDP
+struct UdpDevice {
+    connection_id: String,
+    /// Registration of the device, kept as long as the listener runs
+    active_connection: Arc<ActiveConnection>,
+    records_handler: Arc<TeltonikaRecordsHandler>,
+    timestamp_normalizer: TeltonikaTimestampNormalizer,
+}
+
+/// Listener receiving datagrams from Teltonika devices over UDP
+pub struct TeltonikaUdpListener {
+    socket: UdpSocket,
+    base_file_path: PathBuf,
+    devices: HashMap<String, UdpDevice>,
+}
+
+impl TeltonikaUdpListener {
+    /// Creates a new [TeltonikaUdpListener]
+    ///
+    /// # Arguments
+    /// * `socket` - Bound UDP socket
+    /// * `base_file_path` - Base path for the cache files
+    pub fn new(socket: UdpSocket, base_file_path: &Path) -> Self {
+        TeltonikaUdpListener {
+            socket,
+            base_file_path: base_file_path.to_path_buf(),
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Receives and handles datagrams until receiving from the socket fails
+    ///
+    /// Datagrams are handled in the order they are received, so that the records of each device stay in order.
+    pub async fn run(mut self) -> std::io::Result<()> {
+        let mut receive_buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let (bytes_read, peer_address) = self.socket.recv_from(&mut receive_buffer).await?;
+            self.handle_datagram(&receive_buffer[..bytes_read], peer_address)
+                .await;
+        }
+    }
+
+    /// Handles a datagram received from a device
+    ///
+    /// The datagram is acknowledged before its records are dispatched, like frames received over TCP.
+    ///
+    /// # Arguments
+    /// * `packet` - Received datagram
+    /// * `peer_address` - Address the datagram was received from
+    async fn handle_datagram(&mut self, packet: &[u8], peer_address: SocketAddr) {
+        let datagram = match parse_datagram(packet) {
+            Ok(datagram) => datagram,
+            Err(err) => {
+                metrics::increment_counter(PARSE_ERRORS_METRIC, &[]);
+                if let Some(suppressed) =
+                    log_throttle::throttle(&format!("{}:udp_parse_error", peer_address.ip()))
+                {
+                    error!(
+                        "Failed to parse datagram from [{}]: {}{}",
+                        peer_address,
+                        err,
+                        log_throttle::describe_suppressed(suppressed)
+                    );
+                }
+                return;
+            }
+        };
+        let imei = datagram.imei.clone();
+        if !is_valid_imei(&imei) {
+            warn!(target: &imei, "IMEI failed checksum validation");
+        }
+        if let Err(err) = self
+            .socket
+            .send_to(&build_datagram_ack(&datagram), peer_address)
+            .await
+        {
+            warn!(target: &imei, "Failed to write datagram ACK: {}", err);
+            device_stats::record_ack_failure(&imei);
+        }
+        // Synthetic devices are excluded from the statistics
+        if synthetic::is_synthetic_imei(&imei) {
+            synthetic::record_synthetic_frame(&imei);
+        } else {
+            metrics::increment_counter(FRAMES_METRIC, &[]);
+            metrics::add_to_counter(RECORDS_METRIC, &[], datagram.records.len() as u64);
+            metrics::add_to_counter(BYTES_METRIC, &[], packet.len() as u64);
+            device_stats::record_frame(&imei, datagram.records.len());
+        }
+        let processing_mode = processing::get_processing_mode(&imei);
+        if processing_mode == ProcessingMode::Muted {
+            processing::record_muted_frame(&imei);
+            invariants::check_frame_records(
+                &imei,
+                datagram.records.len(),
+                0,
+                datagram.records.len(),
+            );
+            return;
+        }
+
+        let base_file_path = get_device_cache_path(&self.base_file_path, &imei);
+        let device = self.devices.entry(imei.clone()).or_insert_with(|| {
+            let records_handler = Arc::new(TeltonikaRecordsHandler::new(
+                &base_file_path,
+                None,
+                imei.clone(),
+                DeviceFamily::default(),
+            ));
+            let connection_id = uuid::Uuid::new_v4().to_string();
+            let active_connection = get_connection_registry().register(
+                &imei,
+                &connection_id,
+                Transport::Udp,
+                Some(peer_address.ip()),
+                &records_handler,
+            );
+
+            UdpDevice {
+                connection_id,
+                active_connection,
+                records_handler,
+                timestamp_normalizer: TeltonikaTimestampNormalizer::new(&imei),
+            }
+        });
+        device.active_connection.record_frame();
+        let mut records = datagram.records;
+        device.timestamp_normalizer.normalize_records(&mut records);
+        if !synthetic::is_synthetic_imei(&imei) {
+            completeness::record_received(&imei, &records);
+        }
+        if device.records_handler.get_truck_vin().is_none() {
+            device
+                .records_handler
+                .set_truck_vin(device.records_handler.get_truck_vin_from_records(&records));
+        }
+        let truck_vin = device.records_handler.get_truck_vin();
+        if device.records_handler.get_truck_id().is_none()
+            && truck_vin.is_some()
+            && !processing::is_maintenance_mode()
+        {
+            if let Some(truck_id) = get_truck_id_by_vin(&truck_vin).await {
+                debug!(target: &imei, "Found Truck ID [{}] for VIN [{}]", truck_id, truck_vin.clone().unwrap());
+                get_api_routing().register_truck(&imei, &truck_id.to_string());
+                device
+                    .records_handler
+                    .set_truck_id(Some(truck_id.to_string()));
+            }
+        }
+
+        let provenance = FrameProvenance::new(
+            crc16(packet) as u32,
+            &device.connection_id,
+            Some(peer_address.ip()),
+        );
+        let span = info_span!(
+            "connection",
+            imei = %imei,
+            connection_id = %device.connection_id,
+            transport = "udp",
+            truck_id = device.records_handler.get_truck_id().as_deref(),
+        );
+        dispatch_records(
+            &device.records_handler,
+            &imei,
+            records,
+            processing_mode,
+            provenance,
+        )
+        .instrument(span)
+        .await;
+    }
+}
@@ -1,12 +1,11 @@
 use std::time::Duration;
 
-use log::LevelFilter;
 use nom_teltonika::{AVLEventIO, AVLEventIOValue, Priority};
 use tempfile::tempdir;
 use tokio_test::io::Builder;
 
 use crate::{
-    teltonika::connection::TeltonikaConnection,
+    teltonika::{connection::TeltonikaConnection, device_family::DeviceFamily},
     utils::{
         avl_frame_builder::AVLFrameBuilder,
         avl_packet::AVLPacketToBytes,
@@ -29,12 +28,9 @@ async fn test_driver_one_card_removal() {
     let vin_events = vin_to_three_part_events("W1T96302X10704959".to_string()).to_vec();
     start_vehicle_management_mock();
 
-    env_logger::builder()
-        .is_test(true)
-        .filter_module("hyper", LevelFilter::Off)
-        .filter_module("reqwest", LevelFilter::Off)
-        .filter_module("httpmock", LevelFilter::Off)
-        .filter_level(LevelFilter::Debug)
+    tracing_subscriber::fmt()
+        .with_test_writer()
+        .with_env_filter("debug,hyper=off,reqwest=off,httpmock=off")
         .try_init()
         .unwrap();
     let imei = build_valid_imei_packet(&get_random_imei_of_length(10));
@@ -76,7 +72,15 @@ async fn test_driver_one_card_removal() {
         .read(&frame_without_card.to_bytes())
         .write(&(frame_without_card.records.len() as u32).to_be_bytes())
         .build();
-    let result = TeltonikaConnection::handle_connection(mock_stream, temp_dir.path(), 1_000).await;
+    let result = TeltonikaConnection::handle_connection(
+        mock_stream,
+        None,
+        DeviceFamily::default(),
+        temp_dir.path(),
+        1_000,
+        0,
+    )
+    .await;
 
     assert!(result.is_ok());
 }
@@ -0,0 +1,284 @@
+//! Replay idempotence tests
+//!
+//! Devices resend frames they didn't receive an ACK for, so the receiver delivers events at least once. Requests of the
+//! same event must carry the same idempotency key, so that the API can ignore the replays and store each event once.
+//! These tests force resends through the connection and check the requests received by the mock API.
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::Utc;
+use nom_teltonika::{AVLEventIO, AVLEventIOValue, AVLFrame};
+use tempfile::tempdir;
+use tokio_test::io::Builder;
+
+use crate::{
+    teltonika::{connection::TeltonikaConnection, device_family::DeviceFamily},
+    utils::{
+        avl_frame_builder::AVLFrameBuilder,
+        avl_packet::AVLPacketToBytes,
+        avl_record_builder::avl_record_builder::AVLRecordBuilder,
+        imei::{build_valid_imei_packet, get_random_imei_of_length},
+        test_utils::{
+            fail_first_attempts, get_received_requests, start_vehicle_management_mock,
+            vin_to_three_part_events, ReceivedRequest,
+        },
+        truck_cache::get_truck_cache,
+    },
+};
+
+/// Builds a frame with a location and a tachograph speed in each record
+///
+/// The VIN of the truck is sent in the first record, so that the connection can look up the truck.
+///
+/// # Arguments
+/// * `vin` - VIN of the truck
+/// * `records_count` - Number of records in the frame
+fn build_frame(vin: &str, records_count: i64) -> AVLFrame {
+    let started_at = Utc::now() - chrono::Duration::minutes(records_count);
+    let records = (0..records_count)
+        .map(|index| {
+            let record = AVLRecordBuilder::new()
+                .with_timestamp(started_at + chrono::Duration::minutes(index))
+                .with_latitude(61.68779453479687)
+                .with_longitude(27.27297030282335 + index as f64 * 0.001)
+                .with_trigger_event_id(191)
+                .with_io_events(vec![AVLEventIO {
+                    id: 191,
+                    value: AVLEventIOValue::U16(50 + index as u16),
+                }]);
+            match index {
+                0 => record.add_io_events(vin_to_three_part_events(vin.to_string()).to_vec()),
+                _ => record,
+            }
+            .build()
+        })
+        .collect();
+
+    return AVLFrameBuilder::new().with_records(records).build();
+}
+
+/// Registers a truck for a VIN in the truck cache, so that the connection doesn't look it up from the API
+///
+/// # Arguments
+/// * `vin` - VIN of the truck
+fn register_truck(vin: &str) -> String {
+    let truck_id = uuid::Uuid::new_v4();
+    get_truck_cache().insert(vin, Some(truck_id), Utc::now());
+
+    return truck_id.to_string();
+}
+
+/// Gets the requests creating events the mock API has received for a truck
+///
+/// # Arguments
+/// * `truck_id` - Truck ID of the events
+fn get_create_requests(truck_id: &str) -> Vec<ReceivedRequest> {
+    return get_received_requests(truck_id)
+        .into_iter()
+        .filter(|request| request.method == "POST")
+        .collect();
+}
+
+/// Groups the received requests by the idempotency key they were sent with
+///
+/// An API honoring idempotency keys stores one event per key.
+///
+/// # Arguments
+/// * `requests` - Received requests
+fn group_by_idempotency_key(
+    requests: &[ReceivedRequest],
+) -> BTreeMap<String, Vec<&ReceivedRequest>> {
+    let mut requests_by_key: BTreeMap<String, Vec<&ReceivedRequest>> = BTreeMap::new();
+    for request in requests {
+        let idempotency_key = request
+            .idempotency_key
+            .clone()
+            .expect("Request has no idempotency key");
+        requests_by_key
+            .entry(idempotency_key)
+            .or_default()
+            .push(request);
+    }
+
+    return requests_by_key;
+}
+
+/// Asserts that each logical event is stored exactly once by an API honoring idempotency keys
+///
+/// # Arguments
+/// * `requests` - Received requests
+/// * `expected_events` - Number of logical events sent by the device
+fn assert_each_event_stored_once(requests: &[ReceivedRequest], expected_events: usize) {
+    let requests_by_key = group_by_idempotency_key(requests);
+    assert_eq!(expected_events, requests_by_key.len());
+    for replayed_requests in requests_by_key.values() {
+        // Replays of an event must be identical, otherwise the API would drop a differing event
+        assert!(replayed_requests
+            .iter()
+            .all(|request| *request == replayed_requests[0]));
+    }
+}
+
+/// Device doesn't receive the ACK in time and resends the frame on the same connection
+#[tokio::test]
+async fn test_replay_after_delayed_ack() {
+    start_vehicle_management_mock();
+    let vin = "YV2RT40A9PB000001";
+    let truck_id = register_truck(vin);
+    let frame = build_frame(vin, 3);
+    let frame_ack = (frame.records.len() as u32).to_be_bytes();
+    let temp_dir = tempdir().unwrap();
+    let mock_stream = Builder::new()
+        .read(&build_valid_imei_packet(&get_random_imei_of_length(10)))
+        .write(b"\x01")
+        .read(&frame.to_bytes())
+        .wait(Duration::from_millis(100))
+        .write(&frame_ack)
+        .read(&frame.to_bytes())
+        .wait(Duration::from_millis(100))
+        .write(&frame_ack)
+        .build();
+
+    TeltonikaConnection::handle_connection(
+        mock_stream,
+        None,
+        DeviceFamily::default(),
+        temp_dir.path(),
+        1_000,
+        0,
+    )
+    .await
+    .unwrap();
+
+    // Each record has a location and a speed, both of which are sent again for the resent frame
+    let requests = get_create_requests(&truck_id);
+    assert_eq!(12, requests.len());
+    assert_each_event_stored_once(&requests, 6);
+}
+
+/// Connection drops in the middle of a frame and the device resends the whole frame after reconnecting
+#[tokio::test]
+async fn test_replay_after_connection_dropped_mid_frame() {
+    start_vehicle_management_mock();
+    let vin = "YV2RT40A9PB000002";
+    let truck_id = register_truck(vin);
+    let frame = build_frame(vin, 3);
+    let frame_bytes = frame.to_bytes();
+    let imei_packet = build_valid_imei_packet(&get_random_imei_of_length(10));
+    let temp_dir = tempdir().unwrap();
+    let dropped_stream = Builder::new()
+        .read(&imei_packet)
+        .write(b"\x01")
+        .read(&frame_bytes[..frame_bytes.len() / 2])
+        .build();
+    let reconnected_stream = Builder::new()
+        .read(&imei_packet)
+        .write(b"\x01")
+        .read(&frame_bytes)
+        .wait(Duration::from_millis(100))
+        .write(&(frame.records.len() as u32).to_be_bytes())
+        .build();
+
+    TeltonikaConnection::handle_connection(
+        dropped_stream,
+        None,
+        DeviceFamily::default(),
+        temp_dir.path(),
+        1_000,
+        0,
+    )
+    .await
+    .unwrap();
+    // Partial frame is neither acknowledged nor handled
+    assert!(get_create_requests(&truck_id).is_empty());
+    TeltonikaConnection::handle_connection(
+        reconnected_stream,
+        None,
+        DeviceFamily::default(),
+        temp_dir.path(),
+        1_000,
+        0,
+    )
+    .await
+    .unwrap();
+
+    let requests = get_create_requests(&truck_id);
+    assert_eq!(6, requests.len());
+    assert_each_event_stored_once(&requests, 6);
+}
+
+/// Device resends a frame on a new connection after the ACK of the previous connection was lost
+///
+/// The new connection has no memory of the previous one, so the idempotency keys must be derived from the events only.
+#[tokio::test]
+async fn test_replay_across_connections() {
+    start_vehicle_management_mock();
+    let vin = "YV2RT40A9PB000003";
+    let truck_id = register_truck(vin);
+    let frame = build_frame(vin, 2);
+    let imei_packet = build_valid_imei_packet(&get_random_imei_of_length(10));
+    let temp_dir = tempdir().unwrap();
+    for _ in 0..2 {
+        let mock_stream = Builder::new()
+            .read(&imei_packet)
+            .write(b"\x01")
+            .read(&frame.to_bytes())
+            .wait(Duration::from_millis(100))
+            .write(&(frame.records.len() as u32).to_be_bytes())
+            .build();
+        TeltonikaConnection::handle_connection(
+            mock_stream,
+            None,
+            DeviceFamily::default(),
+            temp_dir.path(),
+            1_000,
+            0,
+        )
+        .await
+        .unwrap();
+    }
+
+    let requests = get_create_requests(&truck_id);
+    assert_eq!(8, requests.len());
+    assert_each_event_stored_once(&requests, 4);
+}
+
+/// API fails the first attempt of each request, so that the requests are retried
+///
+/// All attempts of a request must carry the same idempotency key, so that an API having stored the event of a failed
+/// attempt ignores the retry.
+#[tokio::test]
+async fn test_replay_of_retried_requests() {
+    start_vehicle_management_mock();
+    let vin = "YV2RT40A9PB000004";
+    let truck_id = register_truck(vin);
+    fail_first_attempts(&truck_id);
+    let frame = build_frame(vin, 2);
+    let temp_dir = tempdir().unwrap();
+    let mock_stream = Builder::new()
+        .read(&build_valid_imei_packet(&get_random_imei_of_length(10)))
+        .write(b"\x01")
+        .read(&frame.to_bytes())
+        .wait(Duration::from_millis(100))
+        .write(&(frame.records.len() as u32).to_be_bytes())
+        .build();
+
+    TeltonikaConnection::handle_connection(
+        mock_stream,
+        None,
+        DeviceFamily::default(),
+        temp_dir.path(),
+        1_000,
+        0,
+    )
+    .await
+    .unwrap();
+
+    // Each location and speed is sent twice, as the first attempt fails
+    let requests = get_create_requests(&truck_id);
+    assert_eq!(8, requests.len());
+    assert_each_event_stored_once(&requests, 4);
+    let requests_by_key = group_by_idempotency_key(&requests);
+    assert!(requests_by_key
+        .values()
+        .all(|retried_requests| retried_requests.len() == 2));
+}
@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+use chrono::TimeZone;
+use nom_teltonika::{AVLEventIO, AVLEventIOValue, AVLFrame, Priority};
+
+use crate::utils::{
+    avl_frame_builder::AVLFrameBuilder,
+    avl_record_builder::avl_record_builder::AVLRecordBuilder,
+    test_utils::{
+        driver_card_id_to_two_part_events, get_received_requests, get_teltonika_records_handler,
+        start_vehicle_management_mock,
+    },
+};
+
+/// Environment variable for rewriting the snapshots with the current output instead of comparing to them
+const UPDATE_SNAPSHOTS_ENV_KEY: &str = "UPDATE_SNAPSHOTS";
+
+/// Runs the records of the frame through [crate::teltonika::records::TeltonikaRecordsHandler] and compares the API requests
+/// received by the mock API to the snapshot with the given name.
+///
+/// Each snapshot has a truck of its own derived from its name, so that the requests of other tests sharing the mock API
+/// are told apart.
+///
+/// # Arguments
+/// * `snapshot_name` - Name of the snapshot file in `src/tests/snapshots` without the extension
+/// * `frame` - Frame to handle
+async fn assert_requests_match_snapshot(snapshot_name: &str, frame: AVLFrame) {
+    start_vehicle_management_mock();
+    let truck_id =
+        uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, snapshot_name.as_bytes()).to_string();
+    let records_handler =
+        get_teltonika_records_handler(Some(truck_id.clone()), Some(snapshot_name.to_string()));
+    records_handler.handle_records(frame.records).await;
+    let received_requests = get_received_requests(&truck_id)
+        .into_iter()
+        .map(|request| {
+            serde_json::json!({
+                "method": request.method,
+                "path": request.path,
+                "payload": request.body,
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+
+    let snapshot_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/tests/snapshots")
+        .join(format!("{}.json", snapshot_name));
+    if std::env::var(UPDATE_SNAPSHOTS_ENV_KEY).is_ok() {
+        let snapshot = serde_json::to_string_pretty(&received_requests).unwrap() + "\n";
+        std::fs::write(&snapshot_path, snapshot).expect("Failed to write snapshot");
+        return;
+    }
+    let snapshot = std::fs::read_to_string(&snapshot_path).unwrap_or_else(|_| {
+        panic!(
+            "Snapshot {} not found. Run the tests with {}=1 to create it.",
+            snapshot_path.display(),
+            UPDATE_SNAPSHOTS_ENV_KEY
+        )
+    });
+    let expected: serde_json::Value = serde_json::from_str(&snapshot).unwrap();
+
+    assert_eq!(
+        expected,
+        serde_json::Value::from(received_requests),
+        "API requests differ from snapshot {}",
+        snapshot_name
+    );
+}
+
+#[tokio::test]
+async fn test_location_and_speed_snapshot() {
+    let timestamp = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+    let frame = AVLFrameBuilder::new()
+        .with_records(vec![
+            AVLRecordBuilder::new()
+                .with_timestamp(timestamp)
+                .with_latitude(61.6885)
+                .with_longitude(27.2723)
+                .with_angle(90)
+                .add_io_event(AVLEventIO {
+                    id: 191,
+                    value: AVLEventIOValue::U8(80),
+                })
+                .build(),
+            AVLRecordBuilder::new()
+                .with_timestamp(timestamp + chrono::Duration::seconds(30))
+                .with_latitude(61.6891)
+                .with_longitude(27.2745)
+                .with_angle(95)
+                .build(),
+        ])
+        .build();
+
+    assert_requests_match_snapshot("location_and_speed", frame).await;
+}
+
+#[tokio::test]
+async fn test_driver_card_and_drive_state_snapshot() {
+    let timestamp = chrono::Utc.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+    let frame = AVLFrameBuilder::new()
+        .with_records(vec![AVLRecordBuilder::new()
+            .with_timestamp(timestamp)
+            .with_priority(Priority::High)
+            .with_latitude(61.6885)
+            .with_longitude(27.2723)
+            .with_trigger_event_id(187)
+            .add_io_event(AVLEventIO {
+                id: 187,
+                value: AVLEventIOValue::U8(1),
+            })
+            .add_io_event(AVLEventIO {
+                id: 184,
+                value: AVLEventIOValue::U8(3),
+            })
+            .add_io_events(
+                driver_card_id_to_two_part_events("1069619335000001".to_string()).to_vec(),
+            )
+            .build()])
+        .build();
+
+    assert_requests_match_snapshot("driver_card_and_drive_state", frame).await;
+}
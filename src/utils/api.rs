@@ -1,15 +1,1293 @@
+use std::{
+    fmt,
+    future::Future,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
 use chrono::{DateTime, Utc};
-use log::{info, warn};
+use log::{debug, info, warn};
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    Method,
+};
+use serde::{de::DeserializeOwned, de::IgnoredAny, Serialize};
+use tokio::runtime::{Builder, Runtime};
+use tracing::{field::Empty, instrument, Span};
 use uuid::Uuid;
-use vehicle_management_service::apis::{
-    public_trucks_api::ListPublicTrucksParams,
-    trucks_api::{
-        delete_truck_driver_card, list_truck_driver_cards, DeleteTruckDriverCardParams,
-        ListTruckDriverCardsParams,
+use vehicle_management_service::{
+    apis::{configuration::Configuration, urlencode, Error, ResponseContent},
+    models::{PublicTruck, TruckDriveState, TruckDriverCard, TruckLocation, TruckSpeed},
+};
+
+use crate::{
+    batching,
+    config::{get_config, telemetry::inject_trace_context},
+    metrics,
+    teltonika::{
+        events::{
+            axle_weight_event_handler::TruckAxleWeights,
+            ble_sensor_event_handler::BleSensorReadings,
+            engine_hours_event_handler::TruckEngineHours, engine_rpm_event_handler::TruckEngineRpm,
+            fault_code_event_handler::FaultRecord, geofence_event_handler::ZoneEvent,
+            harsh_driving_event_handler::DriverBehaviorEvent,
+        },
+        records::{
+            teltonika_odometer_reconciler::OdometerDiscrepancy,
+            teltonika_shift_tracker::ShiftSummary,
+        },
     },
 };
 
-use super::get_vehicle_management_api_config;
+use super::{
+    api_routing::get_api_routing,
+    circuit_breaker::get_circuit_breakers,
+    get_vehicle_management_api_config, outbound_capture,
+    truck_cache::{get_truck_cache, record_lookup_latency},
+    validation::ValidatePayload,
+};
+
+/// Name of the counter describing the number of API requests by operation and result
+pub const API_REQUESTS_METRIC: &str = "receiver_api_requests_total";
+/// Name of the counter describing the number of driver behavior events by type
+pub const DRIVER_BEHAVIOR_EVENTS_METRIC: &str = "receiver_driver_behavior_events_total";
+/// Name of the counter describing the number of BLE sensor measurements by kind
+pub const BLE_SENSOR_MEASUREMENTS_METRIC: &str = "receiver_ble_sensor_measurements_total";
+/// Name of the counter describing the number of axle weights by axle
+pub const AXLE_WEIGHTS_METRIC: &str = "receiver_axle_weights_total";
+/// Name of the counter describing the number of engine readings by type
+pub const ENGINE_READINGS_METRIC: &str = "receiver_engine_readings_total";
+/// Name of the counter describing the number of fault records by whether faults are active
+pub const FAULT_RECORDS_METRIC: &str = "receiver_fault_records_total";
+/// Name of the counter describing the number of zone events by direction
+pub const ZONE_EVENTS_METRIC: &str = "receiver_zone_events_total";
+/// Maximum number of attempts for a single API request
+const MAX_API_REQUEST_ATTEMPTS: u32 = 3;
+/// Delay before retrying a failed API request, doubled by each further attempt
+const API_REQUEST_RETRY_DELAY: Duration = Duration::from_millis(200);
+/// Number of trucks listed per request when warming up the truck cache
+const TRUCK_CACHE_WARMUP_PAGE_SIZE: i32 = 100;
+/// Header for passing the request ID to the API, allowing cross-referencing the logs of both services
+pub const REQUEST_ID_HEADER: &str = "X-Request-ID";
+/// Header for passing the idempotency key of a create request, allowing the API to ignore replayed requests
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Header for passing the API key to the API
+const API_KEY_HEADER: &str = "X-API-Key";
+/// Header for passing the time a driver card was removed when deleting it
+const DRIVER_CARD_REMOVED_AT_HEADER: &str = "X-Driver-Card-Removed-At";
+
+static API_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// Dedicated runtime for API requests
+///
+/// When not initialized, API requests are run on the runtime of the caller.
+static API_RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Initializes a dedicated runtime for API requests
+///
+/// Running API requests on a separate runtime prevents a slow API from delaying frame reads and ACKs of the listener.
+///
+/// # Arguments
+/// * `worker_threads` - Number of worker threads of the runtime
+pub fn init_api_runtime(worker_threads: usize) -> std::io::Result<()> {
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(worker_threads)
+        .thread_name("vehicle-management-api")
+        .enable_all()
+        .build()?;
+    if API_RUNTIME.set(runtime).is_err() {
+        warn!("API runtime is already initialized");
+    }
+
+    return Ok(());
+}
+
+/// Runs an API request on the dedicated API runtime if it is initialized and on the current runtime otherwise
+///
+/// # Arguments
+/// * `request` - API request to run
+///
+/// # Returns
+/// * Output of the request
+pub async fn run_api_request<F>(request: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let Some(runtime) = API_RUNTIME.get() else {
+        return request.await;
+    };
+    match runtime.spawn(request).await {
+        Ok(output) => output,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// Error of a Vehicle Management Service API request
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleApiError {
+    /// ID of the failed request, sent to the API in [REQUEST_ID_HEADER]
+    pub request_id: Uuid,
+    /// Kind of the error
+    pub kind: VehicleApiErrorKind,
+}
+
+impl fmt::Display for VehicleApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request [{}] failed: {}", self.request_id, self.kind)
+    }
+}
+
+/// Kinds of Vehicle Management Service API request errors
+#[derive(Debug, Clone, PartialEq)]
+pub enum VehicleApiErrorKind {
+    /// The resource already exists
+    Conflict,
+    /// The resource was not found
+    NotFound,
+    /// The API key was rejected
+    Unauthorized,
+    /// The request was rejected by the API
+    Rejected { status: u16, content: String },
+    /// The API failed to handle the request
+    Server { status: u16 },
+    /// The request could not be sent or the response could not be read
+    Transport(String),
+    /// The payload failed validation and was not sent
+    Invalid(String),
+    /// The request was not sent, as the circuit breaker of the operation is open
+    CircuitOpen,
+    /// The request was not sent, as the API has no endpoint for the operation
+    Unsupported,
+}
+
+impl VehicleApiErrorKind {
+    /// Checks whether the failed request may succeed when retried
+    ///
+    /// Server errors, transport errors such as timeouts and rate limited requests are retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            VehicleApiErrorKind::Server { .. }
+                | VehicleApiErrorKind::Transport(_)
+                | VehicleApiErrorKind::Rejected { status: 429, .. }
+        )
+    }
+
+    /// Checks whether the payload of the failed request should be cached for sending it later
+    ///
+    /// Invalid payloads and payloads of unsupported operations would never be accepted, so they are dropped instead.
+    pub fn is_cacheable(&self) -> bool {
+        !matches!(
+            self,
+            VehicleApiErrorKind::Invalid(_) | VehicleApiErrorKind::Unsupported
+        )
+    }
+}
+
+impl fmt::Display for VehicleApiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VehicleApiErrorKind::Conflict => write!(f, "resource already exists"),
+            VehicleApiErrorKind::NotFound => write!(f, "resource not found"),
+            VehicleApiErrorKind::Unauthorized => write!(f, "API key was rejected"),
+            VehicleApiErrorKind::Rejected { status, content } => {
+                write!(
+                    f,
+                    "request rejected with status code {}: {}",
+                    status, content
+                )
+            }
+            VehicleApiErrorKind::Server { status } => {
+                write!(f, "server failed with status code {}", status)
+            }
+            VehicleApiErrorKind::Transport(err) => write!(f, "transport error: {}", err),
+            VehicleApiErrorKind::Invalid(err) => write!(f, "invalid payload: {}", err),
+            VehicleApiErrorKind::CircuitOpen => write!(f, "circuit breaker is open"),
+            VehicleApiErrorKind::Unsupported => write!(f, "operation is not supported by the API"),
+        }
+    }
+}
+
+impl<T> From<Error<T>> for VehicleApiErrorKind {
+    fn from(err: Error<T>) -> Self {
+        match err {
+            Error::ResponseError(response) => match response.status.as_u16() {
+                401 | 403 => VehicleApiErrorKind::Unauthorized,
+                404 => VehicleApiErrorKind::NotFound,
+                409 => VehicleApiErrorKind::Conflict,
+                status if response.status.is_server_error() => {
+                    VehicleApiErrorKind::Server { status }
+                }
+                status => VehicleApiErrorKind::Rejected {
+                    status,
+                    content: response.content,
+                },
+            },
+            Error::Reqwest(err) => VehicleApiErrorKind::Transport(err.to_string()),
+            Error::Serde(err) => VehicleApiErrorKind::Transport(err.to_string()),
+            Error::Io(err) => VehicleApiErrorKind::Transport(err.to_string()),
+        }
+    }
+}
+
+/// Gets the idempotency key of a create request
+///
+/// The key is derived from the IMEI of the device, the operation and the serialized payload, so that replays of the
+/// same event from the cache get the same key regardless of the truck of the device, while distinct events with the same
+/// timestamp get distinct keys.
+///
+/// # Arguments
+/// * `operation` - Name of the operation
+/// * `imei` - IMEI of the device the event was received from
+/// * `payload` - Serialized payload of the request
+pub fn get_idempotency_key(operation: &str, imei: &str, payload: &serde_json::Value) -> Uuid {
+    let name = format!("{}:{}:{}", imei, operation, payload);
+
+    return Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes());
+}
+
+/// Façade for VP-Kuljetus Vehicle Management Service API
+///
+/// Sends the requests with the models of the generated client, adding typed errors, retries, metrics and request IDs,
+/// so that the rest of the codebase doesn't depend on the generated client.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VehicleApi;
+
+impl VehicleApi {
+    /// Lists public trucks
+    ///
+    /// # Arguments
+    /// * `vin` - VIN to filter the trucks with
+    pub async fn list_public_trucks(
+        &self,
+        vin: Option<String>,
+    ) -> Result<Vec<PublicTruck>, VehicleApiError> {
+        self.list_public_trucks_page(vin, None, None).await
+    }
+
+    /// Lists a page of public trucks
+    ///
+    /// # Arguments
+    /// * `vin` - VIN to filter the trucks with
+    /// * `first` - Index of the first truck of the page
+    /// * `max` - Maximum number of trucks in the page
+    pub async fn list_public_trucks_page(
+        &self,
+        vin: Option<String>,
+        first: Option<i32>,
+        max: Option<i32>,
+    ) -> Result<Vec<PublicTruck>, VehicleApiError> {
+        let request = ApiRequest::new(Method::GET, "/v1/publicTrucks".to_string())
+            .query("vin", vin)
+            .query("first", first)
+            .query("max", max);
+
+        return self.execute("list_public_trucks", request).await;
+    }
+
+    /// Lists driver cards of a truck
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    pub async fn list_truck_driver_cards(
+        &self,
+        truck_id: &str,
+    ) -> Result<Vec<TruckDriverCard>, VehicleApiError> {
+        let request = ApiRequest::new(
+            Method::GET,
+            format!("/v1/trucks/{}/driverCards", urlencode(truck_id)),
+        );
+
+        return self
+            .execute_for_truck("list_truck_driver_cards", truck_id, request)
+            .await;
+    }
+
+    /// Creates a driver card for a truck
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `imei` - IMEI of the device the driver card was received from
+    /// * `truck_driver_card` - Driver card to create
+    pub async fn create_truck_driver_card(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_driver_card: TruckDriverCard,
+    ) -> Result<(), VehicleApiError> {
+        self.create(
+            "create_truck_driver_card",
+            truck_id,
+            imei,
+            truck_driver_card,
+            "driverCards",
+        )
+        .await
+    }
+
+    /// Deletes a driver card from a truck
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `driver_card_id` - Driver card ID
+    /// * `removed_at` - Time when the driver card was removed
+    pub async fn delete_truck_driver_card(
+        &self,
+        truck_id: &str,
+        driver_card_id: &str,
+        removed_at: DateTime<Utc>,
+    ) -> Result<(), VehicleApiError> {
+        let request = ApiRequest::new(
+            Method::DELETE,
+            format!(
+                "/v1/trucks/{}/driverCards/{}",
+                urlencode(truck_id),
+                urlencode(driver_card_id)
+            ),
+        )
+        .header(DRIVER_CARD_REMOVED_AT_HEADER, &removed_at.to_string());
+
+        return self
+            .execute_for_truck::<IgnoredAny>("delete_truck_driver_card", truck_id, request)
+            .await
+            .map(|_| ());
+    }
+
+    /// Creates a location for a truck
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `imei` - IMEI of the device the location was received from
+    /// * `truck_location` - Location to create
+    pub async fn create_truck_location(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_location: TruckLocation,
+    ) -> Result<(), VehicleApiError> {
+        self.create(
+            "create_truck_location",
+            truck_id,
+            imei,
+            truck_location,
+            "locations",
+        )
+        .await
+    }
+
+    /// Creates a batch of locations for a truck
+    ///
+    /// The Vehicle Management Service has no bulk endpoint for locations yet, so the locations are created one by one.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `imei` - IMEI of the device the locations were received from
+    /// * `truck_locations` - Locations to create
+    ///
+    /// # Returns
+    /// * Result of creating each location in the order of the locations
+    pub async fn create_truck_locations(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_locations: Vec<TruckLocation>,
+    ) -> Vec<Result<(), VehicleApiError>> {
+        let mut results = Vec::with_capacity(truck_locations.len());
+        for truck_location in truck_locations {
+            results.push(
+                self.create_truck_location(truck_id, imei, truck_location)
+                    .await,
+            );
+        }
+
+        return results;
+    }
+
+    /// Creates a speed for a truck
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `imei` - IMEI of the device the speed was received from
+    /// * `truck_speed` - Speed to create
+    pub async fn create_truck_speed(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_speed: TruckSpeed,
+    ) -> Result<(), VehicleApiError> {
+        self.create("create_truck_speed", truck_id, imei, truck_speed, "speeds")
+            .await
+    }
+
+    /// Creates a drive state for a truck
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `imei` - IMEI of the device the drive state was received from
+    /// * `truck_drive_state` - Drive state to create
+    pub async fn create_drive_state(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_drive_state: TruckDriveState,
+    ) -> Result<(), VehicleApiError> {
+        self.create(
+            "create_drive_state",
+            truck_id,
+            imei,
+            truck_drive_state,
+            "driveStates",
+        )
+        .await
+    }
+
+    /// Creates a driver behavior event for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for driver behavior events.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `driver_behavior_event` - Driver behavior event to create
+    pub async fn create_driver_behavior_event(
+        &self,
+        truck_id: &str,
+        driver_behavior_event: DriverBehaviorEvent,
+    ) -> Result<(), VehicleApiError> {
+        metrics::increment_counter(
+            DRIVER_BEHAVIOR_EVENTS_METRIC,
+            &[("type", driver_behavior_event.event_type.as_str())],
+        );
+
+        return self.unsupported(
+            "create_driver_behavior_event",
+            truck_id,
+            &driver_behavior_event,
+        );
+    }
+
+    /// Creates BLE sensor readings for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides endpoints for temperature, humidity or door state readings.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `ble_sensor_readings` - BLE sensor readings to create
+    pub async fn create_ble_sensor_readings(
+        &self,
+        truck_id: &str,
+        ble_sensor_readings: BleSensorReadings,
+    ) -> Result<(), VehicleApiError> {
+        for reading in ble_sensor_readings.readings.iter() {
+            let measurements = [
+                ("temperature", reading.temperature.is_some()),
+                ("humidity", reading.humidity.is_some()),
+                ("door_state", reading.door_open.is_some()),
+            ];
+            for (kind, _) in measurements.iter().filter(|(_, is_present)| *is_present) {
+                metrics::increment_counter(BLE_SENSOR_MEASUREMENTS_METRIC, &[("kind", kind)]);
+            }
+        }
+
+        return self.unsupported("create_ble_sensor_readings", truck_id, &ble_sensor_readings);
+    }
+
+    /// Creates axle weights for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for axle weights.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `truck_axle_weights` - Axle weights to create
+    pub async fn create_truck_axle_weights(
+        &self,
+        truck_id: &str,
+        truck_axle_weights: TruckAxleWeights,
+    ) -> Result<(), VehicleApiError> {
+        for axle_weight in truck_axle_weights.axles.iter() {
+            metrics::increment_counter(
+                AXLE_WEIGHTS_METRIC,
+                &[("axle", &axle_weight.axle.to_string())],
+            );
+        }
+
+        return self.unsupported("create_truck_axle_weights", truck_id, &truck_axle_weights);
+    }
+
+    /// Creates engine hours for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for engine hours.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `truck_engine_hours` - Engine hours to create
+    pub async fn create_truck_engine_hours(
+        &self,
+        truck_id: &str,
+        truck_engine_hours: TruckEngineHours,
+    ) -> Result<(), VehicleApiError> {
+        metrics::increment_counter(ENGINE_READINGS_METRIC, &[("type", "engine_hours")]);
+
+        return self.unsupported("create_truck_engine_hours", truck_id, &truck_engine_hours);
+    }
+
+    /// Creates an engine speed for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for engine speeds.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `truck_engine_rpm` - Engine speed to create
+    pub async fn create_truck_engine_rpm(
+        &self,
+        truck_id: &str,
+        truck_engine_rpm: TruckEngineRpm,
+    ) -> Result<(), VehicleApiError> {
+        metrics::increment_counter(ENGINE_READINGS_METRIC, &[("type", "rpm")]);
+
+        return self.unsupported("create_truck_engine_rpm", truck_id, &truck_engine_rpm);
+    }
+
+    /// Creates a fault record for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for fault records.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `fault_record` - Fault record to create
+    pub async fn create_fault_record(
+        &self,
+        truck_id: &str,
+        fault_record: FaultRecord,
+    ) -> Result<(), VehicleApiError> {
+        let is_active = if fault_record.active_count > 0 {
+            "true"
+        } else {
+            "false"
+        };
+        metrics::increment_counter(FAULT_RECORDS_METRIC, &[("active", is_active)]);
+
+        return self.unsupported("create_fault_record", truck_id, &fault_record);
+    }
+
+    /// Creates a zone event for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for zone events.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `zone_event` - Zone event to create
+    pub async fn create_zone_event(
+        &self,
+        truck_id: &str,
+        zone_event: ZoneEvent,
+    ) -> Result<(), VehicleApiError> {
+        metrics::increment_counter(
+            ZONE_EVENTS_METRIC,
+            &[("direction", zone_event.direction.as_str())],
+        );
+
+        return self.unsupported("create_zone_event", truck_id, &zone_event);
+    }
+
+    /// Creates a shift summary for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for shift summaries.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `shift_summary` - Shift summary to create
+    pub async fn create_shift_summary(
+        &self,
+        truck_id: &str,
+        shift_summary: ShiftSummary,
+    ) -> Result<(), VehicleApiError> {
+        return self.unsupported("create_shift_summary", truck_id, &shift_summary);
+    }
+
+    /// Creates an odometer discrepancy for a truck
+    ///
+    /// Fails as unsupported until Vehicle Management Service provides an endpoint for odometer discrepancies.
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    /// * `odometer_discrepancy` - Odometer discrepancy to create
+    pub async fn create_odometer_discrepancy(
+        &self,
+        truck_id: &str,
+        odometer_discrepancy: OdometerDiscrepancy,
+    ) -> Result<(), VehicleApiError> {
+        return self.unsupported(
+            "create_odometer_discrepancy",
+            truck_id,
+            &odometer_discrepancy,
+        );
+    }
+
+    /// Fails a request for an operation Vehicle Management Service doesn't provide an endpoint for
+    ///
+    /// The receiver decodes some events before Vehicle Management Service is able to store them. Their payloads
+    /// are logged so that they can be inspected, but the request fails with [VehicleApiErrorKind::Unsupported]
+    /// instead of succeeding, so that the events are neither counted as sent nor cached for retrying.
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation for logs and metrics
+    /// * `truck_id` - Truck ID
+    /// * `imei` - IMEI of the device the payload was received from
+    /// * `payload` - Payload of the request
+    fn unsupported<P: Serialize>(
+        &self,
+        operation: &str,
+        truck_id: &str,
+        payload: &P,
+    ) -> Result<(), VehicleApiError> {
+        metrics::increment_counter(
+            API_REQUESTS_METRIC,
+            &[("operation", operation), ("result", "unsupported")],
+        );
+        info!(
+            "Unsupported {} for truck [{}]: {}",
+            operation,
+            truck_id,
+            serde_json::to_string(payload).unwrap_or_default()
+        );
+
+        return Err(VehicleApiError {
+            request_id: Uuid::new_v4(),
+            kind: VehicleApiErrorKind::Unsupported,
+        });
+    }
+
+    /// Validates the payload of a request
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation for logs and metrics
+    /// * `payload` - Payload to validate
+    fn validate<P: ValidatePayload>(
+        &self,
+        operation: &str,
+        payload: &P,
+    ) -> Result<(), VehicleApiError> {
+        return payload.validate().map_err(|err| {
+            metrics::increment_counter(
+                API_REQUESTS_METRIC,
+                &[("operation", operation), ("result", "invalid")],
+            );
+            VehicleApiError {
+                request_id: Uuid::new_v4(),
+                kind: VehicleApiErrorKind::Invalid(err),
+            }
+        });
+    }
+
+    /// Executes an API request creating a resource for a truck
+    ///
+    /// The payload is validated before sending it, the request is sent with an idempotency key in [IDEMPOTENCY_KEY_HEADER]
+    /// and the request is captured for debugging.
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation for logs and metrics
+    /// * `truck_id` - Truck ID
+    /// * `payload` - Payload of the request
+    /// * `resource` - Path of the resource collection of the truck, e.g. `locations`
+    async fn create<P>(
+        &self,
+        operation: &str,
+        truck_id: &str,
+        imei: &str,
+        payload: P,
+        resource: &str,
+    ) -> Result<(), VehicleApiError>
+    where
+        P: ValidatePayload + Serialize,
+    {
+        self.validate(operation, &payload)?;
+        let captured_payload = serde_json::to_value(&payload).unwrap_or_default();
+        let idempotency_key = get_idempotency_key(operation, imei, &captured_payload);
+        let request_id = Uuid::new_v4();
+        let request = ApiRequest::new(
+            Method::POST,
+            format!("/v1/trucks/{}/{}", urlencode(truck_id), resource),
+        )
+        .json(captured_payload.clone());
+        let result = self
+            .execute_with_headers::<IgnoredAny>(
+                operation,
+                Some(truck_id),
+                request_id,
+                Some(idempotency_key),
+                request,
+            )
+            .await
+            .map(|_| ());
+        let captured_result = match &result {
+            Ok(()) => "success".to_string(),
+            Err(err) => err.kind.to_string(),
+        };
+        outbound_capture::capture(
+            operation,
+            request_id,
+            truck_id,
+            captured_payload,
+            captured_result,
+        );
+
+        return result;
+    }
+
+    /// Executes an API request
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation for logs and metrics
+    /// * `request` - Request to send
+    ///
+    /// # Returns
+    /// * Response of the request or the error of the last attempt
+    async fn execute<T>(&self, operation: &str, request: ApiRequest) -> Result<T, VehicleApiError>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        return self
+            .execute_with_headers(operation, None, Uuid::new_v4(), None, request)
+            .await;
+    }
+
+    /// Executes an API request concerning a truck
+    ///
+    /// The request is routed to the alternate API of the truck if one is configured.
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation for logs and metrics
+    /// * `truck_id` - Truck ID
+    /// * `request` - Request to send
+    ///
+    /// # Returns
+    /// * Response of the request or the error of the last attempt
+    async fn execute_for_truck<T>(
+        &self,
+        operation: &str,
+        truck_id: &str,
+        request: ApiRequest,
+    ) -> Result<T, VehicleApiError>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        return self
+            .execute_with_headers(operation, Some(truck_id), Uuid::new_v4(), None, request)
+            .await;
+    }
+
+    /// Executes an API request with the given request ID and idempotency key
+    ///
+    /// Requests failing due to transport or server errors or rate limiting are retried up to [MAX_API_REQUEST_ATTEMPTS]
+    /// times with jittered exponential backoff. All attempts share the same request ID and idempotency key.
+    /// Requests of an operation whose circuit breaker is open fail right away without being sent.
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation for logs and metrics
+    /// * `truck_id` - Truck ID used for routing the request or `None` if the request doesn't concern a single truck
+    /// * `request_id` - ID of the request to send in [REQUEST_ID_HEADER]
+    /// * `idempotency_key` - Idempotency key to send in [IDEMPOTENCY_KEY_HEADER]
+    /// * `request` - Request to send
+    ///
+    /// # Returns
+    /// * Response of the request or the error of the last attempt
+    #[instrument(
+        name = "api_request",
+        skip_all,
+        fields(
+            operation = operation,
+            request_id = %request_id,
+            attempts = Empty,
+            otel.kind = "client",
+            otel.status_code = Empty,
+        )
+    )]
+    async fn execute_with_headers<T>(
+        &self,
+        operation: &str,
+        truck_id: Option<&str>,
+        request_id: Uuid,
+        idempotency_key: Option<Uuid>,
+        request: ApiRequest,
+    ) -> Result<T, VehicleApiError>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let circuit_breakers = get_circuit_breakers();
+        if !circuit_breakers.try_acquire(operation, Instant::now()) {
+            debug!(
+                "Request [{}] {} not sent, circuit breaker is open",
+                request_id, operation
+            );
+            Span::current()
+                .record("attempts", 0)
+                .record("otel.status_code", "ERROR");
+            metrics::increment_counter(
+                API_REQUESTS_METRIC,
+                &[("operation", operation), ("result", "short_circuited")],
+            );
+            return Err(VehicleApiError {
+                request_id,
+                kind: VehicleApiErrorKind::CircuitOpen,
+            });
+        }
+        let configuration = get_request_configuration(truck_id);
+        let headers = get_request_headers(request_id, idempotency_key);
+        let started_at = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let result = run_api_request(
+                request
+                    .clone()
+                    .send::<T>(configuration.clone(), headers.clone()),
+            )
+            .await
+            .map_err(VehicleApiErrorKind::from);
+            match result {
+                Ok(output) => {
+                    debug!("Request [{}] {} succeeded", request_id, operation);
+                    circuit_breakers.record_success(operation);
+                    batching::record_api_request(started_at.elapsed(), true);
+                    Span::current().record("attempts", attempt);
+                    metrics::increment_counter(
+                        API_REQUESTS_METRIC,
+                        &[("operation", operation), ("result", "success")],
+                    );
+                    return Ok(output);
+                }
+                Err(err) if err.is_retryable() && attempt < MAX_API_REQUEST_ATTEMPTS => {
+                    debug!(
+                        "Request [{}] {} failed on attempt {}: {}. Retrying...",
+                        request_id, operation, attempt, err
+                    );
+                    tokio::time::sleep(get_retry_delay(attempt)).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    debug!(
+                        "Request [{}] {} failed on attempt {}: {}",
+                        request_id, operation, attempt, err
+                    );
+                    // Errors other than transport and server errors mean the API is reachable
+                    if err.is_retryable() {
+                        circuit_breakers.record_failure(operation, Instant::now());
+                    } else {
+                        circuit_breakers.record_success(operation);
+                    }
+                    batching::record_api_request(started_at.elapsed(), !err.is_retryable());
+                    Span::current()
+                        .record("attempts", attempt)
+                        .record("otel.status_code", "ERROR");
+                    metrics::increment_counter(
+                        API_REQUESTS_METRIC,
+                        &[("operation", operation), ("result", "failure")],
+                    );
+                    return Err(VehicleApiError {
+                        request_id,
+                        kind: err,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Gets the delay before retrying a failed API request
+///
+/// The delay doubles for each attempt and is randomized to between half and all of it, so that requests failed at the
+/// same time aren't retried in lockstep.
+///
+/// # Arguments
+/// * `attempt` - Number of the failed attempt, starting from 1
+pub fn get_retry_delay(attempt: u32) -> Duration {
+    let delay = API_REQUEST_RETRY_DELAY * 2_u32.pow(attempt.clamp(1, 16) - 1);
+
+    return rand::thread_rng().gen_range(delay / 2..=delay);
+}
+
+/// Operations of the Vehicle Management Service API the event handlers send their events with
+///
+/// Event handlers depend on this trait instead of [VehicleApi], so that they can be unit tested with a fake API.
+pub trait TruckEventApi {
+    /// Creates a driver card for a truck
+    async fn create_truck_driver_card(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_driver_card: TruckDriverCard,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates a speed for a truck
+    async fn create_truck_speed(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_speed: TruckSpeed,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates a batch of speeds for a truck
+    ///
+    /// The Vehicle Management Service has no bulk endpoint for speeds yet, so the speeds are created one by one.
+    ///
+    /// # Returns
+    /// * Result of creating each speed in the order of the speeds
+    async fn create_truck_speeds(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_speeds: Vec<TruckSpeed>,
+    ) -> Vec<Result<(), VehicleApiError>> {
+        let mut results = Vec::with_capacity(truck_speeds.len());
+        for truck_speed in truck_speeds {
+            results.push(self.create_truck_speed(truck_id, imei, truck_speed).await);
+        }
+
+        return results;
+    }
+
+    /// Creates a drive state for a truck
+    async fn create_drive_state(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_drive_state: TruckDriveState,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates a driver behavior event for a truck
+    async fn create_driver_behavior_event(
+        &self,
+        truck_id: &str,
+        driver_behavior_event: DriverBehaviorEvent,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates BLE sensor readings for a truck
+    async fn create_ble_sensor_readings(
+        &self,
+        truck_id: &str,
+        ble_sensor_readings: BleSensorReadings,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates axle weights for a truck
+    async fn create_truck_axle_weights(
+        &self,
+        truck_id: &str,
+        truck_axle_weights: TruckAxleWeights,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates engine hours for a truck
+    async fn create_truck_engine_hours(
+        &self,
+        truck_id: &str,
+        truck_engine_hours: TruckEngineHours,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates an engine speed for a truck
+    async fn create_truck_engine_rpm(
+        &self,
+        truck_id: &str,
+        truck_engine_rpm: TruckEngineRpm,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates a fault record for a truck
+    async fn create_fault_record(
+        &self,
+        truck_id: &str,
+        fault_record: FaultRecord,
+    ) -> Result<(), VehicleApiError>;
+
+    /// Creates a zone event for a truck
+    async fn create_zone_event(
+        &self,
+        truck_id: &str,
+        zone_event: ZoneEvent,
+    ) -> Result<(), VehicleApiError>;
+}
+
+impl TruckEventApi for VehicleApi {
+    async fn create_truck_driver_card(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_driver_card: TruckDriverCard,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_truck_driver_card(self, truck_id, imei, truck_driver_card).await
+    }
+
+    async fn create_truck_speed(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_speed: TruckSpeed,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_truck_speed(self, truck_id, imei, truck_speed).await
+    }
+
+    async fn create_drive_state(
+        &self,
+        truck_id: &str,
+        imei: &str,
+        truck_drive_state: TruckDriveState,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_drive_state(self, truck_id, imei, truck_drive_state).await
+    }
+
+    async fn create_driver_behavior_event(
+        &self,
+        truck_id: &str,
+        driver_behavior_event: DriverBehaviorEvent,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_driver_behavior_event(self, truck_id, driver_behavior_event).await
+    }
+
+    async fn create_ble_sensor_readings(
+        &self,
+        truck_id: &str,
+        ble_sensor_readings: BleSensorReadings,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_ble_sensor_readings(self, truck_id, ble_sensor_readings).await
+    }
+
+    async fn create_truck_axle_weights(
+        &self,
+        truck_id: &str,
+        truck_axle_weights: TruckAxleWeights,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_truck_axle_weights(self, truck_id, truck_axle_weights).await
+    }
+
+    async fn create_truck_engine_hours(
+        &self,
+        truck_id: &str,
+        truck_engine_hours: TruckEngineHours,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_truck_engine_hours(self, truck_id, truck_engine_hours).await
+    }
+
+    async fn create_truck_engine_rpm(
+        &self,
+        truck_id: &str,
+        truck_engine_rpm: TruckEngineRpm,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_truck_engine_rpm(self, truck_id, truck_engine_rpm).await
+    }
+
+    async fn create_fault_record(
+        &self,
+        truck_id: &str,
+        fault_record: FaultRecord,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_fault_record(self, truck_id, fault_record).await
+    }
+
+    async fn create_zone_event(
+        &self,
+        truck_id: &str,
+        zone_event: ZoneEvent,
+    ) -> Result<(), VehicleApiError> {
+        VehicleApi::create_zone_event(self, truck_id, zone_event).await
+    }
+}
+
+/// Gets the API configuration for a single request
+///
+/// Requests concerning a truck routed to an alternate API are sent to the base URL of that API.
+///
+/// # Arguments
+/// * `truck_id` - Truck ID used for routing the request or `None` if the request doesn't concern a single truck
+fn get_request_configuration(truck_id: Option<&str>) -> Configuration {
+    let mut configuration = get_vehicle_management_api_config();
+    if let Some(base_url) = truck_id.and_then(|truck_id| get_api_routing().get_base_url(truck_id)) {
+        configuration.base_path = base_url;
+    }
+
+    return configuration;
+}
+
+/// Gets the headers of a single request
+///
+/// The headers are shared by all attempts of the request.
+///
+/// # Arguments
+/// * `request_id` - ID of the request to send in [REQUEST_ID_HEADER]
+/// * `idempotency_key` - Idempotency key to send in [IDEMPOTENCY_KEY_HEADER]
+fn get_request_headers(request_id: Uuid, idempotency_key: Option<Uuid>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        REQUEST_ID_HEADER,
+        HeaderValue::from_str(&request_id.to_string()).expect("Invalid request ID"),
+    );
+    if let Some(idempotency_key) = idempotency_key {
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_str(&idempotency_key.to_string()).expect("Invalid idempotency key"),
+        );
+    }
+    inject_trace_context(&mut headers);
+
+    return headers;
+}
+
+/// Request to the Vehicle Management Service API
+///
+/// The generated client builds each request inside its operation functions with no way to add headers to it, so the
+/// requests are built here using the models of the generated client instead. That way all requests are sent through
+/// the shared client of [get_api_client] with the headers of each request.
+#[derive(Debug, Clone)]
+struct ApiRequest {
+    method: Method,
+    /// Path of the request relative to the base URL of the API
+    path: String,
+    query: Vec<(&'static str, String)>,
+    headers: HeaderMap,
+    body: Option<serde_json::Value>,
+}
+
+impl ApiRequest {
+    /// Creates a new [ApiRequest]
+    ///
+    /// # Arguments
+    /// * `method` - HTTP method of the request
+    /// * `path` - Path of the request relative to the base URL of the API
+    fn new(method: Method, path: String) -> Self {
+        ApiRequest {
+            method,
+            path,
+            query: Vec::new(),
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    /// Adds a query parameter to the request if it has a value
+    ///
+    /// # Arguments
+    /// * `name` - Name of the parameter
+    /// * `value` - Value of the parameter
+    fn query<V: ToString>(mut self, name: &'static str, value: Option<V>) -> Self {
+        if let Some(value) = value {
+            self.query.push((name, value.to_string()));
+        }
+
+        return self;
+    }
+
+    /// Adds a header to the request
+    ///
+    /// # Arguments
+    /// * `name` - Name of the header
+    /// * `value` - Value of the header
+    fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.insert(
+            name,
+            HeaderValue::from_str(value).expect("Invalid header value"),
+        );
+
+        return self;
+    }
+
+    /// Sets the JSON body of the request
+    ///
+    /// # Arguments
+    /// * `body` - Body of the request
+    fn json(mut self, body: serde_json::Value) -> Self {
+        self.body = Some(body);
+
+        return self;
+    }
+
+    /// Sends the request
+    ///
+    /// Like the requests of the generated client, the request carries the user agent and API key of the
+    /// configuration, and responses with an error status fail with [Error::ResponseError].
+    ///
+    /// # Arguments
+    /// * `configuration` - API configuration
+    /// * `headers` - Headers of the request in addition to its own headers
+    ///
+    /// # Returns
+    /// * The deserialized response body
+    async fn send<T: DeserializeOwned>(
+        self,
+        configuration: Configuration,
+        headers: HeaderMap,
+    ) -> Result<T, Error<()>> {
+        let mut request_builder = configuration
+            .client
+            .request(
+                self.method,
+                format!("{}{}", configuration.base_path, self.path),
+            )
+            .query(&self.query)
+            .headers(headers)
+            .headers(self.headers);
+        if let Some(user_agent) = configuration.user_agent {
+            request_builder = request_builder.header(USER_AGENT, user_agent);
+        }
+        if let Some(api_key) = configuration.api_key {
+            let api_key = match api_key.prefix {
+                Some(prefix) => format!("{} {}", prefix, api_key.key),
+                None => api_key.key,
+            };
+            request_builder = request_builder.header(API_KEY_HEADER, api_key);
+        }
+        if let Some(body) = self.body {
+            request_builder = request_builder.json(&body);
+        }
+        let response = request_builder.send().await?;
+        let status = response.status();
+        let content = response.text().await?;
+        if status.is_client_error() || status.is_server_error() {
+            return Err(Error::ResponseError(ResponseContent {
+                status,
+                content,
+                entity: None,
+            }));
+        }
+        // Responses without a body are read as null, which e.g. IgnoredAny accepts
+        let content = if content.is_empty() { "null" } else { &content };
+
+        return serde_json::from_str(content).map_err(Error::Serde);
+    }
+}
+
+/// Timeout and connection pool settings of the HTTP client sending the API requests
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApiClientSettings {
+    /// Maximum time to wait for a connection to the API to be established
+    pub connect_timeout: Duration,
+    /// Maximum time to wait for each read of the response
+    pub read_timeout: Duration,
+    /// Time idle connections are kept in the pool
+    pub pool_idle_timeout: Duration,
+    /// Maximum number of idle connections kept in the pool per host
+    pub pool_max_idle_per_host: usize,
+}
+
+/// Builds an HTTP client for API requests
+///
+/// # Arguments
+/// * `settings` - Timeouts and connection pool settings of the client
+pub fn build_api_client(settings: &ApiClientSettings) -> reqwest::Client {
+    return reqwest::Client::builder()
+        .connect_timeout(settings.connect_timeout)
+        .read_timeout(settings.read_timeout)
+        .pool_idle_timeout(settings.pool_idle_timeout)
+        .pool_max_idle_per_host(settings.pool_max_idle_per_host)
+        .build()
+        .expect("Failed to build API client");
+}
+
+/// Gets the HTTP client shared by all API requests
+///
+/// Clones of the client share its connection pool, so that requests reuse the connections to the API instead of
+/// connecting for each request.
+pub fn get_api_client() -> reqwest::Client {
+    return API_CLIENT
+        .get_or_init(|| build_api_client(&get_config().api.client))
+        .clone();
+}
 
 /// Gets truck ID by VIN
 ///
@@ -21,34 +1299,54 @@ use super::get_vehicle_management_api_config;
 /// # Returns
 /// * `Option<Uuid>` - Truck ID
 pub async fn get_truck_id_by_vin(vin: &Option<String>) -> Option<Uuid> {
-    if vin.is_none() {
+    let Some(vin) = vin else {
         return None;
-    }
+    };
 
-    match vehicle_management_service::apis::public_trucks_api::list_public_trucks(
-        &get_vehicle_management_api_config(),
-        ListPublicTrucksParams {
-            vin: vin.clone(),
-            first: None,
-            max: None,
-        },
-    )
-    .await
-    {
-        Ok(trucks) => {
-            return trucks
-                .iter()
-                .find(|truck| truck.vin == vin.clone().unwrap())
-                .map(|truck| truck.id.clone())
-                .unwrap_or(None)
+    return get_truck_cache()
+        .get_or_fetch(vin, || async {
+            let lookup_started_at = std::time::Instant::now();
+            let result = VehicleApi.list_public_trucks(Some(vin.clone())).await;
+            record_lookup_latency(lookup_started_at.elapsed());
+            match result {
+                Ok(trucks) => Ok(trucks
+                    .iter()
+                    .find(|truck| &truck.vin == vin)
+                    .and_then(|truck| truck.id)),
+                Err(err) => {
+                    warn!("Failed to get truck ID by VIN [{}]: {}", vin, err);
+                    Err(())
+                }
+            }
+        })
+        .await;
+}
+
+/// Warms up the truck cache by listing all public trucks
+///
+/// Pre-populating the cache prevents the first wave of reconnecting devices after a deploy from looking up
+/// their trucks one by one.
+///
+/// # Returns
+/// * Number of cached trucks
+pub async fn warm_up_truck_cache() -> Result<usize, VehicleApiError> {
+    let truck_cache = get_truck_cache();
+    let mut cached_trucks = 0;
+    loop {
+        let trucks = VehicleApi
+            .list_public_trucks_page(
+                None,
+                Some(cached_trucks as i32),
+                Some(TRUCK_CACHE_WARMUP_PAGE_SIZE),
+            )
+            .await?;
+        let now = Utc::now();
+        for truck in trucks.iter() {
+            truck_cache.insert(&truck.vin, truck.id, now);
         }
-        Err(err) => {
-            warn!(
-                "Failed to get truck ID by VIN [{}]: {}",
-                vin.clone().unwrap(),
-                err
-            );
-            return None;
+        cached_trucks += trucks.len();
+        if trucks.len() < TRUCK_CACHE_WARMUP_PAGE_SIZE as usize {
+            return Ok(cached_trucks);
         }
     }
 }
@@ -61,18 +1359,11 @@ pub async fn get_truck_id_by_vin(vin: &Option<String>) -> Option<Uuid> {
 /// * `truck_id` - Truck ID
 ///
 /// # Returns
-/// * `Option<String>` - Driver card ID
-pub async fn get_truck_driver_card_id(truck_id: String) -> Option<String> {
-    let Ok(driver_cards) = list_truck_driver_cards(
-        &get_vehicle_management_api_config(),
-        ListTruckDriverCardsParams {
-            truck_id: truck_id.clone(),
-        },
-    )
-    .await
-    else {
+/// * `Result<Option<String>, ()>` - Driver card ID or error if driver cards could not be listed
+pub async fn get_truck_driver_card_id(truck_id: String) -> Result<Option<String>, ()> {
+    let Ok(driver_cards) = VehicleApi.list_truck_driver_cards(&truck_id).await else {
         info!("Failed to get driver cards for truck [{}]", truck_id);
-        return None;
+        return Err(());
     };
     assert!(
         driver_cards.len() <= 1,
@@ -80,10 +1371,10 @@ pub async fn get_truck_driver_card_id(truck_id: String) -> Option<String> {
     );
     let Some(driver_card) = driver_cards.first() else {
         info!("Truck [{}] has no driver card", truck_id);
-        return None;
+        return Ok(None);
     };
 
-    Some(driver_card.id.clone())
+    Ok(Some(driver_card.id.clone()))
 }
 
 /// Deletes truck driver card from truck
@@ -92,28 +1383,31 @@ pub async fn get_truck_driver_card_id(truck_id: String) -> Option<String> {
 /// * `truck_id` - Truck ID
 /// * `driver_card_id` - Driver card ID
 /// * `removed_at` - Time when the driver card was removed
+///
+/// # Returns
+/// * `Result<(), ()>` - Whether the driver card was deleted
 pub async fn delete_truck_driver_card_by_id(
     truck_id: String,
     driver_card_id: String,
     removed_at: DateTime<Utc>,
-) {
-    match delete_truck_driver_card(
-        &get_vehicle_management_api_config(),
-        DeleteTruckDriverCardParams {
-            truck_id: truck_id.clone(),
-            driver_card_id: driver_card_id.clone(),
-            x_driver_card_removed_at: removed_at.to_string(),
-        },
-    )
-    .await
+) -> Result<(), ()> {
+    match VehicleApi
+        .delete_truck_driver_card(&truck_id, &driver_card_id, removed_at)
+        .await
     {
-        Ok(_) => info!(
-            "Driver card [{}] deleted from truck [{}]",
-            driver_card_id, truck_id
-        ),
-        Err(err) => warn!(
-            "Failed to delete driver card [{}] from truck [{}]: {}",
-            driver_card_id, truck_id, err
-        ),
-    };
+        Ok(_) => {
+            info!(
+                "Driver card [{}] deleted from truck [{}]",
+                driver_card_id, truck_id
+            );
+            Ok(())
+        }
+        Err(err) => {
+            warn!(
+                "Failed to delete driver card [{}] from truck [{}]: {}",
+                driver_card_id, truck_id, err
+            );
+            Err(())
+        }
+    }
 }
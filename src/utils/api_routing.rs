@@ -0,0 +1,149 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use log::info;
+
+use crate::{config::get_config, synthetic::get_synthetic_devices};
+
+static API_ROUTING: OnceLock<ApiRouting> = OnceLock::new();
+
+/// Selector of the trucks routed to an alternate API
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteSelector {
+    /// Truck with the given ID
+    Truck(String),
+    /// Trucks of devices with IMEIs matching the pattern, where `*` matches any number of characters
+    Imei(String),
+}
+
+/// Override routing the requests of the selected trucks to an alternate API
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingOverride {
+    pub selector: RouteSelector,
+    pub base_url: String,
+}
+
+/// Routing of requests to alternate APIs by truck
+pub struct ApiRouting {
+    overrides: Vec<RoutingOverride>,
+    routed_trucks: Mutex<HashMap<String, String>>,
+}
+
+impl ApiRouting {
+    /// Creates a new [ApiRouting]
+    ///
+    /// # Arguments
+    /// * `overrides` - Routing overrides, the first matching override is used
+    pub fn new(overrides: Vec<RoutingOverride>) -> Self {
+        ApiRouting {
+            overrides,
+            routed_trucks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers the truck of a device, routing it to an alternate API if an override matches
+    ///
+    /// # Arguments
+    /// * `imei` - IMEI of the device
+    /// * `truck_id` - Truck ID of the device
+    pub fn register_truck(&self, imei: &str, truck_id: &str) {
+        let matching_override =
+            self.overrides
+                .iter()
+                .find(|routing_override| match &routing_override.selector {
+                    RouteSelector::Truck(id) => id == truck_id,
+                    RouteSelector::Imei(pattern) => matches_pattern(pattern, imei),
+                });
+        let mut routed_trucks = self.routed_trucks.lock().unwrap();
+        match matching_override {
+            Some(routing_override) => {
+                info!(target: imei,
+                    "Routing requests of truck [{}] to [{}]",
+                    truck_id,
+                    routing_override.base_url
+                );
+                routed_trucks.insert(truck_id.to_string(), routing_override.base_url.clone());
+            }
+            None => {
+                routed_trucks.remove(truck_id);
+            }
+        }
+    }
+
+    /// Gets the base URL of the alternate API of a truck
+    ///
+    /// # Arguments
+    /// * `truck_id` - Truck ID
+    ///
+    /// # Returns
+    /// * Base URL of the alternate API or `None` if the requests of the truck aren't routed
+    pub fn get_base_url(&self, truck_id: &str) -> Option<String> {
+        if let Some(base_url) = self.routed_trucks.lock().unwrap().get(truck_id) {
+            return Some(base_url.clone());
+        }
+
+        return self
+            .overrides
+            .iter()
+            .find(|routing_override| {
+                routing_override.selector == RouteSelector::Truck(truck_id.to_string())
+            })
+            .map(|routing_override| routing_override.base_url.clone());
+    }
+}
+
+/// Parses routing overrides
+///
+/// Overrides are separated by commas and consist of a selector and a base URL separated by `=`,
+/// e.g. `imei:35209*=https://pilot.example.com,truck:3ffaf18c-69e4-4f8a-9179-9aec5bc96e1c=https://pilot.example.com`.
+///
+/// # Arguments
+/// * `overrides` - Routing overrides to parse
+pub fn parse_routing_overrides(overrides: &str) -> Result<Vec<RoutingOverride>, String> {
+    return overrides
+        .split(',')
+        .map(str::trim)
+        .filter(|routing_override| !routing_override.is_empty())
+        .map(|routing_override| {
+            let (selector, base_url) = routing_override
+                .split_once('=')
+                .ok_or(format!("Missing base URL in [{}]", routing_override))?;
+            let selector = match selector.split_once(':') {
+                Some(("truck", truck_id)) => RouteSelector::Truck(truck_id.to_string()),
+                Some(("imei", pattern)) => RouteSelector::Imei(pattern.to_string()),
+                _ => return Err(format!("Invalid selector [{}]", selector)),
+            };
+            Ok(RoutingOverride {
+                selector,
+                base_url: base_url.to_string(),
+            })
+        })
+        .collect();
+}
+
+/// Gets the global API routing with the overrides of the configuration
+///
+/// Synthetic devices are routed to their sandbox API before any other overrides.
+pub fn get_api_routing() -> &'static ApiRouting {
+    API_ROUTING.get_or_init(|| {
+        let mut overrides = get_synthetic_devices().get_routing_overrides();
+        overrides.extend(get_config().api.routing_overrides.iter().cloned());
+        ApiRouting::new(overrides)
+    })
+}
+
+/// Checks whether a value matches a pattern where `*` matches any number of characters
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == value;
+    };
+    let Some(value_rest) = value.strip_prefix(prefix) else {
+        return false;
+    };
+
+    return (0..=value_rest.len())
+        .filter(|index| value_rest.is_char_boundary(*index))
+        .any(|index| matches_pattern(rest, &value_rest[index..]));
+}
@@ -1,11 +1,13 @@
 /// Module containing utility functions for testing AVL packets
-use nom_teltonika::{crc16, AVLEventIO, AVLEventIOValue, AVLFrame, AVLRecord, Priority};
+use nom_teltonika::{
+    crc16, AVLEventIO, AVLEventIOValue, AVLFrame, AVLRecord, Codec, EventGenerationCause, Priority,
+};
 const AVL_PACKET_PREAMBLE: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
-const AVL_PACKET_CODEC8: [u8; 1] = [0x08];
 
 /// Trait for converting AVL packet to bytes
 ///
 /// Allows for constructing AVL packets from the given data for testing various parsing scenarios.
+/// Frames are written in the codec they were built or received with.
 /// See https://wiki.teltonika-gps.com/view/Codec#Codec_8 for reference of byte order etc.
 pub trait AVLPacketToBytes {
     /// Converts the AVL packet to vector of bytes
@@ -26,15 +28,14 @@ impl AVLPacketToBytes for AVLFrame {
     fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         let mut bytes_for_crc: Vec<u8> = Vec::new();
-        let mut number_of_data: u8 = 0;
-        for _ in &self.records {
-            number_of_data = number_of_data + 1;
-        }
+        let number_of_data = self.records.len() as u8;
 
         bytes.append(&mut AVL_PACKET_PREAMBLE.to_vec());
-        bytes_for_crc.append(&mut AVL_PACKET_CODEC8.to_vec());
+        bytes_for_crc.push(u8::from(self.codec));
         bytes_for_crc.append(&mut number_of_data.to_be_bytes().to_vec());
-        bytes_for_crc.append(&mut self.records.to_bytes());
+        for record in &self.records {
+            bytes_for_crc.append(&mut record_to_bytes(record, self.codec));
+        }
         bytes_for_crc.append(&mut number_of_data.to_be_bytes().to_vec());
         let crc16 = crc16(&bytes_for_crc) as u32;
         let mut data_field_length = (bytes_for_crc.len() as i32).to_be_bytes().to_vec();
@@ -60,50 +61,108 @@ impl AVLPacketToBytes for Vec<AVLRecord> {
 
 impl AVLPacketToBytes for AVLRecord {
     fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+        return record_to_bytes(self, Codec::C8);
+    }
+}
 
-        gps_element_to_bytes(&mut bytes, self);
-
-        let trigger_event_id = (self.trigger_event_id as i8).to_be_bytes();
-        let mut u8_events: Vec<(u8, u8)> = Vec::new();
-        let mut u16_events: Vec<(u8, u16)> = Vec::new();
-        let mut u32_events: Vec<(u8, u32)> = Vec::new();
-        let mut u64_events: Vec<(u8, u64)> = Vec::new();
-
-        for event in &self.io_events {
-            match event.value {
-                AVLEventIOValue::U8(value) => u8_events.push((event.id as u8, value)),
-                AVLEventIOValue::U16(value) => u16_events.push((event.id as u8, value)),
-                AVLEventIOValue::U32(value) => u32_events.push((event.id as u8, value)),
-                AVLEventIOValue::U64(value) => u64_events.push((event.id as u8, value)),
-                AVLEventIOValue::Variable(_) => (),
+/// Converts the AVL record to bytes in the format of the given codec
+///
+/// Codec 8 Extended uses two bytes for IO IDs and counts and adds variable length IO elements,
+/// while Codec 16 uses two bytes for IO IDs and adds the Generation Type after the trigger event ID.
+/// See https://wiki.teltonika-gps.com/view/Codec#Codec_16 for reference.
+///
+/// # Arguments
+/// * `record` - AVL record to convert
+/// * `codec` - Codec of the frame the record belongs to
+pub fn record_to_bytes(record: &AVLRecord, codec: Codec) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    gps_element_to_bytes(&mut bytes, record);
+
+    let mut u8_events: Vec<(u16, Vec<u8>)> = Vec::new();
+    let mut u16_events: Vec<(u16, Vec<u8>)> = Vec::new();
+    let mut u32_events: Vec<(u16, Vec<u8>)> = Vec::new();
+    let mut u64_events: Vec<(u16, Vec<u8>)> = Vec::new();
+    let mut variable_events: Vec<(u16, Vec<u8>)> = Vec::new();
+
+    for event in &record.io_events {
+        match &event.value {
+            AVLEventIOValue::U8(value) => u8_events.push((event.id, value.to_be_bytes().to_vec())),
+            AVLEventIOValue::U16(value) => {
+                u16_events.push((event.id, value.to_be_bytes().to_vec()))
+            }
+            AVLEventIOValue::U32(value) => {
+                u32_events.push((event.id, value.to_be_bytes().to_vec()))
+            }
+            AVLEventIOValue::U64(value) => {
+                u64_events.push((event.id, value.to_be_bytes().to_vec()))
+            }
+            AVLEventIOValue::Variable(value) if codec == Codec::C8Ext => {
+                let mut length_and_value = (value.len() as u16).to_be_bytes().to_vec();
+                length_and_value.extend_from_slice(value);
+                variable_events.push((event.id, length_and_value))
             }
+            AVLEventIOValue::Variable(_) => (),
         }
-        bytes.append(&mut trigger_event_id.to_vec());
+    }
 
-        bytes.append(&mut (self.io_events.len() as u8).to_be_bytes().to_vec());
-        bytes.append(&mut (u8_events.len() as u8).to_be_bytes().to_vec());
-        for (id, value) in u8_events {
-            bytes.append(&mut id.to_be_bytes().to_vec());
-            bytes.append(&mut value.to_be_bytes().to_vec());
-        }
-        bytes.append(&mut (u16_events.len() as u8).to_be_bytes().to_vec());
-        for (id, value) in u16_events {
-            bytes.append(&mut id.to_be_bytes().to_vec());
-            bytes.append(&mut value.to_be_bytes().to_vec());
-        }
-        bytes.append(&mut (u32_events.len() as u8).to_be_bytes().to_vec());
-        for (id, value) in u32_events {
-            bytes.append(&mut id.to_be_bytes().to_vec());
-            bytes.append(&mut value.to_be_bytes().to_vec());
+    bytes.append(&mut io_id_to_bytes(record.trigger_event_id, codec));
+    if codec == Codec::C16 {
+        bytes.push(generation_type_to_byte(record.generation_type.as_ref()));
+    }
+    let event_count = u8_events.len()
+        + u16_events.len()
+        + u32_events.len()
+        + u64_events.len()
+        + variable_events.len();
+    bytes.append(&mut io_count_to_bytes(event_count, codec));
+    for events in [u8_events, u16_events, u32_events, u64_events] {
+        bytes.append(&mut io_count_to_bytes(events.len(), codec));
+        for (id, mut value) in events {
+            bytes.append(&mut io_id_to_bytes(id, codec));
+            bytes.append(&mut value);
         }
-        bytes.append(&mut (u64_events.len() as u8).to_be_bytes().to_vec());
-        for (id, value) in u64_events {
-            bytes.append(&mut id.to_be_bytes().to_vec());
-            bytes.append(&mut value.to_be_bytes().to_vec());
+    }
+    if codec == Codec::C8Ext {
+        bytes.append(&mut io_count_to_bytes(variable_events.len(), codec));
+        for (id, mut value) in variable_events {
+            bytes.append(&mut io_id_to_bytes(id, codec));
+            bytes.append(&mut value);
         }
+    }
 
-        return bytes;
+    return bytes;
+}
+
+/// Converts an IO ID to bytes in the format of the given codec
+fn io_id_to_bytes(id: u16, codec: Codec) -> Vec<u8> {
+    match codec {
+        Codec::C8Ext | Codec::C16 => id.to_be_bytes().to_vec(),
+        _ => vec![id as u8],
+    }
+}
+
+/// Converts an IO element count to bytes in the format of the given codec
+fn io_count_to_bytes(count: usize, codec: Codec) -> Vec<u8> {
+    match codec {
+        Codec::C8Ext => (count as u16).to_be_bytes().to_vec(),
+        _ => vec![count as u8],
+    }
+}
+
+/// Converts the Generation Type of a Codec 16 record to its byte
+///
+/// Records without a Generation Type are written as periodical, which is how devices report regular records.
+fn generation_type_to_byte(generation_type: Option<&EventGenerationCause>) -> u8 {
+    match generation_type {
+        Some(EventGenerationCause::OnExit) => 0,
+        Some(EventGenerationCause::OnEntrance) => 1,
+        Some(EventGenerationCause::OnBoth) => 2,
+        Some(EventGenerationCause::Reserved) => 3,
+        Some(EventGenerationCause::Hysteresis) => 4,
+        Some(EventGenerationCause::OnChange) => 5,
+        Some(EventGenerationCause::Eventual) => 6,
+        Some(EventGenerationCause::Periodical) | Some(EventGenerationCause::None) | None => 7,
     }
 }
 
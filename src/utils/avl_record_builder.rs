@@ -1,9 +1,10 @@
 #![allow(dead_code)]
 /// Module containing utilities testing building AVL Records sent by Teltonika Telematics devices for testing purposes
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 pub mod avl_record_builder {
     use chrono::{DateTime, Utc};
-    use nom_teltonika::{AVLEventIO, AVLRecord, Priority};
+    use nom_teltonika::{AVLEventIO, AVLRecord, EventGenerationCause, Priority};
 
     /// Builder for [`AVLRecord`]s
     ///
@@ -13,10 +14,13 @@ pub mod avl_record_builder {
         timestamp: Option<DateTime<Utc>>,
         priority: Option<Priority>,
         trigger_event_id: Option<u16>,
+        generation_type: Option<EventGenerationCause>,
         io_events: Vec<AVLEventIO>,
         longitude: Option<f64>,
         latitude: Option<f64>,
         angle: Option<u16>,
+        satellites: Option<u8>,
+        speed: Option<u16>,
     }
 
     impl AVLRecordBuilder {
@@ -26,10 +30,13 @@ pub mod avl_record_builder {
                 timestamp: Some(Utc::now()),
                 priority: Some(Priority::Low),
                 trigger_event_id: None,
+                generation_type: None,
                 io_events: vec![],
                 longitude: None,
                 latitude: None,
                 angle: None,
+                satellites: None,
+                speed: None,
             }
         }
 
@@ -42,10 +49,10 @@ pub mod avl_record_builder {
                 latitude: self.latitude.unwrap_or(0.0),
                 altitude: 0,
                 angle: self.angle.unwrap_or(0),
-                satellites: 0,
-                speed: 0,
+                satellites: self.satellites.unwrap_or(0),
+                speed: self.speed.unwrap_or(0),
                 trigger_event_id: self.trigger_event_id.unwrap_or(0),
-                generation_type: None,
+                generation_type: self.generation_type,
                 io_events: self.io_events,
             }
         }
@@ -68,6 +75,18 @@ pub mod avl_record_builder {
             return self;
         }
 
+        /// Sets the number of satellites of the [`AVLRecord`]
+        pub fn with_satellites(mut self, satellites: u8) -> AVLRecordBuilder {
+            self.satellites = Some(satellites);
+            return self;
+        }
+
+        /// Sets the speed of the [`AVLRecord`] in km/h
+        pub fn with_speed(mut self, speed: u16) -> AVLRecordBuilder {
+            self.speed = Some(speed);
+            return self;
+        }
+
         /// Sets the timestamp of the [`AVLRecord`]
         pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> AVLRecordBuilder {
             self.timestamp = Some(timestamp);
@@ -86,6 +105,15 @@ pub mod avl_record_builder {
             return self;
         }
 
+        /// Sets the generation type of the [`AVLRecord`], sent only in Codec 16 frames
+        pub fn with_generation_type(
+            mut self,
+            generation_type: EventGenerationCause,
+        ) -> AVLRecordBuilder {
+            self.generation_type = Some(generation_type);
+            return self;
+        }
+
         /// Adds an [`AVLEventIO`] to the [`AVLRecord`]
         pub fn add_io_event(mut self, io_event: AVLEventIO) -> AVLRecordBuilder {
             self.io_events.push(io_event);
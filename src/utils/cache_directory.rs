@@ -0,0 +1,155 @@
+//! Startup check of the cache directory
+//!
+//! Caches and raw frame captures are written lazily while devices are sending data, where a missing directory, lacking
+//! permissions or a full disk would panic in the middle of traffic. The directory is checked before accepting
+//! connections instead, failing fast with errors telling how to fix the deployment.
+use std::{
+    ffi::CString,
+    fmt,
+    fs::{create_dir_all, remove_file, File},
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+
+/// Name of the file written to check that the directory is writable
+const WRITE_CHECK_FILE_NAME: &str = ".write-check";
+
+/// Error of the cache directory check
+#[derive(Debug)]
+pub enum CacheDirectoryError {
+    /// Directory doesn't exist and couldn't be created
+    NotCreatable(PathBuf, std::io::Error),
+    /// Path exists but isn't a directory
+    NotADirectory(PathBuf),
+    /// Directory isn't writable by the receiver
+    NotWritable(PathBuf, std::io::Error),
+    /// File system of the directory has less free space than required
+    InsufficientSpace {
+        path: PathBuf,
+        available_mb: u64,
+        required_mb: u64,
+    },
+}
+
+impl fmt::Display for CacheDirectoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheDirectoryError::NotCreatable(path, error) => write!(
+                f,
+                "Cache directory [{}] doesn't exist and couldn't be created: {}. Create it or mount a volume to it",
+                path.display(),
+                error
+            ),
+            CacheDirectoryError::NotADirectory(path) => write!(
+                f,
+                "Cache path [{}] is not a directory. Point BASE_FILE_PATH to a directory",
+                path.display()
+            ),
+            CacheDirectoryError::NotWritable(path, error) => write!(
+                f,
+                "Cache directory [{}] is not writable: {}. Grant the user running the receiver (UID {}) write access to it",
+                path.display(),
+                error,
+                get_effective_uid()
+            ),
+            CacheDirectoryError::InsufficientSpace {
+                path,
+                available_mb,
+                required_mb,
+            } => write!(
+                f,
+                "Cache directory [{}] has {} MB free, at least {} MB is required. Free up space or lower MIN_CACHE_FREE_SPACE_MB",
+                path.display(),
+                available_mb,
+                required_mb
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CacheDirectoryError {}
+
+/// Checks that the cache directory exists, is writable and has enough free space
+///
+/// A missing directory is created. Directories writable by anyone or owned by another user work but are logged as warnings.
+///
+/// # Arguments
+/// * `path` - Path of the cache directory
+/// * `min_free_space_mb` - Minimum free space in megabytes, 0 to skip the check
+pub fn check_cache_directory(
+    path: &Path,
+    min_free_space_mb: u64,
+) -> Result<(), CacheDirectoryError> {
+    if !path.exists() {
+        create_dir_all(path)
+            .map_err(|error| CacheDirectoryError::NotCreatable(path.to_path_buf(), error))?;
+        info!("Created cache directory [{}]", path.display());
+    }
+    let metadata = path
+        .metadata()
+        .map_err(|error| CacheDirectoryError::NotCreatable(path.to_path_buf(), error))?;
+    if !metadata.is_dir() {
+        return Err(CacheDirectoryError::NotADirectory(path.to_path_buf()));
+    }
+
+    let write_check_path = path.join(WRITE_CHECK_FILE_NAME);
+    File::create(&write_check_path)
+        .and_then(|_| remove_file(&write_check_path))
+        .map_err(|error| CacheDirectoryError::NotWritable(path.to_path_buf(), error))?;
+
+    if metadata.mode() & 0o002 != 0 {
+        warn!(
+            "Cache directory [{}] is writable by anyone (mode {:o}), consider restricting it to the user running the receiver",
+            path.display(),
+            metadata.mode() & 0o777
+        );
+    }
+    if metadata.uid() != get_effective_uid() {
+        warn!(
+            "Cache directory [{}] is owned by UID {} but the receiver runs as UID {}, files it creates may not be accessible to the owner",
+            path.display(),
+            metadata.uid(),
+            get_effective_uid()
+        );
+    }
+
+    if min_free_space_mb > 0 {
+        if let Some(available_mb) = get_available_space_mb(path) {
+            if available_mb < min_free_space_mb {
+                return Err(CacheDirectoryError::InsufficientSpace {
+                    path: path.to_path_buf(),
+                    available_mb,
+                    required_mb: min_free_space_mb,
+                });
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+/// Gets the space in megabytes available to unprivileged users on the file system of a path
+///
+/// # Arguments
+/// * `path` - Path on the file system
+fn get_available_space_mb(path: &Path) -> Option<u64> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: path is a valid NUL-terminated string and stats is only read after statvfs has succeeded
+    let stats = unsafe {
+        if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stats.assume_init()
+    };
+
+    return Some(stats.f_bavail as u64 * stats.f_frsize as u64 / (1024 * 1024));
+}
+
+/// Gets the effective UID of the receiver
+fn get_effective_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and always succeeds
+    unsafe { libc::geteuid() }
+}
@@ -0,0 +1,144 @@
+//! Circuit breakers of the Vehicle Management Service API operations
+//!
+//! When the API is down, every event would otherwise wait for all attempts of its request to fail before being cached.
+//! Each operation has a circuit that opens after `API_CIRCUIT_BREAKER_FAILURE_THRESHOLD` consecutive requests failed
+//! with transport or server errors. While open, requests of the operation fail right away, so that their events go
+//! straight to the cache. After `API_CIRCUIT_BREAKER_OPEN_SECONDS` a single probe request is let through, closing the
+//! circuit if it succeeds and opening it again otherwise.
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use crate::{config::get_config, metrics};
+
+/// Name of the gauge describing whether the circuit of an operation is open
+pub const CIRCUIT_BREAKER_OPEN_METRIC: &str = "receiver_api_circuit_breaker_open";
+
+static CIRCUIT_BREAKERS: OnceLock<CircuitBreakers> = OnceLock::new();
+
+/// State of the circuit of a single operation
+#[derive(Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// Time the probe request in flight was let through
+    probe_started_at: Option<Instant>,
+}
+
+/// Circuit breakers of the API operations
+pub struct CircuitBreakers {
+    failure_threshold: Option<u32>,
+    open_duration: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+impl CircuitBreakers {
+    /// Creates new [CircuitBreakers]
+    ///
+    /// # Arguments
+    /// * `failure_threshold` - Number of consecutive failed requests opening a circuit, or `None` to never open them
+    /// * `open_duration` - Time a circuit stays open before a probe request is let through
+    pub fn new(failure_threshold: Option<u32>, open_duration: Duration) -> Self {
+        CircuitBreakers {
+            failure_threshold,
+            open_duration,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks whether a request of an operation may be sent
+    ///
+    /// Once an open circuit has been open long enough, a single probe request is allowed. Another probe is allowed if
+    /// the previous one doesn't complete within the open duration.
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation
+    /// * `now` - Current time
+    pub fn try_acquire(&self, operation: &str, now: Instant) -> bool {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(operation) else {
+            return true;
+        };
+        let Some(open_until) = circuit.open_until else {
+            return true;
+        };
+        if now < open_until {
+            return false;
+        }
+        let is_probing = circuit.probe_started_at.is_some_and(|probe_started_at| {
+            now.duration_since(probe_started_at) < self.open_duration
+        });
+        if is_probing {
+            return false;
+        }
+        circuit.probe_started_at = Some(now);
+
+        return true;
+    }
+
+    /// Records a request of an operation reaching the API
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation
+    pub fn record_success(&self, operation: &str) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.remove(operation) else {
+            return;
+        };
+        if circuit.open_until.is_some() {
+            info!("API circuit breaker of {} closed", operation);
+            metrics::set_gauge(
+                CIRCUIT_BREAKER_OPEN_METRIC,
+                &[("operation", operation)],
+                0.0,
+            );
+        }
+    }
+
+    /// Records a request of an operation failing to reach the API, opening the circuit if the threshold is reached
+    ///
+    /// # Arguments
+    /// * `operation` - Name of the operation
+    /// * `now` - Current time
+    pub fn record_failure(&self, operation: &str, now: Instant) {
+        let Some(failure_threshold) = self.failure_threshold else {
+            return;
+        };
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(operation.to_string()).or_default();
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+        if circuit.probe_started_at.is_none() && circuit.consecutive_failures < failure_threshold {
+            return;
+        }
+        if circuit.open_until.is_none() {
+            warn!(
+                "API circuit breaker of {} opened after {} consecutive failures",
+                operation, circuit.consecutive_failures
+            );
+            metrics::set_gauge(
+                CIRCUIT_BREAKER_OPEN_METRIC,
+                &[("operation", operation)],
+                1.0,
+            );
+        }
+        circuit.open_until = Some(now + self.open_duration);
+        circuit.probe_started_at = None;
+    }
+}
+
+/// Gets the global circuit breakers with the thresholds of the configuration
+///
+/// A failure threshold of zero disables the circuit breakers.
+pub fn get_circuit_breakers() -> &'static CircuitBreakers {
+    CIRCUIT_BREAKERS.get_or_init(|| {
+        let config = get_config();
+        CircuitBreakers::new(
+            config.api.circuit_breaker_failure_threshold,
+            config.api.circuit_breaker_open,
+        )
+    })
+}
@@ -0,0 +1,36 @@
+//! Module containing geographical utility functions
+
+/// Mean radius of the Earth in meters
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Calculates the great-circle distance between two coordinates using the haversine formula
+///
+/// # Arguments
+/// * `from` - Latitude and longitude of the start point in degrees
+/// * `to` - Latitude and longitude of the end point in degrees
+///
+/// # Returns
+/// * `f64` - Distance in meters
+pub fn haversine_distance_meters(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (from_latitude, from_longitude) = (from.0.to_radians(), from.1.to_radians());
+    let (to_latitude, to_longitude) = (to.0.to_radians(), to.1.to_radians());
+    let latitude_delta = to_latitude - from_latitude;
+    let longitude_delta = to_longitude - from_longitude;
+    let a = (latitude_delta / 2.0).sin().powi(2)
+        + from_latitude.cos() * to_latitude.cos() * (longitude_delta / 2.0).sin().powi(2);
+
+    return 2.0 * EARTH_RADIUS_METERS * a.sqrt().asin();
+}
+
+/// Checks whether the given coordinates are a real position
+///
+/// Teltonika devices report 0.0, 0.0 when they don't have a GNSS fix.
+///
+/// # Arguments
+/// * `latitude` - Latitude in degrees
+/// * `longitude` - Longitude in degrees
+pub fn is_valid_position(latitude: f64, longitude: f64) -> bool {
+    return (-90.0..=90.0).contains(&latitude)
+        && (-180.0..=180.0).contains(&longitude)
+        && !(latitude == 0.0 && longitude == 0.0);
+}
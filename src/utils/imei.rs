@@ -1,6 +1,60 @@
-/// Module containing utility functions for testing IMEI parsing
+//! IMEI validation and utility functions for testing IMEI parsing
+#[cfg(test)]
 use rand::{distributions::Alphanumeric, Rng};
 
+/// Length of an IMEI with a Luhn check digit
+const IMEI_LENGTH: usize = 15;
+/// Length of an IMEISV, which has a two digit software version number instead of a check digit
+const IMEISV_LENGTH: usize = 16;
+
+/// Validates an IMEI
+///
+/// 15-digit IMEIs are validated with the Luhn check digit and 16-digit IMEISVs are validated to consist of digits only,
+/// as they don't have a check digit.
+///
+/// # Arguments
+/// * `imei` - The IMEI to validate
+///
+/// # Returns
+/// * `bool` - Whether the IMEI is valid
+pub fn is_valid_imei(imei: &str) -> bool {
+    if !imei.bytes().all(|byte| byte.is_ascii_digit()) {
+        return false;
+    }
+
+    return match imei.len() {
+        IMEI_LENGTH => is_valid_luhn_checksum(imei),
+        IMEISV_LENGTH => true,
+        _ => false,
+    };
+}
+
+/// Validates the Luhn check digit of a string of digits
+///
+/// # Arguments
+/// * `digits` - The digits to validate, the last one being the check digit
+///
+/// # Returns
+/// * `bool` - Whether the check digit is valid
+fn is_valid_luhn_checksum(digits: &str) -> bool {
+    let Some((check_digit, payload)) = digits.as_bytes().split_last() else {
+        return false;
+    };
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .map(|byte| u32::from(byte - b'0'))
+        .enumerate()
+        .map(|(index, digit)| match index % 2 {
+            0 if digit * 2 > 9 => digit * 2 - 9,
+            0 => digit * 2,
+            _ => digit,
+        })
+        .sum();
+
+    return (10 - sum % 10) % 10 == u32::from(check_digit - b'0');
+}
+
 /// Builds a valid IMEI packet from the given IMEI
 ///
 /// The first two bytes denote the length of the IMEI and the rest are the IMEI itself.
@@ -10,6 +64,7 @@ use rand::{distributions::Alphanumeric, Rng};
 ///
 /// # Returns
 /// * `Vec<u8>` - The IMEI packet
+#[cfg(test)]
 pub fn build_valid_imei_packet(imei: &str) -> Vec<u8> {
     let length = imei.len() as i16;
     let mut imei_byte_array = length.to_be_bytes().to_vec();
@@ -28,6 +83,7 @@ pub fn build_valid_imei_packet(imei: &str) -> Vec<u8> {
 ///
 /// # Returns
 /// * `Vec<u8>` - The IMEI packet
+#[cfg(test)]
 pub fn build_invalid_imei_packet(imei: &str) -> Vec<u8> {
     return imei.as_bytes().to_vec();
 }
@@ -39,6 +95,7 @@ pub fn build_invalid_imei_packet(imei: &str) -> Vec<u8> {
 ///
 /// # Returns
 /// * `String` - The generated IMEI
+#[cfg(test)]
 pub fn get_random_imei_of_length(length: i16) -> String {
     rand::thread_rng()
         .sample_iter(&Alphanumeric)
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::config::get_config;
+
+static LOG_THROTTLE: OnceLock<LogThrottle> = OnceLock::new();
+
+/// Log state of a single key
+struct ThrottledKey {
+    logged_at: Instant,
+    suppressed: u64,
+}
+
+/// Throttle for repetitive log messages
+///
+/// A message is logged once per key per interval, and the number of messages suppressed in between is reported with the next logged one.
+pub struct LogThrottle {
+    interval: Duration,
+    keys: Mutex<HashMap<String, ThrottledKey>>,
+}
+
+impl LogThrottle {
+    /// Creates a new [LogThrottle]
+    ///
+    /// # Arguments
+    /// * `interval` - Interval a message is logged at most once per key
+    pub fn new(interval: Duration) -> Self {
+        LogThrottle {
+            interval,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks whether a message should be logged
+    ///
+    /// # Arguments
+    /// * `key` - Key of the message, e.g. the IMEI of the device and the kind of the message
+    /// * `now` - Time of the message
+    ///
+    /// # Returns
+    /// * Number of messages suppressed since the previous logged one or `None` if the message should be suppressed
+    pub fn check(&self, key: &str, now: Instant) -> Option<u64> {
+        let mut keys = self.keys.lock().unwrap();
+        match keys.get_mut(key) {
+            Some(throttled_key) if now.duration_since(throttled_key.logged_at) < self.interval => {
+                throttled_key.suppressed += 1;
+                return None;
+            }
+            Some(throttled_key) => {
+                let suppressed = throttled_key.suppressed;
+                throttled_key.logged_at = now;
+                throttled_key.suppressed = 0;
+                return Some(suppressed);
+            }
+            None => {
+                keys.insert(
+                    key.to_string(),
+                    ThrottledKey {
+                        logged_at: now,
+                        suppressed: 0,
+                    },
+                );
+                return Some(0);
+            }
+        }
+    }
+}
+
+/// Checks whether a message should be logged using the global throttle with the interval of the configuration
+///
+/// # Arguments
+/// * `key` - Key of the message, e.g. the IMEI of the device and the kind of the message
+///
+/// # Returns
+/// * Number of messages suppressed since the previous logged one or `None` if the message should be suppressed
+pub fn throttle(key: &str) -> Option<u64> {
+    return LOG_THROTTLE
+        .get_or_init(|| LogThrottle::new(get_config().monitoring.log_throttle_interval))
+        .check(key, Instant::now());
+}
+
+/// Describes the number of suppressed messages to be appended to a logged message
+///
+/// # Arguments
+/// * `suppressed` - Number of messages suppressed since the previous logged one
+pub fn describe_suppressed(suppressed: u64) -> String {
+    if suppressed == 0 {
+        return String::new();
+    }
+
+    return format!(" ({} similar messages suppressed)", suppressed);
+}
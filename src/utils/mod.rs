@@ -1,15 +1,23 @@
-use std::str::FromStr;
-
 use vehicle_management_service::apis::configuration::Configuration;
 
+use crate::config::get_config;
+
 pub mod api;
+pub mod api_routing;
 pub mod avl_frame_builder;
 pub mod avl_packet;
 pub mod avl_record_builder;
-#[cfg(test)]
+pub mod cache_directory;
+pub mod circuit_breaker;
+pub mod geo;
 pub mod imei;
+pub mod log_throttle;
+pub mod outbound_capture;
+pub mod socket_options;
 #[cfg(test)]
 pub mod test_utils;
+pub mod truck_cache;
+pub mod validation;
 
 /// Converts a hex string to a byte vector
 ///
@@ -33,60 +41,19 @@ pub fn str_to_bytes(input: &str) -> Vec<u8> {
     return bytes;
 }
 
-/// Reads environment variable and parses it to the desired type
-///
-/// Panics if the environment variable is not set
-///
-/// # Arguments
-/// * `key` - The environment variable key
-///
-/// # Returns
-/// * `T` - The parsed environment variable
-pub fn read_env_variable<T: FromStr>(key: &str) -> T {
-    match std::env::var(key) {
-        Ok(value) => parse_env_variable(value),
-        Err(_) => panic!("{} environment variable not set", key),
-    }
-}
-
-/// Reads environment variable and parses it to the desired type wrapped in an Option
-///
-/// # Arguments
-/// * `key` - The environment variable key
-///
-/// # Returns
-/// * `Option<T>` - The parsed environment variable
-pub fn read_optional_env_variable<T: FromStr>(key: &str) -> Option<T> {
-    match std::env::var(key) {
-        Ok(value) => Some(parse_env_variable(value)),
-        Err(_) => None,
-    }
-}
-
-/// Parses an environment variable to the desired type
-///
-/// Panics if the parsing fails
-///
-/// # Arguments
-/// * `value` - The environment variable value
-fn parse_env_variable<T: FromStr>(value: String) -> T {
-    match value.parse() {
-        Ok(parsed) => parsed,
-        Err(_) => panic!("Failed to parse environment variable"),
-    }
-}
-
 /// Gets the API configuration for VP-Kuljetus Vehicle Management Service
 ///
 /// # Returns
 /// * [`Configuration`] - The API configuration
 pub fn get_vehicle_management_api_config() -> Configuration {
+    let config = get_config();
     let api_key = vehicle_management_service::apis::configuration::ApiKey {
         prefix: None,
-        key: read_env_variable("VEHICLE_MANAGEMENT_SERVICE_API_KEY"),
+        key: config.api.api_key.clone(),
     };
     Configuration {
-        base_path: read_env_variable("API_BASE_URL"),
+        base_path: config.api.base_url.clone(),
+        client: api::get_api_client(),
         api_key: Some(api_key),
         ..Default::default()
     }
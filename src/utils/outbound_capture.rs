@@ -0,0 +1,98 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Number of captured requests kept per operation
+const CAPTURED_REQUESTS_PER_OPERATION: usize = 20;
+/// Number of trailing characters left visible in redacted values
+const VISIBLE_REDACTED_CHARACTERS: usize = 4;
+
+static CAPTURED_REQUESTS: OnceLock<Mutex<BTreeMap<String, VecDeque<CapturedRequest>>>> =
+    OnceLock::new();
+
+/// Outgoing API request captured for debugging
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CapturedRequest {
+    pub request_id: Uuid,
+    pub truck_id: String,
+    pub payload: serde_json::Value,
+    pub result: String,
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Captures an outgoing API request
+///
+/// Only the latest [CAPTURED_REQUESTS_PER_OPERATION] requests are kept per operation. Driver card IDs are redacted.
+///
+/// # Arguments
+/// * `operation` - Name of the operation
+/// * `request_id` - ID of the request
+/// * `truck_id` - Truck ID of the request
+/// * `payload` - Payload of the request
+/// * `result` - Description of the result of the request
+pub fn capture(
+    operation: &str,
+    request_id: Uuid,
+    truck_id: &str,
+    mut payload: serde_json::Value,
+    result: String,
+) {
+    if let Some(fields) = payload.as_object_mut() {
+        for (field, value) in fields.iter_mut() {
+            if let (true, Some(id)) = (is_driver_card_id_field(operation, field), value.as_str()) {
+                *value = serde_json::Value::String(redact(id));
+            }
+        }
+    }
+    let mut captured_requests = CAPTURED_REQUESTS
+        .get_or_init(|| Mutex::new(BTreeMap::new()))
+        .lock()
+        .unwrap();
+    let operation_requests = captured_requests.entry(operation.to_string()).or_default();
+    if operation_requests.len() == CAPTURED_REQUESTS_PER_OPERATION {
+        operation_requests.pop_front();
+    }
+    operation_requests.push_back(CapturedRequest {
+        request_id,
+        truck_id: truck_id.to_string(),
+        payload,
+        result,
+        captured_at: Utc::now(),
+    });
+}
+
+/// Gets the captured requests by operation, latest first
+pub fn get_captured_requests() -> BTreeMap<String, Vec<CapturedRequest>> {
+    let Some(captured_requests) = CAPTURED_REQUESTS.get() else {
+        return BTreeMap::new();
+    };
+
+    return captured_requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(operation, requests)| (operation.clone(), requests.iter().rev().cloned().collect()))
+        .collect();
+}
+
+/// Checks whether a payload field of an operation contains a driver card ID
+fn is_driver_card_id_field(operation: &str, field: &str) -> bool {
+    return field == "driverCardId" || (operation == "create_truck_driver_card" && field == "id");
+}
+
+/// Redacts all but the last characters of a value
+fn redact(value: &str) -> String {
+    let visible_start = value
+        .char_indices()
+        .rev()
+        .nth(VISIBLE_REDACTED_CHARACTERS - 1)
+        .map(|(index, _)| index)
+        .unwrap_or(0);
+
+    return "*".repeat(value[..visible_start].chars().count()) + &value[visible_start..];
+}
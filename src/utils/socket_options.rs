@@ -0,0 +1,72 @@
+//! Socket options for device connections
+//!
+//! Operating system defaults behave poorly on high-latency cellular links, so the options can be tuned in the configuration
+//! per listener, falling back to the global `TCP_*` options.
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::{TcpStream, UdpSocket};
+
+/// Socket options applied to accepted connections
+///
+/// Options that are not set are left to the operating system defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SocketOptions {
+    /// Whether to disable Nagle's algorithm
+    pub nodelay: Option<bool>,
+    /// Idle time before the first keepalive probe is sent
+    pub keepalive_time: Option<Duration>,
+    /// Interval between keepalive probes
+    pub keepalive_interval: Option<Duration>,
+    /// Size of the receive buffer in bytes
+    pub recv_buffer_size: Option<usize>,
+    /// Size of the send buffer in bytes
+    pub send_buffer_size: Option<usize>,
+}
+
+impl SocketOptions {
+    /// Applies the options to a socket
+    ///
+    /// # Arguments
+    /// * `stream` - Socket to apply the options to
+    pub fn apply(&self, stream: &TcpStream) -> std::io::Result<()> {
+        let socket = SockRef::from(stream);
+        if let Some(nodelay) = self.nodelay {
+            socket.set_nodelay(nodelay)?;
+        }
+        if self.keepalive_time.is_some() || self.keepalive_interval.is_some() {
+            let mut keepalive = TcpKeepalive::new();
+            if let Some(keepalive_time) = self.keepalive_time {
+                keepalive = keepalive.with_time(keepalive_time);
+            }
+            if let Some(keepalive_interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(keepalive_interval);
+            }
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+        if let Some(recv_buffer_size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(recv_buffer_size)?;
+        }
+        if let Some(send_buffer_size) = self.send_buffer_size {
+            socket.set_send_buffer_size(send_buffer_size)?;
+        }
+
+        return Ok(());
+    }
+
+    /// Applies the buffer sizes of the options to a UDP socket
+    ///
+    /// # Arguments
+    /// * `udp_socket` - Socket to apply the buffer sizes to
+    pub fn apply_to_udp(&self, udp_socket: &UdpSocket) -> std::io::Result<()> {
+        let socket = SockRef::from(udp_socket);
+        if let Some(recv_buffer_size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(recv_buffer_size)?;
+        }
+        if let Some(send_buffer_size) = self.send_buffer_size {
+            socket.set_send_buffer_size(send_buffer_size)?;
+        }
+
+        return Ok(());
+    }
+}
@@ -1,15 +1,26 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
 
 use httpmock::{
+    prelude::HttpMockRequest,
     Method::{DELETE, GET, POST},
     MockServer, Regex,
 };
-use nom_teltonika::AVLEventIO;
+use nom_teltonika::{AVLEventIO, AVLFrame};
 use tempfile::tempdir;
 use uuid::Uuid;
 use vehicle_management_service::models::{PublicTruck, TruckDriverCard};
 
-use crate::teltonika::records::TeltonikaRecordsHandler;
+use crate::{
+    config::update_config,
+    teltonika::{device_family::DeviceFamily, records::TeltonikaRecordsHandler},
+    utils::{
+        api::{IDEMPOTENCY_KEY_HEADER, REQUEST_ID_HEADER},
+        avl_packet::AVLPacketToBytes,
+    },
+};
 
 /// Converts a VIN number to 3 part events.
 pub fn vin_to_three_part_events(vin: String) -> [AVLEventIO; 3] {
@@ -94,14 +105,43 @@ pub fn string_to_hex_to_dec(string: &str) -> u64 {
 ///
 /// # Returns
 /// * `(bool, Option<String>)` - Whether the IMEI was successfully parsed and the IMEI itself as an `Option<String>`
-pub fn read_imei(buffer: &Vec<u8>) -> (bool, Option<String>) {
-    let result = nom_teltonika::parser::imei(&buffer);
+pub fn read_imei(buffer: &[u8]) -> (bool, Option<String>) {
+    let result = nom_teltonika::parser::imei(buffer);
     match result {
         Ok((_, imei)) => (true, Some(imei)),
         Err(_) => (false, None),
     }
 }
 
+/// Builds a UDP datagram carrying the data of a frame
+///
+/// # Arguments
+/// * `imei` - IMEI of the device
+/// * `packet_id` - UDP channel packet ID
+/// * `avl_packet_id` - AVL packet ID
+/// * `frame` - Frame whose data is sent
+pub fn build_udp_datagram(
+    imei: &str,
+    packet_id: u16,
+    avl_packet_id: u8,
+    frame: &AVLFrame,
+) -> Vec<u8> {
+    let frame_bytes = frame.to_bytes();
+    // AVL data of the frame without preamble, data length and CRC
+    let avl_data = &frame_bytes[8..frame_bytes.len() - 4];
+    let mut packet = packet_id.to_be_bytes().to_vec();
+    packet.push(1);
+    packet.push(avl_packet_id);
+    packet.extend_from_slice(&(imei.len() as u16).to_be_bytes());
+    packet.extend_from_slice(imei.as_bytes());
+    packet.extend_from_slice(avl_data);
+
+    let mut datagram = (packet.len() as u16).to_be_bytes().to_vec();
+    datagram.extend_from_slice(&packet);
+
+    return datagram;
+}
+
 /// Gets a TeltonikaRecordsHandler for testing
 ///
 /// Uses a temporary directory for the cache
@@ -111,20 +151,149 @@ pub fn get_teltonika_records_handler(
 ) -> TeltonikaRecordsHandler {
     let test_cache_dir = tempdir().unwrap();
     let test_cache_path = test_cache_dir.path();
-    let imei = imei.unwrap_or(String::new());
+    let imei = imei.unwrap_or_default();
+
+    return TeltonikaRecordsHandler::new(test_cache_path, truck_id, imei, DeviceFamily::default());
+}
+
+/// Mock server for the Vehicle Management Service shared by the tests
+static VEHICLE_MANAGEMENT_MOCK: OnceLock<MockServer> = OnceLock::new();
+
+/// Requests received by the mock Vehicle Management Service
+static RECEIVED_REQUESTS: Mutex<Vec<ReceivedRequest>> = Mutex::new(Vec::new());
+
+/// Trucks whose requests the mock Vehicle Management Service fails on the first attempt
+static FLAKY_TRUCKS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// IDs of the requests of flaky trucks the mock Vehicle Management Service has failed
+static FAILED_REQUEST_IDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Request received by the mock Vehicle Management Service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    pub idempotency_key: Option<String>,
+    pub body: Option<serde_json::Value>,
+}
+
+/// Records a request received by the mock Vehicle Management Service
+///
+/// Used as the matcher of a mock matching no requests, so that each request is recorded once before it is matched.
+///
+/// # Arguments
+/// * `request` - Received request
+fn record_received_request(request: &HttpMockRequest) -> bool {
+    RECEIVED_REQUESTS.lock().unwrap().push(ReceivedRequest {
+        method: request.method.clone(),
+        path: request.path.clone(),
+        idempotency_key: get_header(request, IDEMPOTENCY_KEY_HEADER),
+        body: request
+            .body
+            .as_ref()
+            .and_then(|body| serde_json::from_slice(body).ok()),
+    });
+
+    return false;
+}
+
+/// Checks whether a request is the first attempt of a request of a flaky truck
+///
+/// Attempts are told apart by the request ID shared by all attempts of a request.
+///
+/// # Arguments
+/// * `request` - Received request
+fn is_first_attempt_of_flaky_truck(request: &HttpMockRequest) -> bool {
+    let is_flaky = FLAKY_TRUCKS.lock().unwrap().iter().any(|truck_id| {
+        request
+            .path
+            .starts_with(&format!("/v1/trucks/{}/", truck_id))
+    });
+    let Some(request_id) = get_header(request, REQUEST_ID_HEADER).filter(|_| is_flaky) else {
+        return false;
+    };
+    let mut failed_request_ids = FAILED_REQUEST_IDS.lock().unwrap();
+    if failed_request_ids.contains(&request_id) {
+        return false;
+    }
+    failed_request_ids.push(request_id);
+
+    return true;
+}
+
+/// Gets the value of a header of a received request
+///
+/// # Arguments
+/// * `request` - Received request
+/// * `name` - Name of the header
+fn get_header(request: &HttpMockRequest, name: &str) -> Option<String> {
+    return request.headers.as_ref().and_then(|headers| {
+        headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    });
+}
+
+/// Gets the requests the mock Vehicle Management Service has received for a truck
+///
+/// # Arguments
+/// * `truck_id` - Truck ID
+pub fn get_received_requests(truck_id: &str) -> Vec<ReceivedRequest> {
+    let truck_path = format!("/v1/trucks/{}/", truck_id);
+
+    return RECEIVED_REQUESTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|request| request.path.starts_with(&truck_path))
+        .cloned()
+        .collect();
+}
 
-    return TeltonikaRecordsHandler::new(test_cache_path, truck_id, imei);
+/// Makes the mock Vehicle Management Service fail the first attempt of each request of a truck with a server error
+///
+/// # Arguments
+/// * `truck_id` - Truck ID
+pub fn fail_first_attempts(truck_id: &str) {
+    FLAKY_TRUCKS.lock().unwrap().push(truck_id.to_string());
 }
 
 /// Starts a mock server for the Vehicle Management Service
-pub fn start_vehicle_management_mock() -> MockServer {
-    let mock_server = MockServer::start();
+///
+/// The server is shared by the tests, so that tests running in parallel send their requests to the same server.
+/// Requests received by the server are recorded, see [get_received_requests].
+pub fn start_vehicle_management_mock() -> &'static MockServer {
+    return VEHICLE_MANAGEMENT_MOCK.get_or_init(|| {
+        let mock_server = MockServer::start();
+        register_vehicle_management_mocks(&mock_server);
+
+        return mock_server;
+    });
+}
+
+/// Registers the mocks of the Vehicle Management Service
+///
+/// # Arguments
+/// * `mock_server` - Mock server to register the mocks on
+fn register_vehicle_management_mocks(mock_server: &MockServer) {
     let mut server_address = String::from("http://");
     server_address.push_str(mock_server.address().to_string().as_str());
 
-    std::env::set_var("API_BASE_URL", &server_address);
-    std::env::set_var("VEHICLE_MANAGEMENT_SERVICE_API_KEY", "API_KEY");
+    update_config(|config| {
+        config.api.base_url = server_address;
+        config.api.api_key = String::from("API_KEY");
+    });
 
+    // Mocks are matched in the order they are registered
+    let _record_request_mock = mock_server.mock(|when, then| {
+        when.matches(record_received_request);
+        then.status(500);
+    });
+    let _flaky_request_mock = mock_server.mock(|when, then| {
+        when.method(POST).matches(is_first_attempt_of_flaky_truck);
+        then.status(503);
+    });
     let _public_trucks_mock = mock_server.mock(|when, then| {
         when.method(GET)
             .path("/v1/publicTrucks")
@@ -189,6 +358,4 @@ pub fn start_vehicle_management_mock() -> MockServer {
             .header("X-API-KEY", "API_KEY");
         then.status(204);
     });
-
-    mock_server
 }
@@ -0,0 +1,236 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::{config::get_config, metrics};
+
+/// Name of the counter describing the number of truck cache lookups by result
+pub const TRUCK_CACHE_LOOKUPS_METRIC: &str = "receiver_truck_cache_lookups_total";
+/// Name of the gauge describing the latency of the latest truck lookup from the API
+pub const TRUCK_LOOKUP_LATENCY_METRIC: &str = "receiver_truck_lookup_latency_seconds";
+/// Name of the counter describing the total time spent on truck lookups from the API
+pub const TRUCK_LOOKUP_DURATION_METRIC: &str = "receiver_truck_lookup_duration_milliseconds_total";
+/// Name of the counter describing the number of truck lookups joining a lookup of the same VIN already in flight
+pub const TRUCK_LOOKUPS_COALESCED_METRIC: &str = "receiver_truck_lookups_coalesced_total";
+/// Name of the counter describing the number of VINs evicted from the truck cache to keep it within its capacity
+pub const TRUCK_CACHE_EVICTIONS_METRIC: &str = "receiver_truck_cache_evictions_total";
+
+static TRUCK_CACHE: OnceLock<TruckCache> = OnceLock::new();
+
+/// Result of a truck cache lookup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TruckCacheLookup {
+    /// The truck is cached
+    Hit(Uuid),
+    /// The VIN is cached as not having a truck
+    NegativeHit,
+    /// The VIN is not cached or the cached entry has expired
+    Miss,
+}
+
+/// Cached truck ID of a VIN
+struct CachedTruck {
+    truck_id: Option<Uuid>,
+    cached_at: DateTime<Utc>,
+}
+
+/// Lookup of a truck ID in flight, resolving to `Err` if the lookup failed
+type InFlightLookup = Arc<OnceCell<Result<Option<Uuid>, ()>>>;
+
+/// Cache of truck IDs by VIN
+///
+/// VINs without a truck are cached for a shorter time, so that newly created trucks are found soon. The number of
+/// cached VINs is limited, so that devices reporting arbitrary VINs can't grow the cache without bounds.
+pub struct TruckCache {
+    ttl: Duration,
+    negative_ttl: Duration,
+    max_entries: usize,
+    trucks: Mutex<HashMap<String, CachedTruck>>,
+    in_flight_lookups: Mutex<HashMap<String, InFlightLookup>>,
+}
+
+impl TruckCache {
+    /// Creates a new [TruckCache]
+    ///
+    /// # Arguments
+    /// * `ttl_seconds` - Time a found truck is cached
+    /// * `negative_ttl_seconds` - Time a VIN without a truck is cached
+    /// * `max_entries` - Maximum number of VINs cached
+    pub fn new(ttl_seconds: i64, negative_ttl_seconds: i64, max_entries: usize) -> Self {
+        TruckCache {
+            ttl: Duration::seconds(ttl_seconds),
+            negative_ttl: Duration::seconds(negative_ttl_seconds),
+            max_entries,
+            trucks: Mutex::new(HashMap::new()),
+            in_flight_lookups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Looks up the truck ID of a VIN
+    ///
+    /// # Arguments
+    /// * `vin` - VIN of the truck
+    /// * `now` - Time of the lookup
+    pub fn lookup(&self, vin: &str, now: DateTime<Utc>) -> TruckCacheLookup {
+        let trucks = self.trucks.lock().unwrap();
+        let lookup = match trucks.get(vin) {
+            Some(CachedTruck {
+                truck_id: Some(truck_id),
+                cached_at,
+            }) if now - *cached_at < self.ttl => TruckCacheLookup::Hit(*truck_id),
+            Some(CachedTruck {
+                truck_id: None,
+                cached_at,
+            }) if now - *cached_at < self.negative_ttl => TruckCacheLookup::NegativeHit,
+            _ => TruckCacheLookup::Miss,
+        };
+        let result = match lookup {
+            TruckCacheLookup::Hit(_) => "hit",
+            TruckCacheLookup::NegativeHit => "negative_hit",
+            TruckCacheLookup::Miss => "miss",
+        };
+        metrics::increment_counter(TRUCK_CACHE_LOOKUPS_METRIC, &[("result", result)]);
+
+        return lookup;
+    }
+
+    /// Caches the truck ID of a VIN
+    ///
+    /// When the cache is full, expired entries are evicted first and then the entries cached the longest ago.
+    ///
+    /// # Arguments
+    /// * `vin` - VIN of the truck
+    /// * `truck_id` - Truck ID or `None` if the VIN doesn't have a truck
+    /// * `now` - Time of caching
+    pub fn insert(&self, vin: &str, truck_id: Option<Uuid>, now: DateTime<Utc>) {
+        let mut trucks = self.trucks.lock().unwrap();
+        if !trucks.contains_key(vin) && trucks.len() >= self.max_entries {
+            let cached_count = trucks.len();
+            trucks.retain(|_, cached_truck| !self.is_expired(cached_truck, now));
+            while trucks.len() >= self.max_entries {
+                let Some(oldest_vin) = trucks
+                    .iter()
+                    .min_by_key(|(_, cached_truck)| cached_truck.cached_at)
+                    .map(|(vin, _)| vin.clone())
+                else {
+                    break;
+                };
+                trucks.remove(&oldest_vin);
+            }
+            metrics::add_to_counter(
+                TRUCK_CACHE_EVICTIONS_METRIC,
+                &[],
+                (cached_count - trucks.len()) as u64,
+            );
+        }
+        trucks.insert(
+            vin.to_string(),
+            CachedTruck {
+                truck_id,
+                cached_at: now,
+            },
+        );
+    }
+
+    /// Gets the number of cached VINs
+    #[cfg(test)]
+    pub fn get_cached_count(&self) -> usize {
+        self.trucks.lock().unwrap().len()
+    }
+
+    /// Checks whether a cached entry has expired
+    ///
+    /// # Arguments
+    /// * `cached_truck` - Cached entry
+    /// * `now` - Current time
+    fn is_expired(&self, cached_truck: &CachedTruck, now: DateTime<Utc>) -> bool {
+        let ttl = match cached_truck.truck_id {
+            Some(_) => self.ttl,
+            None => self.negative_ttl,
+        };
+
+        return now - cached_truck.cached_at >= ttl;
+    }
+
+    /// Gets the truck ID of a VIN from the cache, fetching it on a miss
+    ///
+    /// Concurrent misses of the same VIN share a single fetch, so that a storm of devices of the same truck
+    /// reconnecting at once sends a single request. Locks are not held while fetching, so lookups of other VINs
+    /// proceed meanwhile. Failed fetches are not cached and the next lookup fetches again.
+    ///
+    /// # Arguments
+    /// * `vin` - VIN of the truck
+    /// * `fetch` - Function fetching the truck ID, `Ok(None)` if the VIN doesn't have a truck
+    pub async fn get_or_fetch<F, Fut>(&self, vin: &str, fetch: F) -> Option<Uuid>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<Uuid>, ()>>,
+    {
+        match self.lookup(vin, Utc::now()) {
+            TruckCacheLookup::Hit(truck_id) => return Some(truck_id),
+            TruckCacheLookup::NegativeHit => return None,
+            TruckCacheLookup::Miss => {}
+        }
+
+        let lookup = {
+            let mut in_flight_lookups = self.in_flight_lookups.lock().unwrap();
+            if in_flight_lookups.contains_key(vin) {
+                metrics::increment_counter(TRUCK_LOOKUPS_COALESCED_METRIC, &[]);
+            }
+            in_flight_lookups
+                .entry(vin.to_string())
+                .or_default()
+                .clone()
+        };
+        let result = *lookup
+            .get_or_init(|| async {
+                let result = fetch().await;
+                // Cached before the lookup is finished, so that later lookups don't fetch again
+                if let Ok(truck_id) = result {
+                    self.insert(vin, truck_id, Utc::now());
+                }
+                result
+            })
+            .await;
+        let mut in_flight_lookups = self.in_flight_lookups.lock().unwrap();
+        if in_flight_lookups
+            .get(vin)
+            .is_some_and(|in_flight_lookup| Arc::ptr_eq(in_flight_lookup, &lookup))
+        {
+            in_flight_lookups.remove(vin);
+        }
+
+        return result.ok().flatten();
+    }
+}
+
+/// Gets the global truck cache with the TTLs and size of the configuration
+pub fn get_truck_cache() -> &'static TruckCache {
+    TRUCK_CACHE.get_or_init(|| {
+        let config = get_config();
+        TruckCache::new(
+            config.api.truck_cache_ttl_seconds,
+            config.api.truck_cache_negative_ttl_seconds,
+            config.api.truck_cache_max_entries,
+        )
+    })
+}
+
+/// Records the latency of a truck lookup from the API
+///
+/// # Arguments
+/// * `latency` - Latency of the lookup
+pub fn record_lookup_latency(latency: std::time::Duration) {
+    metrics::set_gauge(TRUCK_LOOKUP_LATENCY_METRIC, &[], latency.as_secs_f64());
+    metrics::add_to_counter(
+        TRUCK_LOOKUP_DURATION_METRIC,
+        &[],
+        latency.as_millis() as u64,
+    );
+}
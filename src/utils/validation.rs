@@ -0,0 +1,95 @@
+use chrono::Utc;
+use vehicle_management_service::models::{
+    TruckDriveState, TruckDriverCard, TruckLocation, TruckSpeed,
+};
+
+/// Maximum time in seconds a payload timestamp may be in the future
+const MAX_FUTURE_TIMESTAMP_SECONDS: i64 = 24 * 60 * 60;
+/// Maximum plausible speed of a truck in km/h
+const MAX_SPEED: f32 = 300.0;
+/// Maximum length of a driver card ID
+const MAX_DRIVER_CARD_ID_LENGTH: usize = 32;
+
+/// Trait for validating payloads before sending them to the Vehicle Management Service
+///
+/// Malformed payloads produced e.g. by parsing bugs would be rejected by the API, so they are rejected locally instead.
+pub trait ValidatePayload {
+    /// Validates the payload
+    ///
+    /// # Returns
+    /// * Description of the first invalid field if the payload is invalid
+    fn validate(&self) -> Result<(), String>;
+}
+
+impl ValidatePayload for TruckLocation {
+    fn validate(&self) -> Result<(), String> {
+        validate_timestamp(self.timestamp)?;
+        validate_range("latitude", self.latitude, -90.0, 90.0)?;
+        validate_range("longitude", self.longitude, -180.0, 180.0)?;
+        validate_range("heading", self.heading, 0.0, 360.0)?;
+
+        return Ok(());
+    }
+}
+
+impl ValidatePayload for TruckSpeed {
+    fn validate(&self) -> Result<(), String> {
+        validate_timestamp(self.timestamp)?;
+        validate_range("speed", self.speed.into(), 0.0, MAX_SPEED.into())?;
+
+        return Ok(());
+    }
+}
+
+impl ValidatePayload for TruckDriverCard {
+    fn validate(&self) -> Result<(), String> {
+        validate_timestamp(self.timestamp)?;
+        validate_driver_card_id(&self.id)?;
+
+        return Ok(());
+    }
+}
+
+impl ValidatePayload for TruckDriveState {
+    fn validate(&self) -> Result<(), String> {
+        validate_timestamp(self.timestamp)?;
+        if let Some(driver_card_id) = &self.driver_card_id {
+            validate_driver_card_id(driver_card_id)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/// Validates that a timestamp in seconds is positive and not too far in the future
+fn validate_timestamp(timestamp: i64) -> Result<(), String> {
+    if timestamp <= 0 || timestamp > Utc::now().timestamp() + MAX_FUTURE_TIMESTAMP_SECONDS {
+        return Err(format!("timestamp {} is out of range", timestamp));
+    }
+
+    return Ok(());
+}
+
+/// Validates that a value is finite and within the given range
+fn validate_range(field: &str, value: f64, min: f64, max: f64) -> Result<(), String> {
+    if !value.is_finite() || value < min || value > max {
+        return Err(format!(
+            "{} {} is not within [{}, {}]",
+            field, value, min, max
+        ));
+    }
+
+    return Ok(());
+}
+
+/// Validates that a driver card ID is not empty, not too long and consists of printable ASCII characters
+fn validate_driver_card_id(driver_card_id: &str) -> Result<(), String> {
+    if driver_card_id.is_empty()
+        || driver_card_id.len() > MAX_DRIVER_CARD_ID_LENGTH
+        || !driver_card_id.chars().all(|c| c.is_ascii_graphic())
+    {
+        return Err(format!("driver card ID [{}] is invalid", driver_card_id));
+    }
+
+    return Ok(());
+}